@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+use otr::{compiler::{Compiler, file_reader::{FileReader, ImportAddress}}, runtime::RuntimeObject};
+
+const MEMBER_ACCESS: &str = r#"
+module Bench {
+    struct Point { public x, public y }
+
+    @entrypoint
+    proc main() {
+        let point = Bench::Point { x: 1, y: 2 };
+        let sum = 0;
+        let i = 0;
+        while (i < 1000) {
+            sum = sum + point.x + point.y;
+            point.x = point.y;
+            i = i + 1;
+        }
+        return sum;
+    }
+    export main;
+}
+"#;
+
+const ARRAY_INDEXING: &str = r#"
+module Bench {
+    @entrypoint
+    proc main() {
+        let values = [1, 2, 3, 4, 5, 6, 7, 8];
+        let sum = 0;
+        let i = 0;
+        while (i < 1000) {
+            sum = sum + values[i % 8];
+            values[i % 8] = i;
+            i = i + 1;
+        }
+        return sum;
+    }
+    export main;
+}
+"#;
+
+fn compile(source: &str) -> RuntimeObject {
+    let mut file_reader = FileReader::new(PathBuf::new()).with_source("Bench", source);
+    file_reader.enqueue(ImportAddress { module_id: "Bench".to_string(), path: None });
+
+    Compiler::new(file_reader)
+        .compile()
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+fn interpreter(c: &mut Criterion) {
+    for (name, source) in [("member_access", MEMBER_ACCESS), ("array_indexing", ARRAY_INDEXING)] {
+        let runtime_object = compile(source);
+
+        c.bench_function(name, |b| b.iter(|| runtime_object.execute().unwrap()));
+    }
+}
+
+criterion_group!(benches, interpreter);
+criterion_main!(benches);
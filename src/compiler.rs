@@ -1,16 +1,314 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::{HashMap, HashSet}, error::Error, fmt::{self, Debug, Display}, path::PathBuf, str::FromStr};
 
-use crate::{compiler::{file_reader::FileReader, states::CompilerBaseState}, lexer::{FragmentStream, Tokenizer, token::Token}, runtime::{RuntimeObject, environment::Environment}};
+use crate::{compiler::{expression_parser::ExpressionParser, file_reader::{FileReader, ImportAddress}, states::CompilerBaseState}, diagnostics::{self, Diagnostic, internal::{self, InternalError}}, lexer::{FragmentStream, FragmentationError, TokenizeError, Tokenizer, token::{PrimitiveTypeToken, SourceSpan, Token}}, lockfile::{LOCKFILE_NAME, Lockfile, ModuleChange}, manifest::MANIFEST_FILE_NAME, registry::INDEX_ENV_VAR, runtime::{RuntimeObject, environment::Environment}};
 
 #[derive(Debug)]
 pub struct CompilerError {
-    pub message: String,
+    pub kind: CompilerErrorKind,
+    // Where the error was found, if it was found while reading a file. Errors of the program as a
+    // whole, e.g. from linking its modules, have neither.
+    pub file: Option<String>,
+    pub span: Option<SourceSpan>,
+}
+
+// What went wrong, for hosts that handle some errors themselves. Errors defined by a host are
+// described by their message.
+#[derive(Debug)]
+pub enum CompilerErrorKind {
+    // 'found' is the token as printed in the message.
+    UnexpectedToken { expected: Option<String>, found: String },
+    UnbalancedBrackets,
+    UnexpectedClosingBrace,
+    UnclosedScope,
+    Fragmentation(FragmentationError),
+    Tokenization(TokenizeError),
+    // 'item' is what the name was missing for, e.g. "procedure".
+    MissingName { item: &'static str },
+    IncompleteStatement,
+    IncompleteForeach,
+    IncompleteExpression,
+    UnfinishedModule,
+    MisplacedElse,
+    MisplacedCatch,
+    MissingCatch,
+    BreakOutsideLoop,
+    LeadingBinaryOperator,
+    MissingOperand { operator: String },
+    MissingOperator,
+    EmptySubexpression,
+    MisplacedSpread,
+    NotPrefixOperator { operator: String },
+    NotBinaryOperator { operator: String },
+    AssignmentInExpression,
+    InvalidAddress,
+    UnresolvedAddress,
+    // The text of a literal that does not fit its type, e.g. an Integer too large for 64 bits.
+    InvalidLiteral { literal: String, expected: &'static str },
+    InvalidKeywordAlias { alias: String, keyword: String },
+    UnknownKeyword { keyword: String },
+    // Boxed to keep errors, which are returned through every compiler pass, small.
+    ImportNotFound { module: Box<ImportAddress>, path: PathBuf, reason: String },
+    // 'clause' is "alias" or "location".
+    DuplicateImportClause { clause: &'static str },
+    MissingImportLocation,
+    MissingModuleDeclaration { file: String },
+    // Every file along the cycle, starting and ending with the same one.
+    CircularImport { cycle: Vec<String> },
+    DuplicateModule { module_id: String },
+    DuplicateModuleVariable { identifier: String },
+    DuplicateField { struct_id: String, field: String },
+    MemberNotFound { member: String },
+    SharedModule { module_id: String, action: &'static str },
+    UndeniableCapability { module_id: String },
+    UnsupportedDecorator { name: String },
+    InvalidDecoratorArgument { found: String },
+    DecoratorArguments { name: String },
+    DuplicateEntrypoint { entrypoint: String },
+    InvalidBudget { name: String, found: String },
+    UnknownBudget { name: String },
+    EmptyBudget,
+    // 'operation' completes "Cannot ...", e.g. "add". Unary operations have no 'rhs'.
+    InvalidOperands { operation: &'static str, lhs: PrimitiveTypeToken, rhs: Option<PrimitiveTypeToken> },
+    // 'subject' is what has the wrong type, e.g. "condition".
+    ExpectedType { subject: &'static str, expected: PrimitiveTypeToken, found: PrimitiveTypeToken },
+    AtInstruction { instruction: usize, cause: Box<CompilerErrorKind> },
+    TypeMismatch { procedure: String, reason: String },
+    MissingReturn { instruction: usize },
+    InconsistentStackDepth { instruction: usize, depths: (usize, usize) },
+    StackUnderflow { instruction: usize },
+    // 'target' is the jump or catch target of the instruction.
+    TargetOutOfBounds { instruction: usize, target: usize, length: usize },
+    VerificationFailed { procedure: String, reason: String },
+    NotSaveable { item: String },
+    InvalidImage { reason: String },
+    // The version of otr the compiled program was written by.
+    VersionMismatch { version: String },
+    // 'subject' names the file or resource, 'action' completes "could not be ...", e.g. "read".
+    // Here and below, text is boxed where Strings would make every error larger.
+    Io { subject: Box<str>, action: &'static str, reason: Box<str> },
+    InvalidFile { subject: String, reason: String },
+    InvalidSetting { name: &'static str, expected: &'static str, found: Box<str> },
+    MissingSettingValue { flag: String },
+    MissingEntryModule,
+    MissingRemoteHash { url: String },
+    RemoteImportsDisabled { url: String },
+    HashMismatch { url: Box<str>, expected: Box<str>, found: Box<str> },
+    // The modules added, removed or changed since the entry module was locked.
+    LockfileMismatch { entry: String, changes: Vec<ModuleChange> },
+    MissingPackageIndex,
+    UnknownPackage { package: String, index: String },
+    PackageWithoutSource { package: String },
+    AlreadyExists { path: PathBuf },
+    CommandFailed { program: String, status: String },
+    Internal(String),
+    Other(String),
+}
+
+impl Display for CompilerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedToken { expected: Some(expected), found } => write!(f, "Unexpected token. Expected {}, found {}!", expected, found),
+            Self::UnexpectedToken { expected: None, found } => write!(f, "Unexpected token: {}", found),
+            Self::UnbalancedBrackets => write!(f, "Invalid parenthesis structure!"),
+            Self::UnexpectedClosingBrace => write!(f, "Invalid closing curly brace!"),
+            Self::UnclosedScope => write!(f, "Unclosed scope!"),
+            Self::Fragmentation(err) => write!(f, "Fragmentation error: {:?}", err),
+            Self::Tokenization(err) => write!(f, "Tokenization error: {:?}", err),
+            Self::MissingName { item } => write!(f, "Missing {} name!", item),
+            Self::IncompleteStatement => write!(f, "Incomplete instruction!"),
+            Self::IncompleteForeach => write!(f, "Incomplete foreach statement! Expected 'for item in collection {{'."),
+            Self::IncompleteExpression => write!(f, "Incomplete expression!"),
+            Self::UnfinishedModule => write!(f, "Unfinished module declaration!"),
+            Self::MisplacedElse => write!(f, "else-clauses can only extend 'if' clauses!"),
+            Self::MisplacedCatch => write!(f, "catch-clauses have to directly follow a 'try' block!"),
+            Self::MissingCatch => write!(f, "Missing catch-clause after 'try' block!"),
+            Self::BreakOutsideLoop => write!(f, "'break' can only be used inside of a loop!"),
+            Self::LeadingBinaryOperator => write!(f, "Expressions may not start with a binary operator!"),
+            Self::MissingOperand { operator } => write!(f, "Missing operand for '{}'!", operator),
+            Self::MissingOperator => write!(f, "Missing operator!"),
+            Self::EmptySubexpression => write!(f, "Found empty subexpression atom!"),
+            Self::MisplacedSpread => write!(f, "Struct spread has to be the last field!"),
+            Self::NotPrefixOperator { operator } => write!(f, "'{}' operator is not a prefix operator!", operator),
+            Self::NotBinaryOperator { operator } => write!(f, "'{}' operator is not a binary operator!", operator),
+            Self::AssignmentInExpression => write!(f, "Assignment operator disallowed in expressions!"),
+            Self::InvalidAddress => write!(f, "Address could not be parsed!"),
+            Self::UnresolvedAddress => write!(f, "Could not resolve variable's address!"),
+            Self::InvalidLiteral { literal, expected } => write!(f, "Could not parse '{}' as {}!", literal, expected),
+            Self::InvalidKeywordAlias { alias, keyword } => write!(f, "Invalid alias '{}' for keyword '{}'! Aliases have to be ASCII identifiers.", alias, keyword),
+            Self::UnknownKeyword { keyword } => write!(f, "Cannot alias unknown keyword '{}'!", keyword),
+            Self::ImportNotFound { module, path, reason } => write!(f, "Module '{}' could not be loaded from '{}'! {}", module, path.display(), reason),
+            Self::DuplicateImportClause { clause } => write!(f, "Cannot declare more than one {} for an import!", clause),
+            Self::MissingImportLocation => write!(f, "Unexpected String literal. Try adding 'from' to declare a location for an import!"),
+            Self::MissingModuleDeclaration { file } => write!(f, "Imported file '{}' does not declare a module!", file),
+            Self::CircularImport { cycle } => write!(f, "Circular import detected: {}!", cycle.join(" -> ")),
+            Self::DuplicateModule { module_id } => write!(f, "Module '{}' is declared more than once!", module_id),
+            Self::DuplicateModuleVariable { identifier } => write!(f, "Module variable '{}' is declared more than once!", identifier),
+            Self::DuplicateField { struct_id, field } => write!(f, "Field '{}' of struct '{}' is declared more than once!", field, struct_id),
+            Self::MemberNotFound { member } => write!(f, "Member '{}' not found!", member),
+            Self::SharedModule { module_id, action } => write!(f, "Module '{}' is shared and cannot be {}!", module_id, action),
+            Self::UndeniableCapability { module_id } => write!(f, "Capability '{}' cannot be denied! Only builtin modules can be sandboxed.", module_id),
+            Self::UnsupportedDecorator { name } => write!(f, "Unsupported decorator '{}'!", name),
+            Self::InvalidDecoratorArgument { found } => write!(f, "Invalid decorator argument {}! Expected 'name = literal'.", found),
+            Self::DecoratorArguments { name } => write!(f, "Decorator '{}' takes no arguments!", name),
+            Self::DuplicateEntrypoint { entrypoint } => write!(f, "Duplicate entrypoint! Entrypoint is already set to '{}'!", entrypoint),
+            Self::InvalidBudget { name, found } => write!(f, "Budget '{}' has to be a positive whole number, found {}!", name, found),
+            Self::UnknownBudget { name } => write!(f, "Unknown budget '{}'! Expected 'instructions' or 'allocations'.", name),
+            Self::EmptyBudget => write!(f, "Decorator 'budget' needs at least one of 'instructions' and 'allocations'!"),
+            Self::InvalidOperands { operation, lhs, rhs: Some(rhs) } => write!(f, "Cannot {} {:?} and {:?}!", operation, lhs, rhs),
+            Self::InvalidOperands { operation, lhs, rhs: None } => write!(f, "Cannot {} {:?}!", operation, lhs),
+            Self::ExpectedType { subject, expected, found } => write!(f, "Mismatched types for {}! Expected {:?}, found {:?}!", subject, expected, found),
+            Self::AtInstruction { instruction, cause } => write!(f, "Instruction {}: {}", instruction, cause),
+            Self::TypeMismatch { procedure, reason } => write!(f, "Type error in procedure '{}': {}", procedure, reason),
+            Self::MissingReturn { instruction } => write!(f, "Control flow reaches the end of the procedure at {} without returning!", instruction),
+            Self::InconsistentStackDepth { instruction, depths: (known, found) } => write!(f, "Inconsistent stack depth at instruction {}! Reached with depths {} and {}.", instruction, known, found),
+            Self::StackUnderflow { instruction } => write!(f, "Instruction {} shrinks the stack below its initial frame!", instruction),
+            Self::TargetOutOfBounds { instruction, target, length } => write!(f, "Instruction {} targets {}, which is out of bounds for {} instructions!", instruction, target, length),
+            Self::VerificationFailed { procedure, reason } => write!(f, "Verification of procedure '{}' failed: {}", procedure, reason),
+            Self::NotSaveable { item } => write!(f, "{} cannot be saved in a compiled program!", item),
+            Self::InvalidImage { reason } => write!(f, "Compiled program is invalid! {}", reason),
+            Self::VersionMismatch { version } => write!(f, "Compiled program was written by otr {}, but this is otr {}! Compile it again.", version, env!("CARGO_PKG_VERSION")),
+            Self::Io { subject, action, reason } => write!(f, "{} could not be {}! {}", subject, action, reason),
+            Self::InvalidFile { subject, reason } => write!(f, "{} is invalid! {}", subject, reason),
+            Self::InvalidSetting { name, expected, found } => write!(f, "{} must be {}, found '{}'!", name, expected, found),
+            Self::MissingSettingValue { flag } => write!(f, "{} expects a value!", flag),
+            Self::MissingEntryModule => write!(f, "No module given and {} declares no entry module!", MANIFEST_FILE_NAME),
+            Self::MissingRemoteHash { url } => write!(f, "Remote import '{}' has no content hash in {}!", url, MANIFEST_FILE_NAME),
+            Self::RemoteImportsDisabled { url } => write!(f, "'{}' cannot be downloaded as otr was built without the 'remote-imports' feature!", url),
+            Self::HashMismatch { url, expected, found } => write!(f, "Content hash mismatch for remote import '{}'! Expected {}, found {}.", url, expected, found),
+            Self::LockfileMismatch { entry, changes } => {
+                writeln!(f, "{} does not match the modules imported by '{}':", LOCKFILE_NAME, entry)?;
+
+                for change in changes {
+                    writeln!(f, "  {}", change)?;
+                }
+
+                write!(f, "Run 'otr lock {}' to accept the changes.", entry)
+            }
+            Self::MissingPackageIndex => write!(f, "No package index configured! Set {} or add a [registry] index to {}.", INDEX_ENV_VAR, MANIFEST_FILE_NAME),
+            Self::UnknownPackage { package, index } => write!(f, "Package '{}' is not listed in the index '{}'!", package, index),
+            Self::PackageWithoutSource { package } => write!(f, "Index entry for package '{}' has neither a git URL nor a tarball!", package),
+            Self::AlreadyExists { path } => write!(f, "'{}' already exists!", path.display()),
+            Self::CommandFailed { program, status } => write!(f, "{} failed with {}!", program, status),
+            Self::Internal(message) | Self::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl CompilerErrorKind {
+    // The code 'otr explain' describes this kind of error under, if it has one.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::UnbalancedBrackets | Self::UnexpectedClosingBrace | Self::UnclosedScope => Some("E0001"),
+            Self::UnexpectedToken { .. } | Self::MissingName { .. } | Self::IncompleteStatement | Self::IncompleteForeach |
+            Self::UnfinishedModule | Self::MissingImportLocation | Self::DuplicateImportClause { .. } => Some("E0002"),
+            Self::Fragmentation(_) | Self::Tokenization(_) | Self::InvalidLiteral { .. } => Some("E0003"),
+            Self::IncompleteExpression | Self::LeadingBinaryOperator | Self::MissingOperand { .. } | Self::MissingOperator |
+            Self::EmptySubexpression | Self::MisplacedSpread | Self::NotPrefixOperator { .. } | Self::NotBinaryOperator { .. } |
+            Self::AssignmentInExpression | Self::InvalidAddress => Some("E0004"),
+            Self::DuplicateEntrypoint { .. } => Some("E0005"),
+            Self::ImportNotFound { .. } | Self::MissingModuleDeclaration { .. } | Self::DuplicateModule { .. } => Some("E0006"),
+            Self::UnresolvedAddress | Self::MemberNotFound { .. } => Some("E0007"),
+            Self::VerificationFailed { .. } | Self::MissingReturn { .. } | Self::InconsistentStackDepth { .. } |
+            Self::StackUnderflow { .. } | Self::TargetOutOfBounds { .. } => Some("E0011"),
+            Self::TypeMismatch { .. } | Self::InvalidOperands { .. } | Self::ExpectedType { .. } => Some("E0012"),
+            Self::MisplacedElse | Self::MisplacedCatch | Self::MissingCatch | Self::BreakOutsideLoop => Some("E0013"),
+            Self::UndeniableCapability { .. } => Some("E0014"),
+            Self::UnsupportedDecorator { .. } | Self::InvalidDecoratorArgument { .. } | Self::DecoratorArguments { .. } |
+            Self::InvalidBudget { .. } | Self::UnknownBudget { .. } | Self::EmptyBudget => Some("E0016"),
+            Self::CircularImport { .. } => Some("E0017"),
+            Self::Internal(_) => Some("E0019"),
+            Self::AtInstruction { cause, .. } => cause.code(),
+            Self::InvalidKeywordAlias { .. } | Self::UnknownKeyword { .. } | Self::DuplicateModuleVariable { .. } |
+            Self::DuplicateField { .. } | Self::SharedModule { .. } | Self::NotSaveable { .. } | Self::InvalidImage { .. } |
+            Self::VersionMismatch { .. } | Self::Io { .. } | Self::InvalidFile { .. } | Self::InvalidSetting { .. } |
+            Self::MissingSettingValue { .. } | Self::MissingEntryModule | Self::MissingRemoteHash { .. } |
+            Self::RemoteImportsDisabled { .. } | Self::HashMismatch { .. } | Self::LockfileMismatch { .. } |
+            Self::MissingPackageIndex | Self::UnknownPackage { .. } | Self::PackageWithoutSource { .. } |
+            Self::AlreadyExists { .. } | Self::CommandFailed { .. } | Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<CompilerErrorKind> for CompilerError {
+    fn from(kind: CompilerErrorKind) -> Self {
+        Self { kind, file: None, span: None }
+    }
+}
+
+impl From<InternalError> for CompilerError {
+    fn from(err: InternalError) -> Self {
+        CompilerErrorKind::Internal(err.to_string()).into()
+    }
+}
+
+// 'src/main.otr:3:14: Unexpected token. ...', with as much of the location as is known.
+impl Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, &self.span) {
+            (Some(file), Some(span)) => write!(f, "{}:{}: ", file, span)?,
+            (Some(file), None) => write!(f, "{}: ", file)?,
+            (None, Some(span)) => write!(f, "{}: ", span)?,
+            (None, None) => {}
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Error for CompilerError {}
+
+impl CompilerError {
+    pub fn new(message: String) -> Self {
+        CompilerErrorKind::Other(message).into()
+    }
+
+    pub fn unexpected_token(expected: &str, found: impl Debug) -> Self {
+        CompilerErrorKind::UnexpectedToken { expected: Some(expected.to_string()), found: format!("{:?}", found) }.into()
+    }
+
+    pub fn unexpected(found: impl Debug) -> Self {
+        CompilerErrorKind::UnexpectedToken { expected: None, found: format!("{:?}", found) }.into()
+    }
+
+    // E.g. "Manifest 'otr.toml' could not be read! ...".
+    pub(crate) fn io(subject: String, action: &'static str, reason: impl Display) -> Self {
+        CompilerErrorKind::Io { subject: subject.into(), action, reason: reason.to_string().into() }.into()
+    }
+
+    pub(crate) fn invalid_file(subject: String, reason: impl Display) -> Self {
+        CompilerErrorKind::InvalidFile { subject, reason: reason.to_string() }.into()
+    }
+
+    // The error without its location.
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+
+    // Errors keep the innermost location they were given.
+    pub fn in_file(mut self, file: Option<&String>) -> Self {
+        self.file = self.file.or_else(|| file.cloned());
+        self
+    }
+
+    pub fn at(mut self, span: SourceSpan) -> Self {
+        self.span = self.span.or(Some(span));
+        self
+    }
+
+    // The stable code of this kind of error, see 'otr explain'.
+    pub fn code(&self) -> Option<&'static str> {
+        self.kind.code()
+    }
 }
 
 pub trait CompilerState {
     fn read(self: Box<Self>, token: Token, compiler_environment: &mut CompilerEnvironment) -> Result<Box<dyn CompilerState>, CompilerError>;
 
     fn finalize(self: Box<Self>) -> Result<Environment, CompilerError>;
+
+    fn accepts_documentation(&self) -> bool {
+        false
+    }
 }
 
 pub trait Decorator {
@@ -32,8 +330,68 @@ impl Compiler {
         }
     }
 
-    pub fn read(mut self, token: Token) -> Result<Self, CompilerError> {
-        self.state = self.state.read(token, &mut self.compiler_environment)?;
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.compiler_environment.verify = verify;
+        self
+    }
+
+    pub fn with_strict_conditions(mut self, strict_conditions: bool) -> Self {
+        self.compiler_environment.strict_conditions = strict_conditions;
+        self
+    }
+
+    // Removes a builtin module from the compiled program, e.g. to run untrusted scripts.
+    pub fn deny_capability(mut self, module_id: impl Into<String>) -> Self {
+        self.compiler_environment.denied_capabilities.push(module_id.into());
+        self
+    }
+
+    pub fn with_type_checking(mut self, type_check: bool) -> Self {
+        self.compiler_environment.type_check = type_check;
+        self
+    }
+
+    // Lets the entrypoint return the value of its last expression statement, as the REPL and
+    // 'otr eval' expect from snippets.
+    pub fn with_implicit_results(mut self, implicit_results: bool) -> Self {
+        self.compiler_environment.implicit_results = implicit_results;
+        self
+    }
+
+    // Whether the source can be compiled as it is, or still has unclosed brackets or literals
+    // and more input is expected. Malformed input counts as complete so it gets reported.
+    pub fn is_input_complete(&self, source: &str) -> bool {
+        let fragments = match FragmentStream::from_str(source) {
+            Ok(fragments) => fragments,
+            Err(FragmentationError::UnterminatedLiteral { .. }) => return false,
+            Err(_) => return true,
+        };
+
+        self.tokenizer
+            .tokenize(fragments)
+            .map(|tokens| match ExpressionParser::unclosed_brackets(tokens.0.iter().map(|(token, _)| token)) {
+                Ok(unclosed) => unclosed == 0,
+                Err(_) => true,
+            })
+            .unwrap_or(true)
+    }
+
+    // The span is where the token was read from, available to the states while they read it.
+    pub fn read(mut self, token: Token, span: SourceSpan) -> Result<Self, CompilerError> {
+        self.compiler_environment.current_span = span;
+
+        if let Token::DocComment(_) = token {
+            if !self.state.accepts_documentation() {
+                return Ok(self);
+            }
+        }
+
+        self.state = self.state.read(token, &mut self.compiler_environment).map_err(|err| err.at(span))?;
         Ok(self)
     }
 
@@ -42,6 +400,18 @@ impl Compiler {
 
         runtime_object.base_environement = self.state.finalize()?;
 
+        for source in &self.compiler_environment.imported_sources {
+            if !self.compiler_environment.source_modules.contains_key(source) {
+                return Err(CompilerErrorKind::MissingModuleDeclaration { file: source.clone() }.into());
+            }
+        }
+
+        runtime_object.base_environement.link_modules(&self.compiler_environment.source_modules)?;
+
+        for module_id in &self.compiler_environment.denied_capabilities {
+            runtime_object.base_environement.deny_capability(module_id)?;
+        }
+
         for decorator in self.compiler_environment.decorators {
             decorator.apply(&mut runtime_object)?;
         }
@@ -49,24 +419,44 @@ impl Compiler {
         Ok(runtime_object)
     }
 
-    pub fn compile(mut self) -> Result<RuntimeObject, CompilerError> {
-        while let Some(next_module) = self.compiler_environment.file_reader.dequeue()? {
+    pub fn compile(self) -> Result<RuntimeObject, CompilerError> {
+        self.compile_locked().map(|(runtime_object, _)| runtime_object)
+    }
+
+    // Compiles the program and returns the lockfile describing every module that went into it.
+    pub fn compile_locked(self) -> Result<(RuntimeObject, Lockfile), CompilerError> {
+        internal::guard("compiling", || self.compile_sources()).unwrap_or_else(|err| Err(err.into()))
+    }
+
+    // Like `compile`, but reports the error as a diagnostic.
+    pub fn check(self) -> Result<RuntimeObject, Diagnostic> {
+        self.compile().map_err(|err| Diagnostic {
+            span: err.span.map(|span| diagnostics::Span { line: span.line, column: span.column }),
+            ..Diagnostic::error(err.file.clone(), err.message(), err.code())
+        })
+    }
+
+    fn compile_sources(mut self) -> Result<(RuntimeObject, Lockfile), CompilerError> {
+        while let Some((_, next_module)) = self.compiler_environment.file_reader.dequeue()? {
+            let file = self.compiler_environment.file_reader.current_file().cloned();
+
+            self.compiler_environment.begin_source(file.clone().unwrap_or_default());
+
             let fragments = FragmentStream::from_str(&next_module)
-                .map_err(|err| CompilerError {
-                    message: format!("Fragmentation error: {:?}", err)
-                })?;
+                .map_err(|err| CompilerError::from(CompilerErrorKind::Fragmentation(err)).in_file(file.as_ref()))?;
             
             let tokens = self.tokenizer.tokenize(fragments)
-                .map_err(|err| CompilerError {
-                    message: format!("Tokenization error: {:?}", err)
-                })?;
+                .map_err(|err| CompilerError::from(CompilerErrorKind::Tokenization(err)).in_file(file.as_ref()))?;
             
-            for token in tokens {
-                self = self.read(token)?;
+            for (token, span) in tokens {
+                self = self.read(token, span).map_err(|err| err.in_file(file.as_ref()))?;
             }
         }
 
-        self.finalize()
+        self.compiler_environment.file_reader.verify_lockfile()?;
+        let lockfile = self.compiler_environment.file_reader.lock();
+
+        Ok((self.finalize()?, lockfile))
     }
 }
 
@@ -74,6 +464,22 @@ pub struct CompilerEnvironment {
     decorators: Vec<Box<dyn Decorator>>,
 
     file_reader: FileReader,
+
+    verify: bool,
+    type_check: bool,
+    strict_conditions: bool,
+    implicit_results: bool,
+    denied_capabilities: Vec<String>,
+
+    // Aliases declared by the imports of the file currently being compiled.
+    file_aliases: HashMap<String, String>,
+    // Aliases of the current file declared with 'export import'.
+    file_reexports: HashSet<String>,
+    pub(crate) current_source: Option<String>,
+    // The span of the token being read, in the current source.
+    current_span: SourceSpan,
+    imported_sources: HashSet<String>,
+    source_modules: HashMap<String, String>,
 }
 
 impl CompilerEnvironment {
@@ -81,9 +487,81 @@ impl CompilerEnvironment {
         Self {
             decorators: Vec::new(),
             file_reader,
+            verify: cfg!(debug_assertions),
+            type_check: false,
+            strict_conditions: true,
+            implicit_results: false,
+            denied_capabilities: Vec::new(),
+
+            file_aliases: HashMap::new(),
+            file_reexports: HashSet::new(),
+            current_source: None,
+            current_span: SourceSpan::default(),
+            imported_sources: HashSet::new(),
+            source_modules: HashMap::new(),
         }
     }
 
+    pub(crate) fn import(&mut self, alias: String, address: ImportAddress) -> Result<(), CompilerError> {
+        if address.path.is_none() && Environment::is_builtin_module(&address.module_id) {
+            self.file_aliases.insert(alias, address.module_id);
+            return Ok(());
+        }
+
+        let source = self.file_reader.import(address)?;
+
+        self.imported_sources.insert(source.clone());
+        self.file_aliases.insert(alias, source);
+
+        Ok(())
+    }
+
+    pub(crate) fn file_aliases(&self) -> &HashMap<String, String> {
+        &self.file_aliases
+    }
+
+    pub(crate) fn reexport(&mut self, alias: String) {
+        self.file_reexports.insert(alias);
+    }
+
+    pub(crate) fn file_reexports(&self) -> &HashSet<String> {
+        &self.file_reexports
+    }
+
+    // Registers the id of a module declared in the current file. The first module declared in a
+    // file is the one its importers refer to.
+    pub(crate) fn declare_module(&mut self, module_id: String) {
+        if let Some(source) = &self.current_source {
+            self.source_modules.entry(source.clone()).or_insert(module_id);
+        }
+    }
+
+    fn begin_source(&mut self, source: String) {
+        self.file_aliases.clear();
+        self.file_reexports.clear();
+        self.current_source = Some(source);
+    }
+
+    pub fn current_span(&self) -> SourceSpan {
+        self.current_span
+    }
+
+    pub fn verifies(&self) -> bool {
+        self.verify
+    }
+
+    pub fn type_checks(&self) -> bool {
+        self.type_check
+    }
+
+    pub fn strict_conditions(&self) -> bool {
+        self.strict_conditions
+    }
+
+    pub fn implicit_results(&self) -> bool {
+        self.implicit_results
+    }
+
     pub fn push_decorator(&mut self, decorator: Box<dyn Decorator>) {
         self.decorators.push(decorator);
     }
@@ -100,4 +578,10 @@ impl CompilerEnvironment {
 pub mod states;
 pub mod expression_parser;
 pub mod decorators;
-pub mod file_reader;
\ No newline at end of file
+pub mod file_reader;
+pub mod remote;
+pub mod verifier;
+pub mod type_checker;
+pub mod optimizer;
+pub mod ast;
+pub mod lowering;
\ No newline at end of file
@@ -0,0 +1,115 @@
+use crate::runtime::{ModuleAddress, Value, expressions::arithmetic::Comparison, symbol::Symbol};
+
+// The syntax tree of procedure bodies, as the parser reads it from tokens. Nothing is resolved
+// or checked yet beyond the syntax, 'lowering' turns it into instructions and runtime
+// expressions.
+
+// The tokens a node was read from, as an exclusive range of positions. Statements and their
+// expressions count from the first token of the procedure body, expressions parsed on their own
+// from their own first token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    // The span from the start of this one to the end of the other.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExpressionKind {
+    Literal(Value),
+    Variable(Address),
+    Reference(Address),
+    Clone(Address),
+    // 'Main::points[0]'
+    ModuleVariable { module_id: String, address: Address },
+    Call { procedure_id: ModuleAddress, arguments: Vec<Expression> },
+    // 'Main::Point { x: 1, ..origin }'
+    StructConstruction { struct_id: ModuleAddress, fields: Vec<(Symbol, Expression)>, base: Option<Box<Expression>> },
+    Array(Vec<Expression>),
+    Prefix { operator: PrefixOperator, operand: Box<Expression> },
+    Binary { operator: BinaryOperator, lhs: Box<Expression>, rhs: Box<Expression> },
+    // 'a < b <= c', comparing every operand with the next one.
+    ChainedComparison { operands: Vec<Expression>, comparisons: Vec<Comparison> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixOperator {
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    EuclideanModulo,
+    Power,
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterEquals,
+    LessEquals,
+}
+
+// A variable and the fields and indices following it, e.g. 'points[i].x'.
+#[derive(Debug)]
+pub struct Address {
+    pub variable: Symbol,
+    pub accessors: Vec<Accessor>,
+}
+
+#[derive(Debug)]
+pub enum Accessor {
+    Field(Symbol),
+    Index(Expression),
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum StatementKind {
+    Let { identifier: Symbol, value: Option<Expression> },
+    Assign { target: Address, value: Expression },
+    // 'Main::count = Main::count + 1;'
+    AssignModuleVariable { module_id: String, target: Address, value: Expression },
+    Expression(Expression),
+    If { condition: Expression, body: Vec<Statement>, otherwise: Option<Vec<Statement>> },
+    While { condition: Expression, body: Vec<Statement> },
+    Loop { body: Vec<Statement> },
+    For { item: Symbol, collection: Expression, body: Vec<Statement> },
+    Break,
+    Return(Option<Expression>),
+    Try { body: Vec<Statement>, identifier: Symbol, handler: Vec<Statement> },
+    Throw(Expression),
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{compiler::{CompilerError, Decorator}, lexer::token::Token, runtime::{ModuleAddress, RuntimeObject}};
+use crate::{compiler::{CompilerError, CompilerErrorKind, Decorator, states::decorator::RawDecorator}, lexer::token::LiteralToken, runtime::{ModuleAddress, RuntimeObject, procedures::CallBudget}};
 
 pub struct EntrypointDecorator {
     procedure_id: ModuleAddress
@@ -14,13 +14,38 @@ impl EntrypointDecorator {
 
 impl Decorator for EntrypointDecorator {
     fn apply(self: Box<Self>, runtime_object: &mut RuntimeObject) -> Result<(), CompilerError> {
-        if runtime_object.entrypoint.is_some() {
-            Err(CompilerError {
-                message: format!("Duplicate entrypoint! Entrypoint is already set to {:?}!", runtime_object.entrypoint)
-            })
+        if let Some(entrypoint) = &runtime_object.entrypoint {
+            Err(CompilerErrorKind::DuplicateEntrypoint { entrypoint: entrypoint.to_string() }.into())
         } else {
             runtime_object.entrypoint = Some(self.procedure_id);
             Ok(())
         }
     }
-}
\ No newline at end of file
+}
+// Reads '@budget(instructions = ..., allocations = ...)'. At least one limit has to be given.
+pub fn call_budget(decorator: &RawDecorator) -> Result<CallBudget, CompilerError> {
+    let mut budget = CallBudget::default();
+
+    for (name, value) in decorator.get_arguments() {
+        let limit = match value {
+            LiteralToken::Integer(limit) => limit.parse::<u64>().map_err(|_| invalid_budget(name, limit.clone()))?,
+            other => return Err(invalid_budget(name, format!("{:?}", other))),
+        };
+
+        match name as &str {
+            "instructions" => budget.instructions = Some(limit),
+            "allocations" => budget.allocations = Some(limit),
+            other => return Err(CompilerErrorKind::UnknownBudget { name: other.to_string() }.into()),
+        }
+    }
+
+    if budget == CallBudget::default() {
+        return Err(CompilerErrorKind::EmptyBudget.into());
+    }
+
+    Ok(budget)
+}
+
+fn invalid_budget(name: &str, found: String) -> CompilerError {
+    CompilerErrorKind::InvalidBudget { name: name.to_string(), found }.into()
+}
@@ -1,108 +1,125 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{iter::Peekable, vec::IntoIter};
 
-use crate::{compiler::CompilerError, lexer::token::{KeywordToken, OperatorToken, ParenthesisType, PunctuationToken, Token}, runtime::{Expression, ModuleAddress, scope::{ScopeAddress, ScopeAddressant}, Value, expressions::{CloneExpression, EqualityExpression, ProcedureCallExpression, ReferenceExpression, StructConstructionExpression, VariableExpression, arithmetic::{AddExpression, DivideExpression, GreaterThanExpression, ModuloExpression, MultiplyExpression, PowerExpression, SubtractExpression}, boolean::{AndExpression, NotExpression, OrExpression}}}};
+use crate::{compiler::{CompilerError, CompilerErrorKind, ast::{self, Accessor, Address, BinaryOperator, ExpressionKind, PrefixOperator, Span}, lowering}, lexer::token::{KeywordToken, LiteralToken, OperatorToken, ParenthesisType, PunctuationToken, Token}, runtime::{Expression, ModuleAddress, symbol::Symbol, Value, expressions::arithmetic::Comparison}};
+
+// Builtin procedures that can be called without naming their module.
+const PRELUDE: &[(&str, &str)] = &[
+    ("panic", "Assert"),
+];
 
 #[derive(Debug)]
 pub enum ExpressionAtom {
-    Subexpression(Box<dyn Expression>),
-    Operator(OperatorToken)
-}
-
-impl ExpressionAtom {
-    fn unwrap_subexpression(self) -> Box<dyn Expression> {
-        match self {
-            ExpressionAtom::Subexpression(expression) => expression,
-            ExpressionAtom::Operator(_) => panic!("Called unwrap on non subexpression!"),
-        }
-    }
+    Subexpression(ast::Expression),
+    // The operator and its position.
+    Operator(OperatorToken, usize),
 }
 
 #[derive(Debug)]
 pub enum RawExpressionAtom {
-    Subexpression(Vec<Token>),
-    Operator(OperatorToken),
+    Subexpression(Vec<Token>, Span),
+    Operator(OperatorToken, usize),
 }
 
 pub struct ExpressionParser;
 
 impl ExpressionParser {
     pub fn parse(expression: impl IntoIterator<Item = Token>) -> Result<Box<dyn Expression>, CompilerError> {
-        let atoms = Self::atomize(expression)?;
+        Ok(lowering::lower_expression(Self::parse_syntax(expression)?))
+    }
 
-        let mut operator_order = Vec::new();
-        for i in 0..atoms.len() {
-            if let ExpressionAtom::Operator(operator) = &atoms[i] {
-                operator_order.push((Self::get_precedence(operator), i));
+    // The syntax tree of the expression, its spans counting from its first token.
+    pub fn parse_syntax(expression: impl IntoIterator<Item = Token>) -> Result<ast::Expression, CompilerError> {
+        Self::parse_at(expression, 0)
+    }
+
+    // Like 'parse_syntax', for tokens found at the given position.
+    pub fn parse_at(expression: impl IntoIterator<Item = Token>, start: usize) -> Result<ast::Expression, CompilerError> {
+        let mut atoms = Self::atomize(expression, start)?.into_iter().peekable();
+
+        Self::parse_binding(&mut atoms, 0)
+    }
+
+    // Parses operands joined by the operators that bind at least as tightly as the minimum and
+    // leaves the first operator binding looser to the caller.
+    fn parse_binding(atoms: &mut Peekable<IntoIter<ExpressionAtom>>, min_binding_power: u8) -> Result<ast::Expression, CompilerError> {
+        let mut lhs = match atoms.next() {
+            Some(ExpressionAtom::Subexpression(expression)) => expression,
+            Some(ExpressionAtom::Operator(operator, position)) => {
+                let Some(binding_power) = Self::prefix_binding_power(&operator) else {
+                    return Err(CompilerErrorKind::LeadingBinaryOperator.into());
+                };
+
+                let has_operand = match atoms.peek() {
+                    Some(ExpressionAtom::Subexpression(_)) => true,
+                    Some(ExpressionAtom::Operator(next, _)) => Self::prefix_binding_power(next).is_some(),
+                    None => false,
+                };
+                if !has_operand {
+                    return Err(CompilerErrorKind::MissingOperand { operator: "!".into() }.into());
+                }
+
+                Self::resolve_prefix_operator(&operator, position, Self::parse_binding(atoms, binding_power)?)?
             }
-        }
-        operator_order.sort_by_key(|(precedence, _i)| usize::MAX - *precedence);
-
-        let mut atoms = atoms
-            .into_iter()
-            .map(|atom| Some(atom))
-            .collect::<Vec<_>>();
-
-        for i in 0..operator_order.len() {
-            if let Some(ExpressionAtom::Operator(op)) = atoms[operator_order[i].1].take() {
-                match op {
-                    OperatorToken::Not => {
-                        if let Some(ExpressionAtom::Subexpression(subexpr)) = atoms[operator_order[i].1 + 1].take() {
-                            let splice = vec![Some(ExpressionAtom::Subexpression(
-                                Box::new(NotExpression::new(subexpr))
-                            ))];
-
-                            atoms.splice(i..=i+1, splice);
-
-                            for operator in &mut operator_order {
-                                if operator.1 > i {
-                                    *operator = (operator.0, operator.1 - 2);
-                                }
-                            }
-                        }
-                    }
+            None => return Err(CompilerErrorKind::IncompleteExpression.into()),
+        };
 
-                    op => {
-                        if operator_order[i].1 == 0 {
-                            return Err(CompilerError { message: "Expressions may not start with a binary operator!".into() });
-                        }
-                        if let (
-                            Some(ExpressionAtom::Subexpression(lhs)),
-                            Some(ExpressionAtom::Subexpression(rhs))
-                        ) = (
-                            atoms[operator_order[i].1 - 1].take(),
-                            atoms[operator_order[i].1 + 1].take()
-                        ) {
-                            let splice = vec![Some(ExpressionAtom::Subexpression(
-                                Self::resolve_binary_operator(&op, lhs, rhs)?
-                            ))];
-                            let op_index = operator_order[i].1;
-
-                            atoms.splice(
-                                (operator_order[i].1 - 1)..=(operator_order[i].1 + 1),
-                                splice
-                            );
-
-                            for operator in &mut operator_order {
-                                if operator.1 > op_index {
-                                    *operator = (operator.0, operator.1 - 2);
-                                }
-                            }
-                        }
-                    }
+        // Postfix operators would be applied here, before looking for an infix one.
+        loop {
+            let (left_binding_power, right_binding_power) = match atoms.peek() {
+                Some(ExpressionAtom::Operator(operator, _)) => Self::infix_binding_power(operator).ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingOperator))?,
+                Some(ExpressionAtom::Subexpression(_)) => return Err(CompilerErrorKind::MissingOperator.into()),
+                None => break,
+            };
+
+            if left_binding_power < min_binding_power {
+                break;
+            }
+
+            let Some(ExpressionAtom::Operator(operator, _)) = atoms.next() else {
+                unreachable!();
+            };
+
+            let rhs = Self::parse_binding(atoms, right_binding_power)?;
+
+            if Self::get_comparison(&operator).is_none() {
+                lhs = Self::resolve_binary_operator(&operator, lhs, rhs)?;
+                continue;
+            }
+
+            // 'a < b < c' compares every operand with the next one rather than 'a < b' with 'c'.
+            let mut operands = vec![lhs, rhs];
+            let mut comparisons = vec![operator];
+
+            while let Some(ExpressionAtom::Operator(next, _)) = atoms.peek() {
+                if Self::get_comparison(next).is_none() {
+                    break;
                 }
+                let Some(ExpressionAtom::Operator(next, _)) = atoms.next() else {
+                    unreachable!();
+                };
 
-            } else {
-                Err(CompilerError {
-                    message: "Missing operator!".into()
-                })?;
+                operands.push(Self::parse_binding(atoms, right_binding_power)?);
+                comparisons.push(next);
             }
+
+            lhs = if comparisons.len() == 1 {
+                let rhs = operands.pop().unwrap();
+                let lhs = operands.pop().unwrap();
+
+                Self::resolve_binary_operator(&comparisons[0], lhs, rhs)?
+            } else {
+                let span = operands[0].span.to(operands[operands.len() - 1].span);
+                let comparisons = comparisons.iter().filter_map(Self::get_comparison).collect();
+
+                ast::Expression::new(ExpressionKind::ChainedComparison { operands, comparisons }, span)
+            };
         }
 
-        Ok(atoms[0].take().unwrap().unwrap_subexpression())
+        Ok(lhs)
     }
 
-    pub fn atomize(expression: impl IntoIterator<Item = Token>) -> Result<Vec<ExpressionAtom>, CompilerError> {
-        let raw_atoms = Self::split(expression)?;
+    pub fn atomize(expression: impl IntoIterator<Item = Token>, start: usize) -> Result<Vec<ExpressionAtom>, CompilerError> {
+        let raw_atoms = Self::split(expression, start)?;
 
         let mut atoms = Vec::new();
 
@@ -150,16 +167,14 @@ impl ExpressionParser {
                             match p {
                                 ParenthesisType::Opening => stack.push(punct),
                                 ParenthesisType::Closing => {
-                                    let top = stack.pop().ok_or(CompilerError {
-                                        message: "Invalid parenthesis structure!".into()
-                                    })?;
+                                    let top = stack.pop().ok_or_else(|| CompilerError::from(CompilerErrorKind::UnbalancedBrackets))?;
 
                                     match (&top, &punct) {
                                         (Parenthesis(_), Parenthesis(_)) |
                                         (SquareBrackets(_), SquareBrackets(_)) |
                                         (CurlyBraces(_), CurlyBraces(_)) => {}
                                         _ => {
-                                            return Err(CompilerError { message: "Invalid parenthesis structure!".into() });
+                                            return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets));
                                         }                                        
                                     }
                                 },
@@ -179,15 +194,57 @@ impl ExpressionParser {
         }
 
         if !stack.is_empty() {
-            return Err(CompilerError {
-                message: "Invalid parenthesis structure!".into()
-            });
+            return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets));
         }
 
         Ok(slice)
     }
 
 
+    // Pushes opening brackets onto the stack and pops them again for their matching closing bracket.
+    fn track_bracket(stack: &mut Vec<PunctuationToken>, punct: &PunctuationToken) -> Result<(), CompilerError> {
+        use PunctuationToken::*;
+
+        match punct {
+            Parenthesis(p) |
+            SquareBrackets(p) |
+            CurlyBraces(p) => {
+                match p {
+                    ParenthesisType::Opening => stack.push(punct.clone()),
+                    ParenthesisType::Closing => {
+                        let top = stack.pop().ok_or_else(|| CompilerError::from(CompilerErrorKind::UnbalancedBrackets))?;
+
+                        match (&top, punct) {
+                            (Parenthesis(_), Parenthesis(_)) |
+                            (SquareBrackets(_), SquareBrackets(_)) |
+                            (CurlyBraces(_), CurlyBraces(_)) => {}
+                            _ => {
+                                return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets));
+                            }
+                        }
+                    },
+                }
+            }
+
+            _ => {}
+        };
+
+        Ok(())
+    }
+
+    // The number of brackets left open at the end of the tokens, e.g. while a procedure is still being typed.
+    pub fn unclosed_brackets<'a>(tokens: impl IntoIterator<Item = &'a Token>) -> Result<usize, CompilerError> {
+        let mut stack = Vec::new();
+
+        for token in tokens {
+            if let Token::Punctuation(punct) = token {
+                Self::track_bracket(&mut stack, punct)?;
+            }
+        }
+
+        Ok(stack.len())
+    }
+
     pub fn split_by_commas(tokens: impl IntoIterator<Item = Token>) -> Result<Vec<Vec<Token>>, CompilerError> {
 
         let mut iter = tokens.into_iter();
@@ -199,34 +256,8 @@ impl ExpressionParser {
         let mut stack = Vec::new();        
 
         while let Some(next) = iter.next() {
-            if let Token::Punctuation(punct) = next.clone() {
-                use PunctuationToken::*;
-
-                match &punct {
-                    Parenthesis(p) |
-                    SquareBrackets(p) |
-                    CurlyBraces(p) => {
-                        match p {
-                            ParenthesisType::Opening => stack.push(punct),
-                            ParenthesisType::Closing => {
-                                let top = stack.pop().ok_or(CompilerError {
-                                    message: "Invalid parenthesis structure!".into()
-                                })?;
-
-                                match (&top, &punct) {
-                                    (Parenthesis(_), Parenthesis(_)) |
-                                    (SquareBrackets(_), SquareBrackets(_)) |
-                                    (CurlyBraces(_), CurlyBraces(_)) => {}
-                                    _ => {
-                                        return Err(CompilerError { message: "Invalid parenthesis structure!".into() });
-                                    }                                        
-                                }
-                            },
-                        }
-                    }
-
-                    _ => {}
-                };
+            if let Token::Punctuation(punct) = &next {
+                Self::track_bracket(&mut stack, punct)?;
             }
 
             if let Token::Punctuation(PunctuationToken::Comma) = next {
@@ -247,53 +278,57 @@ impl ExpressionParser {
         Ok(slices)
     }
 
-    pub fn split(tokens: impl IntoIterator<Item = Token>) -> Result<Vec<RawExpressionAtom>, CompilerError> {
-        let mut tokens = tokens.into_iter();
+    pub fn split(tokens: impl IntoIterator<Item = Token>, start: usize) -> Result<Vec<RawExpressionAtom>, CompilerError> {
+        let mut tokens = tokens.into_iter().peekable();
 
         let mut atoms = Vec::new();
         let mut current_subexpression = Vec::new();
 
+        // The position of the next token, and of the first one of the current subexpression
+        let mut position = start;
+        let mut subexpression_start = start;
+
         let mut stack = Vec::new();   
 
         while let Some(next) = tokens.next() {
+            let index = position;
+            position += 1;
+
             match next.clone() {
                 Token::Punctuation(punct) => {
-                    use PunctuationToken::*;
-
-                    match &punct {
-                        Parenthesis(p) |
-                        SquareBrackets(p) |
-                        CurlyBraces(p) => {
-                            match p {
-                                ParenthesisType::Opening => stack.push(punct),
-                                ParenthesisType::Closing => {
-                                    let top = stack.pop().ok_or(CompilerError {
-                                        message: "Invalid parenthesis structure!".into()
-                                    })?;
+                    Self::track_bracket(&mut stack, &punct)?;
+                }
 
-                                    match (&top, &punct) {
-                                        (Parenthesis(_), Parenthesis(_)) |
-                                        (SquareBrackets(_), SquareBrackets(_)) |
-                                        (CurlyBraces(_), CurlyBraces(_)) => {}
-                                        _ => {
-                                            return Err(CompilerError { message: "Invalid parenthesis structure!".into() });
-                                        }                                        
-                                    }
-                                },
+                Token::Operator(operator) => {
+                    // A minus without a left operand negates the number literal following it, e.g. 'arr[-1]'
+                    let is_prefix = current_subexpression.is_empty()
+                        && matches!(atoms.last(), None | Some(RawExpressionAtom::Operator(..)));
+
+                    if operator == OperatorToken::Minus && is_prefix {
+                        match tokens.peek() {
+                            Some(Token::Literal(LiteralToken::Integer(number))) => {
+                                current_subexpression.push(Token::Literal(LiteralToken::Integer(format!("-{}", number))));
+                                tokens.next();
+                                position += 1;
+                                continue;
                             }
+                            Some(Token::Literal(LiteralToken::Decimal(number))) => {
+                                current_subexpression.push(Token::Literal(LiteralToken::Decimal(format!("-{}", number))));
+                                tokens.next();
+                                position += 1;
+                                continue;
+                            }
+                            _ => {}
                         }
+                    }
 
-                        _ => {}
-                    };
-                }
-
-                Token::Operator(operator) => {
                     if stack.is_empty() {
                         if !current_subexpression.is_empty() {
-                            atoms.push(RawExpressionAtom::Subexpression(current_subexpression));
+                            atoms.push(RawExpressionAtom::Subexpression(current_subexpression, Span::new(subexpression_start, index)));
                         }
                         current_subexpression = Vec::new();
-                        atoms.push(RawExpressionAtom::Operator(operator));
+                        subexpression_start = position;
+                        atoms.push(RawExpressionAtom::Operator(operator, index));
                         continue;
                     }
                 }
@@ -303,189 +338,262 @@ impl ExpressionParser {
             current_subexpression.push(next);
         }
 
-        atoms.push(RawExpressionAtom::Subexpression(current_subexpression));
+        atoms.push(RawExpressionAtom::Subexpression(current_subexpression, Span::new(subexpression_start, position)));
 
         Ok(atoms)
     }
 
     fn parse_raw_atom(atom: RawExpressionAtom) -> Result<ExpressionAtom, CompilerError> {
         match atom {
-            RawExpressionAtom::Operator(operator) => Ok(ExpressionAtom::Operator(operator)),
-            RawExpressionAtom::Subexpression(tokens) => {
-                // Epmpty
-                if tokens.len() == 0 {
-                    return Err(CompilerError {
-                        message: "Found empty subexpression atom!".into()
-                    });
+            RawExpressionAtom::Operator(operator, position) => Ok(ExpressionAtom::Operator(operator, position)),
+            RawExpressionAtom::Subexpression(tokens, span) => Ok(ExpressionAtom::Subexpression(Self::parse_operand(tokens, span)?)),
+        }
+    }
+
+    // An operand between operators, e.g. a literal, a variable or a procedure call.
+    fn parse_operand(tokens: Vec<Token>, span: Span) -> Result<ast::Expression, CompilerError> {
+        // Epmpty
+        if tokens.is_empty() {
+            return Err(CompilerErrorKind::EmptySubexpression.into());
+        }
+
+        // Single token
+        if tokens.len() == 1 {
+            let token = &tokens[0];
+            return match token {
+                Token::Literal(literal) => {
+                    Ok(ast::Expression::new(ExpressionKind::Literal(Value::try_from(literal.to_owned())?), span))
                 }
+                Token::Identifier(ident) => {
+                    let address = Address { variable: Symbol::intern(ident), accessors: Vec::new() };
 
-                // Single token
-                if tokens.len() == 1 {
-                    let token = &tokens[0];
-                    match token {
-                        Token::Literal(literal) => {
-                            return Ok(ExpressionAtom::Subexpression(Box::new(Value::try_from(literal.to_owned())?)))
-                        }
-                        Token::Identifier(ident) => {
-                            return Ok(ExpressionAtom::Subexpression(Box::new(VariableExpression {
-                                variable_address: vec![ScopeAddressant::Identifier(ident.to_owned())]
-                                    .try_into()
-                                    .map_err(|_| CompilerError {
-                                        message: format!("Could not resolve identifier '{}'!", ident)
-                                    })?
-                            })))
-                        }
-                        _ => {
-                            return Err(CompilerError {
-                                message: format!("Unexpected token. Expected literal or identifier, found {:?}", token)
-                            });
-                        }
-                    }
+                    Ok(ast::Expression::new(ExpressionKind::Variable(address), span))
                 }
+                _ => {
+                    Err(CompilerError::unexpected_token("literal or identifier", token))
+                }
+            };
+        }
 
-                if let Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening)) = tokens[0] {
-                    let mut tokens = tokens.into_iter().skip(1);
-                    let subexpression = Self::take_until_closing(
-                        &mut tokens,
-                        Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing))
-                    )?;
+        if let Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening)) = tokens[0] {
+            let mut tokens = tokens.into_iter().skip(1);
+            let subexpression = Self::take_until_closing(
+                &mut tokens,
+                Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing))
+            )?;
 
-                    if let Some(token) = tokens.next() {
-                        Err(CompilerError {
-                            message: format!("Unexpected token. Expected operator, found {:?}", token)
-                        })?;
-                    }
+            if let Some(token) = tokens.next() {
+                Err(CompilerError::unexpected_token("operator", token))?;
+            }
+
+            // The parentheses belong to the span of the expression within them
+            let mut expression = Self::parse_at(subexpression, span.start + 1)?;
+            expression.span = span;
+
+            return Ok(expression);
+        }
+
+        if let Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Opening)) = tokens[0] {
+            let mut tokens = tokens.into_iter().skip(1);
+            let elements = Self::take_until_closing(
+                &mut tokens,
+                Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Closing))
+            )?;
+
+            if let Some(token) = tokens.next() {
+                Err(CompilerError::unexpected_token("operator", token))?;
+            }
+
+            return Ok(ast::Expression::new(ExpressionKind::Array(Self::parse_list(elements, span.start + 1)?), span));
+        }
 
-                    return Ok(ExpressionAtom::Subexpression(Self::parse(subexpression)?));
+        // Prelude procedure
+        if let (
+            Token::Identifier(ident),
+            Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening))
+        ) = (&tokens[0], &tokens[1]) {
+            if let Some((_, module_id)) = PRELUDE.iter().find(|(name, _)| name == ident) {
+                return Self::parse_member(module_id.to_string(), tokens, span.start, span);
+            }
+        }
+
+        match &tokens[0] {
+            Token::Identifier(base_ident) => {
+                // Member of a module
+                if let Token::Punctuation(PunctuationToken::DoubleColon) = tokens[1] {
+                    let module_id = base_ident.clone();
+
+                    return Self::parse_member(module_id, tokens.into_iter().skip(2), span.start + 2, span);
                 }
 
+                Ok(ast::Expression::new(ExpressionKind::Variable(Self::parse_variable_address(tokens, span.start)?), span))
+            }
+            Token::Keyword(KeywordToken::Ref) => {
+                let address = Self::parse_address(tokens.into_iter().skip(1), span.start + 1)?;
 
-                let base_ident = tokens[0].to_owned();
-                match base_ident {
-                    Token::Identifier(base_ident) => {
-                        let first_separator = tokens[1].to_owned();
-
-                        // Member of a module
-                        if let Token::Punctuation(PunctuationToken::DoubleColon) = first_separator {
-                            let mut tokens = tokens.into_iter().skip(2);
-
-                            let member_ident = tokens.next();
-                            if let Some(Token::Identifier(member_ident)) = member_ident {
-                                match tokens.next() {
-                                    
-                                    // Procedure
-                                    Some(Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening))) => {
-                                        let arguments = Self::take_until_closing(
-                                            &mut tokens,
-                                            Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing))
-                                        )?;
-
-                                        let arguments = Self::split_by_commas(arguments)?;
-                                        let mut argument_expressions = Vec::new();
-                                        for argument in arguments {
-                                            argument_expressions.push(Self::parse(argument)?);
-                                        }
-
-                                        let module_address = ModuleAddress::new(base_ident, member_ident);
-
-                                        return Ok(ExpressionAtom::Subexpression(Box::new(ProcedureCallExpression {
-                                            procedure_id: module_address,
-                                            arguments: argument_expressions
-                                        })));
-                                    }
+                Ok(ast::Expression::new(ExpressionKind::Reference(address), span))
+            }
+            Token::Keyword(KeywordToken::Clone) => {
+                let address = Self::parse_address(tokens.into_iter().skip(1), span.start + 1)?;
 
-                                    // Struct construction
-                                    Some(Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening))) => {
-                                        let fields = Self::take_until_closing(
-                                            &mut tokens,
-                                            Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing))
-                                        )?;
-                                        let fields = Self::split_by_commas(fields)?;
-
-                                        let mut field_overrides = Vec::new();
-
-                                        for field in fields {
-                                            let mut field = field.into_iter();
-                                            let field_ident = field.next();
-                                            if let Some(Token::Identifier(field_ident)) = field_ident {
-                                                let separator = field.next();
-                                                if let Some(Token::Punctuation(PunctuationToken::Colon)) = separator {
-                                                    field_overrides.push((
-                                                        field_ident,
-                                                        Self::parse(field)?
-                                                    ));
-                                                } else {
-                                                    return Err(CompilerError {
-                                                        message: format!("Unexpected token. Expected identifier, found {:?}!", separator)
-                                                    });
-                                                }
-                                            } else {
-                                                return Err(CompilerError {
-                                                    message: format!("Unexpected token. Expected identifier, found {:?}!", field_ident)
-                                                });
-                                            }
-                                        }
-
-                                        let module_address = ModuleAddress::new(base_ident, member_ident);
-
-                                        return Ok(ExpressionAtom::Subexpression(Box::new(StructConstructionExpression {
-                                            struct_id: module_address,
-                                            field_overrides
-                                        })));
-                                    }
+                Ok(ast::Expression::new(ExpressionKind::Clone(address), span))
+            }
+            base_ident => {
+                Err(CompilerError::unexpected_token("identifier", base_ident))
+            }
+        }
+    }
 
-                                    other => {
-                                        return Err(CompilerError {
-                                            message: format!("Unexpected token: {:?}", other)
-                                        });
-                                    }
-                                }
-                            } else {
-                                return Err(CompilerError {
-                                    message: format!("Unexpected token. Expected identifier, found {:?}", member_ident)
-                                });
-                            }
-                        } else {
-                            return Self::parse_variable_address(tokens);
-                        }
-                    }
-                    Token::Keyword(KeywordToken::Ref) => {
-                        let mut tokens = tokens;
-                        let tokens: Vec<Token> = tokens.drain(1..).collect();
+    // The member following 'Module::', starting at the given position.
+    fn parse_member(
+        module_id: String,
+        tokens: impl IntoIterator<Item = Token>,
+        position: usize,
+        span: Span
+    ) -> Result<ast::Expression, CompilerError> {
+        let mut tokens = tokens.into_iter();
+
+        // Builtin members may share their name with a keyword, e.g. 'Bools::from'
+        let member_ident = match tokens.next() {
+            Some(Token::Keyword(keyword)) => Some(Token::Identifier(keyword.as_str().into())),
+            other => other,
+        };
+        let member_ident = match member_ident {
+            Some(Token::Identifier(member_ident)) => member_ident,
+            other => {
+                return Err(CompilerError::unexpected_token("identifier", other));
+            }
+        };
 
-                        let variable_address = ScopeAddress::try_from(tokens)?;
+        match tokens.next() {
+            // Procedure
+            Some(Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening))) => {
+                let arguments = Self::take_until_closing(
+                    &mut tokens,
+                    Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing))
+                )?;
+
+                Ok(ast::Expression::new(ExpressionKind::Call {
+                    procedure_id: ModuleAddress::new(module_id, member_ident),
+                    arguments: Self::parse_list(arguments, position + 2)?
+                }, span))
+            }
+
+            // Struct construction
+            Some(Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening))) => {
+                let fields = Self::take_until_closing(
+                    &mut tokens,
+                    Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing))
+                )?;
+
+                let mut field_overrides = Vec::new();
+                let mut base = None;
+                let mut field_start = position + 2;
 
-                        Ok(ExpressionAtom::Subexpression(Box::new(ReferenceExpression { variable_address })))
+                for field in Self::split_by_commas(fields)? {
+                    if base.is_some() {
+                        return Err(CompilerErrorKind::MisplacedSpread.into());
                     }
-                    Token::Keyword(KeywordToken::Clone) => {
-                        let mut tokens = tokens;
-                        let tokens: Vec<Token> = tokens.drain(1..).collect();
 
-                        let variable_address = ScopeAddress::try_from(tokens)?;
+                    let start = field_start;
+                    field_start += field.len() + 1;
 
-                        Ok(ExpressionAtom::Subexpression(Box::new(CloneExpression { variable_address })))
+                    if let [Token::Punctuation(PunctuationToken::Dot), Token::Punctuation(PunctuationToken::Dot), ..] = field.as_slice() {
+                        base = Some(Box::new(Self::parse_at(field.into_iter().skip(2), start + 2)?));
+                        continue;
                     }
-                    _ => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected identifier, found {:?}!", base_ident)
-                        });
+
+                    let mut field = field.into_iter();
+                    let field_ident = field.next();
+                    if let Some(Token::Identifier(field_ident)) = field_ident {
+                        let separator = field.next();
+                        if let Some(Token::Punctuation(PunctuationToken::Colon)) = separator {
+                            field_overrides.push((
+                                Symbol::intern(&field_ident),
+                                Self::parse_at(field, start + 2)?
+                            ));
+                        } else {
+                            return Err(CompilerError::unexpected_token("identifier", separator));
+                        }
+                    } else {
+                        return Err(CompilerError::unexpected_token("identifier", field_ident));
                     }
                 }
-            },
+
+                Ok(ast::Expression::new(ExpressionKind::StructConstruction {
+                    struct_id: ModuleAddress::new(module_id, member_ident),
+                    fields: field_overrides,
+                    base
+                }, span))
+            }
+
+            // Module variable
+            next @ (None |
+            Some(Token::Punctuation(PunctuationToken::Dot)) |
+            Some(Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Opening)))) => {
+                let address = Self::parse_address(
+                    std::iter::once(Token::Identifier(member_ident)).chain(next).chain(tokens),
+                    position
+                )?;
+
+                Ok(ast::Expression::new(ExpressionKind::ModuleVariable { module_id, address }, span))
+            }
+
+            other => {
+                Err(CompilerError::unexpected(other))
+            }
         }
+    }
 
+    // Comma separated expressions, the first of them starting at the given position.
+    fn parse_list(tokens: Vec<Token>, start: usize) -> Result<Vec<ast::Expression>, CompilerError> {
+        let mut expressions = Vec::new();
+        let mut position = start;
+
+        for item in Self::split_by_commas(tokens)? {
+            let length = item.len();
+
+            expressions.push(Self::parse_at(item, position)?);
+            position += length + 1;
+        }
+
+        Ok(expressions)
     }
 
-    fn parse_variable_address(tokens: impl IntoIterator<Item = Token>) -> Result<ExpressionAtom, CompilerError> {
+    // The target of an assignment or the operand of 'ref' and 'clone'.
+    pub fn parse_address(tokens: impl IntoIterator<Item = Token>, start: usize) -> Result<Address, CompilerError> {
+        let address = Self::address(tokens, start, |token| CompilerError::unexpected_token("address", token))?;
+
+        address.ok_or_else(|| CompilerErrorKind::InvalidAddress.into())
+    }
 
-        let mut address = Vec::new();
+    fn parse_variable_address(tokens: impl IntoIterator<Item = Token>, start: usize) -> Result<Address, CompilerError> {
+        let address = Self::address(tokens, start, |token| CompilerError::unexpected_token("addressant", token))?;
+
+        address.ok_or_else(|| CompilerErrorKind::UnresolvedAddress.into())
+    }
 
+    // None if the tokens do not start with the variable.
+    fn address(
+        tokens: impl IntoIterator<Item = Token>,
+        start: usize,
+        unexpected: impl Fn(Token) -> CompilerError
+    ) -> Result<Option<Address>, CompilerError> {
         let mut tokens = tokens.into_iter();
 
+        let mut variable = None;
+        let mut accessors = Vec::new();
+        let mut position = start;
+
         while let Some(next) = tokens.next() {
+            position += 1;
+
             match next {
+                Token::Identifier(ident) if variable.is_none() && accessors.is_empty() => {
+                    variable = Some(Symbol::intern(&ident));
+                }
                 Token::Identifier(ident) => {
-                    address.push(ScopeAddressant::Identifier(ident));
+                    accessors.push(Accessor::Field(Symbol::intern(&ident)));
                 }
                 Token::Punctuation(PunctuationToken::Dot) => {}
                 Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Opening)) => {
@@ -494,77 +602,92 @@ impl ExpressionParser {
                         Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Closing))
                     )?;
 
-                    let index_expression = Self::parse(inner)?;
+                    let inner_start = position;
+                    position += inner.len() + 1;
 
-                    address.push(ScopeAddressant::DynamicIndex(index_expression.into()));
+                    accessors.push(Accessor::Index(Self::parse_at(inner, inner_start)?));
                 }
 
-                _ => Err(CompilerError {
-                    message: format!("Unexpected token. Expected addressant, found {:?}!", next)
-                })?
+                other => return Err(unexpected(other)),
             }
         }
 
+        Ok(variable.map(|variable| Address { variable, accessors }))
+    }
+
+    // How tightly an infix operator binds its left and right operand, higher binding tighter.
+    // A right binding power below the left one makes the operator right associative, so
+    // '2 ^ 3 ^ 2' is '2 ^ (3 ^ 2)'. All others associate to the left.
+    fn infix_binding_power(operator: &OperatorToken) -> Option<(u8, u8)> {
+        use OperatorToken::*;
 
-        Ok(ExpressionAtom::Subexpression(Box::new(VariableExpression {
-            variable_address: address.try_into().map_err(|_| CompilerError {
-                message: "Could not resolve variable's address!".into()
-            })?
-        })))
+        match operator {
+            Assignment | Equality | Inequality | Greater | Less | GreaterEquals | LessEquals => Some((1, 2)),
+            Plus | Minus | Or => Some((3, 4)),
+            Multiply | Divide | And => Some((5, 6)),
+            Modulo | EuclideanModulo => Some((7, 8)),
+            Power => Some((10, 9)),
+            Not => None,
+        }
     }
 
-    fn get_precedence(operator: &OperatorToken) -> usize {
+    // Prefix operators bind tighter than any infix one, so '!a == b' is '(!a) == b'.
+    fn prefix_binding_power(operator: &OperatorToken) -> Option<u8> {
         match operator {
-            OperatorToken::Assignment => 0,
-            OperatorToken::Plus => 1,
-            OperatorToken::Minus => 1,
-            OperatorToken::Multiply => 2,
-            OperatorToken::Divide => 2,
-            OperatorToken::Modulo => 3,
-            OperatorToken::Power => 4,
-            OperatorToken::Not => 10,
-            OperatorToken::And => 2,
-            OperatorToken::Or => 1,
-            OperatorToken::Equality => 0,
-            OperatorToken::Inequality => 0,
-            OperatorToken::Greater => 0,
-            OperatorToken::Less => 0,
-            OperatorToken::GreaterEquals => 0,
-            OperatorToken::LessEquals => 0,
+            OperatorToken::Not => Some(11),
+            _ => None,
         }
     }
 
-    fn resolve_binary_operator(
-        operator: &OperatorToken,
-        lhs: Box<dyn Expression>,
-        rhs: Box<dyn Expression>
-    ) -> Result<Box<dyn Expression>, CompilerError> {
+    fn resolve_prefix_operator(operator: &OperatorToken, position: usize, operand: ast::Expression) -> Result<ast::Expression, CompilerError> {
+        let operator = match operator {
+            OperatorToken::Not => PrefixOperator::Not,
+            operator => return Err(CompilerErrorKind::NotPrefixOperator { operator: format!("{:?}", operator) }.into()),
+        };
+
+        let span = Span::new(position, operand.span.end);
+
+        Ok(ast::Expression::new(ExpressionKind::Prefix { operator, operand: Box::new(operand) }, span))
+    }
+
+    fn get_comparison(operator: &OperatorToken) -> Option<Comparison> {
         match operator {
-            OperatorToken::Assignment => Err(CompilerError {
-                message: "Assignment operator disallowed in expressions!".into()
-            }),
-            OperatorToken::Plus => Ok(Box::new(AddExpression::new(lhs, rhs))),
-            OperatorToken::Minus => Ok(Box::new(SubtractExpression::new(lhs, rhs))),
-            OperatorToken::Multiply => Ok(Box::new(MultiplyExpression::new(lhs, rhs))),
-            OperatorToken::Divide => Ok(Box::new(DivideExpression::new(lhs, rhs))),
-            OperatorToken::Modulo => Ok(Box::new(ModuloExpression::new(lhs, rhs))),
-            OperatorToken::Power => Ok(Box::new(PowerExpression::new(lhs, rhs))),
-            OperatorToken::And => Ok(Box::new(AndExpression::new(lhs, rhs))),
-            OperatorToken::Or => Ok(Box::new(OrExpression::new(lhs, rhs))),
-            OperatorToken::Equality => Ok(Box::new(EqualityExpression::new(lhs, rhs))),
-            OperatorToken::Inequality => Ok(Box::new(NotExpression::new(Box::new(EqualityExpression::new(lhs, rhs))))),
-            OperatorToken::Not => Err(CompilerError {
-                message: "'Not' operator is not a binary operator!".into()
-            }),
-            OperatorToken::Greater => Ok(Box::new(GreaterThanExpression::new(lhs, rhs))),
-            OperatorToken::Less => Ok(Box::new(GreaterThanExpression::new(rhs, lhs))),
-            OperatorToken::GreaterEquals => Ok(Box::new(
-                NotExpression::new(Box::new(GreaterThanExpression::new(rhs, lhs)))
-            )),
-            OperatorToken::LessEquals => Ok(Box::new(
-                NotExpression::new(Box::new(GreaterThanExpression::new(lhs, rhs)))
-            )),
+            OperatorToken::Greater => Some(Comparison::Greater),
+            OperatorToken::Less => Some(Comparison::Less),
+            OperatorToken::GreaterEquals => Some(Comparison::GreaterEquals),
+            OperatorToken::LessEquals => Some(Comparison::LessEquals),
+            _ => None,
         }
     }
+
+    fn resolve_binary_operator(
+        operator: &OperatorToken,
+        lhs: ast::Expression,
+        rhs: ast::Expression
+    ) -> Result<ast::Expression, CompilerError> {
+        let operator = match operator {
+            OperatorToken::Assignment => return Err(CompilerErrorKind::AssignmentInExpression.into()),
+            OperatorToken::Plus => BinaryOperator::Add,
+            OperatorToken::Minus => BinaryOperator::Subtract,
+            OperatorToken::Multiply => BinaryOperator::Multiply,
+            OperatorToken::Divide => BinaryOperator::Divide,
+            OperatorToken::Modulo => BinaryOperator::Modulo,
+            OperatorToken::EuclideanModulo => BinaryOperator::EuclideanModulo,
+            OperatorToken::Power => BinaryOperator::Power,
+            OperatorToken::And => BinaryOperator::And,
+            OperatorToken::Or => BinaryOperator::Or,
+            OperatorToken::Equality => BinaryOperator::Equal,
+            OperatorToken::Inequality => BinaryOperator::NotEqual,
+            OperatorToken::Not => return Err(CompilerErrorKind::NotBinaryOperator { operator: format!("{:?}", operator) }.into()),
+            OperatorToken::Greater => BinaryOperator::Greater,
+            OperatorToken::Less => BinaryOperator::Less,
+            OperatorToken::GreaterEquals => BinaryOperator::GreaterEquals,
+            OperatorToken::LessEquals => BinaryOperator::LessEquals,
+        };
+
+        let span = lhs.span.to(rhs.span);
+
+        Ok(ast::Expression::new(ExpressionKind::Binary { operator, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span))
+    }
     
-}
\ No newline at end of file
+}
@@ -1,6 +1,8 @@
-use std::{collections::{HashSet, VecDeque}, fmt::Display, fs, path::{Path, PathBuf}, str::FromStr};
+use std::{collections::{BTreeMap, HashMap, HashSet, VecDeque}, fmt::Display, fs, path::{Component, Path, PathBuf}};
 
-use crate::{compiler::CompilerError, lexer::{FragmentStream, token::Token}};
+use sha2::{Digest, Sha256};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, remote}, lockfile::Lockfile, manifest::Manifest};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct ImportAddress {
@@ -14,49 +16,237 @@ impl Display for ImportAddress {
     }
 }
 
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 pub struct FileReader {
     root_file_path: PathBuf,
+    manifest: Manifest,
+    lockfile: Option<Lockfile>,
     queue: VecDeque<ImportAddress>,
-    read_modules: HashSet<ImportAddress>
+    // Locations of the modules enqueued so far, so a file reached by different paths is read once.
+    read_modules: HashSet<String>,
+    // The locations each file imports, keyed by the location of the importing file.
+    imports: HashMap<String, Vec<String>>,
+    read_hashes: BTreeMap<String, String>,
+    // The module enqueued first, which the modules read are locked under.
+    entry: Option<String>,
+    // The file of the module dequeued last, relative to the root.
+    current_file: Option<String>,
+    // Modules provided as text instead of files, e.g. the input of the REPL.
+    sources: HashMap<String, String>,
 }
 
 impl FileReader {
     pub fn new(root_file_path: PathBuf) -> Self {
         Self {
             root_file_path,
+            manifest: Manifest::default(),
+            lockfile: None,
 
             queue: VecDeque::new(),
             read_modules: HashSet::new(),
+            imports: HashMap::new(),
+            read_hashes: BTreeMap::new(),
+            entry: None,
+            current_file: None,
+            sources: HashMap::new(),
         }
     }
 
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    pub fn with_lockfile(mut self, lockfile: Lockfile) -> Self {
+        self.lockfile = Some(lockfile);
+        self
+    }
+
+    // Serves the module from the given text instead of reading '<module_id>.otr'.
+    pub fn with_source(mut self, module_id: impl Into<String>, content: impl Into<String>) -> Self {
+        self.sources.insert(module_id.into(), content.into());
+        self
+    }
+
     pub fn try_read_module(&self, module: &ImportAddress) -> Result<String, CompilerError> {
-        let mut path = self.root_file_path.clone();
-        
-            if let Some(location) = &module.path {
-                path = path.join(location);
-            }
-            path = path.join(module.module_id.clone() + ".otr");
+        self.read_module_source(module).map(|(_, content)| content)
+    }
+
+    // Where the module is read from: the URL for remote imports and the path relative to the
+    // project root otherwise. This is also the key of the module in the lockfile.
+    pub fn locate(&self, module: &ImportAddress) -> String {
+        if self.sources.contains_key(&module.module_id) && module.path.is_none() {
+            return format!("<{}>", module.module_id);
+        }
+
+        if let Some(location) = module.path.as_ref().filter(|location| remote::is_remote(location)) {
+            return location.clone();
+        }
+
+        let path = self.module_path(module);
+
+        path.strip_prefix(&self.root_file_path).unwrap_or(&path).to_string_lossy().replace('\\', "/")
+    }
+
+    fn module_path(&self, module: &ImportAddress) -> PathBuf {
+        let mut relative_path = PathBuf::new();
+        if let Some(location) = &module.path {
+            relative_path = self.manifest.resolve_package(location).unwrap_or_else(|| PathBuf::from(location));
+        }
+        relative_path = normalize(&relative_path.join(module.module_id.clone() + ".otr"));
+
+        let path = self.root_file_path.join(&relative_path);
 
-        fs::read_to_string(path).map_err(|err| CompilerError {
-            message: format!("Module '{}' could not be loaded from the file system! {}", module, err)
-        })
+        if path.exists() {
+            return path;
+        }
+
+        self.manifest
+            .search_paths(&self.root_file_path)
+            .into_iter()
+            .map(|search_path| search_path.join(&relative_path))
+            .find(|candidate| candidate.exists())
+            .unwrap_or(path)
+    }
+
+    // Returns the location of the module alongside its content.
+    fn read_module_source(&self, module: &ImportAddress) -> Result<(String, String), CompilerError> {
+        if let Some(content) = self.sources.get(&module.module_id).filter(|_| module.path.is_none()) {
+            return Ok((self.locate(module), content.clone()));
+        }
+
+        if let Some(location) = module.path.as_ref().filter(|location| remote::is_remote(location)) {
+            let content = remote::read_remote(location, &self.manifest, &self.root_file_path.join(".otr").join("cache"))?;
+            return Ok((location.clone(), content));
+        }
+
+        let path = self.module_path(module);
+
+        let content = fs::read_to_string(&path).map_err(|err| CompilerErrorKind::ImportNotFound { module: Box::new(module.clone()), path: path.clone(), reason: err.to_string() })?;
+
+        Ok((self.locate(module), content))
     }
 
     pub fn enqueue(&mut self, module: ImportAddress) {
-        if !self.read_modules.contains(&module) {
-            self.queue.push_back(module.clone());
-            self.read_modules.insert(module);
+        if self.entry.is_none() {
+            self.entry = Some(module.module_id.clone());
+        }
+
+        if self.read_modules.insert(self.locate(&module)) {
+            self.queue.push_back(module);
         }
     }
 
-    pub fn dequeue(&mut self) -> Result<Option<String>, CompilerError> {
+    // Enqueues a module imported by the file dequeued last and returns its location. Fails if
+    // the import closes a cycle, naming every file along it.
+    pub fn import(&mut self, module: ImportAddress) -> Result<String, CompilerError> {
+        let location = self.locate(&module);
+
+        if let Some(importer) = self.current_file.clone() {
+            if let Some(mut cycle) = self.import_chain(&location, &importer) {
+                cycle.insert(0, importer);
+
+                return Err(CompilerErrorKind::CircularImport { cycle }.into());
+            }
+
+            self.imports.entry(importer).or_default().push(location.clone());
+        }
+
+        self.enqueue(module);
+
+        Ok(location)
+    }
+
+    // The files along a chain of imports leading from one file to another, both included.
+    fn import_chain(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![vec![from.to_string()]];
+
+        while let Some(chain) = stack.pop() {
+            let last = chain.last().unwrap();
+
+            if last == to {
+                return Some(chain);
+            }
+
+            if !visited.insert(last.clone()) {
+                continue;
+            }
+
+            for next in self.imports.get(last).into_iter().flatten() {
+                let mut extended = chain.clone();
+                extended.push(next.clone());
+                stack.push(extended);
+            }
+        }
+
+        None
+    }
+
+    pub fn dequeue(&mut self) -> Result<Option<(ImportAddress, String)>, CompilerError> {
         if self.queue.is_empty() {
             return Ok(None);
         }
 
         let module = self.queue.pop_front().unwrap();
 
-        Ok(Some(self.try_read_module(&module)?))
+        let (key, content) = self.read_module_source(&module)?;
+        if self.is_locked(&module) {
+            self.read_hashes.insert(key.clone(), content_hash(&content));
+        }
+        self.current_file = Some(key);
+
+        Ok(Some((module, content)))
+    }
+
+    pub fn current_file(&self) -> Option<&String> {
+        self.current_file.as_ref()
     }
-}
\ No newline at end of file
+
+    // Whether the module is read from a file or a remote location. Modules provided as text, e.g.
+    // the input of the REPL, have no content to lock.
+    fn is_locked(&self, module: &ImportAddress) -> bool {
+        !(self.sources.contains_key(&module.module_id) && module.path.is_none())
+    }
+
+    // The lockfile describing every module read so far, listed under the entry module.
+    pub fn lock(&self) -> Lockfile {
+        let mut lockfile = Lockfile::default();
+
+        if let Some(entry) = &self.entry {
+            lockfile.entries.insert(entry.clone(), self.read_hashes.clone());
+        }
+
+        lockfile
+    }
+
+    pub fn verify_lockfile(&self) -> Result<(), CompilerError> {
+        match &self.lockfile {
+            Some(lockfile) => lockfile.verify(&self.lock()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Resolves '.' and '..' without touching the file system, so 'lib/../lib' and 'lib' agree.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
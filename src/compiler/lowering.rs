@@ -0,0 +1,522 @@
+use std::mem;
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, ast::{self, Accessor, Address, BinaryOperator, ExpressionKind, PrefixOperator, Span, Statement, StatementKind}}, runtime::{
+    Expression, Value, expressions::{ArrayLiteralExpression, CloneExpression, EqualityExpression, HasNextElementExpression, ModuleVariableAssignmentExpression, ModuleVariableExpression, ProcedureCallExpression, ReferenceExpression, StructConstructionExpression, VariableExpression, arithmetic::{AddExpression, ChainedComparisonExpression, DivideExpression, EuclideanModuloExpression, GreaterThanExpression, ModuloExpression, MultiplyExpression, PowerExpression, SubtractExpression}, boolean::{AndExpression, NotExpression, OrExpression, TruthinessExpression}}, procedures::Instruction, scope::{FieldAccessor, ScopeAddress, ScopeAddressant}, symbol::Symbol,
+}};
+
+// Turns the syntax tree into what the runtime executes: expressions into runtime expressions
+// and procedure bodies into instructions, with their jumps resolved.
+
+pub fn lower_expression(expression: ast::Expression) -> Box<dyn Expression> {
+    match expression.kind {
+        ExpressionKind::Literal(value) => Box::new(value),
+        ExpressionKind::Variable(address) => Box::new(VariableExpression { variable_address: lower_address(address) }),
+        ExpressionKind::Reference(address) => Box::new(ReferenceExpression { variable_address: lower_address(address) }),
+        ExpressionKind::Clone(address) => Box::new(CloneExpression { variable_address: lower_address(address) }),
+        ExpressionKind::ModuleVariable { module_id, address } => Box::new(ModuleVariableExpression {
+            module_id,
+            variable_address: lower_address(address)
+        }),
+        ExpressionKind::Call { procedure_id, arguments } => Box::new(ProcedureCallExpression {
+            procedure_id,
+            arguments: arguments.into_iter().map(lower_expression).collect()
+        }),
+        ExpressionKind::StructConstruction { struct_id, fields, base } => Box::new(StructConstructionExpression {
+            struct_id,
+            field_overrides: fields.into_iter().map(|(field, value)| (field, lower_expression(value))).collect(),
+            base: base.map(|base| lower_expression(*base))
+        }),
+        ExpressionKind::Array(elements) => Box::new(ArrayLiteralExpression {
+            elements: elements.into_iter().map(lower_expression).collect()
+        }),
+        ExpressionKind::Prefix { operator: PrefixOperator::Not, operand } => Box::new(NotExpression::new(lower_expression(*operand))),
+        ExpressionKind::Binary { operator, lhs, rhs } => lower_binary(operator, lower_expression(*lhs), lower_expression(*rhs)),
+        ExpressionKind::ChainedComparison { operands, comparisons } => Box::new(ChainedComparisonExpression::new(
+            operands.into_iter().map(lower_expression).collect(),
+            comparisons
+        )),
+    }
+}
+
+// Operators without an expression of their own are expressed by others, e.g. 'a <= b' as '!(a > b)'.
+fn lower_binary(operator: BinaryOperator, lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> Box<dyn Expression> {
+    match operator {
+        BinaryOperator::Add => Box::new(AddExpression::new(lhs, rhs)),
+        BinaryOperator::Subtract => Box::new(SubtractExpression::new(lhs, rhs)),
+        BinaryOperator::Multiply => Box::new(MultiplyExpression::new(lhs, rhs)),
+        BinaryOperator::Divide => Box::new(DivideExpression::new(lhs, rhs)),
+        BinaryOperator::Modulo => Box::new(ModuloExpression::new(lhs, rhs)),
+        BinaryOperator::EuclideanModulo => Box::new(EuclideanModuloExpression::new(lhs, rhs)),
+        BinaryOperator::Power => Box::new(PowerExpression::new(lhs, rhs)),
+        BinaryOperator::And => Box::new(AndExpression::new(lhs, rhs)),
+        BinaryOperator::Or => Box::new(OrExpression::new(lhs, rhs)),
+        BinaryOperator::Equal => Box::new(EqualityExpression::new(lhs, rhs)),
+        BinaryOperator::NotEqual => Box::new(NotExpression::new(Box::new(EqualityExpression::new(lhs, rhs)))),
+        BinaryOperator::Greater => Box::new(GreaterThanExpression::new(lhs, rhs)),
+        BinaryOperator::Less => Box::new(GreaterThanExpression::new(rhs, lhs)),
+        BinaryOperator::GreaterEquals => Box::new(NotExpression::new(Box::new(GreaterThanExpression::new(rhs, lhs)))),
+        BinaryOperator::LessEquals => Box::new(NotExpression::new(Box::new(GreaterThanExpression::new(lhs, rhs)))),
+    }
+}
+
+pub fn lower_address(address: Address) -> ScopeAddress {
+    let mut addressants = vec![ScopeAddressant::Identifier(address.variable)];
+
+    for accessor in address.accessors {
+        addressants.push(match accessor {
+            Accessor::Field(name) => ScopeAddressant::Field(FieldAccessor::new(name)),
+            Accessor::Index(index) => ScopeAddressant::DynamicIndex(lower_expression(index).into()),
+        });
+    }
+
+    addressants.try_into().expect("an address starts with its variable")
+}
+
+fn variable_address(identifier: Symbol) -> ScopeAddress {
+    vec![ScopeAddressant::Identifier(identifier)].try_into().unwrap()
+}
+
+// Emits the instructions of a procedure body. Blocks are entered with a scope escape handler,
+// which resolves the jumps around the block once its end is known.
+pub struct ProcedureLowering {
+    instructions: Vec<Instruction>,
+    scope_stack: Vec<Box<dyn ScopeExcapeHandler>>,
+    // Whether if and while conditions have to evaluate to Booleans.
+    strict_conditions: bool,
+    // Jumps of break statements, along with the position of their loop in the scope stack.
+    pending_breaks: Vec<(usize, usize)>,
+    // The span of the statement of every instruction, and of the statement being lowered.
+    spans: Vec<Span>,
+    span: Span,
+}
+
+impl ProcedureLowering {
+    pub fn new(strict_conditions: bool) -> Self {
+        Self {
+            instructions: Vec::new(),
+            scope_stack: Vec::new(),
+            strict_conditions,
+            pending_breaks: Vec::new(),
+            spans: Vec::new(),
+            span: Span::default(),
+        }
+    }
+
+    // Returns the instructions along with the span of the statement each was lowered from.
+    pub fn lower(mut self, body: Vec<Statement>) -> Result<(Vec<Instruction>, Vec<Span>), CompilerError> {
+        self.block(body)?;
+        self.mark_spans();
+
+        Ok((self.instructions, self.spans))
+    }
+
+    // Instructions emitted since the last statement started or ended belong to the current one.
+    fn mark_spans(&mut self) {
+        self.spans.resize(self.instructions.len(), self.span);
+    }
+
+    fn block(&mut self, statements: Vec<Statement>) -> Result<(), CompilerError> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+
+        Ok(())
+    }
+
+    // Lowers the body of a block whose opening instructions were emitted already.
+    fn scope(&mut self, handler: Box<dyn ScopeExcapeHandler>, body: Vec<Statement>) -> Result<(), CompilerError> {
+        self.scope_stack.push(handler);
+        self.block(body)?;
+
+        let handler = self.scope_stack.pop().expect("the scope of the block is still open");
+
+        handler.resolve(&mut self.instructions);
+
+        if handler.is_loop() {
+            let depth = self.scope_stack.len();
+            let end = self.instructions.len();
+
+            for (_, jump) in self.pending_breaks.iter().filter(|(loop_depth, _)| *loop_depth == depth) {
+                if let Some(Instruction::JumpConditional { jump_target, .. }) = self.instructions.get_mut(*jump) {
+                    *jump_target = end;
+                }
+            }
+
+            self.pending_breaks.retain(|(loop_depth, _)| *loop_depth != depth);
+        }
+
+        Ok(())
+    }
+
+    // The condition of a jump past a block, which is taken when the one of the block does not hold.
+    fn skip_condition(&self, condition: ast::Expression) -> Box<dyn Expression> {
+        let condition = lower_expression(condition);
+
+        if self.strict_conditions {
+            Box::new(NotExpression::new(condition))
+        } else {
+            Box::new(NotExpression::new(Box::new(TruthinessExpression::new(condition))))
+        }
+    }
+
+    fn statement(&mut self, statement: Statement) -> Result<(), CompilerError> {
+        self.mark_spans();
+        let enclosing = mem::replace(&mut self.span, statement.span);
+
+        self.statement_kind(statement.kind)?;
+
+        self.mark_spans();
+        self.span = enclosing;
+
+        Ok(())
+    }
+
+    fn statement_kind(&mut self, kind: StatementKind) -> Result<(), CompilerError> {
+        match kind {
+            StatementKind::Let { identifier, value } => {
+                self.instructions.push(Instruction::PushVarToScope { identifier });
+
+                if let Some(value) = value {
+                    self.instructions.push(Instruction::EvaluateExpression {
+                        expression: lower_expression(value),
+                        target: Some(variable_address(identifier))
+                    });
+                }
+            }
+            StatementKind::Assign { target, value } => {
+                let target = Some(lower_address(target));
+
+                self.instructions.push(Instruction::EvaluateExpression { expression: lower_expression(value), target });
+            }
+            StatementKind::AssignModuleVariable { module_id, target, value } => {
+                let expression = Box::new(ModuleVariableAssignmentExpression {
+                    module_id,
+                    variable_address: lower_address(target),
+                    expression: lower_expression(value),
+                });
+
+                self.instructions.push(Instruction::EvaluateExpression { expression, target: None });
+            }
+            StatementKind::Expression(expression) => {
+                self.instructions.push(Instruction::EvaluateExpression { expression: lower_expression(expression), target: None });
+            }
+            StatementKind::If { condition, body, otherwise } => {
+                let jump = self.instructions.len();
+
+                self.instructions.push(Instruction::JumpConditional {
+                    condition_expression: self.skip_condition(condition),
+                    jump_target: usize::MAX
+                });
+                self.instructions.push(Instruction::GrowStack);
+
+                self.scope(Box::new(IfScopeEscapeHandler { target_instruction: jump }), body)?;
+
+                if let Some(otherwise) = otherwise {
+                    // The jump past the body enters the else-clause rather than the jump over it
+                    if let Some(Instruction::JumpConditional { jump_target, .. }) = self.instructions.get_mut(jump) {
+                        *jump_target += 1;
+                    }
+
+                    let skip_jump = self.instructions.len();
+
+                    self.instructions.push(Instruction::JumpConditional {
+                        condition_expression: Box::new(Value::Bool(true)),
+                        jump_target: usize::MAX
+                    });
+                    self.instructions.push(Instruction::GrowStack);
+
+                    self.scope(Box::new(IfScopeEscapeHandler { target_instruction: skip_jump }), otherwise)?;
+                }
+            }
+            StatementKind::While { condition, body } => {
+                let jump = self.instructions.len();
+
+                self.instructions.push(Instruction::JumpConditional {
+                    condition_expression: self.skip_condition(condition),
+                    jump_target: usize::MAX
+                });
+                self.instructions.push(Instruction::GrowStack);
+
+                self.scope(Box::new(WhileScopeEscapeHandler { target_instruction: jump }), body)?;
+            }
+            StatementKind::Loop { body } => {
+                let start = self.instructions.len();
+
+                self.instructions.push(Instruction::GrowStack);
+
+                self.scope(Box::new(LoopScopeEscapeHandler { start_instruction: start }), body)?;
+            }
+            StatementKind::For { item, collection, body } => {
+                let collection_expression = lower_expression(collection);
+
+                // Hidden variables, named so they can't clash with those of the program or nested loops
+                let depth = self.scope_stack.len();
+                let collection = Symbol::intern(&format!("$collection{}", depth));
+                let index = Symbol::intern(&format!("$index{}", depth));
+
+                let instructions = &mut self.instructions;
+
+                instructions.push(Instruction::GrowStack);
+                instructions.push(Instruction::PushVarToScope { identifier: collection });
+                instructions.push(Instruction::EvaluateExpression {
+                    expression: collection_expression,
+                    target: Some(variable_address(collection))
+                });
+                instructions.push(Instruction::PushVarToScope { identifier: index });
+                instructions.push(Instruction::EvaluateExpression {
+                    expression: Box::new(Value::Integer(0)),
+                    target: Some(variable_address(index))
+                });
+
+                let condition_instruction = instructions.len();
+
+                instructions.push(Instruction::JumpConditional {
+                    condition_expression: Box::new(NotExpression::new(Box::new(HasNextElementExpression {
+                        collection,
+                        index,
+                    }))),
+                    jump_target: usize::MAX
+                });
+                instructions.push(Instruction::GrowStack);
+
+                let index_address = variable_address(index);
+
+                instructions.push(Instruction::PushVarToScope { identifier: item });
+                instructions.push(Instruction::EvaluateExpression {
+                    expression: Box::new(VariableExpression {
+                        variable_address: vec![
+                            ScopeAddressant::Identifier(collection),
+                            VariableExpression { variable_address: index_address.clone() }.into(),
+                        ].try_into().unwrap()
+                    }),
+                    target: Some(variable_address(item))
+                });
+                instructions.push(Instruction::EvaluateExpression {
+                    expression: Box::new(AddExpression::new(
+                        Box::new(VariableExpression { variable_address: index_address.clone() }),
+                        Box::new(Value::Integer(1)),
+                    )),
+                    target: Some(index_address)
+                });
+
+                self.scope(Box::new(ForeachScopeEscapeHandler { condition_instruction }), body)?;
+            }
+            StatementKind::Break => {
+                let loop_depth = self.scope_stack
+                    .iter()
+                    .rposition(|scope| scope.is_loop())
+                    .ok_or_else(|| CompilerError::from(CompilerErrorKind::BreakOutsideLoop))?;
+
+                // Leave every scope up to and including the loop's body
+                for scope in self.scope_stack[loop_depth..].iter().rev() {
+                    scope.unwind(&mut self.instructions);
+                }
+
+                self.pending_breaks.push((loop_depth, self.instructions.len()));
+
+                self.instructions.push(Instruction::JumpConditional {
+                    condition_expression: Box::new(Value::Bool(true)),
+                    jump_target: usize::MAX
+                });
+            }
+            StatementKind::Return(expression) => {
+                let expression = match expression {
+                    Some(expression) => lower_expression(expression),
+                    None => Box::new(Value::Null),
+                };
+
+                self.instructions.push(Instruction::Return { expression });
+            }
+            StatementKind::Try { body, identifier, handler } => {
+                let try_instruction = self.instructions.len();
+
+                self.instructions.push(Instruction::Try { catch_target: usize::MAX });
+                self.instructions.push(Instruction::GrowStack);
+
+                self.scope(Box::new(TryScopeEscapeHandler { try_instruction }), body)?;
+
+                // The try block ends with the jump over its catch-clause
+                let skip_jump = self.instructions.len() - 1;
+
+                self.instructions.push(Instruction::GrowStack);
+                self.instructions.push(Instruction::Catch { identifier });
+
+                self.scope(Box::new(CatchScopeEscapeHandler { skip_jump }), handler)?;
+            }
+            StatementKind::Throw(expression) => {
+                self.instructions.push(Instruction::Throw { expression: lower_expression(expression) });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+trait ScopeExcapeHandler: std::fmt::Debug {
+    fn resolve(&self, instructions: &mut Vec<Instruction>);
+
+
+    // Leaves the scope early, e.g. on 'break'.
+    fn unwind(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+    }
+
+    fn is_loop(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+struct IfScopeEscapeHandler {
+    target_instruction: usize,
+}
+
+impl ScopeExcapeHandler for IfScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+
+        let next_ic = instructions.len();
+
+        if let Some(Instruction::JumpConditional {
+            condition_expression: _,
+            jump_target 
+        }) = instructions.get_mut(self.target_instruction) {
+            *jump_target = next_ic;
+        } else {
+            panic!("Tried resolving if scope escape but initial jump is missing!");
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WhileScopeEscapeHandler {
+    target_instruction: usize,
+}
+
+impl ScopeExcapeHandler for WhileScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::JumpConditional {
+            condition_expression: Box::new(Value::Bool(true)),
+            jump_target: self.target_instruction
+        });
+        let next_ic = instructions.len();
+        if let Some(Instruction::JumpConditional {
+            condition_expression: _,
+            jump_target 
+        }) = instructions.get_mut(self.target_instruction) {
+            
+            *jump_target = next_ic;
+        } else {
+            panic!("Tried resolving if scope escape but initial jump is missing!");
+        }
+    }
+    
+    fn is_loop(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct LoopScopeEscapeHandler {
+    start_instruction: usize,
+}
+
+impl ScopeExcapeHandler for LoopScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::JumpConditional {
+            condition_expression: Box::new(Value::Bool(true)),
+            jump_target: self.start_instruction
+        });
+    }
+
+    fn is_loop(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct ForeachScopeEscapeHandler {
+    condition_instruction: usize,
+}
+
+impl ScopeExcapeHandler for ForeachScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::JumpConditional {
+            condition_expression: Box::new(Value::Bool(true)),
+            jump_target: self.condition_instruction
+        });
+
+        let next_ic = instructions.len();
+        if let Some(Instruction::JumpConditional { jump_target, .. }) = instructions.get_mut(self.condition_instruction) {
+            *jump_target = next_ic;
+        } else {
+            panic!("Tried resolving foreach scope escape but the condition jump is missing!");
+        }
+
+        // Drop the hidden collection and index
+        instructions.push(Instruction::ShrinkStack);
+    }
+
+    fn unwind(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::ShrinkStack);
+    }
+
+    fn is_loop(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct TryScopeEscapeHandler {
+    try_instruction: usize,
+}
+
+impl ScopeExcapeHandler for TryScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::EndTry);
+        instructions.push(Instruction::JumpConditional {
+            condition_expression: Box::new(Value::Bool(true)),
+            jump_target: usize::MAX
+        });
+
+        let next_ic = instructions.len();
+
+        if let Some(Instruction::Try { catch_target }) = instructions.get_mut(self.try_instruction) {
+            *catch_target = next_ic;
+        } else {
+            panic!("Tried resolving try scope escape but initial try is missing!");
+        }
+}
+
+    fn unwind(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+        instructions.push(Instruction::EndTry);
+    }
+}
+
+#[derive(Debug)]
+struct CatchScopeEscapeHandler {
+    skip_jump: usize,
+}
+
+impl ScopeExcapeHandler for CatchScopeEscapeHandler {
+    fn resolve(&self, instructions: &mut Vec<Instruction>) {
+        instructions.push(Instruction::ShrinkStack);
+
+        let next_ic = instructions.len();
+
+        if let Some(Instruction::JumpConditional {
+            condition_expression: _,
+            jump_target
+        }) = instructions.get_mut(self.skip_jump) {
+            *jump_target = next_ic;
+        } else {
+            panic!("Tried resolving catch scope escape but the jump over it is missing!");
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, verifier}, lexer::token::PrimitiveTypeToken, runtime::{Expression, ModuleAddress, RuntimeObject, Value, environment::Environment, image::{self, ExpressionImage}, procedures::{CompiledProcedure, Instruction}, symbol::Symbol}};
+
+// The passes run by 'otr -O', in order.
+const PASSES: &[Pass] = &[
+    Pass::FoldConstants,
+    Pass::EliminateDeadCode,
+    Pass::Peephole,
+    Pass::ResolveSlots,
+    Pass::Inline,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    FoldConstants,
+    EliminateDeadCode,
+    Peephole,
+    ResolveSlots,
+    Inline,
+}
+
+impl Pass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::FoldConstants => "constant folding",
+            Pass::EliminateDeadCode => "dead code elimination",
+            Pass::Peephole => "peephole optimization",
+            Pass::ResolveSlots => "address slot resolution",
+            Pass::Inline => "inlining",
+        }
+    }
+}
+
+// Instruction counts of the whole program before optimizing and after every pass.
+#[derive(Debug, Clone)]
+pub struct OptimizationReport {
+    pub instructions_before: usize,
+    pub passes: Vec<(Pass, usize)>,
+}
+
+impl OptimizationReport {
+    pub fn instructions_after(&self) -> usize {
+        self.passes.last().map(|(_, count)| *count).unwrap_or(self.instructions_before)
+    }
+}
+
+impl std::fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:>6} instructions before optimizing", self.instructions_before)?;
+        for (pass, count) in &self.passes {
+            writeln!(f, "{:>6} after {}", count, pass.name())?;
+        }
+        write!(f, "{:>6} instructions after optimizing", self.instructions_after())
+    }
+}
+
+// A procedure whose body only returns a constant. Calls to it are replaced by the constant.
+#[derive(Debug, Clone)]
+struct InlineCandidate {
+    value: Value,
+    exported: bool,
+    argument_types: Vec<Option<PrimitiveTypeToken>>,
+}
+
+// Handed to `Expression::fold_constants`. Evaluates constant subexpressions ahead of time and,
+// depending on the pass, resolves constant indices in addresses or inlines procedure calls.
+pub struct ConstantFolder {
+    // Only knows the imports of the loaded modules, which is enough to resolve addresses.
+    environment: Environment,
+    resolve_slots: bool,
+    inline_candidates: HashMap<(String, String), InlineCandidate>,
+}
+
+impl ConstantFolder {
+    // Folds the expression and replaces it with its value if it turned out to be constant.
+    pub fn fold(&self, expression: &mut Box<dyn Expression>) -> bool {
+        match expression.fold_constants(self) {
+            Some(value) => {
+                *expression = Box::new(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Folds every operand, even after one of them turned out not to be constant.
+    pub fn fold_all<'a>(&self, expressions: impl IntoIterator<Item = &'a mut Box<dyn Expression>>) -> bool {
+        let mut constant = true;
+
+        for expression in expressions {
+            constant &= self.fold(expression);
+        }
+
+        constant
+    }
+
+    // The value of an expression whose operands are all constant. Expressions that fail are
+    // left alone, so they still fail when they run.
+    pub fn evaluate(&self, expression: &dyn Expression) -> Option<Value> {
+        expression.eval(&self.environment).ok()
+    }
+
+    pub fn resolves_slots(&self) -> bool {
+        self.resolve_slots
+    }
+
+    // The value a call evaluates to, if the called procedure is known to return a constant.
+    pub fn inline_call(&self, procedure_id: &ModuleAddress, arguments: &[Box<dyn Expression>]) -> Option<Value> {
+        let address = self.environment.resolve_address(procedure_id);
+
+        let candidate = self.inline_candidates.get(&(address.get_module_id().clone(), address.get_identifier().clone()))?;
+
+        if !candidate.exported && address.get_module_id() != self.environment.get_contained_module_id() {
+            return None;
+        }
+
+        // Arguments are only dropped if evaluating them has no effect and passes the type checks.
+        for (argument, expected_type) in arguments.iter().zip(candidate.argument_types.iter().chain(std::iter::repeat(&None))) {
+            let value = argument.as_constant()?;
+
+            if let Some(expected_type) = expected_type {
+                if !value.is_of_type(expected_type) {
+                    return None;
+                }
+            }
+        }
+
+        Some(candidate.value.clone())
+    }
+
+    fn enter_module(&mut self, module_id: &str) {
+        self.environment.contained_module_id = module_id.to_string();
+    }
+}
+
+// Runs every pass over all compiled procedures of the program.
+pub fn optimize(runtime_object: &mut RuntimeObject) -> Result<OptimizationReport, CompilerError> {
+    let environment = &mut runtime_object.base_environement;
+
+    let mut folder = ConstantFolder {
+        environment: Environment::new(String::new()),
+        resolve_slots: false,
+        inline_candidates: HashMap::new(),
+    };
+    for (module_id, module) in &environment.loaded_modules {
+        folder.environment.load_module(module_id.clone(), Rc::new(module.imports()));
+    }
+
+    // Procedures that verify before optimizing have to verify afterwards as well.
+    let verified: Vec<(String, String)> = environment.loaded_modules
+        .iter()
+        .flat_map(|(module_id, module)| module.procedures().map(move |(identifier, procedure)| (module_id, identifier, procedure)))
+        .filter(|(_, _, procedure)| procedure.as_compiled().is_some_and(|procedure| verifier::verify(procedure).is_ok()))
+        .map(|(module_id, identifier, _)| (module_id.clone(), identifier.clone()))
+        .collect();
+
+    let mut report = OptimizationReport {
+        instructions_before: count_instructions(environment),
+        passes: Vec::new(),
+    };
+
+    for pass in PASSES {
+        if *pass == Pass::Inline {
+            folder.inline_candidates = inline_candidates(environment);
+        }
+        folder.resolve_slots = *pass == Pass::ResolveSlots;
+
+        for (module_id, module) in environment.loaded_modules.iter_mut() {
+            let module = Rc::get_mut(module).ok_or_else(|| CompilerError::from(CompilerErrorKind::SharedModule { module_id: module_id.clone(), action: "optimized" }))?;
+
+            folder.enter_module(module_id);
+
+            for (_, procedure) in module.procedures_mut() {
+                if let Some(procedure) = procedure.as_compiled_mut() {
+                    run_pass(*pass, procedure, &folder);
+                }
+            }
+        }
+
+        report.passes.push((*pass, count_instructions(environment)));
+    }
+
+    // A mistake in one of the passes is caught here rather than when the program runs.
+    for (module_id, identifier) in verified {
+        let procedure = environment.loaded_modules[&module_id].procedures()
+            .find(|(name, _)| **name == identifier)
+            .and_then(|(_, procedure)| procedure.as_compiled());
+
+        if let Some(procedure) = procedure {
+            verifier::verify(procedure).map_err(|err| CompilerErrorKind::VerificationFailed { procedure: format!("{}::{}", module_id, identifier), reason: format!("Optimizing broke the procedure. {}", err.message()) })?;
+        }
+    }
+
+    Ok(report)
+}
+
+fn count_instructions(environment: &Environment) -> usize {
+    environment.loaded_modules
+        .values()
+        .flat_map(|module| module.procedures())
+        .filter_map(|(_, procedure)| procedure.as_compiled())
+        .map(|procedure| procedure.instructions.len())
+        .sum()
+}
+
+fn run_pass(pass: Pass, procedure: &mut CompiledProcedure, folder: &ConstantFolder) {
+    match pass {
+        Pass::FoldConstants | Pass::ResolveSlots | Pass::Inline => fold_instructions(procedure, folder),
+        Pass::EliminateDeadCode => eliminate_dead_code(procedure),
+        Pass::Peephole => peephole(procedure),
+    }
+}
+
+fn fold_instructions(procedure: &mut CompiledProcedure, folder: &ConstantFolder) {
+    for instruction in procedure.instructions.iter_mut() {
+        match instruction {
+            Instruction::EvaluateExpression { expression, target } => {
+                folder.fold(expression);
+                if let Some(target) = target {
+                    target.fold_constants(folder);
+                }
+            }
+            Instruction::JumpConditional { condition_expression, .. } => {
+                folder.fold(condition_expression);
+            }
+            Instruction::Return { expression } | Instruction::Throw { expression } => {
+                folder.fold(expression);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Procedures without budget whose only instruction returns a constant.
+fn inline_candidates(environment: &Environment) -> HashMap<(String, String), InlineCandidate> {
+    let mut candidates = HashMap::new();
+
+    for (module_id, module) in &environment.loaded_modules {
+        for (identifier, procedure) in module.procedures() {
+            let Some(procedure) = procedure.as_compiled() else {
+                continue;
+            };
+
+            let [Instruction::Return { expression }] = procedure.instructions.as_slice() else {
+                continue;
+            };
+
+            let Some(value) = expression.as_constant() else {
+                continue;
+            };
+
+            if procedure.budget.is_some() || procedure.return_type.as_ref().is_some_and(|return_type| !value.is_of_type(return_type)) {
+                continue;
+            }
+
+            candidates.insert((module_id.clone(), identifier.clone()), InlineCandidate {
+                value: value.clone(),
+                exported: module.get_procedure(identifier, false).is_ok(),
+                argument_types: procedure.argument_types.clone(),
+            });
+        }
+    }
+
+    candidates
+}
+
+// Whether the instruction continues with the next one and where else it may continue.
+fn successors(instructions: &[Instruction], pc: usize) -> (bool, Option<usize>) {
+    match &instructions[pc] {
+        Instruction::Return { .. } | Instruction::Throw { .. } => (false, None),
+        Instruction::JumpConditional { jump_target, .. } => {
+            match constant_condition(&instructions[pc]) {
+                Some(true) => (false, Some(*jump_target)),
+                Some(false) => (true, None),
+                None => (true, Some(*jump_target)),
+            }
+        }
+        Instruction::Try { catch_target } => (true, Some(*catch_target)),
+        _ => (true, None),
+    }
+}
+
+fn constant_condition(instruction: &Instruction) -> Option<bool> {
+    match instruction {
+        Instruction::JumpConditional { condition_expression, .. } => match condition_expression.as_constant() {
+            Some(Value::Bool(condition)) => Some(*condition),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Removes instructions no path reaches, jumps that are never taken and variables never read.
+fn eliminate_dead_code(procedure: &mut CompiledProcedure) {
+    let instructions = &procedure.instructions;
+
+    let mut reachable = vec![false; instructions.len()];
+    let mut pending = vec![0];
+
+    while let Some(pc) = pending.pop() {
+        if pc >= instructions.len() || reachable[pc] {
+            continue;
+        }
+        reachable[pc] = true;
+
+        let (falls_through, target) = successors(instructions, pc);
+        if falls_through {
+            pending.push(pc + 1);
+        }
+        pending.extend(target);
+    }
+
+    let removed: Vec<bool> = instructions
+        .iter()
+        .enumerate()
+        .map(|(pc, instruction)| !reachable[pc] || constant_condition(instruction) == Some(false))
+        .collect();
+
+    remove_instructions(procedure, &removed);
+
+    strip_unused_variables(procedure);
+}
+
+// Removes the declarations of local variables that are never read. Assignments to them are
+// still evaluated, only their result is dropped.
+fn strip_unused_variables(procedure: &mut CompiledProcedure) {
+    let Some(read) = read_variables(&procedure.instructions) else {
+        return;
+    };
+
+    let unused: HashSet<Symbol> = procedure.instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::PushVarToScope { identifier } if !read.contains(identifier) && !procedure.arguments_identifiers.contains(identifier) => {
+                Some(*identifier)
+            }
+            _ => None,
+        })
+        .collect();
+
+    if unused.is_empty() {
+        return;
+    }
+
+    let mut removed = vec![false; procedure.instructions.len()];
+
+    for (pc, instruction) in procedure.instructions.iter_mut().enumerate() {
+        match instruction {
+            Instruction::PushVarToScope { identifier } | Instruction::PopVarFromScope { identifier } => {
+                removed[pc] = unused.contains(identifier);
+            }
+            Instruction::EvaluateExpression { target, .. } if target.as_ref().and_then(|target| target.as_identifier()).is_some_and(|identifier| unused.contains(&identifier)) => {
+                *target = None;
+            }
+            _ => {}
+        }
+    }
+
+    remove_instructions(procedure, &removed);
+}
+
+// Every variable the instructions read. None if an expression cannot be inspected or takes a
+// reference, which may keep a value alive after its variable is gone.
+fn read_variables(instructions: &[Instruction]) -> Option<HashSet<Symbol>> {
+    let mut read = HashSet::new();
+    let mut references = false;
+
+    let mut visit = |expression: &ExpressionImage| match expression {
+        ExpressionImage::Variable(address) | ExpressionImage::Clone(address) => {
+            if let Some(image::AddressantImage::Identifier(identifier)) = address.first() {
+                read.insert(Symbol::intern(identifier));
+            }
+        }
+        ExpressionImage::HasNextElement(collection, index) => {
+            read.insert(Symbol::intern(collection));
+            read.insert(Symbol::intern(index));
+        }
+        ExpressionImage::Reference(_) => references = true,
+        _ => {}
+    };
+
+    for instruction in instructions {
+        match instruction {
+            Instruction::EvaluateExpression { expression, target } => {
+                expression.image().ok()?.walk(&mut visit);
+
+                // Assigning to a field or an element reads the variable holding it.
+                if let Some(target) = target.as_ref().filter(|target| target.as_identifier().is_none()) {
+                    ExpressionImage::Variable(image::address_image(target).ok()?).walk(&mut visit);
+                }
+            }
+            Instruction::JumpConditional { condition_expression: expression, .. } |
+            Instruction::Return { expression } |
+            Instruction::Throw { expression } => expression.image().ok()?.walk(&mut visit),
+            _ => {}
+        }
+    }
+
+    (!references).then_some(read)
+}
+
+// Local rewrites: jumps to jumps are threaded, jumps to the next instruction, empty blocks and
+// constant expressions evaluated for nothing are removed.
+// They are repeated until nothing changes, as a removal may leave another block empty.
+fn peephole(procedure: &mut CompiledProcedure) {
+    while rewrite_locally(procedure) {}
+}
+
+// Returns whether any instruction was removed.
+fn rewrite_locally(procedure: &mut CompiledProcedure) -> bool {
+    let instructions = &mut procedure.instructions;
+
+    for pc in 0..instructions.len() {
+        let Instruction::JumpConditional { jump_target, .. } = &instructions[pc] else {
+            continue;
+        };
+
+        let mut target = *jump_target;
+        let mut hops = 0;
+        while let Some(next @ Instruction::JumpConditional { jump_target, .. }) = instructions.get(target) {
+            if constant_condition(next) != Some(true) || hops == instructions.len() {
+                break;
+            }
+            target = *jump_target;
+            hops += 1;
+        }
+
+        if let Instruction::JumpConditional { jump_target, .. } = &mut instructions[pc] {
+            *jump_target = target;
+        }
+    }
+
+    let targets = jump_targets(instructions);
+    let mut removed = vec![false; instructions.len()];
+
+    for pc in 0..instructions.len() {
+        if removed[pc] {
+            continue;
+        }
+
+        match &instructions[pc] {
+            Instruction::JumpConditional { jump_target, .. } => match constant_condition(&instructions[pc]) {
+                Some(true) => removed[pc] = *jump_target == pc + 1,
+                Some(false) => removed[pc] = true,
+                None => {}
+            },
+            Instruction::EvaluateExpression { expression, target: None } if expression.as_constant().is_some() => {
+                removed[pc] = true;
+            }
+            Instruction::GrowStack if matches!(instructions.get(pc + 1), Some(Instruction::ShrinkStack)) && !targets.contains(&(pc + 1)) => {
+                removed[pc] = true;
+                removed[pc + 1] = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !removed.contains(&true) {
+        return false;
+    }
+
+    remove_instructions(procedure, &removed);
+
+    true
+}
+
+fn jump_targets(instructions: &[Instruction]) -> Vec<usize> {
+    instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::JumpConditional { jump_target, .. } => Some(*jump_target),
+            Instruction::Try { catch_target } => Some(*catch_target),
+            _ => None,
+        })
+        .collect()
+}
+
+// Removes the marked instructions and their spans. Jumps to a removed instruction continue at the
+// next one kept.
+fn remove_instructions(procedure: &mut CompiledProcedure, removed: &[bool]) {
+    let mut pc = 0;
+    procedure.spans.retain(|_| {
+        pc += 1;
+        !removed.get(pc - 1).copied().unwrap_or(false)
+    });
+
+    let instructions = &mut procedure.instructions;
+
+    let mut new_positions = Vec::with_capacity(instructions.len() + 1);
+    let mut kept = 0;
+    for is_removed in removed {
+        new_positions.push(kept);
+        if !is_removed {
+            kept += 1;
+        }
+    }
+    new_positions.push(kept);
+
+    let mut pc = 0;
+    instructions.retain(|_| {
+        pc += 1;
+        !removed[pc - 1]
+    });
+
+    for instruction in instructions.iter_mut() {
+        match instruction {
+            Instruction::JumpConditional { jump_target: target, .. } | Instruction::Try { catch_target: target } => {
+                *target = new_positions[*target];
+            }
+            _ => {}
+        }
+    }
+}
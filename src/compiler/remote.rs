@@ -0,0 +1,55 @@
+use std::{fs, path::Path};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, file_reader::content_hash}, manifest::Manifest};
+
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+// Resolves a remote import through the local cache, downloading it on a miss. The content
+// always has to match the hash pinned in the manifest.
+pub fn read_remote(url: &str, manifest: &Manifest, cache_directory: &Path) -> Result<String, CompilerError> {
+    let expected_hash = manifest.remote.get(url).ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingRemoteHash { url: url.to_string() }))?;
+
+    let cached_path = cache_directory.join(format!("{}.otr", expected_hash));
+
+    if let Ok(content) = fs::read_to_string(&cached_path) {
+        if content_hash(&content) == *expected_hash {
+            return Ok(content);
+        }
+    }
+
+    let content = String::from_utf8(download(url)?).map_err(|err| CompilerError::invalid_file(format!("Remote import '{}'", url), err))?;
+
+    let hash = content_hash(&content);
+    if hash != *expected_hash {
+        return Err(CompilerErrorKind::HashMismatch { url: url.into(), expected: expected_hash.as_str().into(), found: hash.into() }.into());
+    }
+
+    fs::create_dir_all(cache_directory)
+        .and_then(|_| fs::write(&cached_path, &content))
+        .map_err(|err| CompilerError::io(format!("Remote import '{}'", url), "cached", err))?;
+
+    Ok(content)
+}
+
+#[cfg(feature = "remote-imports")]
+pub fn download(url: &str) -> Result<Vec<u8>, CompilerError> {
+    use std::io::Read;
+
+    let mut content = Vec::new();
+
+    ureq::get(url)
+        .call()
+        .map_err(|err| CompilerError::io(format!("'{}'", url), "downloaded", err))?
+        .into_reader()
+        .read_to_end(&mut content)
+        .map_err(|err| CompilerError::io(format!("'{}'", url), "read", err))?;
+
+    Ok(content)
+}
+
+#[cfg(not(feature = "remote-imports"))]
+pub fn download(url: &str) -> Result<Vec<u8>, CompilerError> {
+    Err(CompilerErrorKind::RemoteImportsDisabled { url: url.to_string() }.into())
+}
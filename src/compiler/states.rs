@@ -25,9 +25,11 @@ impl CompilerState for CompilerBaseState {
                 Ok(Box::new(CompilerImportState::new(*self)))
             }
 
-            _ => Err(CompilerError {
-                message: format!("Unexpected token: {:?}", token)
-            })
+            Token::Keyword(KeywordToken::Export) => {
+                Ok(Box::new(CompilerImportState::reexport(*self)))
+            }
+
+            _ => Err(CompilerError::unexpected(token))
         }
     }
 
@@ -1,20 +1,28 @@
-use crate::{compiler::{Compiler, CompilerEnvironment, CompilerError, CompilerState, states::{module::CompilerModuleState, procedure::CompilerProcedureState}}, lexer::token::{KeywordToken, PunctuationToken, Token}, runtime::environment::Environment};
+use crate::{compiler::{Compiler, CompilerEnvironment, CompilerError, CompilerErrorKind, CompilerState, expression_parser::ExpressionParser, states::{module::CompilerModuleState, procedure::CompilerProcedureState}}, lexer::token::{KeywordToken, LiteralToken, OperatorToken, ParenthesisType, PunctuationToken, Token}, runtime::environment::Environment};
 
 #[derive(Clone)]
 pub struct RawDecorator {
     ident: String,
+    arguments: Vec<(String, LiteralToken)>,
 }
 
 impl RawDecorator {
     pub fn get_ident(&self) -> &String {
         &self.ident
     }
+
+    // Named arguments as in '@budget(instructions = 100)'.
+    pub fn get_arguments(&self) -> &Vec<(String, LiteralToken)> {
+        &self.arguments
+    }
 }
 
 pub struct CompilerDecoratorState {
     module: CompilerModuleState,
     decorators: Vec<RawDecorator>,
     num_decorators: usize,
+    // Tokens between the parentheses following a decorator's identifier.
+    arguments: Option<Vec<Token>>,
 }
 
 impl CompilerDecoratorState {
@@ -23,20 +31,58 @@ impl CompilerDecoratorState {
             module,
             decorators: Vec::new(),
             num_decorators: 1,
+            arguments: None,
         }
     }
+
+    fn parse_arguments(tokens: Vec<Token>) -> Result<Vec<(String, LiteralToken)>, CompilerError> {
+        let mut arguments = Vec::new();
+
+        for argument in ExpressionParser::split_by_commas(tokens)? {
+            match argument.as_slice() {
+                [Token::Identifier(name), Token::Operator(OperatorToken::Assignment), Token::Literal(value)] => {
+                    arguments.push((name.clone(), value.clone()));
+                }
+
+                other => {
+                    return Err(CompilerErrorKind::InvalidDecoratorArgument { found: format!("{:?}", other) }.into());
+                }
+            }
+        }
+
+        Ok(arguments)
+    }
 }
 
 impl CompilerState for CompilerDecoratorState {
     fn read(mut self: Box<Self>, token: Token, _compiler_environment: &mut CompilerEnvironment) -> Result<Box<dyn CompilerState>, CompilerError> {
         
+        if let Some(arguments) = &mut self.arguments {
+            if token == Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing)) {
+                let arguments = Self::parse_arguments(self.arguments.take().unwrap_or_default())?;
+
+                if let Some(decorator) = self.decorators.last_mut() {
+                    decorator.arguments = arguments;
+                }
+            } else {
+                arguments.push(token);
+            }
+
+            return Ok(self);
+        }
+
         match token {
             
+            Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening))
+                if self.decorators.len() == self.num_decorators
+                && self.decorators.last().is_some_and(|decorator| decorator.arguments.is_empty()) => {
+                self.arguments = Some(Vec::new());
+                Ok(self)
+            }
+
             Token::Punctuation(PunctuationToken::At) => {
                 if self.num_decorators > self.decorators.len() {
-                    Err(CompilerError{
-                        message: format!("Unexpected token! Expected identifier, found {:?}", token)
-                    })
+                    Err(CompilerError::unexpected_token("identifier", token))
                 } else {
                     self.num_decorators += 1;
                     Ok(self)
@@ -45,11 +91,9 @@ impl CompilerState for CompilerDecoratorState {
 
             Token::Identifier(ref ident) => {
                 if self.decorators.len() >= self.num_decorators {
-                    Err(CompilerError{
-                        message: format!("Unexpected token! Expected '@', found {:?}", token)
-                    })
+                    Err(CompilerError::unexpected_token("'@'", token))
                 } else {
-                    self.decorators.push(RawDecorator { ident: ident.to_string() });
+                    self.decorators.push(RawDecorator { ident: ident.to_string(), arguments: Vec::new() });
                     Ok(self)
                 }
             }
@@ -63,16 +107,12 @@ impl CompilerState for CompilerDecoratorState {
                 ));
             }
 
-            _ => Err(CompilerError{
-                message: format!("Unexpected token!")
-            })
+            other => Err(CompilerError::unexpected(other))
         }
 
     }
 
     fn finalize(self: Box<Self>) -> Result<Environment, CompilerError> {
-        Err(CompilerError {
-            message: "Unfinished module declaration!".into()
-        })
+        Err(CompilerErrorKind::UnfinishedModule.into())
     }
 }
\ No newline at end of file
@@ -1,14 +1,30 @@
-use crate::{compiler::{CompilerError, CompilerState, file_reader::ImportAddress, states::CompilerBaseState}, lexer::token::{KeywordToken, LiteralToken, PunctuationToken, Token}};
+use crate::{compiler::{CompilerError, CompilerErrorKind, CompilerState, file_reader::ImportAddress, states::CompilerBaseState}, lexer::token::{KeywordToken, LiteralToken, PunctuationToken, Token}};
 
 pub struct CompilerImportState {
     base_state: CompilerBaseState,
     module_id: Option<ImportAddress>,
+    alias: Option<String>,
+    expects_alias: bool,
+    // Set for 'export import', which has to be followed by the 'import' keyword.
+    exported: bool,
+    expects_import: bool,
 }
 
 impl CompilerState for CompilerImportState {
     fn read(mut self: Box<Self>, token: crate::lexer::token::Token, compiler_environment: &mut crate::compiler::CompilerEnvironment) -> Result<Box<dyn CompilerState>, crate::compiler::CompilerError> {
         
-        if self.module_id.is_none() {
+        if self.expects_import {
+            match token {
+                Token::Keyword(KeywordToken::Import) => {
+                    self.expects_import = false;
+                    return Ok(self);
+                }
+
+                other => {
+                    return Err(CompilerError::unexpected_token("'import'", other));
+                }
+            }
+        } else if self.module_id.is_none() {
             match token {
                 Token::Identifier(ident) => {
                     self.module_id = Some(ImportAddress {
@@ -19,25 +35,48 @@ impl CompilerState for CompilerImportState {
                 }
 
                 other => {
-                    return Err(CompilerError {
-                        message: format!("Unexpected token. Expected identifier, found {:?}!", other)
-                    });
+                    return Err(CompilerError::unexpected_token("identifier", other));
+                }
+            }
+        } else if self.expects_alias {
+            match token {
+                Token::Identifier(ident) => {
+                    self.alias = Some(ident);
+                    self.expects_alias = false;
+                    return Ok(self);
+                }
+
+                other => {
+                    return Err(CompilerError::unexpected_token("alias", other));
                 }
             }
         } else {
             match token {
                 Token::Punctuation(PunctuationToken::Semicolon) => {
-                    compiler_environment.get_file_reader_mut().enqueue(self.module_id.unwrap());
+                    let module_id = self.module_id.unwrap();
+                    let alias = self.alias.unwrap_or_else(|| module_id.module_id.clone());
+
+                    if self.exported {
+                        compiler_environment.reexport(alias.clone());
+                    }
+                    compiler_environment.import(alias, module_id)?;
                     return Ok(Box::new(self.base_state))
                 }
 
+                Token::Keyword(KeywordToken::As) => {
+                    if self.alias.is_some() {
+                        return Err(CompilerErrorKind::DuplicateImportClause { clause: "alias" }.into())
+                    }
+
+                    self.expects_alias = true;
+                    return Ok(self)
+                }
+
                 Token::Keyword(KeywordToken::From) => {
                     let module_id = self.module_id.as_mut().unwrap();
 
                     if module_id.path.is_some() {
-                        return Err(CompilerError {
-                            message: "Cannot declare more than one location for an import!".into()
-                        })
+                        return Err(CompilerErrorKind::DuplicateImportClause { clause: "location" }.into())
                     }
 
                     module_id.path = Some(String::new());
@@ -51,25 +90,19 @@ impl CompilerState for CompilerImportState {
                         module_id.path = Some(path);
                         return Ok(self)
                     } else {
-                        return Err(CompilerError {
-                            message: "Unexpected String literal. Try adding 'from' to declare a location for an import!".into()
-                        })
+                        return Err(CompilerErrorKind::MissingImportLocation.into())
                     }
                 }
                 
                 other => {
-                    return Err(CompilerError {
-                        message: format!("Unexpected token. Expected ';', found {:?}!", other)
-                    });
+                    return Err(CompilerError::unexpected_token("';'", other));
                 }
             }
         }
     }
 
     fn finalize(self: Box<Self>) -> Result<crate::runtime::environment::Environment, crate::compiler::CompilerError> {
-        Err(CompilerError {
-            message: "Unfinished module declaration!".into()
-        })
+        Err(CompilerErrorKind::UnfinishedModule.into())
     }
 }
 
@@ -78,6 +111,18 @@ impl CompilerImportState {
         Self {
             base_state,
             module_id: None,
+            alias: None,
+            expects_alias: false,
+            exported: false,
+            expects_import: false,
+        }
+    }
+
+    pub fn reexport(base_state: CompilerBaseState) -> Self {
+        Self {
+            exported: true,
+            expects_import: true,
+            ..Self::new(base_state)
         }
     }
 }
\ No newline at end of file
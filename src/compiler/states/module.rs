@@ -1,19 +1,25 @@
 use std::rc::Rc;
 
-use crate::{compiler::{Compiler, CompilerEnvironment, CompilerError, CompilerState, states::{CompilerBaseState, decorator::CompilerDecoratorState, procedure::CompilerProcedureState, r#struct::CompilerStructState}}, lexer::token::{KeywordToken, ParenthesisType, PunctuationToken, Token}, runtime::{RuntimeError, module::Module}};
+use crate::{compiler::{CompilerEnvironment, CompilerError, CompilerErrorKind, CompilerState, expression_parser::ExpressionParser, states::{CompilerBaseState, decorator::CompilerDecoratorState, procedure::CompilerProcedureState, r#struct::CompilerStructState}}, lexer::token::{KeywordToken, OperatorToken, ParenthesisType, PunctuationToken, Token}, runtime::module::Module};
 
 #[derive(Debug, PartialEq, Eq)]
 enum ModuleSubstate {
     PreScope,
     InScope,
     Export,
+    Variable {
+        ident: Option<String>,
+        expression: Option<Vec<Token>>,
+    },
 }
 
 pub struct CompilerModuleState {
     base: CompilerBaseState,
     module_name: Option<String>,
+    module_id: Option<String>,
     substate: ModuleSubstate,
     module: Module,
+    pending_documentation: Vec<String>,
 }
 
 impl CompilerModuleState {
@@ -21,8 +27,18 @@ impl CompilerModuleState {
         Self {
             base,
             module_name: None,
+            module_id: None,
             substate: ModuleSubstate::PreScope,
-            module: Module::default()
+            module: Module::default(),
+            pending_documentation: Vec::new(),
+        }
+    }
+
+    pub fn take_documentation(&mut self) -> Option<String> {
+        if self.pending_documentation.is_empty() {
+            None
+        } else {
+            Some(self.pending_documentation.drain(..).collect::<Vec<_>>().join("\n"))
         }
     }
 
@@ -33,10 +49,16 @@ impl CompilerModuleState {
     pub fn get_name(&self) -> Option<&String> {
         self.module_name.as_ref()
     }
+
+    // The id the module is loaded under. It only differs from the declared name if another
+    // file already declared a module with the same name.
+    pub fn get_id(&self) -> Option<&String> {
+        self.module_id.as_ref()
+    }
 }
 
 impl CompilerState for CompilerModuleState {
-    fn read(mut self: Box<Self>, token: Token, _compiler_environment: &mut CompilerEnvironment) -> Result<Box<dyn CompilerState>, crate::compiler::CompilerError> {
+    fn read(mut self: Box<Self>, token: Token, compiler_environment: &mut CompilerEnvironment) -> Result<Box<dyn CompilerState>, crate::compiler::CompilerError> {
 
         match self.substate {
             ModuleSubstate::PreScope => {
@@ -45,26 +67,48 @@ impl CompilerState for CompilerModuleState {
                         self.module_name = Some(ident);
                         return Ok(self);
                     } else {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token! Expected identifier, found {:?}", token)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", token));
                     }
                 }
 
                 if let Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) = token {
+                    let module_name = self.module_name.clone().unwrap();
+
+                    let module_id = match &compiler_environment.current_source {
+                        Some(source) if self.base.environment.loaded_modules.contains_key(&module_name) => {
+                            format!("{}@{}", module_name, source)
+                        }
+                        _ => module_name.clone(),
+                    };
+
+                    if self.base.environment.loaded_modules.contains_key(&module_id) {
+                        return Err(CompilerErrorKind::DuplicateModule { module_id }.into());
+                    }
+
+                    for (alias, target) in compiler_environment.file_aliases() {
+                        self.module.set_alias(alias.clone(), target.clone());
+                    }
+                    for alias in compiler_environment.file_reexports() {
+                        self.module.reexport_alias(alias.clone());
+                    }
+                    if module_id != module_name && !compiler_environment.file_aliases().contains_key(&module_name) {
+                        self.module.set_alias(module_name, module_id.clone());
+                    }
+
+                    compiler_environment.declare_module(module_id.clone());
+                    self.module_id = Some(module_id);
+
                     self.substate = ModuleSubstate::InScope;
                     return Ok(self);
                 } else {
-                    return Err(CompilerError {
-                        message: format!("Unexpected token! Expected '{{', found {:?}", token)
-                    });
+                    return Err(CompilerError::unexpected_token("'{'", token));
                 }
             },
             ModuleSubstate::InScope => {
                 match token {
                     Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing)) => {
                         self.base.environment.load_module(
-                            self.module_name.unwrap(),
+                            self.module_id.unwrap(),
                             Rc::new(self.module)
                         );
                         Ok(Box::new(self.base))
@@ -89,10 +133,18 @@ impl CompilerState for CompilerModuleState {
                         return Ok(self);
                     }
 
+                    Token::Keyword(KeywordToken::Let) => {
+                        self.substate = ModuleSubstate::Variable { ident: None, expression: None };
+                        return Ok(self);
+                    }
+
+                    Token::DocComment(text) => {
+                        self.pending_documentation.push(text);
+                        return Ok(self);
+                    }
+
                     _ => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token! Expected procedure/struct declaration, found {:?}", token)
-                        });
+                        return Err(CompilerError::unexpected_token("procedure/struct declaration", token));
                     }
                 }
             },
@@ -113,20 +165,51 @@ impl CompilerState for CompilerModuleState {
                     }
 
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected identifier, found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", other));
                     }
                 }
             },
+            ModuleSubstate::Variable { ref mut ident, ref mut expression } => {
+                match (ident.take(), expression.take(), token) {
+                    (None, _, Token::Identifier(name)) => {
+                        *ident = Some(name);
+                    }
+                    (Some(name), None, Token::Operator(OperatorToken::Assignment)) => {
+                        *ident = Some(name);
+                        *expression = Some(Vec::new());
+                    }
+                    (Some(name), tokens, Token::Punctuation(PunctuationToken::Semicolon)) => {
+                        let initializer = tokens.map(ExpressionParser::parse).transpose()?;
+
+                        self.module.declare_variable(name, initializer)?;
+                        self.substate = ModuleSubstate::InScope;
+                    }
+                    (Some(name), Some(mut tokens), other) => {
+                        tokens.push(other);
+
+                        *ident = Some(name);
+                        *expression = Some(tokens);
+                    }
+                    (None, _, other) => {
+                        return Err(CompilerError::unexpected_token("identifier", other));
+                    }
+                    (Some(_), None, other) => {
+                        return Err(CompilerError::unexpected_token("'=' or ';'", other));
+                    }
+                }
+
+                return Ok(self);
+            },
         }
 
         
     }
 
     fn finalize(self: Box<Self>) -> Result<crate::runtime::environment::Environment, crate::compiler::CompilerError> {
-        Err(CompilerError {
-            message: "Unfinished module declaration!".into()
-        })
+        Err(CompilerErrorKind::UnfinishedModule.into())
+    }
+
+    fn accepts_documentation(&self) -> bool {
+        self.substate == ModuleSubstate::InScope
     }
 }
\ No newline at end of file
@@ -1,13 +1,17 @@
 use std::fmt::Arguments;
 
-use crate::{compiler::{Compiler, CompilerEnvironment, CompilerError, CompilerState, decorators::EntrypointDecorator, states::{decorator::{self, RawDecorator}, module::CompilerModuleState}}, lexer::token::{ParenthesisType, PunctuationToken, Token}, runtime::{ModuleAddress, procedures::CompiledProcedureBuilder}};
+use crate::{compiler::{CompilerEnvironment, CompilerError, CompilerErrorKind, CompilerState, decorators::{self, EntrypointDecorator}, type_checker, verifier, states::{decorator::RawDecorator, module::CompilerModuleState}}, lexer::token::{ParenthesisType, PunctuationToken, Token}, runtime::{ModuleAddress, procedures::CompiledProcedureBuilder}};
 
 #[derive(Debug, PartialEq, Eq)]
 enum ProcedureSubstate {
     Ident,
     PreArgument,
     Argument,
+    ArgumentType,
+    TypedArgument,
     PreInstructions,
+    ReturnType,
+    TypedReturn,
     Instructions,
 }
 
@@ -39,9 +43,7 @@ impl CompilerState for CompilerProcedureState {
                 self.name = Some(ident);
                 return Ok(self);
             } else {
-                return Err(CompilerError {
-                    message: format!("Unexpected token! Expected identifier, found {:?}", token)
-                });
+                return Err(CompilerError::unexpected_token("identifier", token));
             }
         }
 
@@ -51,9 +53,7 @@ impl CompilerState for CompilerProcedureState {
                     self.substate = ProcedureSubstate::PreArgument;
                     return Ok(self);
                 } else {
-                    Err(CompilerError {
-                        message: format!("Unexpected token! Expected '(', found {:?}", token)
-                    })
+                    Err(CompilerError::unexpected_token("'('", token))
                 }
             }
             ProcedureSubstate::PreArgument => {
@@ -71,14 +71,17 @@ impl CompilerState for CompilerProcedureState {
 
 
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token! Expected identifier, found {:?}", other)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", other));
                     }
                 }
             },
-            ProcedureSubstate::Argument => {
+            ProcedureSubstate::Argument | ProcedureSubstate::TypedArgument => {
                 match token {
+                    Token::Punctuation(PunctuationToken::Colon) if self.substate == ProcedureSubstate::Argument => {
+                        self.substate = ProcedureSubstate::ArgumentType;
+                        return Ok(self);
+                    }
+
                     Token::Punctuation(PunctuationToken::Comma) => {
                         self.substate = ProcedureSubstate::PreArgument;
                         return Ok(self);
@@ -90,29 +93,65 @@ impl CompilerState for CompilerProcedureState {
                     }
 
                     _ => {
-                        return Err(CompilerError{
-                            message: format!("Unexpected token! Expected ',' or ')', found {:?}", token)
-                        });
+                        return Err(CompilerError::unexpected_token("',' or ')'", token));
                     }
                 }
             }
-            ProcedureSubstate::PreInstructions => {
+            ProcedureSubstate::ArgumentType => {
+                if let Token::PrimitiveType(argument_type) = token {
+                    self.procedure = self.procedure.set_argument_type(argument_type);
+                    self.substate = ProcedureSubstate::TypedArgument;
+                    return Ok(self);
+                } else {
+                    return Err(CompilerError::unexpected_token("type", token));
+                }
+            }
+            ProcedureSubstate::PreInstructions | ProcedureSubstate::TypedReturn => {
                 if let Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) = token {
+                    let is_entrypoint = self.decorators.iter().any(|decorator| decorator.get_ident() == "entrypoint");
+
+                    self.procedure = self.procedure
+                        .with_strict_conditions(compiler_environment.strict_conditions())
+                        .with_implicit_result(compiler_environment.implicit_results() && is_entrypoint)
+                        .with_file(compiler_environment.current_source.as_ref());
                     self.substate = ProcedureSubstate::Instructions;
                     return Ok(self);
+                } else if token == Token::Punctuation(PunctuationToken::Arrow) && self.substate == ProcedureSubstate::PreInstructions {
+                    self.substate = ProcedureSubstate::ReturnType;
+                    return Ok(self);
                 } else {
-                    return Err(CompilerError{
-                        message: format!("Unexpected token! Expected '{{', found {:?}", token)
-                    });
+                    return Err(CompilerError::unexpected_token("'{'", token));
                 }
             },
+            ProcedureSubstate::ReturnType => {
+                if let Token::PrimitiveType(return_type) = token {
+                    self.procedure = self.procedure.set_return_type(return_type);
+                    self.substate = ProcedureSubstate::TypedReturn;
+                    return Ok(self);
+                } else {
+                    return Err(CompilerError::unexpected_token("type", token));
+                }
+            }
             ProcedureSubstate::Instructions => {
                 if let Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing)) = token {
                     if self.procedure.scope_stack_size() == 0 && !self.procedure.is_scanning() {
-                        let procedure = self.procedure.build()?;
-                        let name = self.name.ok_or(CompilerError {
-                            message: "Missing procedure name!".into()
-                        })?;
+                        let mut procedure = self.procedure;
+                        for decorator in &self.decorators {
+                            if decorator.get_ident() == "budget" {
+                                procedure = procedure.with_budget(decorators::call_budget(decorator)?);
+                            }
+                        }
+
+                        let procedure = procedure.build()?;
+                        let name = self.name.ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingName { item: "procedure" }))?;
+
+                        if compiler_environment.verifies() {
+                            verifier::verify(&procedure).map_err(|err| CompilerErrorKind::VerificationFailed { procedure: name.clone(), reason: err.message() })?;
+                        }
+
+                        if compiler_environment.type_checks() {
+                            type_checker::check(&procedure).map_err(|err| CompilerErrorKind::TypeMismatch { procedure: name.clone(), reason: err.message() })?;
+                        }
 
                         self.module.get_module_mut().insert_procedure(
                             name.clone(),
@@ -120,25 +159,32 @@ impl CompilerState for CompilerProcedureState {
                             false
                         );
 
+                        if let Some(documentation) = self.module.take_documentation() {
+                            self.module.get_module_mut().set_documentation(name.clone(), documentation);
+                        }
+
                         for decorator in self.decorators {
                             match decorator.get_ident() as &str {
+                                "entrypoint" if !decorator.get_arguments().is_empty() => {
+                                    return Err(CompilerErrorKind::DecoratorArguments { name: "entrypoint".into() }.into());
+                                }
+
                                 "entrypoint" => {
                                     compiler_environment.push_decorator(
                                         Box::new(EntrypointDecorator::new(
                                             ModuleAddress::new(
                                                 self.module
-                                                    .get_name().ok_or(CompilerError {
-                                                        message: "Contained module has no name!".into()
-                                                    })?.to_owned(),
+                                                    .get_id().ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingName { item: "module" }))?.to_owned(),
                                                     name.clone()
                                                 )
                                         ))
                                     );
                                 }
 
-                                other => {return Err(CompilerError {
-                                    message: format!("Unsupported decorator '{}'!", other)
-                                })}
+                                // Applied before the procedure was built.
+                                "budget" => {}
+
+                                other => {return Err(CompilerErrorKind::UnsupportedDecorator { name: other.to_string() }.into())}
                             }
                         }
 
@@ -146,15 +192,13 @@ impl CompilerState for CompilerProcedureState {
                     }
                 }
 
-                self.procedure = self.procedure.read(token)?;
+                self.procedure = self.procedure.read(token, compiler_environment.current_span())?;
                 Ok(self)
             },
         }
     }
 
     fn finalize(self: Box<Self>) -> Result<crate::runtime::environment::Environment, crate::compiler::CompilerError> {
-        Err(CompilerError {
-            message: "Unfinished module declaration!".into()
-        })
+        Err(CompilerErrorKind::UnfinishedModule.into())
     }
 }
\ No newline at end of file
@@ -1,8 +1,9 @@
-use crate::{compiler::{CompilerError, CompilerState, states::module::CompilerModuleState}, lexer::token::{KeywordToken, ParenthesisType, PunctuationToken, Token}, runtime::{ModuleAddress, Struct, Value}};
+use crate::{compiler::{CompilerError, CompilerErrorKind, CompilerState, states::module::CompilerModuleState}, lexer::token::{KeywordToken, ParenthesisType, PunctuationToken, Token}, runtime::{ModuleAddress, Struct, Value}};
 
 enum CompilerStructSubstate {
     Identifier,
     PreFields,
+    PrivateConstructor,
     Field {
         is_public: bool,
     },
@@ -15,6 +16,7 @@ pub struct CompilerStructState {
 
     identifier: Option<String>,
     fields: Vec<(String, bool)>,
+    private_constructor: bool,
 }
 
 impl CompilerState for CompilerStructState {
@@ -29,9 +31,7 @@ impl CompilerState for CompilerStructState {
                     }
 
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected identifier, found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", other));
                     }
                 }
             },
@@ -44,10 +44,26 @@ impl CompilerState for CompilerStructState {
                         return Ok(self);
                     }
 
+                    Token::Keyword(KeywordToken::Private) if !self.private_constructor => {
+                        self.substate = CompilerStructSubstate::PrivateConstructor;
+                        return Ok(self);
+                    }
+
+                    other => {
+                        return Err(CompilerError::unexpected_token("'{'", other));
+                    }
+                }
+            },
+            CompilerStructSubstate::PrivateConstructor => {
+                match token {
+                    Token::Keyword(KeywordToken::Constructor) => {
+                        self.private_constructor = true;
+                        self.substate = CompilerStructSubstate::PreFields;
+                        return Ok(self);
+                    }
+
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected '{{', found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("'constructor'", other));
                     }
                 }
             },
@@ -63,11 +79,14 @@ impl CompilerState for CompilerStructState {
                         self.substate = CompilerStructSubstate::AfterField;
                         return Ok(self);
                     }
+
+                    // Empty structs and a trailing comma after the last field
+                    Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing)) if !is_public => {
+                        return self.finish();
+                    }
                     
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected identifier, found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", other));
                     }
                 }
             },
@@ -81,30 +100,11 @@ impl CompilerState for CompilerStructState {
                     }
 
                     Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing)) => {
-                        let struct_id = ModuleAddress::new(
-                            self.module.get_name().unwrap().to_owned(),
-                            self.identifier.clone().unwrap()
-                        );
-
-                        let mut prototype = Struct::new(struct_id);
-
-                        let members = prototype.get_members_mut();
-
-                        for field in self.fields {
-                            members.insert_member(field.0, Value::Null, field.1).map_err(|err| CompilerError {
-                                message: format!("Error while parsing struct prototype: {:?}", err)
-                            })?;
-                        }
-
-                        self.module.get_module_mut().insert_struct(self.identifier.unwrap(), prototype, false);
-
-                        return Ok(Box::new(self.module));
+                        return self.finish();
                     }
 
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected ',' or '}}', found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("',' or '}'", other));
                     }
                 }
             }
@@ -112,19 +112,49 @@ impl CompilerState for CompilerStructState {
     }
 
     fn finalize(self: Box<Self>) -> Result<crate::runtime::environment::Environment, crate::compiler::CompilerError> {
-        Err(CompilerError {
-            message: "Unfinished module declaration!".into()
-        })
+        Err(CompilerErrorKind::UnfinishedModule.into())
     }
 }
 
 impl CompilerStructState {
+    // Registers the struct with the module once its closing brace is read.
+    fn finish(mut self: Box<Self>) -> Result<Box<dyn CompilerState>, CompilerError> {
+        let struct_id = ModuleAddress::new(
+            self.module.get_id().unwrap().to_owned(),
+            self.identifier.clone().unwrap()
+        );
+
+        let duplicate = |field: &str| CompilerErrorKind::DuplicateField { struct_id: struct_id.to_string(), field: field.to_string() };
+        let mut prototype = Struct::new(struct_id.clone());
+
+        let members = prototype.get_members_mut();
+
+        for (field, is_public) in self.fields {
+            members.insert_member(field.as_str().into(), Value::Null, is_public).map_err(|_| duplicate(&field))?;
+        }
+
+        let identifier = self.identifier.unwrap();
+
+        if let Some(documentation) = self.module.take_documentation() {
+            self.module.get_module_mut().set_documentation(identifier.clone(), documentation);
+        }
+
+        if self.private_constructor {
+            self.module.get_module_mut().set_private_constructor(identifier.clone());
+        }
+
+        self.module.get_module_mut().insert_struct(identifier, prototype, false);
+
+        return Ok(Box::new(self.module));
+    }
+
     pub fn new(module: CompilerModuleState) -> Self {
         Self {
             module,
             substate: CompilerStructSubstate::Identifier,
             identifier: None,
             fields: Vec::new(),
+            private_constructor: false,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crate::{compiler::{CompilerError, CompilerErrorKind}, lexer::token::PrimitiveTypeToken, runtime::{Expression, procedures::{CompiledProcedure, Instruction}, symbol::Symbol}};
+
+// Statically known variable types. Variables missing from the map have an unknown type.
+pub type TypeEnvironment = HashMap<Symbol, PrimitiveTypeToken>;
+
+type Blocks = Vec<Vec<(Symbol, Option<PrimitiveTypeToken>)>>;
+
+// Infers the type of a binary operation. Unknown operand types are never reported;
+// `rule` returns None for combinations that are known to fail at runtime.
+pub(crate) fn infer_binary(
+    lhs: &dyn Expression,
+    rhs: &dyn Expression,
+    types: &TypeEnvironment,
+    rule: impl FnOnce(&PrimitiveTypeToken, &PrimitiveTypeToken) -> Option<PrimitiveTypeToken>,
+    operation: &'static str,
+) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+    let lhs = lhs.static_type(types)?;
+    let rhs = rhs.static_type(types)?;
+
+    let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+        return Ok(None);
+    };
+
+    match rule(&lhs, &rhs) {
+        Some(result) => Ok(Some(result)),
+        None => Err(CompilerErrorKind::InvalidOperands { operation, lhs, rhs: Some(rhs) }.into()),
+    }
+}
+
+pub(crate) fn expect_type(
+    expression: &dyn Expression,
+    types: &TypeEnvironment,
+    expected: PrimitiveTypeToken,
+    subject: &'static str,
+) -> Result<(), CompilerError> {
+    match expression.static_type(types)? {
+        Some(found) if found != expected => Err(CompilerErrorKind::ExpectedType { subject, expected, found }.into()),
+        _ => Ok(()),
+    }
+}
+
+// Propagates variable types along every path through the procedure and reports operations
+// whose operand types are known to be incompatible.
+pub fn check(procedure: &CompiledProcedure) -> Result<(), CompilerError> {
+    let instructions = &procedure.instructions;
+
+    let mut entry: Vec<(Symbol, Option<PrimitiveTypeToken>)> = Vec::new();
+    for (identifier, argument_type) in procedure.arguments_identifiers.iter().zip(procedure.argument_types.iter()) {
+        entry.push((*identifier, argument_type.clone()));
+    }
+
+    let mut states: HashMap<usize, Blocks> = HashMap::new();
+    let mut pending = vec![(0, vec![entry])];
+
+    while let Some((pc, blocks)) = pending.pop() {
+        let Some(instruction) = instructions.get(pc) else {
+            continue;
+        };
+
+        let blocks = match states.get(&pc) {
+            Some(known) => {
+                let merged = merge(known, &blocks);
+                if merged == *known {
+                    continue;
+                }
+                merged
+            }
+            None => blocks,
+        };
+        states.insert(pc, blocks.clone());
+
+        let mut next = blocks;
+        match instruction {
+            Instruction::PushVarToScope { identifier } => {
+                if let Some(block) = next.last_mut() {
+                    block.push((*identifier, None));
+                }
+            }
+            Instruction::PopVarFromScope { identifier } => {
+                for block in next.iter_mut().rev() {
+                    if let Some(position) = block.iter().position(|(ident, _)| ident == identifier) {
+                        block.remove(position);
+                        break;
+                    }
+                }
+            }
+            Instruction::GrowStack => next.push(Vec::new()),
+            Instruction::ShrinkStack => {
+                next.pop();
+            }
+            Instruction::EvaluateExpression { expression, target } => {
+                if let Some(identifier) = target.as_ref().and_then(|target| target.as_identifier()) {
+                    let value_type = expression.static_type(&flatten(&next)).ok().flatten();
+                    assign(&mut next, identifier, value_type);
+                }
+            }
+            Instruction::JumpConditional { jump_target, .. } => {
+                pending.push((*jump_target, next.clone()));
+
+                if instruction.is_unconditional_jump() {
+                    continue;
+                }
+            }
+            Instruction::Try { catch_target } => {
+                // The try block may have reassigned any variable before failing.
+                let unknown = next.iter()
+                    .map(|block| block.iter().map(|(identifier, _)| (*identifier, None)).collect())
+                    .collect();
+                pending.push((*catch_target, unknown));
+            }
+            Instruction::Catch { identifier } => {
+                if let Some(block) = next.last_mut() {
+                    block.push((*identifier, None));
+                }
+            }
+            Instruction::EndTry => {}
+            Instruction::Return { .. } |
+            Instruction::Throw { .. } => continue,
+        }
+
+        pending.push((pc + 1, next));
+    }
+
+    let mut pcs: Vec<_> = states.keys().copied().collect();
+    pcs.sort();
+
+    for pc in pcs {
+        let types = flatten(&states[&pc]);
+
+        check_instruction(procedure, &instructions[pc], &types).map_err(|err| CompilerErrorKind::AtInstruction { instruction: pc, cause: Box::new(err.kind) })?;
+    }
+
+    Ok(())
+}
+
+fn check_instruction(procedure: &CompiledProcedure, instruction: &Instruction, types: &TypeEnvironment) -> Result<(), CompilerError> {
+    match instruction {
+        Instruction::EvaluateExpression { expression, target } => {
+            if let Some(target) = target {
+                target.static_type(types)?;
+            }
+            expression.static_type(types)?;
+        }
+        Instruction::JumpConditional { condition_expression, .. } => {
+            expect_type(condition_expression.as_ref(), types, PrimitiveTypeToken::Boolean, "condition")?;
+        }
+        Instruction::Return { expression } => {
+            match &procedure.return_type {
+                Some(return_type) => expect_type(expression.as_ref(), types, return_type.clone(), "return value")?,
+                None => {
+                    expression.static_type(types)?;
+                }
+            }
+        }
+        Instruction::Throw { expression } => {
+            expression.static_type(types)?;
+        }
+        Instruction::PushVarToScope { .. } |
+        Instruction::PopVarFromScope { .. } |
+        Instruction::GrowStack |
+        Instruction::ShrinkStack |
+        Instruction::Try { .. } |
+        Instruction::EndTry |
+        Instruction::Catch { .. } => {}
+    }
+
+    Ok(())
+}
+
+fn assign(blocks: &mut Blocks, identifier: Symbol, value_type: Option<PrimitiveTypeToken>) {
+    for block in blocks.iter_mut().rev() {
+        if let Some(variable) = block.iter_mut().find(|(ident, _)| *ident == identifier) {
+            variable.1 = value_type;
+            return;
+        }
+    }
+}
+
+fn flatten(blocks: &Blocks) -> TypeEnvironment {
+    let mut types = TypeEnvironment::new();
+
+    for (identifier, variable_type) in blocks.iter().flatten() {
+        match variable_type {
+            Some(variable_type) => types.insert(*identifier, variable_type.clone()),
+            None => types.remove(identifier),
+        };
+    }
+
+    types
+}
+
+// Keeps a variable's type only if it agrees on both incoming paths.
+fn merge(known: &Blocks, incoming: &Blocks) -> Blocks {
+    known.iter()
+        .zip(incoming.iter())
+        .map(|(known, incoming)| known.iter()
+            .map(|(identifier, known_type)| {
+                let incoming_type = incoming.iter()
+                    .find(|(ident, _)| ident == identifier)
+                    .and_then(|(_, incoming_type)| incoming_type.clone());
+
+                if *known_type == incoming_type {
+                    (*identifier, incoming_type)
+                } else {
+                    (*identifier, None)
+                }
+            })
+            .collect())
+        .collect()
+}
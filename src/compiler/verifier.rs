@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::{compiler::{CompilerError, CompilerErrorKind}, runtime::procedures::{CompiledProcedure, Instruction}};
+
+// Walks every path through the instruction list, checking that jumps stay in bounds,
+// that each GrowStack is matched by a ShrinkStack and that every path ends in a Return or Throw.
+pub fn verify(procedure: &CompiledProcedure) -> Result<(), CompilerError> {
+    let instructions = &procedure.instructions;
+
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut pending = vec![(0, 0)];
+
+    while let Some((pc, depth)) = pending.pop() {
+        let Some(instruction) = instructions.get(pc) else {
+            return Err(CompilerErrorKind::MissingReturn { instruction: pc }.into());
+        };
+
+        if let Some(known_depth) = depths.get(&pc) {
+            if *known_depth != depth {
+                return Err(CompilerErrorKind::InconsistentStackDepth { instruction: pc, depths: (*known_depth, depth) }.into());
+            }
+            continue;
+        }
+        depths.insert(pc, depth);
+
+        match instruction {
+            Instruction::GrowStack => pending.push((pc + 1, depth + 1)),
+            Instruction::ShrinkStack => {
+                if depth == 0 {
+                    return Err(CompilerErrorKind::StackUnderflow { instruction: pc }.into());
+                }
+                pending.push((pc + 1, depth - 1));
+            }
+            Instruction::JumpConditional { jump_target, .. } => {
+                if *jump_target >= instructions.len() {
+                    return Err(CompilerErrorKind::TargetOutOfBounds { instruction: pc, target: *jump_target, length: instructions.len() }.into());
+                }
+                if !instruction.is_unconditional_jump() {
+                    pending.push((pc + 1, depth));
+                }
+                pending.push((*jump_target, depth));
+            }
+            Instruction::Try { catch_target } => {
+                if *catch_target >= instructions.len() {
+                    return Err(CompilerErrorKind::TargetOutOfBounds { instruction: pc, target: *catch_target, length: instructions.len() }.into());
+                }
+                pending.push((pc + 1, depth));
+                pending.push((*catch_target, depth));
+            }
+            Instruction::Return { .. } |
+            Instruction::Throw { .. } => {}
+            Instruction::PushVarToScope { .. } |
+            Instruction::PopVarFromScope { .. } |
+            Instruction::EvaluateExpression { .. } |
+            Instruction::EndTry |
+            Instruction::Catch { .. } => pending.push((pc + 1, depth)),
+        }
+    }
+
+    Ok(())
+}
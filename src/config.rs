@@ -0,0 +1,144 @@
+use std::{env, fs, path::{Path, PathBuf}, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind}, manifest::Manifest, runtime::RuntimeObject};
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Runtime settings. Each one is taken from the first of these that sets it:
+//   1. command line flags: --stack-limit <n>, --fuel <n>, --memory-limit <bytes>, --strict and
+//      --no-strict
+//   2. environment variables: OTR_STACK_LIMIT, OTR_FUEL, OTR_MEMORY_LIMIT and OTR_STRICT
+//   3. the project manifest, for 'strict_conditions'
+//   4. the user config file, ~/.otr/config.toml
+// Unset, the stack limit is 10000, there is neither fuel nor a memory limit and conditions are
+// strict.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // Maximum depth of nested calls, the entrypoint included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_limit: Option<usize>,
+
+    // Maximum number of instructions a single run may execute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel: Option<u64>,
+
+    // Maximum approximate bytes held by the values of a run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<usize>,
+
+    // Whether if and while conditions have to be booleans.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl Config {
+    // Settings of this config, falling back to the other one for those left unset.
+    pub fn or(self, fallback: Config) -> Config {
+        Config {
+            stack_limit: self.stack_limit.or(fallback.stack_limit),
+            fuel: self.fuel.or(fallback.fuel),
+            memory_limit: self.memory_limit.or(fallback.memory_limit),
+            strict: self.strict.or(fallback.strict),
+        }
+    }
+
+    // Takes the flags out of the command line arguments, wherever they appear, and returns the
+    // remaining arguments.
+    pub fn from_args(args: Vec<String>) -> Result<(Config, Vec<String>), CompilerError> {
+        let mut config = Config::default();
+        let mut remaining = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--stack-limit" => config.stack_limit = Some(flag_value("--stack-limit", args.next())?),
+                "--fuel" => config.fuel = Some(flag_value("--fuel", args.next())?),
+                "--memory-limit" => config.memory_limit = Some(flag_value("--memory-limit", args.next())?),
+                "--strict" => config.strict = Some(true),
+                "--no-strict" => config.strict = Some(false),
+                _ => remaining.push(arg),
+            }
+        }
+
+        Ok((config, remaining))
+    }
+
+    pub fn from_manifest(manifest: &Manifest) -> Config {
+        Config {
+            strict: manifest.strict_conditions,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> Result<Config, CompilerError> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    // Like `from_env`, but reads the variables through the given lookup.
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Config, CompilerError> {
+        Ok(Config {
+            stack_limit: parse_var("OTR_STACK_LIMIT", lookup("OTR_STACK_LIMIT"))?,
+            fuel: parse_var("OTR_FUEL", lookup("OTR_FUEL"))?,
+            memory_limit: parse_var("OTR_MEMORY_LIMIT", lookup("OTR_MEMORY_LIMIT"))?,
+            strict: lookup("OTR_STRICT").map(|value| parse_flag("OTR_STRICT", &value)).transpose()?,
+        })
+    }
+
+    // The user config file, usually '~/.otr/config.toml'. None if the home directory is unknown.
+    pub fn user_path() -> Option<PathBuf> {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".otr").join(CONFIG_FILE_NAME))
+    }
+
+    pub fn load_user() -> Result<Config, CompilerError> {
+        match Self::user_path() {
+            Some(path) => Self::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    // A missing config file is the same as an empty one.
+    pub fn load(path: &Path) -> Result<Config, CompilerError> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(path).map_err(|err| CompilerError::io(format!("Config '{}'", path.display()), "read", err))?;
+
+        toml::from_str(&content).map_err(|err| CompilerError::invalid_file(format!("Config '{}'", path.display()), err))
+    }
+
+    pub fn strict_conditions(&self) -> bool {
+        self.strict.unwrap_or(true)
+    }
+
+    // Sets the runtime limits of the compiled program.
+    pub fn apply(&self, runtime_object: &RuntimeObject) {
+        runtime_object.set_stack_limit(self.stack_limit);
+        runtime_object.set_fuel(self.fuel);
+        runtime_object.set_memory_limit(self.memory_limit);
+    }
+}
+
+fn parse_var<T: FromStr>(name: &'static str, value: Option<String>) -> Result<Option<T>, CompilerError> {
+    value
+        .map(|value| value.trim().parse().map_err(|_| CompilerError::from(CompilerErrorKind::InvalidSetting { name, expected: "a non-negative integer", found: value.as_str().into() })))
+        .transpose()
+}
+
+fn flag_value<T: FromStr>(flag: &'static str, value: Option<String>) -> Result<T, CompilerError> {
+    parse_var(flag, value)?.ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingSettingValue { flag: flag.to_string() }))
+}
+
+// Accepts the usual spellings of booleans, e.g. in 'OTR_STRICT=0'.
+fn parse_flag(name: &'static str, value: &str) -> Result<bool, CompilerError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(CompilerErrorKind::InvalidSetting { name, expected: "true or false", found: value.into() }.into()),
+    }
+}
@@ -0,0 +1,140 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{compiler::{Compiler, CompilerError, file_reader::{FileReader, ImportAddress}, optimizer}, runtime::RuntimeObject};
+
+pub const CONFORMANCE_DIR: &str = "tests/conformance";
+
+const EXPECTED_EXTENSION: &str = "expected";
+
+// A program paired with the output it has to produce. Programs without an expected-output
+// file are not cases themselves, so cases can import helper modules from the same directory.
+#[derive(Debug, Clone)]
+pub struct ConformanceCase {
+    pub name: String,
+    pub root: PathBuf,
+    pub expected_path: PathBuf,
+}
+
+// Variations of how a case is run. Every one of them has to produce the same output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunOptions {
+    pub optimize: bool,
+    // Runs the program loaded back from its serialized form instead of the compiled one.
+    pub reload: bool,
+}
+
+#[derive(Debug)]
+pub struct CaseResult {
+    pub case: ConformanceCase,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+pub fn discover(dir: &Path) -> Result<Vec<ConformanceCase>, CompilerError> {
+    let entries = fs::read_dir(dir).map_err(|err| CompilerError::io(format!("Conformance directory '{}'", dir.display()), "read", err))?;
+
+    let mut cases = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().is_none_or(|extension| extension != "otr") {
+            continue;
+        }
+
+        let expected_path = path.with_extension(EXPECTED_EXTENSION);
+        if !expected_path.exists() {
+            continue;
+        }
+
+        let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            continue;
+        };
+
+        cases.push(ConformanceCase { name, root: dir.to_path_buf(), expected_path });
+    }
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(cases)
+}
+
+// Compiles and runs the case, rendering the outcome the same way the expected-output files do:
+// the debug representation of the value, 'Err(<message>)' if running fails, 'exit: <code>' after
+// 'Sys::exit' or 'error: <message>' if compilation fails. Text written through the IO module
+// comes first.
+pub fn run_case(case: &ConformanceCase) -> String {
+    run_case_with(case, RunOptions::default())
+}
+
+pub fn run_case_with(case: &ConformanceCase, options: RunOptions) -> String {
+    let mut file_reader = FileReader::new(case.root.clone());
+    file_reader.enqueue(ImportAddress { module_id: case.name.clone(), path: None });
+
+    let mut runtime_object = match Compiler::new(file_reader).with_verification(true).compile() {
+        Ok(runtime_object) => runtime_object,
+        Err(err) => return format!("error: {}", err.message()),
+    };
+
+    if options.optimize {
+        if let Err(err) = optimizer::optimize(&mut runtime_object) {
+            return format!("error: {}", err.message());
+        }
+    }
+
+    if options.reload {
+        runtime_object = match runtime_object.to_bytes().and_then(|bytes| RuntimeObject::from_bytes(&bytes)) {
+            Ok(runtime_object) => runtime_object,
+            Err(err) => return format!("error: {}", err.message()),
+        };
+    }
+
+    runtime_object.capture_output();
+    runtime_object.provide_input(Vec::new());
+
+    let result = runtime_object.execute();
+    let output = runtime_object.take_output();
+
+    match runtime_object.exit_code() {
+        Some(code) => format!("{}exit: {}", output, code),
+        None => match result {
+            Ok(value) => format!("{}{:?}", output, Ok::<_, ()>(value)),
+            Err(err) => format!("{}Err({})", output, err),
+        },
+    }
+}
+
+pub fn run(dir: &Path) -> Result<Vec<CaseResult>, CompilerError> {
+    run_with(dir, RunOptions::default())
+}
+
+pub fn run_with(dir: &Path, options: RunOptions) -> Result<Vec<CaseResult>, CompilerError> {
+    let mut results = Vec::new();
+
+    for case in discover(dir)? {
+        let expected = fs::read_to_string(&case.expected_path).map_err(|err| CompilerError::io(format!("Expected output '{}'", case.expected_path.display()), "read", err))?;
+
+        let actual = run_case_with(&case, options);
+
+        results.push(CaseResult { case, expected: expected.trim_end().to_string(), actual });
+    }
+
+    Ok(results)
+}
+
+// Overwrites every expected-output file with the output the case currently produces.
+pub fn bless(dir: &Path) -> Result<usize, CompilerError> {
+    let cases = discover(dir)?;
+
+    for case in &cases {
+        fs::write(&case.expected_path, run_case(case) + "\n").map_err(|err| CompilerError::io(format!("Expected output '{}'", case.expected_path.display()), "written", err))?;
+    }
+
+    Ok(cases.len())
+}
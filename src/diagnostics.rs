@@ -0,0 +1,62 @@
+use serde::Serialize;
+
+pub mod codes;
+pub mod internal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+// A problem found while checking a program. Fields that are not known for a problem are
+// serialized as null so consumers can rely on every key being present.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    pub message: String,
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(file: Option<String>, message: String, code: Option<&str>) -> Self {
+        Self { severity: Severity::Error, file, span: None, message, code: code.map(str::to_string) }
+    }
+
+    // A single line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        write!(f, "{}", severity)?;
+
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+
+        match (&self.file, &self.span) {
+            (Some(file), Some(span)) => write!(f, " {}:{}:{}", file, span.line, span.column)?,
+            (Some(file), None) => write!(f, " {}", file)?,
+            _ => {}
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
@@ -0,0 +1,217 @@
+use super::Severity;
+
+// A stable identifier for a kind of problem. The error kinds name their code, see
+// `CompilerErrorKind::code` and `RuntimeErrorKind::code`.
+#[derive(Debug)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+    pub fix: &'static str,
+}
+
+impl ErrorCode {
+    // The long form printed by 'otr explain'.
+    pub fn explain(&self) -> String {
+        format!(
+            "{}: {}\n\n{}\n\nExample:\n\n{}\n\nFix:\n\n{}\n",
+            self.code,
+            self.title,
+            self.description,
+            indent(self.example),
+            self.fix,
+        )
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        severity: Severity::Error,
+        title: "Invalid parenthesis structure",
+        description: "A parenthesis, square bracket or curly brace is never closed, or is closed by the wrong kind of bracket.",
+        example: "let total = (1 + 2;",
+        fix: "Close every bracket with its counterpart, innermost first: 'let total = (1 + 2);'.",
+    },
+    ErrorCode {
+        code: "E0002",
+        severity: Severity::Error,
+        title: "Unexpected token",
+        description: "The parser found a token that cannot appear at this position, e.g. a missing ';' or a keyword used as a name.",
+        example: "let x = 1\nlet y = 2;",
+        fix: "Check the statement before the reported token. Most often a ';' or a closing brace is missing.",
+    },
+    ErrorCode {
+        code: "E0003",
+        severity: Severity::Error,
+        title: "Malformed source text",
+        description: "The source could not be split into tokens, e.g. because of an unterminated string or char literal or an unknown character.",
+        example: "let name = \"unterminated;",
+        fix: "Terminate every literal and remove characters the language does not know.",
+    },
+    ErrorCode {
+        code: "E0004",
+        severity: Severity::Error,
+        title: "Malformed expression",
+        description: "An expression has operands without an operator between them or an operator without operands.",
+        example: "let x = * 2;",
+        fix: "Put exactly one binary operator between two operands: 'let x = 3 * 2;'.",
+    },
+    ErrorCode {
+        code: "E0005",
+        severity: Severity::Error,
+        title: "Missing or duplicate entrypoint",
+        description: "A program needs exactly one procedure marked with '@entrypoint'. It is called with an array of the program's arguments as Strings, which it may ignore.",
+        example: "module Main {\n    proc main(args) {\n        return 0;\n    }\n}",
+        fix: "Mark the procedure the program starts at with '@entrypoint' and remove the decorator from all others.",
+    },
+    ErrorCode {
+        code: "E0006",
+        severity: Severity::Error,
+        title: "Module could not be loaded",
+        description: "An imported module does not exist, its file declares no module or two files declare the same module.",
+        example: "import Utils;",
+        fix: "Make sure 'Utils.otr' exists in the project root or a dependency and declares 'module Utils { ... }'.",
+    },
+    ErrorCode {
+        code: "E0007",
+        severity: Severity::Error,
+        title: "Unresolved identifier",
+        description: "A name refers to no variable, procedure or struct field visible at this point.",
+        example: "proc main() {\n    return count;\n}",
+        fix: "Declare the variable with 'let' before using it or check the spelling of the name.",
+    },
+    ErrorCode {
+        code: "E0008",
+        severity: Severity::Error,
+        title: "Use of moved value",
+        description: "Structs move when a variable holding one is read. Reading the variable again afterwards fails. References follow a struct as it moves and die once it is dropped.",
+        example: "let a = Main::Point { x: 1, y: 2 };\nlet b = a;\nreturn a;",
+        fix: "Use 'clone a' to copy the struct or 'ref a' to share it.",
+    },
+    ErrorCode {
+        code: "E0009",
+        severity: Severity::Error,
+        title: "Private item accessed",
+        description: "Fields that are not 'public' and procedures or structs that are not exported can only be used within their own module.",
+        example: "struct Point { x }\n...\nreturn point.x;",
+        fix: "Mark the field 'public' or add 'export Name;' to the defining module.",
+    },
+    ErrorCode {
+        code: "E0010",
+        severity: Severity::Error,
+        title: "Index out of bounds",
+        description: "An array or string was indexed past its end. Negative indices count from the end and are bounded as well.",
+        example: "let values = [1, 2];\nreturn values[2];",
+        fix: "Check the index against 'Arrays::size' or 'Strings::length' first.",
+    },
+    ErrorCode {
+        code: "E0011",
+        severity: Severity::Error,
+        title: "Verification failed",
+        description: "A compiled procedure has a path that does not return or leaves its scopes unbalanced.",
+        example: "proc sign(n) {\n    if (n < 0) {\n        return -1;\n    }\n}",
+        fix: "Return a value on every path through the procedure.",
+    },
+    ErrorCode {
+        code: "E0012",
+        severity: Severity::Error,
+        title: "Type mismatch",
+        description: "A value does not have the type its argument, return type or condition requires.",
+        example: "proc double(n: Integer) -> Integer {\n    return \"twice\";\n}",
+        fix: "Convert the value, e.g. with 'Numbers::parse', or change the declared type.",
+    },
+    ErrorCode {
+        code: "E0013",
+        severity: Severity::Error,
+        title: "Misplaced statement",
+        description: "'else' has to follow an 'if' block, 'catch' has to follow a 'try' block and 'break' has to be inside a loop.",
+        example: "try {\n    Main::run();\n}\nreturn 0;",
+        fix: "Add the missing 'catch (err) { ... }' block or move the statement to where it belongs.",
+    },
+    ErrorCode {
+        code: "E0014",
+        severity: Severity::Error,
+        title: "Capability not granted",
+        description: "The program uses a builtin module that the manifest denies with 'deny = [...]'.",
+        example: "deny = [\"Sys\"]",
+        fix: "Remove the module from 'deny' in otr.toml or check 'Capabilities::has' before using it.",
+    },
+    ErrorCode {
+        code: "E0015",
+        severity: Severity::Error,
+        title: "Budget exceeded",
+        description: "A procedure decorated with '@budget' executed more instructions or allocated more values than allowed, or the program ran out of the fuel set with '--fuel', OTR_FUEL or the config file.",
+        example: "@budget(instructions = 10)\nproc work() { ... }",
+        fix: "Raise the limit or reduce the work done per call.",
+    },
+    ErrorCode {
+        code: "E0016",
+        severity: Severity::Error,
+        title: "Invalid decorator",
+        description: "A decorator is unknown or was given arguments it does not accept.",
+        example: "@entrypoint(fast = true)\nproc main() { ... }",
+        fix: "Use '@entrypoint' without arguments and '@budget(instructions = N, allocations = N)'.",
+    },
+    ErrorCode {
+        code: "E0017",
+        severity: Severity::Error,
+        title: "Circular import",
+        description: "A file imports itself, directly or through other imports. The error lists every file along the cycle.",
+        example: "// A.otr\nimport B;\n\n// B.otr\nimport A;",
+        fix: "Move the items both files need into a third module that imports neither of them.",
+    },
+    ErrorCode {
+        code: "E0018",
+        severity: Severity::Error,
+        title: "Maximum call depth exceeded",
+        description: "Calls were nested deeper than the stack limit, which is 10000 unless set with '--stack-limit', OTR_STACK_LIMIT or the config file.",
+        example: "proc loop(n) {\n    return Main::loop(n + 1);\n}",
+        fix: "Make sure the recursion ends, or raise the stack limit.",
+    },
+    ErrorCode {
+        code: "E0019",
+        severity: Severity::Error,
+        title: "Internal error",
+        description: "The compiler or the runtime failed unexpectedly. This is a bug in otr itself and was caught before it could take down the host.",
+        example: "// Any program that makes otr report an internal error.",
+        fix: "Report the bug together with the program that caused it. Rewriting the failing expression usually works around it.",
+    },
+    ErrorCode {
+        code: "E0020",
+        severity: Severity::Error,
+        title: "Integer overflow",
+        description: "An Integer operation had a result outside of the 64 bit range. Integers never wrap around.",
+        example: "let big = 9223372036854775807;\nreturn big + 1;",
+        fix: "Use Floats for values this large, or 'Math::checkedAdd' and its siblings to handle the overflow.",
+    },
+    ErrorCode {
+        code: "E0021",
+        severity: Severity::Error,
+        title: "Division by zero",
+        description: "An Integer was divided by zero, either with '/' or with one of the modulo operators. Floats give an infinity or NaN instead.",
+        example: "let count = 0;\nreturn 10 / count;",
+        fix: "Check the divisor before dividing, or use 'Math::checkedDiv' to handle the error.",
+    },
+    ErrorCode {
+        code: "E0022",
+        severity: Severity::Error,
+        title: "Memory limit exceeded",
+        description: "The arrays, strings and structs held by the program took up more memory than the limit set with '--memory-limit', OTR_MEMORY_LIMIT or the config file. The size is an estimate of the values themselves.",
+        example: "let words = [];\nloop {\n    words = Arrays::push(words, \"word\");\n}",
+        fix: "Release values that are no longer needed, e.g. by overwriting their variables, or raise the limit.",
+    },
+];
+
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
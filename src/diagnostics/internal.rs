@@ -0,0 +1,46 @@
+use std::{any::Any, fmt, panic::{self, AssertUnwindSafe}};
+
+// A panic inside the compiler or the runtime, i.e. a bug in otr rather than in the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternalError {
+    // What otr was doing, e.g. "compiling" or "running".
+    pub stage: &'static str,
+    pub message: String,
+}
+
+impl InternalError {
+    fn from_panic(stage: &'static str, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .unwrap_or_else(|| "unknown panic".into()),
+        };
+
+        Self { stage, message }
+    }
+}
+
+impl fmt::Display for InternalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Internal error while {}: {}! This is a bug in otr, not in your program. Please report it together with the program that caused it.",
+            self.stage,
+            self.message
+        )
+    }
+}
+
+// Runs `f`, turning a panic into an internal error so that hosts are never taken down by a script.
+// Debug builds let the panic continue, so bugs are not hidden during development.
+pub(crate) fn guard<T>(stage: &'static str, f: impl FnOnce() -> T) -> Result<T, InternalError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        if cfg!(debug_assertions) {
+            panic::resume_unwind(payload);
+        }
+
+        InternalError::from_panic(stage, payload)
+    })
+}
@@ -0,0 +1,382 @@
+use serde_json::{json, Value};
+
+use crate::lexer::{Tokenizer, rules::RuleDescription, token::{KeywordToken, Token}};
+
+// Editor grammars derived from the tokenizer rules. Token classes (keywords, types, operators)
+// come straight from the rules, so renamed or added keywords show up without further changes.
+// The statement structure mirrors the compiler states and has to be extended with them.
+
+struct TokenClasses {
+    rules: Vec<RuleDescription>,
+}
+
+impl TokenClasses {
+    fn new(tokenizer: &Tokenizer) -> Self {
+        Self { rules: tokenizer.describe() }
+    }
+
+    fn keywords_matching(&self, filter: impl Fn(&Token) -> bool) -> Vec<String> {
+        let mut texts: Vec<String> = self.rules
+            .iter()
+            .filter_map(|rule| match rule {
+                RuleDescription::Keyword { text, token } if filter(token) => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+
+        texts.dedup();
+        texts
+    }
+
+    fn keyword(&self, keyword: KeywordToken) -> Vec<String> {
+        self.keywords_matching(|token| *token == Token::Keyword(keyword.clone()))
+    }
+
+    fn operators(&self) -> Vec<String> {
+        let mut operators: Vec<String> = self.rules
+            .iter()
+            .filter_map(|rule| match rule {
+                RuleDescription::Pattern { text, token: Token::Operator(_) } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // Longer operators have to be tried first, e.g. '%%' before '%'.
+        operators.sort_by_key(|operator| std::cmp::Reverse(operator.len()));
+        operators
+    }
+}
+
+fn escape_regex(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| if "\\^$.|?*+()[]{}/".contains(c) {
+            vec!['\\', c]
+        } else {
+            vec![c]
+        })
+        .collect()
+}
+
+fn alternation(words: &[String]) -> String {
+    words.iter().map(|word| escape_regex(word)).collect::<Vec<_>>().join("|")
+}
+
+fn word_pattern(words: &[String]) -> String {
+    format!("\\b({})\\b", alternation(words))
+}
+
+pub fn textmate(tokenizer: &Tokenizer) -> String {
+    let classes = TokenClasses::new(tokenizer);
+
+    let keywords = classes.keywords_matching(|token| matches!(token, Token::Keyword(_)));
+    let types = classes.keywords_matching(|token| matches!(token, Token::PrimitiveType(_)));
+    let mut constants = classes.keywords_matching(|token| matches!(token, Token::Literal(_)));
+    if classes.rules.iter().any(|rule| matches!(rule, RuleDescription::BooleanLiteral)) {
+        constants.extend(["true".to_string(), "false".to_string()]);
+    }
+
+    let mut declaration_keywords = classes.keyword(KeywordToken::Proc);
+    declaration_keywords.extend(classes.keyword(KeywordToken::Struct));
+    declaration_keywords.extend(classes.keyword(KeywordToken::Module));
+
+    let grammar = json!({
+        "name": "otr",
+        "scopeName": "source.otr",
+        "fileTypes": ["otr"],
+        "patterns": [
+            { "include": "#comments" },
+            { "include": "#strings" },
+            { "include": "#raw-identifiers" },
+            { "include": "#declarations" },
+            { "include": "#decorators" },
+            { "include": "#module-paths" },
+            { "include": "#keywords" },
+            { "include": "#types" },
+            { "include": "#constants" },
+            { "include": "#numbers" },
+            { "include": "#operators" }
+        ],
+        "repository": {
+            "comments": {
+                "patterns": [
+                    { "name": "comment.line.documentation.otr", "match": "##.*$" },
+                    { "name": "comment.line.number-sign.otr", "match": "#.*$" }
+                ]
+            },
+            "strings": {
+                "patterns": [
+                    { "name": "string.quoted.triple.otr", "begin": "\"\"\"", "end": "\"\"\"" },
+                    {
+                        "name": "string.quoted.double.otr",
+                        "begin": "\"",
+                        "end": "\"",
+                        "patterns": [{ "include": "#escapes" }]
+                    },
+                    {
+                        "name": "string.quoted.single.otr",
+                        "match": "'(\\\\(x[0-9a-fA-F]{2}|u\\{[0-9a-fA-F]{1,6}\\}|.)|[^'\\\\])'"
+                    }
+                ]
+            },
+            "raw-identifiers": {
+                "name": "variable.other.otr",
+                "match": "`[^`]+`"
+            },
+            "escapes": {
+                "name": "constant.character.escape.otr",
+                "match": "\\\\(n|t|r|0|'|\"|\\\\|x[0-9a-fA-F]{2}|u\\{[0-9a-fA-F]{1,6}\\})"
+            },
+            "declarations": {
+                "match": format!("\\b({})\\s+([A-Za-z_][A-Za-z0-9_]*)", alternation(&declaration_keywords)),
+                "captures": {
+                    "1": { "name": "keyword.other.declaration.otr" },
+                    "2": { "name": "entity.name.function.otr" }
+                }
+            },
+            "decorators": {
+                "name": "entity.name.function.decorator.otr",
+                "match": "@\\s*[A-Za-z_][A-Za-z0-9_]*"
+            },
+            "module-paths": {
+                "match": "\\b([A-Za-z_][A-Za-z0-9_]*)\\s*(::)",
+                "captures": {
+                    "1": { "name": "entity.name.namespace.otr" },
+                    "2": { "name": "punctuation.separator.namespace.otr" }
+                }
+            },
+            "keywords": { "name": "keyword.control.otr", "match": word_pattern(&keywords) },
+            "types": { "name": "storage.type.otr", "match": word_pattern(&types) },
+            "constants": { "name": "constant.language.otr", "match": word_pattern(&constants) },
+            "numbers": { "name": "constant.numeric.otr", "match": "\\b[0-9]+(\\.[0-9]+)?\\b" },
+            "operators": { "name": "keyword.operator.otr", "match": alternation(&classes.operators()) }
+        }
+    });
+
+    to_pretty_json(&grammar)
+}
+
+fn to_pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default() + "\n"
+}
+
+// A tree-sitter grammar.js. Keywords with several spellings become a choice between them.
+pub fn tree_sitter(tokenizer: &Tokenizer) -> String {
+    use KeywordToken::*;
+
+    let classes = TokenClasses::new(tokenizer);
+
+    let keyword = |keyword: KeywordToken| {
+        let texts: Vec<String> = classes.keyword(keyword.clone())
+            .iter()
+            .map(|text| format!("'{}'", text))
+            .collect();
+
+        match texts.len() {
+            0 => format!("'{}'", keyword.as_str()),
+            1 => texts[0].clone(),
+            _ => format!("choice({})", texts.join(", ")),
+        }
+    };
+
+    let quoted = |texts: Vec<String>| texts.iter().map(|text| format!("'{}'", text)).collect::<Vec<_>>().join(", ");
+
+    let types = quoted(classes.keywords_matching(|token| matches!(token, Token::PrimitiveType(_))));
+    let literals = quoted(classes.keywords_matching(|token| matches!(token, Token::Literal(_))));
+
+    let binary_operators = quoted(
+        classes.operators()
+            .into_iter()
+            .filter(|operator| operator != "=" && operator != "!")
+            .collect()
+    );
+
+    format!(r#"// Generated by 'otr grammar tree-sitter'.
+// A trailing comma after the last element is allowed.
+const commaSep = rule => optional(seq(rule, repeat(seq(',', rule)), optional(',')));
+
+module.exports = grammar({{
+  name: 'otr',
+
+  extras: $ => [/\s/, $.comment],
+
+  word: $ => $.identifier,
+
+  rules: {{
+    source_file: $ => repeat(choice($.import_declaration, $.module_declaration)),
+
+    import_declaration: $ => seq(
+      optional(field('reexport', {export_keyword})),
+      {import_keyword},
+      field('module', $.identifier),
+      optional(seq({from_keyword}, field('path', $.string))),
+      optional(seq({alias_keyword}, field('alias', $.identifier))),
+      ';'
+    ),
+
+    module_declaration: $ => seq({module_keyword}, field('name', $.identifier), '{{', repeat($._module_item), '}}'),
+
+    _module_item: $ => choice(
+      $.procedure_declaration,
+      $.struct_declaration,
+      $.let_statement,
+      $.export_declaration,
+      $.doc_comment
+    ),
+
+    export_declaration: $ => seq({export_keyword}, commaSep($.identifier), ';'),
+
+    decorator: $ => seq('@', $.identifier, optional(seq('(', commaSep(seq($.identifier, '=', $._literal)), ')'))),
+
+    procedure_declaration: $ => seq(
+      repeat($.decorator),
+      {proc_keyword},
+      field('name', $.identifier),
+      '(', commaSep($.parameter), ')',
+      optional(seq('->', field('return_type', $.primitive_type))),
+      $.block
+    ),
+
+    parameter: $ => seq($.identifier, optional(seq(':', $.primitive_type))),
+
+    struct_declaration: $ => seq(
+      {struct_keyword},
+      field('name', $.identifier),
+      optional(seq({private_keyword}, {constructor_keyword})),
+      '{{', commaSep($.field_declaration), '}}'
+    ),
+
+    field_declaration: $ => seq(optional({public_keyword}), $.identifier),
+
+    block: $ => seq('{{', repeat($._statement), '}}'),
+
+    _statement: $ => choice(
+      $.let_statement,
+      $.assignment,
+      $.if_statement,
+      $.while_statement,
+      $.loop_statement,
+      $.foreach_statement,
+      $.break_statement,
+      $.try_statement,
+      $.throw_statement,
+      $.return_statement,
+      $.expression_statement
+    ),
+
+    let_statement: $ => seq({let_keyword}, $.identifier, optional(seq('=', $._expression)), ';'),
+
+    assignment: $ => seq($._place, '=', $._expression, ';'),
+
+    if_statement: $ => seq({if_keyword}, '(', $._expression, ')', $.block, optional(seq({else_keyword}, $.block))),
+
+    while_statement: $ => seq({while_keyword}, '(', $._expression, ')', $.block),
+
+    loop_statement: $ => seq({loop_keyword}, $.block),
+
+    foreach_statement: $ => seq({for_keyword}, $.identifier, {in_keyword}, $._expression, $.block),
+
+    break_statement: $ => seq({break_keyword}, ';'),
+
+    try_statement: $ => seq({try_keyword}, $.block, {catch_keyword}, '(', $.identifier, ')', $.block),
+
+    throw_statement: $ => seq({throw_keyword}, $._expression, ';'),
+
+    return_statement: $ => seq({return_keyword}, optional($._expression), ';'),
+
+    expression_statement: $ => seq($._expression, ';'),
+
+    _expression: $ => choice(
+      $.binary_expression,
+      $.unary_expression,
+      $.call_expression,
+      $.struct_expression,
+      $.array_expression,
+      $.reference_expression,
+      $.clone_expression,
+      $.parenthesized_expression,
+      $._place,
+      $._literal
+    ),
+
+    binary_expression: $ => prec.left(1, seq($._expression, choice({binary_operators}), $._expression)),
+
+    unary_expression: $ => prec(2, seq('!', $._expression)),
+
+    call_expression: $ => seq($.module_path, '(', commaSep($._expression), ')'),
+
+    struct_expression: $ => seq(
+      $.module_path,
+      '{{', commaSep(choice(seq($.identifier, ':', $._expression), $.struct_spread)), '}}'
+    ),
+
+    struct_spread: $ => seq('..', $._expression),
+
+    array_expression: $ => seq('[', commaSep($._expression), ']'),
+
+    reference_expression: $ => seq({ref_keyword}, $._place),
+
+    clone_expression: $ => seq({clone_keyword}, $._place),
+
+    parenthesized_expression: $ => seq('(', $._expression, ')'),
+
+    module_path: $ => seq($.identifier, '::', $.identifier),
+
+    _place: $ => choice($.identifier, $.module_path, $.member_access, $.index_access),
+
+    member_access: $ => prec(3, seq($._place, '.', $.identifier)),
+
+    index_access: $ => prec(3, seq($._place, '[', $._expression, ']')),
+
+    _literal: $ => choice($.integer, $.decimal, $.boolean, $.char, $.string, $.constant),
+
+    primitive_type: $ => choice({types}),
+
+    constant: $ => choice({literals}),
+
+    integer: $ => /[0-9]+/,
+
+    decimal: $ => /[0-9]+\.[0-9]+/,
+
+    boolean: $ => choice('true', 'false'),
+
+    char: $ => /'(\\.|[^'\\])'/,
+
+    string: $ => choice(/"""([^"]|"[^"]|""[^"])*"""/, /"(\\.|[^"\\])*"/),
+
+    identifier: $ => /[A-Za-z_][A-Za-z0-9_]*|`[^`\n]+`/,
+
+    doc_comment: $ => token(prec(1, /##.*/)),
+
+    comment: $ => token(/#.*/),
+  }}
+}});
+"#,
+        import_keyword = keyword(Import),
+        from_keyword = keyword(From),
+        alias_keyword = keyword(As),
+        module_keyword = keyword(Module),
+        export_keyword = keyword(Export),
+        proc_keyword = keyword(Proc),
+        struct_keyword = keyword(Struct),
+        public_keyword = keyword(Public),
+        private_keyword = keyword(Private),
+        constructor_keyword = keyword(Constructor),
+        let_keyword = keyword(Let),
+        if_keyword = keyword(If),
+        else_keyword = keyword(Else),
+        while_keyword = keyword(While),
+        loop_keyword = keyword(Loop),
+        for_keyword = keyword(For),
+        in_keyword = keyword(In),
+        break_keyword = keyword(Break),
+        try_keyword = keyword(Try),
+        catch_keyword = keyword(Catch),
+        throw_keyword = keyword(Throw),
+        return_keyword = keyword(Return),
+        ref_keyword = keyword(Ref),
+        clone_keyword = keyword(Clone),
+        types = types,
+        literals = literals,
+        binary_operators = binary_operators,
+    )
+}
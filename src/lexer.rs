@@ -2,58 +2,191 @@ use std::str::FromStr;
 
 use derive_more::IntoIterator;
 
-use crate::lexer::{
+use crate::{compiler::{CompilerError, CompilerErrorKind}, lexer::{
+    keywords::KeywordTable,
     rules::{
-        BooleanLiteralRule, CharLiteralRule, IdentifierRule, KeywordRule, NumberLiteralRule,
-        PatternRule, StringLiteralRule,
+        BooleanLiteralRule, CharLiteralRule, DocCommentRule, IdentifierRule, KeywordRule, NumberLiteralRule,
+        PatternRule, RawIdentifierRule, RuleDescription, StringLiteralRule,
     },
-    token::{Token, TokenStream},
-};
+    token::{SourceSpan, Token, TokenStream},
+}};
 
+pub mod keywords;
 pub mod rules;
 pub mod token;
 
 #[derive(Debug, IntoIterator)]
-pub struct FragmentStream(Vec<String>);
+pub struct FragmentStream(Vec<(String, SourceSpan)>);
 
 #[derive(Debug)]
 pub enum FragmentationError {
     InvalidControlCharacter,
+    InvalidEscapeSequence(String),
+    // A string or char literal still open at the end of the source, by the line it starts on.
+    UnterminatedLiteral { line: usize },
+    UnsupportedCharacter { character: char, line: usize },
+    UnterminatedRawIdentifier { line: usize },
+}
+
+// The characters of a source alongside the byte offset each of them starts at in the original
+// text, which may still contain a BOM and Windows line endings.
+struct SourceText {
+    chars: Vec<char>,
+    offsets: Vec<usize>,
+    // The index of the first character of every line.
+    line_starts: Vec<usize>,
+}
+
+impl SourceText {
+    // Sources saved with a BOM or Windows line endings fragment like their Unix counterparts
+    fn new(source: &str) -> Self {
+        let mut chars = Vec::new();
+        let mut offsets = Vec::new();
+        let mut line_starts = vec![0];
+
+        let mut indices = source.char_indices().peekable();
+
+        while let Some((offset, c)) = indices.next() {
+            if (offset == 0 && c == '\u{feff}') || (c == '\r' && indices.peek().is_some_and(|(_, next)| *next == '\n')) {
+                continue;
+            }
+
+            chars.push(c);
+            offsets.push(offset);
+
+            if c == '\n' {
+                line_starts.push(chars.len());
+            }
+        }
+
+        offsets.push(source.len());
+
+        Self { chars, offsets, line_starts }
+    }
+
+    // The line of the character at `chars[i]`, counting from 1.
+    fn line(&self, i: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= i)
+    }
+
+    fn span(&self, start: usize, end: usize) -> SourceSpan {
+        let line = self.line(start);
+
+        let end = end.min(self.chars.len());
+
+        // The end of the last character, as a line ending dropped after it is not part of the span.
+        SourceSpan {
+            start: self.offsets[start.min(end)],
+            end: if end > start { self.offsets[end - 1] + self.chars[end - 1].len_utf8() } else { self.offsets[end] },
+            line,
+            column: start - self.line_starts[line - 1] + 1,
+        }
+    }
+}
+
+// The fragments read so far and the one currently growing, by the index of its first character.
+struct Fragments<'a> {
+    source: &'a SourceText,
+    stream: Vec<(String, SourceSpan)>,
+    current: String,
+    current_start: usize,
+}
+
+impl Fragments<'_> {
+    fn push_char(&mut self, c: char, at: usize) {
+        if self.current.is_empty() {
+            self.current_start = at;
+        }
+
+        self.current.push(c);
+    }
+
+    // Ends the current fragment before the character at `end`.
+    fn flush(&mut self, end: usize) {
+        if !self.current.is_empty() {
+            let span = self.source.span(self.current_start, end);
+            self.stream.push((std::mem::take(&mut self.current), span));
+        }
+    }
+
+    fn push(&mut self, fragment: String, start: usize, end: usize) {
+        self.stream.push((fragment, self.source.span(start, end)));
+    }
+}
+
+// Decodes the escape sequence starting at the backslash at `chars[*i]` and advances `i` past it.
+fn read_escape_sequence(chars: &[char], i: &mut usize) -> Result<char, FragmentationError> {
+    let kind = *chars.get(*i + 1).ok_or(FragmentationError::InvalidControlCharacter)?;
+    *i += 2;
+
+    match kind {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        '\"' => Ok('\"'),
+        '\'' => Ok('\''),
+        '\\' => Ok('\\'),
+        'x' => {
+            let digits: String = chars.iter().skip(*i).take(2).collect();
+            *i += 2;
+
+            u8::from_str_radix(&digits, 16)
+                .ok()
+                .filter(|code| code.is_ascii())
+                .map(char::from)
+                .ok_or_else(|| FragmentationError::InvalidEscapeSequence(format!("\\x{}", digits)))
+        }
+        'u' => {
+            if chars.get(*i) != Some(&'{') {
+                return Err(FragmentationError::InvalidEscapeSequence("\\u".into()));
+            }
+
+            let digits: String = chars.iter().skip(*i + 1).take_while(|c| **c != '}').collect();
+            *i += digits.chars().count() + 2;
+
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .filter(|_| digits.len() <= 6)
+                .and_then(char::from_u32)
+                .ok_or_else(|| FragmentationError::InvalidEscapeSequence(format!("\\u{{{}}}", digits)))
+        }
+        _ => Err(FragmentationError::InvalidControlCharacter),
+    }
 }
 
 impl FromStr for FragmentStream {
     type Err = FragmentationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut stream = Vec::new();
-
-        #[derive(Debug, PartialEq)]
+        #[derive(Debug, PartialEq, Clone, Copy)]
         enum CharKind {
             Alphabetic,
             Numeric,
             Punctuation,
         }
 
-        impl From<char> for CharKind {
-            fn from(value: char) -> Self {
+        impl CharKind {
+            fn of(value: char) -> Option<Self> {
                 if value.is_ascii_alphabetic() {
-                    return Self::Alphabetic;
+                    return Some(Self::Alphabetic);
                 }
                 if value.is_numeric() {
-                    return Self::Numeric;
+                    return Some(Self::Numeric);
                 }
                 if value.is_ascii_punctuation() {
-                    return Self::Punctuation;
+                    return Some(Self::Punctuation);
                 }
 
-                panic!("Unsupported char kind");
+                None
             }
         }
 
-        let mut current = String::new();
-        let mut current_kind = CharKind::Alphabetic;
+        let source = SourceText::new(s);
+        let chars = &source.chars;
 
-        let chars: Vec<char> = s.chars().collect();
+        let mut fragments = Fragments { source: &source, stream: Vec::new(), current: String::new(), current_start: 0 };
+        let mut current_kind = CharKind::Alphabetic;
 
         let mut i = 0;
 
@@ -63,83 +196,158 @@ impl FromStr for FragmentStream {
             i += 1;
 
             if c == '\'' {
-                if !current.is_empty() {
-                    stream.push(current);
-                    current = String::new();
+                let start = i - 1;
+
+                fragments.flush(start);
+
+                let mut literal = String::from('\'');
+
+                match chars.get(i) {
+                    Some('\\') => literal.push(read_escape_sequence(chars, &mut i)?),
+                    Some(c) => {
+                        literal.push(*c);
+                        i += 1;
+                    }
+                    None => return Err(FragmentationError::UnterminatedLiteral { line: source.line(start) }),
+                }
+
+                if chars.get(i) != Some(&'\'') {
+                    return Err(FragmentationError::UnterminatedLiteral { line: source.line(start) });
+                }
+                i += 1;
+
+                literal.push('\'');
+
+                fragments.push(literal, start, i);
+
+                continue;
+            }
+
+            // Raw identifiers like `type` name fields and variables after keywords
+            if c == '`' {
+                let start = i - 1;
+
+                fragments.flush(start);
+
+                let mut identifier = String::from('`');
+
+                loop {
+                    match chars.get(i) {
+                        Some('`') => break,
+                        Some('\n') | None => return Err(FragmentationError::UnterminatedRawIdentifier { line: source.line(start) }),
+                        Some(c) => identifier.push(*c),
+                    }
+
+                    i += 1;
                 }
 
-                current.push('\'');
+                identifier.push('`');
+
+                i += 1;
+
+                fragments.push(identifier, start, i);
 
-                current.push(chars[i]);
+                continue;
+            }
 
-                current.push('\'');
+            if c == '\"' && chars.get(i) == Some(&'\"') && chars.get(i + 1) == Some(&'\"') {
+                let start = i - 1;
 
-                stream.push(current);
-                current = String::new();
+                fragments.flush(start);
 
                 i += 2;
+
+                // A line break directly after the opening quotes is not part of the literal
+                if chars.get(i) == Some(&'\n') {
+                    i += 1;
+                }
+
+                // Enclosed in one quote on each side like any other string literal, so the
+                // tokenizer doesn't have to tell them apart by their quotes.
+                let mut literal = String::from('\"');
+
+                loop {
+                    if i + 2 >= chars.len() {
+                        return Err(FragmentationError::UnterminatedLiteral { line: source.line(start) });
+                    }
+                    if chars[i] == '\"' && chars[i + 1] == '\"' && chars[i + 2] == '\"' {
+                        break;
+                    }
+
+                    literal.push(chars[i]);
+
+                    i += 1;
+                }
+
+                literal.push('\"');
+
+                i += 3;
+
+                fragments.push(literal, start, i);
+
                 continue;
             }
 
             if c == '\"' {
-                if !current.is_empty() {
-                    stream.push(current);
-                    current = String::new();
-                }
+                let start = i - 1;
 
-                current.push('\"');
-
-                while chars[i] != '\"' {
-                    if chars[i] == '\\' {
-                        match chars[i + 1] {
-                            'n' => {
-                                current.push('\n');
-                            }
-                            't' => {
-                                current.push('\t');
-                            }
-                            '\"' => {
-                                current.push('\"');
-                            }
-                            '\\' => {
-                                current.push('\\');
-                            }
-                            _ => return Err(FragmentationError::InvalidControlCharacter),
-                        }
-                        i = i + 2;
+                fragments.flush(start);
+
+                let mut literal = String::from('\"');
+
+                loop {
+                    let Some(next) = chars.get(i) else {
+                        return Err(FragmentationError::UnterminatedLiteral { line: source.line(start) });
+                    };
+
+                    if *next == '\"' {
+                        break;
+                    }
+
+                    if *next == '\\' {
+                        literal.push(read_escape_sequence(chars, &mut i)?);
                         continue;
                     }
 
-                    current.push(chars[i]);
+                    literal.push(*next);
 
                     i += 1;
                 }
 
-                current.push('\"');
-
-                stream.push(current);
-                current = String::new();
+                literal.push('\"');
 
                 i += 1;
+
+                fragments.push(literal, start, i);
+
                 continue;
             }
 
             if c.is_ascii_whitespace() {
-                if current.is_empty() {
-                    continue;
-                }
-                stream.push(current);
-                current = String::new();
+                fragments.flush(i - 1);
                 continue;
             }
 
             if c == '#' {
-                if !current.is_empty() {
-                    stream.push(current);
-                    current = String::new();
+                let start = i - 1;
+
+                fragments.flush(start);
+
+                // Doc comments are kept as a single fragment so they can be attached to declarations
+                if chars.get(i) == Some(&'#') {
+                    let mut comment = String::from('#');
+
+                    while i < chars.len() && chars[i] != '\n' {
+                        comment.push(chars[i]);
+                        i += 1;
+                    }
+
+                    fragments.push(comment, start, i);
+
+                    continue;
                 }
 
-                while chars[i] != '\n' && i < chars.len() {
+                while i < chars.len() && chars[i] != '\n' {
                     i += 1;
                 }
 
@@ -147,27 +355,26 @@ impl FromStr for FragmentStream {
             }
 
             if c == ';' {
-                stream.push(current);
-                stream.push(";".into());
-                current = String::new();
+                fragments.flush(i - 1);
+                fragments.push(";".into(), i - 1, i);
                 continue;
             }
 
-            let next_char_kind: CharKind = c.into();
+            let Some(next_char_kind) = CharKind::of(c) else {
+                return Err(FragmentationError::UnsupportedCharacter { character: c, line: source.line(i - 1) });
+            };
 
-            if !current.is_empty() {
+            if !fragments.current.is_empty() {
                 use CharKind::*;
                 match (current_kind, next_char_kind) {
                     (Alphabetic, Punctuation)
                     | (Punctuation, Alphabetic)
                     /*| (Numeric, Alphabetic) */ => {
-                        stream.push(current);
-                        current = String::new();
+                        fragments.flush(i - 1);
                     }
                     (Numeric, Punctuation) => {
                         if c != '.' {
-                            stream.push(current);
-                            current = String::new();
+                            fragments.flush(i - 1);
                         }
                     }
 
@@ -175,16 +382,14 @@ impl FromStr for FragmentStream {
                 }
             }
 
-            current_kind = c.into();
+            current_kind = next_char_kind;
 
-            current.push(c);
+            fragments.push_char(c, i - 1);
         }
 
-        if !current.is_empty() {
-            stream.push(current);
-        }
+        fragments.flush(chars.len());
 
-        Ok(Self(stream))
+        Ok(Self(fragments.stream))
     }
 }
 
@@ -193,6 +398,8 @@ pub enum TokenizeError {}
 
 trait TokenizerRule {
     fn try_apply(&self, fragment: String) -> (Option<Token>, String);
+
+    fn describe(&self) -> RuleDescription;
 }
 
 pub struct Tokenizer {
@@ -212,14 +419,30 @@ impl Tokenizer {
     pub fn tokenize(&self, fragments: FragmentStream) -> Result<TokenStream, TokenizeError> {
         let mut stream = Vec::new();
 
-        for mut frag in fragments {
+        for (mut frag, span) in fragments {
+            // Rules consume a fragment from the front, so the tokens of fragments spelled as in
+            // the source get spans of their own. Literals with escape sequences keep the span of
+            // their fragment.
+            let spelled_out = frag.len() == span.end - span.start;
+            let mut token_span = SourceSpan { end: span.start, ..span };
+
             'scan: while !frag.is_empty() {
                 for rule in self.rules.iter() {
+                    let (length, chars) = (frag.len(), frag.chars().count());
                     let token;
                     (token, frag) = rule.try_apply(frag);
 
                     if let Some(token) = token {
-                        stream.push(token);
+                        if spelled_out {
+                            token_span.start = token_span.end;
+                            token_span.end += length - frag.len();
+                        } else {
+                            token_span = span;
+                        }
+
+                        stream.push((token, token_span));
+
+                        token_span.column += chars - frag.chars().count();
                         continue 'scan;
                     }
                 }
@@ -228,6 +451,47 @@ impl Tokenizer {
 
         Ok(TokenStream(stream))
     }
+
+    // Adds the aliases of the table next to the keyword rules they stand for.
+    pub fn with_keyword_table(mut self, table: &KeywordTable) -> Result<Self, CompilerError> {
+        let rules = std::mem::take(&mut self.rules);
+        let mut known_keywords = Vec::new();
+
+        for rule in rules {
+            if let RuleDescription::Keyword { text, token } = rule.describe() {
+                known_keywords.push(text.clone());
+
+                if let Some(aliases) = table.aliases(&text) {
+                    for alias in aliases.iter() {
+                        if !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                            || !alias.starts_with(|c: char| c.is_ascii_alphabetic())
+                        {
+                            return Err(CompilerErrorKind::InvalidKeywordAlias { alias: alias.clone(), keyword: text }.into());
+                        }
+
+                        self.rules.push(Box::new(KeywordRule::new(alias.clone(), token.clone())));
+                    }
+
+                    if table.replace {
+                        continue;
+                    }
+                }
+            }
+
+            self.rules.push(rule);
+        }
+
+        if let Some(unknown) = table.keywords.keys().find(|keyword| !known_keywords.contains(keyword)) {
+            return Err(CompilerErrorKind::UnknownKeyword { keyword: unknown.clone() }.into());
+        }
+
+        Ok(self)
+    }
+
+    // The rules in the order they are tried.
+    pub fn describe(&self) -> Vec<RuleDescription> {
+        self.rules.iter().map(|rule| rule.describe()).collect()
+    }
 }
 
 impl Default for Tokenizer {
@@ -240,26 +504,37 @@ impl Default for Tokenizer {
         use Token::*;
 
         Self::new()
+            .with_rule(DocCommentRule)
+            .with_rule(RawIdentifierRule)
             .with_rule(KeywordRule::new("break".into(), Keyword(Break)))
             .with_rule(KeywordRule::new("const".into(), Keyword(Const)))
             .with_rule(KeywordRule::new("continue".into(), Keyword(Continue)))
             .with_rule(KeywordRule::new("for".into(), Keyword(For)))
+            .with_rule(KeywordRule::new("in".into(), Keyword(In)))
             .with_rule(KeywordRule::new("let".into(), Keyword(Let)))
             .with_rule(KeywordRule::new("proc".into(), Keyword(Proc)))
             .with_rule(KeywordRule::new("return".into(), Keyword(Return)))
             .with_rule(KeywordRule::new("struct".into(), Keyword(Struct)))
             .with_rule(KeywordRule::new("while".into(), Keyword(While)))
+            .with_rule(KeywordRule::new("loop".into(), Keyword(Loop)))
             .with_rule(KeywordRule::new("if".into(), Keyword(If)))
             .with_rule(KeywordRule::new("else".into(), Keyword(Else)))
             .with_rule(KeywordRule::new("module".into(), Keyword(Module)))
             .with_rule(KeywordRule::new("export".into(), Keyword(Export)))
             .with_rule(KeywordRule::new("import".into(), Keyword(Import)))
             .with_rule(KeywordRule::new("from".into(), Keyword(From)))
+            .with_rule(KeywordRule::new("as".into(), Keyword(As)))
             .with_rule(KeywordRule::new("public".into(), Keyword(Public)))
+            .with_rule(KeywordRule::new("private".into(), Keyword(Private)))
+            .with_rule(KeywordRule::new("constructor".into(), Keyword(Constructor)))
             .with_rule(KeywordRule::new("ref".into(), Keyword(Ref)))
             .with_rule(KeywordRule::new("clone".into(), Keyword(Clone)))
+            .with_rule(KeywordRule::new("try".into(), Keyword(Try)))
+            .with_rule(KeywordRule::new("catch".into(), Keyword(Catch)))
+            .with_rule(KeywordRule::new("throw".into(), Keyword(Throw)))
 
             .with_rule(KeywordRule::new("Null".into(), Literal(LiteralToken::Null)))
+            .with_rule(KeywordRule::new("null".into(), Literal(LiteralToken::Null)))
             .with_rule(KeywordRule::new("Integer".into(), PrimitiveType(PrimitiveTypeToken::Integer)))
             .with_rule(KeywordRule::new("Decimal".into(), PrimitiveType(PrimitiveTypeToken::Decimal)))
             .with_rule(KeywordRule::new("Boolean".into(), PrimitiveType(PrimitiveTypeToken::Boolean)))
@@ -272,6 +547,7 @@ impl Default for Tokenizer {
             .with_rule(PatternRule::new("==".into(), Operator(Equality)))
             .with_rule(PatternRule::new("!=".into(), Operator(Inequality)))
             .with_rule(PatternRule::new("::".into(), Punctuation(DoubleColon)))
+            .with_rule(PatternRule::new("->".into(), Punctuation(Arrow)))
             .with_rule(PatternRule::new(">=".into(), Operator(GreaterEquals)))
             .with_rule(PatternRule::new("<=".into(), Operator(LessEquals)))
             .with_rule(PatternRule::new(">".into(), Operator(Greater)))
@@ -307,6 +583,7 @@ impl Default for Tokenizer {
             .with_rule(PatternRule::new("-".into(), Operator(Minus)))
             .with_rule(PatternRule::new("*".into(), Operator(Multiply)))
             .with_rule(PatternRule::new("/".into(), Operator(Divide)))
+            .with_rule(PatternRule::new("%%".into(), Operator(EuclideanModulo)))
             .with_rule(PatternRule::new("%".into(), Operator(Modulo)))
             .with_rule(PatternRule::new("=".into(), Operator(Assignment)))
             .with_rule(PatternRule::new("^".into(), Operator(Power)))
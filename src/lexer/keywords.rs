@@ -0,0 +1,51 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::compiler::CompilerError;
+
+// Alternative spellings for keywords, loaded from a TOML file:
+//
+//     replace = true
+//
+//     [keywords]
+//     let = "sei"
+//     if = ["wenn", "falls"]
+//
+// Aliases produce the same tokens as the keyword they stand for. With 'replace' set, the
+// original spellings of the listed keywords are no longer recognized.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct KeywordTable {
+    #[serde(default)]
+    pub replace: bool,
+    #[serde(default)]
+    pub keywords: BTreeMap<String, KeywordAliases>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeywordAliases {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl KeywordAliases {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        match self {
+            KeywordAliases::Single(alias) => std::slice::from_ref(alias).iter(),
+            KeywordAliases::Multiple(aliases) => aliases.iter(),
+        }
+    }
+}
+
+impl KeywordTable {
+    pub fn load(path: &Path) -> Result<Self, CompilerError> {
+        let content = fs::read_to_string(path).map_err(|err| CompilerError::io(format!("Keyword table '{}'", path.display()), "read", err))?;
+
+        toml::from_str(&content).map_err(|err| CompilerError::invalid_file(format!("Keyword table '{}'", path.display()), err))
+    }
+
+    pub fn aliases(&self, keyword: &str) -> Option<&KeywordAliases> {
+        self.keywords.get(keyword)
+    }
+}
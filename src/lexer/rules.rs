@@ -1,5 +1,19 @@
 use crate::lexer::{token::Token, TokenizerRule};
 
+// What a rule matches, used to export the token grammar for editors.
+#[derive(Debug, Clone)]
+pub enum RuleDescription {
+    Keyword { text: String, token: Token },
+    Pattern { text: String, token: Token },
+    StringLiteral,
+    CharLiteral,
+    NumberLiteral,
+    BooleanLiteral,
+    DocComment,
+    RawIdentifier,
+    Identifier,
+}
+
 pub(crate) struct KeywordRule {
     keyword: String,
     emits: Token,
@@ -21,6 +35,10 @@ impl TokenizerRule for KeywordRule {
         }
         return (None, fragment);
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::Keyword { text: self.keyword.clone(), token: self.emits.clone() }
+    }
 }
 
 pub(crate) struct PatternRule {
@@ -36,18 +54,16 @@ impl PatternRule {
 
 impl TokenizerRule for PatternRule {
     fn try_apply(&self, fragment: String) -> (Option<Token>, String) {
-        let l = self.pattern.len();
-
-        if fragment.len() < l {
-            return (None, fragment);
-        }
-
-        if fragment[0..l] == self.pattern {
-            return (Some(self.emits.clone()), fragment[l..].to_string());
+        if fragment.starts_with(&self.pattern) {
+            return (Some(self.emits.clone()), fragment[self.pattern.len()..].to_string());
         }
 
         (None, fragment)
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::Pattern { text: self.pattern.clone(), token: self.emits.clone() }
+    }
 }
 
 pub(crate) struct StringLiteralRule;
@@ -57,6 +73,8 @@ impl TokenizerRule for StringLiteralRule {
         use super::token::LiteralToken::*;
         use Token::*;
 
+        // The fragmenter encloses every string literal, triple-quoted or not, in one quote on
+        // each side.
         if fragment.starts_with("\"") && fragment.ends_with("\"") {
             return (
                 Some(Literal(String(fragment[1..(fragment.len() - 1)].into()))),
@@ -66,6 +84,27 @@ impl TokenizerRule for StringLiteralRule {
 
         (None, fragment)
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::StringLiteral
+    }
+}
+
+pub(crate) struct DocCommentRule;
+
+impl TokenizerRule for DocCommentRule {
+    fn try_apply(&self, fragment: String) -> (Option<Token>, String) {
+        if let Some(text) = fragment.strip_prefix("##") {
+            let text = text.strip_prefix(' ').unwrap_or(text).trim_end();
+            return (Some(Token::DocComment(text.into())), "".into());
+        }
+
+        (None, fragment)
+    }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::DocComment
+    }
 }
 
 pub(crate) struct CharLiteralRule;
@@ -75,15 +114,17 @@ impl TokenizerRule for CharLiteralRule {
         use super::token::LiteralToken::*;
         use Token::*;
 
-        if fragment.len() == 3 {
-            let fragment: Vec<char> = fragment.chars().collect();
-            if fragment[0] == '\'' && fragment[2] == '\'' {
-                return (Some(Literal(Char(fragment[1].to_string()))), "".into());
-            }
+        let chars: Vec<char> = fragment.chars().collect();
+        if chars.len() == 3 && chars[0] == '\'' && chars[2] == '\'' {
+            return (Some(Literal(Char(chars[1].to_string()))), "".into());
         }
 
         (None, fragment)
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::CharLiteral
+    }
 }
 
 pub(crate) struct NumberLiteralRule;
@@ -107,6 +148,10 @@ impl TokenizerRule for NumberLiteralRule {
 
         (None, fragment)
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::NumberLiteral
+    }
 }
 
 pub(crate) struct BooleanLiteralRule;
@@ -122,6 +167,26 @@ impl TokenizerRule for BooleanLiteralRule {
 
         (None, fragment)
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::BooleanLiteral
+    }
+}
+
+// `name` is the identifier name, even if name is a keyword.
+pub(crate) struct RawIdentifierRule;
+
+impl TokenizerRule for RawIdentifierRule {
+    fn try_apply(&self, fragment: String) -> (Option<Token>, String) {
+        match fragment.strip_prefix('`').and_then(|rest| rest.strip_suffix('`')) {
+            Some(name) if !name.is_empty() => (Some(Token::Identifier(name.into())), String::new()),
+            _ => (None, fragment),
+        }
+    }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::RawIdentifier
+    }
 }
 
 pub(crate) struct IdentifierRule;
@@ -130,4 +195,8 @@ impl TokenizerRule for IdentifierRule {
     fn try_apply(&self, fragment: String) -> (Option<Token>, String) {
         (Some(Token::Identifier(fragment)), String::new())
     }
+
+    fn describe(&self) -> RuleDescription {
+        RuleDescription::Identifier
+    }
 }
@@ -1,4 +1,7 @@
+use std::fmt::{self, Display};
+
 use derive_more::IntoIterator;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
@@ -7,7 +10,8 @@ pub enum Token {
     Punctuation(PunctuationToken),
     Identifier(String),
     Literal(LiteralToken),
-    PrimitiveType(PrimitiveTypeToken)
+    PrimitiveType(PrimitiveTypeToken),
+    DocComment(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,7 +22,9 @@ pub enum KeywordToken {
     Struct,
     Return,
     For,
+    In,
     While,
+    Loop,
     If,
     Else,
     Continue,
@@ -27,10 +33,51 @@ pub enum KeywordToken {
     Export,
     Import,
     From,
+    As,
     Public,
+    Private,
+    Constructor,
     Is,
     Ref,
     Clone,
+    Try,
+    Catch,
+    Throw,
+}
+
+impl KeywordToken {
+    // The default spelling of the keyword.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeywordToken::Let => "let",
+            KeywordToken::Const => "const",
+            KeywordToken::Proc => "proc",
+            KeywordToken::Struct => "struct",
+            KeywordToken::Return => "return",
+            KeywordToken::For => "for",
+            KeywordToken::In => "in",
+            KeywordToken::While => "while",
+            KeywordToken::Loop => "loop",
+            KeywordToken::If => "if",
+            KeywordToken::Else => "else",
+            KeywordToken::Continue => "continue",
+            KeywordToken::Break => "break",
+            KeywordToken::Module => "module",
+            KeywordToken::Export => "export",
+            KeywordToken::Import => "import",
+            KeywordToken::From => "from",
+            KeywordToken::As => "as",
+            KeywordToken::Public => "public",
+            KeywordToken::Private => "private",
+            KeywordToken::Constructor => "constructor",
+            KeywordToken::Is => "is",
+            KeywordToken::Ref => "ref",
+            KeywordToken::Clone => "clone",
+            KeywordToken::Try => "try",
+            KeywordToken::Catch => "catch",
+            KeywordToken::Throw => "throw",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -41,6 +88,7 @@ pub enum OperatorToken {
     Multiply,
     Divide,
     Modulo,
+    EuclideanModulo,
     Power,
     Not,
     And,
@@ -70,6 +118,7 @@ pub enum PunctuationToken {
     DoubleColon,
     Semicolon,
     At,
+    Arrow,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -82,7 +131,7 @@ pub enum LiteralToken {
     String(String),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PrimitiveTypeToken {
     Integer,
     Decimal,
@@ -92,5 +141,29 @@ pub enum PrimitiveTypeToken {
     Array,
 }
 
+// Where a fragment or token was read from: byte offsets into the source as an exclusive range,
+// and the line and column of its first character, both counting from 1. Columns count
+// characters, not bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Debug, IntoIterator)]
-pub struct TokenStream(pub Vec<Token>);
+pub struct TokenStream(pub Vec<(Token, SourceSpan)>);
+
+impl TokenStream {
+    // The tokens without their spans, e.g. to parse a snippet on its own.
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.0.into_iter().map(|(token, _)| token).collect()
+    }
+}
@@ -1,3 +1,11 @@
 pub mod lexer;
 pub mod runtime;
-pub mod compiler;
\ No newline at end of file
+pub mod compiler;
+pub mod lockfile;
+pub mod manifest;
+pub mod config;
+pub mod registry;
+pub mod conformance;
+pub mod grammar;
+pub mod diagnostics;
+pub mod repl;
@@ -0,0 +1,92 @@
+use std::{collections::BTreeMap, fmt::Display, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::compiler::{CompilerError, CompilerErrorKind};
+
+pub const LOCKFILE_NAME: &str = "otr.lock";
+
+const HEADER: &str = "# This file is generated by otr. Do not edit it manually.\n\n";
+
+// Content hashes of every module a program was compiled from, keyed by path or URL and listed
+// under the entry module it was locked for.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub entries: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+// A difference between the modules a build read and the ones locked for its entry module.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleChange {
+    Added { module: String, hash: String },
+    Removed { module: String, hash: String },
+    Changed { module: String, expected: String, found: String },
+}
+
+impl Display for ModuleChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added { module, hash } => write!(f, "added    {} ({})", module, hash),
+            Self::Removed { module, hash } => write!(f, "removed  {} ({})", module, hash),
+            Self::Changed { module, expected, found } => write!(f, "changed  {} (expected {}, found {})", module, expected, found),
+        }
+    }
+}
+
+impl Lockfile {
+    pub fn load(root: &Path) -> Result<Option<Self>, CompilerError> {
+        let path = root.join(LOCKFILE_NAME);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path).map_err(|err| CompilerError::io(format!("Lockfile '{}'", path.display()), "read", err))?;
+
+        toml::from_str(&content).map(Some).map_err(|err| CompilerError::invalid_file(format!("Lockfile '{}'", path.display()), err))
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), CompilerError> {
+        let path = root.join(LOCKFILE_NAME);
+
+        let content = toml::to_string(self).map_err(|err| CompilerError::io("Lockfile".into(), "serialized", err))?;
+
+        fs::write(&path, HEADER.to_string() + &content).map_err(|err| CompilerError::io(format!("Lockfile '{}'", path.display()), "written", err))
+    }
+
+    // Replaces the modules of the entry modules locked in the other lockfile, keeping the rest.
+    pub fn update(&mut self, other: Lockfile) {
+        self.entries.extend(other.entries);
+    }
+
+    // Compares the modules read in a build with the ones locked for the same entry module. An
+    // entry module that was never locked has all of its modules reported as added.
+    pub fn verify(&self, actual: &Lockfile) -> Result<(), CompilerError> {
+        let none = BTreeMap::new();
+
+        for (entry, modules) in &actual.entries {
+            let locked = self.entries.get(entry).unwrap_or(&none);
+
+            let mut changes: Vec<_> = modules
+                .iter()
+                .filter_map(|(module, hash)| match locked.get(module) {
+                    None => Some(ModuleChange::Added { module: module.clone(), hash: hash.clone() }),
+                    Some(expected) if expected != hash => Some(ModuleChange::Changed { module: module.clone(), expected: expected.clone(), found: hash.clone() }),
+                    Some(_) => None,
+                })
+                .collect();
+
+            changes.extend(locked
+                .iter()
+                .filter(|(module, _)| !modules.contains_key(*module))
+                .map(|(module, hash)| ModuleChange::Removed { module: module.clone(), hash: hash.clone() }));
+
+            if !changes.is_empty() {
+                return Err(CompilerErrorKind::LockfileMismatch { entry: entry.clone(), changes }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,11 @@
-use std::{cell::RefCell, collections::HashMap, env, fs::{self, read_to_string}, rc::Rc, str::FromStr};
+use std::{env, io::{self, BufRead, Write}, path::{Path, PathBuf}, sync::OnceLock};
 
-use otr::{compiler::{Compiler, expression_parser::ExpressionParser, file_reader::{FileReader, ImportAddress}}, lexer::{FragmentStream, Tokenizer, token::{PunctuationToken, Token}}, runtime::{
-    Expression, ModuleAddress, scope::{Scope, ScopeAddressant}, Struct, Value, environment::Environment, expressions::{
-        EqualityExpression, ProcedureCallExpression, VariableExpression, arithmetic::AddExpression, boolean::NotExpression
-    }, module::Module, procedures::{CompiledProcedure, CompiledProcedureBuilder, Instruction, Procedure}
-}};
+use rustyline::{Context, Editor, Helper, completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter, history::DefaultHistory, validate::Validator};
+
+use otr::{config::Config, conformance::{self, CONFORMANCE_DIR}, diagnostics::{Diagnostic, codes}, grammar, compiler::{Compiler, CompilerError, CompilerErrorKind, file_reader::{FileReader, ImportAddress}, optimizer}, lockfile::{LOCKFILE_NAME, Lockfile}, manifest::Manifest, registry, repl::{self, REPL_MODULE_ID, Session}, runtime::{RuntimeError, RuntimeObject, Value, image::COMPILED_EXTENSION, trace::Trace}};
 
 fn main() {
-    
+
     /* let input = "Dere::Saft { saftigkeit: 20 }";
 
     let fragments = FragmentStream::from_str(input).unwrap();
@@ -16,12 +14,345 @@ fn main() {
 
     println!("{:?}", ExpressionParser::parse(tokens)); */
 
-    let mut file_reader = FileReader::new(env::current_dir().unwrap());
+    // '-O' runs the optimizer on the compiled program. It and the flags of `Config` may appear anywhere.
+    let (flags, args) = Config::from_args(env::args().skip(1).collect()).unwrap_or_else(|err| exit_with(err));
+    let _ = OVERRIDES.set(flags.or(Config::from_env().unwrap_or_else(|err| exit_with(err))));
+
+    let optimize = args.iter().any(|arg| arg == "-O");
+    let mut args = args.into_iter().filter(|arg| arg != "-O");
+
+    // Without a command the entry module declared in the manifest is run.
+    let command = args.next().unwrap_or_else(|| "run".to_string());
+
+    match command.as_str() {
+        "replay" => {
+            let module_name = args.next().unwrap_or_else(|| exit_with_usage("otr replay <module> [arguments...]"));
+
+            let runtime_object = compile(module_name, optimize).with_arguments(args.collect());
+
+            let (result, trace) = runtime_object.execute_traced();
+
+            print_result(&result);
+
+            replay(&runtime_object, trace);
+        }
+
+        // The report goes to stderr, so the output of the program stays the same.
+        "profile" => {
+            let module_name = args.next().unwrap_or_else(entry_module);
+
+            let (result, profile) = compile(module_name, optimize).with_arguments(args.collect()).execute_with_profile();
+
+            print_result(&result);
+
+            eprint!("{}", profile);
+        }
+
+        "ast" => {
+            let module_name = args.next().unwrap_or_else(|| exit_with_usage("otr ast <module>"));
+
+            print!("{}", compile(module_name, optimize).disassemble());
+        }
+
+        "add" => {
+            let package = args.next().unwrap_or_else(|| exit_with_usage("otr add <package>"));
+
+            match registry::add(&project_root(), &package) {
+                Ok(dependency) => println!("Added '{}' in {}.", package, dependency.path.display()),
+                Err(err) => exit_with(err),
+            }
+        }
+
+        // Writes the compiled program to '<module>.otrc', or the path given with '-o'. Running
+        // that file skips compiling the sources.
+        "build" => {
+            let module_name = args.next().unwrap_or_else(entry_module);
+
+            let output = match (args.next().as_deref(), args.next()) {
+                (Some("-o"), Some(path)) => PathBuf::from(path),
+                _ => PathBuf::from(format!("{}.{}", module_name, COMPILED_EXTENSION)),
+            };
+
+            compile(module_name.clone(), optimize).save(&output).unwrap_or_else(|err| exit_with(err));
+
+            println!("Compiled {} to {}.", module_name, output.display());
+        }
+
+        "lock" => {
+            let module_name = args.next().unwrap_or_else(|| exit_with_usage("otr lock <module>"));
+
+            let (_, locked) = compiler(module_name.clone(), false)
+                .compile_locked()
+                .unwrap_or_else(|err| exit_with(err));
+            let modules = locked.entries.get(&module_name).map_or(0, |modules| modules.len());
+
+            // Other entry modules keep what was locked for them.
+            let root = project_root();
+            let mut lockfile = Lockfile::load(&root).unwrap_or_else(|err| exit_with(err)).unwrap_or_default();
+            lockfile.update(locked);
+            lockfile.save(&root).unwrap_or_else(|err| exit_with(err));
+
+            println!("Locked {} modules of {} in {}.", modules, module_name, LOCKFILE_NAME);
+        }
+
+        "check" => {
+            let mut module_names = Vec::new();
+            let mut json = false;
+
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--format" => match args.next().as_deref() {
+                        Some("json") => json = true,
+                        Some("text") => json = false,
+                        other => {
+                            println!("Unknown format {:?}! Expected 'text' or 'json'.", other);
+                            std::process::exit(1);
+                        }
+                    },
+                    _ => module_names.push(arg),
+                }
+            }
+
+            // Every module is checked on its own, so one broken module doesn't hide the problems of the others.
+            let diagnostics: Vec<Diagnostic> = module_names
+                .into_iter()
+                .filter_map(|module_name| compiler(module_name, true)
+                    .with_verification(true)
+                    .with_type_checking(true)
+                    .check()
+                    .err())
+                .collect();
+
+            for diagnostic in &diagnostics {
+                if json {
+                    println!("{}", diagnostic.to_json());
+                } else {
+                    println!("{}", diagnostic);
+                }
+            }
+
+            if !diagnostics.is_empty() {
+                std::process::exit(1);
+            }
+
+            if !json {
+                println!("No problems found.");
+            }
+        }
+
+        "repl" => repl(),
+
+        // Runs a snippet like a single REPL input, e.g. 'otr eval "let x = 2; x * 3"'.
+        "eval" => {
+            let snippet = args.collect::<Vec<_>>().join(" ");
+
+            let runtime_object = repl_compiler(Session::new().program(&snippet))
+                .compile()
+                .unwrap_or_else(|err| exit_with(err));
+            configure(&runtime_object);
+
+            execute(&runtime_object);
+        }
+
+        "explain" => {
+            let code = args.next().unwrap_or_default();
+
+            match codes::lookup(&code) {
+                Some(error_code) => print!("{}", error_code.explain()),
+                None => {
+                    println!("Unknown error code '{}'! Known codes:", code);
+                    for error_code in codes::ERROR_CODES {
+                        println!("  {}  {}", error_code.code, error_code.title);
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        "grammar" => {
+            let root = project_root();
+            let tokenizer = Manifest::load(&root)
+                .and_then(|manifest| manifest.tokenizer(&root))
+                .unwrap_or_else(|err| exit_with(err));
+
+            match args.next().as_deref() {
+                Some("textmate") => print!("{}", grammar::textmate(&tokenizer)),
+                Some("tree-sitter") => print!("{}", grammar::tree_sitter(&tokenizer)),
+                other => {
+                    println!("Unknown grammar format {:?}! Expected 'textmate' or 'tree-sitter'.", other);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        "conformance" => {
+            let mut dir = std::path::PathBuf::from(CONFORMANCE_DIR);
+            let mut bless = false;
+
+            for arg in args {
+                match arg.as_str() {
+                    "--bless" => bless = true,
+                    other => dir = other.into(),
+                }
+            }
+
+            if bless {
+                let count = conformance::bless(&dir).unwrap_or_else(|err| exit_with(err));
+                println!("Blessed {} cases.", count);
+                return;
+            }
+
+            let results = conformance::run(&dir).unwrap_or_else(|err| exit_with(err));
+            let failures = results.iter().filter(|result| !result.passed()).count();
+
+            for result in &results {
+                if result.passed() {
+                    println!("PASS {}", result.case.name);
+                } else {
+                    println!("FAIL {}\n  expected: {}\n  actual:   {}", result.case.name, result.expected, result.actual);
+                }
+            }
+
+            println!("{} passed, {} failed.", results.len() - failures, failures);
+
+            if failures > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        "run" => {
+            let module_name = args.next().unwrap_or_else(entry_module);
+
+            run(module_name, args.collect(), optimize);
+        }
+
+        module_name => run(module_name.to_string(), args.collect(), optimize),
+    }
+}
+
+// The arguments following the module name are passed on to the program.
+fn run(module_name: String, arguments: Vec<String>, optimize: bool) {
+    execute(&compile(module_name, optimize).with_arguments(arguments));
+}
+
+fn execute(runtime_object: &RuntimeObject) {
+    let result = runtime_object.execute();
+
+    if let Some(code) = runtime_object.exit_code() {
+        std::process::exit(code);
+    }
+
+    print_result(&result);
+
+    if let Some(code) = result.err().and_then(|err| err.code()) {
+        print_explain_hint(code);
+    }
+}
+
+// Values are printed in their readable form, errors by their message and the calls they
+// occurred in.
+fn print_result(result: &Result<Value, RuntimeError>) {
+    match result {
+        Ok(value) => println!("{}", value),
+        Err(err) => print_error(err),
+    }
+}
+
+fn print_error(err: &RuntimeError) {
+    println!("{}", err);
+
+    // Recursive calls are printed once, with the number of times they repeat.
+    let mut frames = err.stack_trace().iter().peekable();
+    while let Some(frame) = frames.next() {
+        let mut repeats = 0;
+        while frames.next_if_eq(&frame).is_some() {
+            repeats += 1;
+        }
 
-    let mut args = env::args();
-    args.next();
+        match repeats {
+            0 => println!("    at {}", frame),
+            _ => println!("    at {} ({} more times)", frame, repeats),
+        }
+    }
+}
 
-    let module_name = args.next().unwrap();
+// The directory of the nearest manifest, so programs can be run from any of its subdirectories.
+fn project_root() -> PathBuf {
+    Manifest::find_root(&env::current_dir().unwrap())
+}
+
+fn entry_module() -> String {
+    Manifest::load(&project_root())
+        .unwrap_or_else(|err| exit_with(err))
+        .entry
+        .unwrap_or_else(|| exit_with(CompilerError::from(CompilerErrorKind::MissingEntryModule)))
+}
+
+// Settings given on the command line or through environment variables.
+static OVERRIDES: OnceLock<Config> = OnceLock::new();
+
+// The settings of the project, see `Config` for where each one is taken from.
+fn config(manifest: &Manifest) -> Config {
+    OVERRIDES
+        .get()
+        .cloned()
+        .unwrap_or_default()
+        .or(Config::from_manifest(manifest))
+        .or(Config::load_user().unwrap_or_else(|err| exit_with(err)))
+}
+
+fn configure(runtime_object: &RuntimeObject) {
+    let manifest = Manifest::load(&project_root()).unwrap_or_else(|err| exit_with(err));
+
+    config(&manifest).apply(runtime_object);
+}
+
+// Modules given as a path to a '.otrc' file are loaded as they were compiled.
+fn compile(module_name: String, optimize: bool) -> RuntimeObject {
+    let mut runtime_object = if Path::new(&module_name).extension().is_some_and(|extension| extension == COMPILED_EXTENSION) {
+        RuntimeObject::load(Path::new(&module_name)).unwrap_or_else(|err| exit_with(err))
+    } else {
+        compile_sources(module_name)
+    };
+
+    // The report goes to stderr, so the output of the program stays the same.
+    if optimize {
+        let report = optimizer::optimize(&mut runtime_object).unwrap_or_else(|err| exit_with(err));
+        eprintln!("{}", report);
+    }
+
+    configure(&runtime_object);
+
+    runtime_object
+}
+
+fn compile_sources(module_name: String) -> RuntimeObject {
+    compiler(module_name, true)
+        .compile()
+        .unwrap_or_else(|err| exit_with(err))
+}
+
+fn compiler(module_name: String, verify_lockfile: bool) -> Compiler {
+    compiler_with(module_name, verify_lockfile, |file_reader| file_reader)
+}
+
+// Like `compiler`, but lets the caller adjust the file reader, e.g. to provide sources from memory.
+fn compiler_with(module_name: String, verify_lockfile: bool, setup: impl FnOnce(FileReader) -> FileReader) -> Compiler {
+    let root = project_root();
+    let manifest = Manifest::load(&root).unwrap_or_else(|err| exit_with(err));
+
+    let tokenizer = manifest.tokenizer(&root).unwrap_or_else(|err| exit_with(err));
+    let strict_conditions = config(&manifest).strict_conditions();
+    let denied_capabilities = manifest.deny.clone();
+    // Projects with a lockfile are checked against it unless they opt out.
+    let verify_lockfile = verify_lockfile && manifest.locked.unwrap_or(true);
+    let mut file_reader = setup(FileReader::new(root.clone()).with_manifest(manifest));
+
+    if verify_lockfile {
+        if let Some(lockfile) = Lockfile::load(&root).unwrap_or_else(|err| exit_with(err)) {
+            file_reader = file_reader.with_lockfile(lockfile);
+        }
+    }
 
     let main_module = ImportAddress {
         module_id: module_name,
@@ -30,9 +361,214 @@ fn main() {
 
     file_reader.enqueue(main_module);
 
-    let compiler = Compiler::new(file_reader);
+    denied_capabilities.into_iter().fold(
+        Compiler::new(file_reader)
+            .with_tokenizer(tokenizer)
+            .with_strict_conditions(strict_conditions),
+        Compiler::deny_capability
+    )
+}
+
+fn exit_with(err: CompilerError) -> ! {
+    println!("{}", err);
+
+    if let Some(code) = err.code() {
+        print_explain_hint(code);
+    }
+
+    std::process::exit(1);
+}
+
+fn exit_with_usage(usage: &str) -> ! {
+    println!("Missing argument! Usage: {}", usage);
+    std::process::exit(1);
+}
+
+fn print_explain_hint(code: &str) {
+    println!("For more information about this error, run 'otr explain {}'.", code);
+}
+
+fn repl_compiler(source: String) -> Compiler {
+    compiler_with(REPL_MODULE_ID.to_string(), false, |file_reader| file_reader.with_source(REPL_MODULE_ID, source))
+        .with_implicit_results(true)
+}
+
+// Completes module members of the program compiled last.
+struct ReplHelper {
+    addresses: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(repl::complete(&self.addresses, line, pos))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+fn repl() {
+    let history_path = env::current_dir().unwrap().join(".otr").join("repl_history");
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new().unwrap_or_else(|err| {
+        println!("Could not start the REPL! {}", err);
+        std::process::exit(1);
+    });
+    editor.set_helper(Some(ReplHelper { addresses: Vec::new() }));
+    let _ = editor.load_history(&history_path);
+
+    let mut session = Session::new();
+
+    println!("otr REPL. Declare modules or enter statements and expressions. Results are recalled as _1, _2, ... and _. Quit with Ctrl+D.");
+
+    loop {
+        let mut input = String::new();
+
+        // Keep reading while brackets are open. An empty line submits the input as it is.
+        loop {
+            match editor.readline(if input.is_empty() { "otr> " } else { "...> " }) {
+                Ok(line) => {
+                    let submit = line.trim().is_empty();
+                    input.push_str(&line);
+                    input.push('\n');
+
+                    if submit || repl_compiler(String::new()).is_input_complete(&input) {
+                        break;
+                    }
+                }
+                Err(ReadlineError::Interrupted) => {
+                    input.clear();
+                    break;
+                }
+                Err(_) => {
+                    if let Some(parent) = history_path.parent() {
+                        let _ = std::fs::create_dir_all(parent);
+                    }
+                    let _ = editor.save_history(&history_path);
+                    return;
+                }
+            }
+        }
+
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(input.trim_end());
+
+        let source = if Session::is_definition(&input) {
+            session.program_with_definition(&input)
+        } else {
+            session.program(&input)
+        };
+
+        let runtime_object = match repl_compiler(source).compile() {
+            Ok(runtime_object) => runtime_object,
+            Err(err) => {
+                println!("{}", err.message());
+                continue;
+            }
+        };
+        configure(&runtime_object);
+
+        if let Some(helper) = editor.helper_mut() {
+            helper.addresses = runtime_object.exported_addresses();
+        }
+
+        if Session::is_definition(&input) {
+            session.define(input);
+            continue;
+        }
+
+        let result = runtime_object
+            .prepare()
+            .and_then(|execution| execution.with_variables(session.variables()).execute());
+
+        match (runtime_object.exit_code(), result) {
+            (Some(code), _) => println!("exit: {}", code),
+            (None, Ok(value)) => println!("{} = {}", session.record(value.clone()), value),
+            (None, Err(err)) => print_error(&err),
+        }
+    }
+}
+
+fn replay(runtime_object: &RuntimeObject, trace: Trace) {
+    let steps = trace.steps();
+
+    if steps == 0 {
+        println!("Trace is empty!");
+        return;
+    }
+
+    println!("Recorded {} steps. Commands: n [count], b [count], g <step>, p, q", steps);
+
+    if trace.is_truncated() {
+        println!("The trace grew too large and only covers the start of the run.");
+    }
+
+    let mut step = 0;
+    print_step(runtime_object, &trace, step, false);
+
+    let stdin = io::stdin();
+    loop {
+        print!("replay> ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("n");
+        let count = words.next().and_then(|word| word.parse::<usize>().ok());
+
+        match command {
+            "n" | "next" => step = (step + count.unwrap_or(1)).min(steps - 1),
+            "b" | "back" => step = step.saturating_sub(count.unwrap_or(1)),
+            "g" | "goto" => step = count.unwrap_or(step).min(steps - 1),
+            "p" | "print" => {
+                print_step(runtime_object, &trace, step, true);
+                continue;
+            }
+            "q" | "quit" => break,
+            other => {
+                println!("Unknown command '{}'!", other);
+                continue;
+            }
+        }
+
+        print_step(runtime_object, &trace, step, false);
+    }
+}
+
+fn print_step(runtime_object: &RuntimeObject, trace: &Trace, step: usize, with_variables: bool) {
+    let Some(state) = trace.state_at(step) else {
+        return;
+    };
+
+    if let Some(frame) = state.frames.last() {
+        let pc = frame.pc.unwrap_or(0);
+        let instruction = runtime_object.disassemble_instruction(&frame.procedure, pc).unwrap_or_default();
+
+        println!("[{}/{}] {} @ {}  {}", step, trace.steps() - 1, frame.procedure, pc, instruction);
+    }
 
-    let runtime_object = compiler.compile().unwrap();
-    
-    println!("{:?}", runtime_object.execute());
-}
\ No newline at end of file
+    if with_variables {
+        for (depth, frame) in state.frames.iter().enumerate().rev() {
+            println!("  #{} {}", depth, frame.procedure);
+            for (identifier, value) in frame.variables() {
+                println!("      {} = {}", identifier, value);
+            }
+        }
+    }
+}
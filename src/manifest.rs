@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::CompilerError, lexer::{Tokenizer, keywords::KeywordTable}};
+
+pub const MANIFEST_FILE_NAME: &str = "otr.toml";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    // Module run when no module is given on the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+
+    // Local packages by name, relative to the project root. 'import Shapes from "geometry"'
+    // reads 'Shapes.otr' from the package named geometry.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, PathBuf>,
+
+    // Keyword table loaded into the tokenizer, relative to the project root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<PathBuf>,
+
+    // Set to false to let if and while conditions accept any value by its truthiness.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_conditions: Option<bool>,
+
+    // Set to false to stop checking the imported modules against the hashes in otr.lock, e.g.
+    // while editing them. Builds are only checked once 'otr lock' wrote the lockfile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<bool>,
+
+    // Builtin modules the program may not use, e.g. ["Host", "Sys"].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<RegistryConfig>,
+
+    // Maps the URL of every remote import to the sha256 hash of its expected content.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub remote: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    pub index: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependency {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tarball: Option<String>,
+    pub path: PathBuf,
+}
+
+impl Manifest {
+    // The nearest directory containing a manifest, starting at the given one. Without a manifest
+    // the starting directory is the project root.
+    pub fn find_root(start: &Path) -> PathBuf {
+        start
+            .ancestors()
+            .find(|dir| dir.join(MANIFEST_FILE_NAME).exists())
+            .unwrap_or(start)
+            .to_path_buf()
+    }
+
+    // Projects without a manifest behave as if an empty one was present.
+    pub fn load(root: &Path) -> Result<Self, CompilerError> {
+        let path = root.join(MANIFEST_FILE_NAME);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(|err| CompilerError::io(format!("Manifest '{}'", path.display()), "read", err))?;
+
+        toml::from_str(&content).map_err(|err| CompilerError::invalid_file(format!("Manifest '{}'", path.display()), err))
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), CompilerError> {
+        let path = root.join(MANIFEST_FILE_NAME);
+
+        let content = toml::to_string(self).map_err(|err| CompilerError::io("Manifest".into(), "serialized", err))?;
+
+        fs::write(&path, content).map_err(|err| CompilerError::io(format!("Manifest '{}'", path.display()), "written", err))
+    }
+
+    // Directories searched for imported modules after the project root: dependencies first,
+    // then packages, each in declaration order.
+    pub fn search_paths(&self, root: &Path) -> Vec<PathBuf> {
+        self.dependencies
+            .values()
+            .map(|dependency| &dependency.path)
+            .chain(self.packages.values())
+            .map(|path| root.join(path))
+            .collect()
+    }
+
+    // Replaces a leading package name in an import location with the path of the package.
+    pub fn resolve_package(&self, location: &str) -> Option<PathBuf> {
+        let mut components = Path::new(location).components();
+        let name = components.next()?.as_os_str().to_str()?;
+
+        self.packages.get(name).map(|path| path.join(components.as_path()))
+    }
+
+    pub fn tokenizer(&self, root: &Path) -> Result<Tokenizer, CompilerError> {
+        match &self.keywords {
+            Some(path) => Tokenizer::default().with_keyword_table(&KeywordTable::load(&root.join(path))?),
+            None => Ok(Tokenizer::default()),
+        }
+    }
+}
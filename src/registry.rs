@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, env, fs, path::Path, process::Command};
+
+use serde::Deserialize;
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, remote}, manifest::{Dependency, Manifest}};
+
+pub const INDEX_ENV_VAR: &str = "OTR_INDEX";
+pub const DEPENDENCY_DIRECTORY: &str = "deps";
+
+#[derive(Debug, Deserialize)]
+struct Index {
+    #[serde(default)]
+    packages: BTreeMap<String, IndexEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    git: Option<String>,
+    tarball: Option<String>,
+}
+
+// Resolves `package` through the community index, vendors it into `deps/` and records it in the manifest.
+pub fn add(root: &Path, package: &str) -> Result<Dependency, CompilerError> {
+    let mut manifest = Manifest::load(root)?;
+
+    if let Some(dependency) = manifest.dependencies.get(package) {
+        return Ok(dependency.clone());
+    }
+
+    let index_location = env::var(INDEX_ENV_VAR)
+        .ok()
+        .or_else(|| manifest.registry.as_ref().map(|registry| registry.index.clone()))
+        .ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingPackageIndex))?;
+
+    let index = load_index(root, &index_location)?;
+
+    let entry = index.packages.get(package).ok_or_else(|| CompilerError::from(CompilerErrorKind::UnknownPackage { package: package.to_string(), index: index_location.clone() }))?;
+
+    let relative_path = Path::new(DEPENDENCY_DIRECTORY).join(package);
+    let target = root.join(&relative_path);
+
+    if target.exists() {
+        return Err(CompilerErrorKind::AlreadyExists { path: target }.into());
+    }
+
+    let dependency = match (&entry.git, &entry.tarball) {
+        (Some(git), _) => {
+            // The URL comes from the index, so it must not be taken for an option.
+            let result = run(Command::new("git").arg("clone").arg("--depth").arg("1").arg("--").arg(git).arg(&target));
+            remove_on_error(&result, &target);
+            result?;
+
+            Dependency { git: Some(git.clone()), tarball: None, path: relative_path }
+        }
+        (None, Some(tarball)) => {
+            let archive = root.join(DEPENDENCY_DIRECTORY).join(format!("{}.tar.gz", package));
+
+            let content = remote::download(tarball)?;
+
+            let result = fs::create_dir_all(&target)
+                .and_then(|_| fs::write(&archive, content))
+                .map_err(|err| CompilerError::io(format!("Package '{}'", package), "stored", err))
+                .and_then(|_| run(Command::new("tar")
+                    .arg("-xzf").arg(&archive)
+                    .arg("-C").arg(&target)
+                    .arg("--strip-components=1")));
+            let _ = fs::remove_file(&archive);
+            remove_on_error(&result, &target);
+            result?;
+
+            Dependency { git: None, tarball: Some(tarball.clone()), path: relative_path }
+        }
+        (None, None) => {
+            return Err(CompilerErrorKind::PackageWithoutSource { package: package.to_string() }.into());
+        }
+    };
+
+    manifest.dependencies.insert(package.to_string(), dependency.clone());
+    manifest.save(root)?;
+
+    Ok(dependency)
+}
+
+fn load_index(root: &Path, location: &str) -> Result<Index, CompilerError> {
+    let content = if remote::is_remote(location) {
+        String::from_utf8(remote::download(location)?).map_err(|err| CompilerError::invalid_file(format!("Package index '{}'", location), err))?
+    } else {
+        fs::read_to_string(root.join(location)).map_err(|err| CompilerError::io(format!("Package index '{}'", location), "read", err))?
+    };
+
+    toml::from_str(&content).map_err(|err| CompilerError::invalid_file(format!("Package index '{}'", location), err))
+}
+
+// Removes what a failed clone or extraction left of the package, so adding it can be retried.
+fn remove_on_error<T>(result: &Result<T, CompilerError>, target: &Path) {
+    if result.is_err() {
+        let _ = fs::remove_dir_all(target);
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), CompilerError> {
+    let status = command.status().map_err(|err| CompilerError::io(format!("{:?}", command.get_program()), "run", err))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CompilerErrorKind::CommandFailed { program: format!("{:?}", command.get_program()), status: status.to_string() }.into())
+    }
+}
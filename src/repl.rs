@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::runtime::Value;
+
+// The module the REPL compiles its input into.
+pub const REPL_MODULE_ID: &str = "Repl";
+
+// The modules declared during a REPL session. Every other input runs as the body of an
+// entrypoint declared next to them, so it can call into all of them.
+#[derive(Debug, Default)]
+pub struct Session {
+    definitions: Vec<String>,
+    // Results of earlier inputs, recalled as '_1', '_2', ... and the latest one as '_'.
+    results: Vec<Value>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Whether the input declares modules or imports instead of being run.
+    pub fn is_definition(input: &str) -> bool {
+        matches!(input.split_whitespace().next(), Some("module" | "import" | "///"))
+    }
+
+    pub fn define(&mut self, input: String) {
+        self.definitions.push(input);
+    }
+
+    // Stores the result of an input and returns the name it can be recalled by.
+    pub fn record(&mut self, value: Value) -> String {
+        self.results.push(value);
+
+        format!("_{}", self.results.len())
+    }
+
+    // The variables declared for every input, binding the results recorded so far.
+    pub fn variables(&self) -> HashMap<String, Value> {
+        let mut variables: HashMap<String, Value> = self.results
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("_{}", i + 1), value.clone()))
+            .collect();
+
+        if let Some(last) = self.results.last() {
+            variables.insert("_".into(), last.clone());
+        }
+
+        variables
+    }
+
+    // The source of a program running the input. The value of a trailing expression is returned
+    // when compiled with implicit results, and its ';' may be left out.
+    pub fn program(&self, input: &str) -> String {
+        let input = input.trim();
+
+        let body = if input.is_empty() || input.ends_with(';') || input.ends_with('}') {
+            input.to_string()
+        } else {
+            format!("{};", input)
+        };
+
+        let mut source = self.definitions.join("\n");
+
+        source.push_str(&format!(
+            "\nmodule {} {{\n@entrypoint\nproc main() {{\n{}\n}}\nexport main;\n}}\n",
+            REPL_MODULE_ID, body
+        ));
+
+        source
+    }
+
+    // The source of the program with the input added to the declared modules.
+    pub fn program_with_definition(&self, input: &str) -> String {
+        let mut definitions = self.definitions.clone();
+        definitions.push(input.to_string());
+
+        Session { definitions, ..Session::default() }.program("")
+    }
+}
+
+// Completes the name in front of the cursor, e.g. 'Arr' to 'Arrays::' and 'Arrays::s' to
+// 'Arrays::size'. Returns where the completed name starts along with the candidates.
+pub fn complete(addresses: &[String], line: &str, cursor: usize) -> (usize, Vec<String>) {
+    let before = &line[..cursor];
+    let start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|position| position + 1)
+        .unwrap_or(0);
+    let prefix = &before[start..];
+
+    let mut candidates: Vec<String> = if prefix.contains("::") {
+        addresses
+            .iter()
+            .filter(|address| address.starts_with(prefix))
+            .cloned()
+            .collect()
+    } else {
+        addresses
+            .iter()
+            .filter_map(|address| address.split_once("::"))
+            .map(|(module_id, _)| format!("{}::", module_id))
+            .filter(|module| module.starts_with(prefix))
+            .collect()
+    };
+
+    candidates.dedup();
+
+    (start, candidates)
+}
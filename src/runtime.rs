@@ -1,33 +1,369 @@
 use std::cell::{Cell, RefCell};
 use std::fmt::{Display, format};
 use std::ops::Deref;
+use std::path::Path;
 use std::rc::Weak;
 use std::vec::IntoIter;
 use std::{collections::HashMap, rc::Rc};
 
 use derive_more::{Deref, IntoIterator};
+use serde::{Deserialize, Serialize};
 use num::traits::identities;
 
-use crate::compiler::CompilerError;
+use crate::compiler::{CompilerError, CompilerErrorKind};
+use crate::diagnostics::internal::{self, InternalError};
 use crate::compiler::expression_parser::ExpressionParser;
-use crate::lexer::token::{LiteralToken, ParenthesisType, PunctuationToken, Token};
+use crate::compiler::optimizer::ConstantFolder;
+use crate::compiler::type_checker::TypeEnvironment;
+use crate::lexer::token::{LiteralToken, PrimitiveTypeToken, SourceSpan};
+use crate::runtime::bytecode::{Chunk, Op};
+use crate::runtime::context::RuntimeContext;
 use crate::runtime::environment::Environment;
-use crate::runtime::expressions::ProcedureCallExpression;
+use crate::runtime::events::EmittedEvent;
+use crate::runtime::image::{ConstantImage, ExpressionImage};
 use crate::runtime::procedures::{CompiledProcedure, Procedure};
-use crate::runtime::scope::ScopeAddressant;
+use crate::runtime::scope::{Scope, ScopeAddressant};
+use crate::runtime::stats::RuntimeStats;
+use crate::runtime::symbol::Symbol;
+use crate::runtime::{profile::Profile, trace::{Trace, TraceEvent}};
 
+pub mod bytecode;
 pub mod environment;
+pub mod events;
 pub mod expressions;
+pub mod image;
 pub mod module;
 pub mod procedures;
+pub mod profile;
+pub mod stats;
+pub mod symbol;
+pub mod trace;
+pub(crate) mod context;
 
 #[derive(Debug)]
 pub struct RuntimeError {
-    message: String,
+    kind: RuntimeErrorKind,
+    // The call that failed, for builtin procedures whose errors only make sense alongside it,
+    // e.g. assertions.
+    call_site: Option<Box<str>>,
+    // Where the error left the procedure it propagates out of next, see 'unwound_from'.
+    position: Option<Box<Position>>,
+    // The procedures the error propagated out of, innermost first.
+    stack_trace: Vec<Frame>,
+}
+
+// Where in its file an instruction was read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub file: Option<Symbol>,
+    pub span: SourceSpan,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file, self.span),
+            None => write!(f, "{}", self.span),
+        }
+    }
+}
+
+// A call an error propagated out of, with the position it was at in the procedure, e.g. the
+// call of the next frame. Builtin procedures have no position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub procedure: ModuleAddress,
+    pub position: Option<Position>,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.procedure)?;
+
+        if let Some(position) = &self.position {
+            write!(f, " ({})", position)?;
+        }
+
+        Ok(())
+    }
+}
+
+// What went wrong, for hosts that handle some errors themselves. Errors defined by a host are
+// described by their message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    UndefinedVariable { identifier: String },
+    // 'item' is "Procedure", "Struct" or "Variable".
+    NotDefined { item: &'static str, identifier: String },
+    NotExported { item: &'static str, identifier: String },
+    PrivateConstructor { identifier: String },
+    ModuleNotLoaded { module_id: String },
+    UndefinedGlobal { name: String },
+    NoMember { member: String },
+    DuplicateMember { member: String },
+    DuplicateVariable { identifier: String },
+    // 'target' is the type of the addressed value, 'addressant' the addressant as printed.
+    InvalidAddressant { target: String, addressant: String },
+    ExpectedIdentifier,
+    UnresolvedAddress,
+    InvalidReference { found: String },
+    // Of the named argument, or of the return value if there is none.
+    TypeMismatch { argument: Option<String>, expected: PrimitiveTypeToken, found: String },
+    // E.g. of a condition or an index.
+    ExpectedType { expected: PrimitiveTypeToken, found: String },
+    // 'operation' completes "Cannot ...", e.g. "add". Unary operations have no 'rhs'.
+    InvalidOperands { operation: &'static str, lhs: String, rhs: Option<String> },
+    NotIterable { found: String },
+    // 'collection' is "array" or "string".
+    IndexOutOfBounds { index: i64, length: usize, collection: &'static str },
+    ExponentTooLarge { exponent: i64 },
+    InvalidRepetition { count: i64 },
+    RepetitionTooLarge { count: usize },
+    AllocationTooLarge { bytes: usize },
+    MovedValue,
+    DroppedValue,
+    PrivateField,
+    StructInsideItself,
+    CyclicCopy { struct_id: String },
+    // 'found' is the spread value as printed, e.g. "struct 'Main::Size'".
+    InvalidSpread { found: String, target: String },
+    MissingEntrypoint,
+    CapabilityDenied { module_id: String },
+    StackOverflow { limit: usize, procedure: ModuleAddress },
+    InstructionBudgetExceeded { budget: u64 },
+    AllocationBudgetExceeded { budget: u64 },
+    MemoryLimitExceeded { limit: usize, used: usize },
+    IntegerOverflow { origin: String, lhs: i64, operator: String, rhs: i64 },
+    DivisionByZero { origin: String },
+    // 'subject' names the stream or file, 'action' completes "could not be ...", e.g. "read".
+    Io { subject: String, action: &'static str, reason: String },
+    // The arguments of builtin procedures. An unnamed argument is the only one.
+    MissingArgument { procedure: String, argument: Option<String> },
+    ArgumentType { procedure: &'static str, argument: &'static str, expected: &'static str, found: String },
+    InvalidArgument { procedure: String, reason: String },
+    // 'message' is the one given to the assertion, 'reason' describes the values compared.
+    AssertionFailed { message: Option<String>, reason: String },
+    Panic { message: Option<String> },
+    // Set by 'Sys::exit'. The error cannot be caught.
+    Exit { code: i32 },
+    // A value thrown and not caught, as printed in the message.
+    Uncaught { value: String },
+    Internal(String),
+    Other(String),
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndefinedVariable { identifier } => write!(f, "Could not find the variable '{}' in this scope!", identifier),
+            Self::NotDefined { item, identifier } => write!(f, "{} \"{}\" not defined in this module!", item, identifier),
+            Self::NotExported { item, identifier } => write!(f, "{} \"{}\" is not exported by this module!", item, identifier),
+            Self::PrivateConstructor { identifier } => write!(f, "Struct \"{}\" can only be constructed within its defining module!", identifier),
+            Self::ModuleNotLoaded { module_id } => write!(f, "Module '{}' not loaded in this environment!", module_id),
+            Self::UndefinedGlobal { name } => write!(f, "Global '{}' was not provided by the host!", name),
+            Self::NoMember { member } => write!(f, "No member labeled '{}'!", member),
+            Self::DuplicateMember { member } => write!(f, "Cannot insert key '{}' into struct as it is already present!", member),
+            Self::DuplicateVariable { identifier } => write!(f, "Variable '{}' already present in this scope!", identifier),
+            Self::InvalidAddressant { target, addressant } => write!(f, "Values of type {} cannot be addressed by {}!", target, addressant),
+            Self::ExpectedIdentifier => write!(f, "Expected variable identifier, found index!"),
+            Self::UnresolvedAddress => write!(f, "Could not resolve variable's address!"),
+            Self::InvalidReference { found } => write!(f, "Can only reference owned structs, found {}!", found),
+            Self::TypeMismatch { argument: Some(argument), expected, found } => write!(f, "Type mismatch for argument '{}'! Expected {:?}, found {}!", argument, expected, found),
+            Self::TypeMismatch { argument: None, expected, found } => write!(f, "Type mismatch for return value! Expected {:?}, found {}!", expected, found),
+            Self::ExpectedType { expected, found } => write!(f, "Mismatched types! Expected {:?}, found {}!", expected, found),
+            Self::InvalidOperands { operation, lhs, rhs: Some(rhs) } => write!(f, "Cannot {} {} and {}!", operation, lhs, rhs),
+            Self::InvalidOperands { operation, lhs, rhs: None } => write!(f, "Cannot {} {}!", operation, lhs),
+            Self::NotIterable { found } => write!(f, "Cannot iterate over value of type '{}'! Expected Array or String.", found),
+            Self::IndexOutOfBounds { index, length, collection } => write!(f, "Index out of bounds! Index {} on {} of length {}!", index, collection, length),
+            Self::ExponentTooLarge { exponent } => write!(f, "Could not compute power; the exponent {} is too large!", exponent),
+            Self::InvalidRepetition { count } => write!(f, "Cannot repeat a value {} times!", count),
+            Self::RepetitionTooLarge { count } => write!(f, "Cannot repeat a value {} times! The result does not fit into memory.", count),
+            Self::AllocationTooLarge { bytes } => write!(f, "Cannot allocate {} bytes! They do not fit into memory.", bytes),
+            Self::MovedValue => write!(f, "Use of moved value!"),
+            Self::DroppedValue => write!(f, "Use of dropped value!"),
+            Self::PrivateField => write!(f, "Tried to access a private field!"),
+            Self::StructInsideItself => write!(f, "Cannot store a struct inside itself!"),
+            Self::CyclicCopy { struct_id } => write!(f, "Cannot deep copy struct '{}', its references lead back to it!", struct_id),
+            Self::InvalidSpread { found, target } => write!(f, "Cannot spread {} into struct '{}'!", found, target),
+            Self::MissingEntrypoint => write!(f, "No specified entrypoint!"),
+            Self::CapabilityDenied { module_id } => write!(f, "Capability not granted: {}", module_id),
+            Self::StackOverflow { limit, procedure } => write!(f, "Maximum call depth of {} exceeded when calling '{}'!", limit, procedure),
+            Self::InstructionBudgetExceeded { budget } => write!(f, "Instruction budget of {} exceeded!", budget),
+            Self::AllocationBudgetExceeded { budget } => write!(f, "Allocation budget of {} exceeded!", budget),
+            Self::MemoryLimitExceeded { limit, used } => write!(f, "Memory limit of {} bytes exceeded! The program needs about {} bytes.", limit, used),
+            Self::IntegerOverflow { origin, lhs, operator, rhs } => write!(f, "Integer overflow in '{}'! {} {} {} does not fit into an Integer.", origin, lhs, operator, rhs),
+            Self::DivisionByZero { origin } => write!(f, "Division by zero in '{}'!", origin),
+            Self::Io { subject, action, reason } => write!(f, "{} could not be {}! {}", subject, action, reason),
+            Self::MissingArgument { procedure, argument: Some(argument) } => write!(f, "Missing {} argument for '{}'!", argument, procedure),
+            Self::MissingArgument { procedure, argument: None } => write!(f, "Missing argument for '{}'!", procedure),
+            Self::ArgumentType { procedure, argument, expected, found } => write!(f, "The {} passed to '{}' has to be of type {}, found '{}'!", argument, procedure, expected, found),
+            Self::InvalidArgument { procedure, reason } => write!(f, "Invalid argument for '{}'! {}", procedure, reason),
+            Self::AssertionFailed { message: Some(message), reason } => write!(f, "Assertion failed: {}! {}", message, reason),
+            Self::AssertionFailed { message: None, reason } => write!(f, "Assertion failed! {}", reason),
+            Self::Panic { message: Some(message) } => write!(f, "Panic: {}", message),
+            Self::Panic { message: None } => write!(f, "Panic!"),
+            Self::Exit { code } => write!(f, "Exited with code {}", code),
+            Self::Uncaught { value } => write!(f, "Uncaught exception: {}", value),
+            Self::Internal(message) | Self::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl RuntimeErrorKind {
+    // The code 'otr explain' describes this kind of error under, if it has one. Values thrown by
+    // the program and errors defined by a host have none.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::MissingEntrypoint => Some("E0005"),
+            Self::ModuleNotLoaded { .. } => Some("E0006"),
+            Self::UndefinedVariable { .. } | Self::NotDefined { .. } | Self::NoMember { .. } | Self::UnresolvedAddress => Some("E0007"),
+            Self::MovedValue | Self::DroppedValue => Some("E0008"),
+            Self::PrivateField | Self::NotExported { .. } | Self::PrivateConstructor { .. } => Some("E0009"),
+            Self::IndexOutOfBounds { .. } => Some("E0010"),
+            Self::TypeMismatch { .. } | Self::ExpectedType { .. } | Self::InvalidOperands { .. } | Self::ArgumentType { .. } => Some("E0012"),
+            Self::CapabilityDenied { .. } => Some("E0014"),
+            Self::InstructionBudgetExceeded { .. } | Self::AllocationBudgetExceeded { .. } => Some("E0015"),
+            Self::StackOverflow { .. } => Some("E0018"),
+            Self::Internal(_) => Some("E0019"),
+            Self::IntegerOverflow { .. } => Some("E0020"),
+            Self::DivisionByZero { .. } => Some("E0021"),
+            Self::MemoryLimitExceeded { .. } => Some("E0022"),
+            Self::UndefinedGlobal { .. } | Self::DuplicateMember { .. } | Self::DuplicateVariable { .. } |
+            Self::InvalidAddressant { .. } | Self::ExpectedIdentifier | Self::InvalidReference { .. } |
+            Self::NotIterable { .. } | Self::ExponentTooLarge { .. } | Self::InvalidRepetition { .. } |
+            Self::RepetitionTooLarge { .. } | Self::AllocationTooLarge { .. } | Self::StructInsideItself | Self::CyclicCopy { .. } |
+            Self::InvalidSpread { .. } | Self::Io { .. } | Self::MissingArgument { .. } |
+            Self::InvalidArgument { .. } | Self::AssertionFailed { .. } | Self::Panic { .. } |
+            Self::Exit { .. } | Self::Uncaught { .. } | Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<RuntimeErrorKind> for RuntimeError {
+    fn from(kind: RuntimeErrorKind) -> Self {
+        Self { kind, call_site: None, position: None, stack_trace: Vec::new() }
+    }
+}
+
+impl From<InternalError> for RuntimeError {
+    fn from(err: InternalError) -> Self {
+        RuntimeErrorKind::Internal(err.to_string()).into()
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(call_site) = &self.call_site {
+            write!(f, " In '{}'.", call_site)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl RuntimeError {
+    pub fn new(message: String) -> Self {
+        RuntimeErrorKind::Other(message).into()
+    }
+
+    pub(crate) fn missing_argument(procedure: &str, argument: Option<&str>) -> Self {
+        RuntimeErrorKind::MissingArgument { procedure: procedure.to_string(), argument: argument.map(str::to_string) }.into()
+    }
+
+    // E.g. "The separator passed to 'Arrays::join' has to be of type String, found 'Integer'!".
+    pub(crate) fn argument_type(procedure: &'static str, argument: &'static str, expected: &'static str, found: &Value) -> Self {
+        RuntimeErrorKind::ArgumentType { procedure, argument, expected, found: found.get_type_id() }.into()
+    }
+
+    pub(crate) fn invalid_argument(procedure: &str, reason: impl Into<String>) -> Self {
+        RuntimeErrorKind::InvalidArgument { procedure: procedure.to_string(), reason: reason.into() }.into()
+    }
+
+    pub(crate) fn io(subject: String, action: &'static str, reason: impl Display) -> Self {
+        RuntimeErrorKind::Io { subject, action, reason: reason.to_string() }.into()
+    }
+
+    pub(crate) fn invalid_operands(operation: &'static str, lhs: &Value, rhs: &Value) -> Self {
+        RuntimeErrorKind::InvalidOperands { operation, lhs: lhs.get_type_id(), rhs: Some(rhs.get_type_id()) }.into()
+    }
+
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.kind
+    }
+
+    pub(crate) fn in_call(mut self, call_site: String) -> Self {
+        self.call_site = Some(call_site.into());
+        self
+    }
+
+    // Kept for the frame of the procedure the error is about to leave.
+    pub(crate) fn at_position(mut self, position: Position) -> Self {
+        self.position.get_or_insert_with(|| Box::new(position));
+        self
+    }
+
+    pub(crate) fn unwound_from(mut self, procedure_id: &ModuleAddress) -> Self {
+        let position = self.position.take().map(|position| *position);
+        self.stack_trace.push(Frame { procedure: procedure_id.clone(), position });
+        self
+    }
+
+    // The calls that were active when the error occurred, from the procedure it occurred in out
+    // to the entrypoint.
+    pub fn stack_trace(&self) -> &[Frame] {
+        &self.stack_trace
+    }
+
+    // The stable code of this kind of error, see 'otr explain'.
+    pub fn code(&self) -> Option<&'static str> {
+        self.kind.code()
+    }
+
+    // Whether the program was stopped for running more instructions than its fuel or the
+    // '@budget' of a procedure allows, e.g. in an endless loop.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.kind, RuntimeErrorKind::InstructionBudgetExceeded { .. })
+    }
 }
 
 pub trait Expression: std::fmt::Debug {
+    fn summary(&self) -> String;
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError>;
+
+    fn static_type(&self, _types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        Ok(None)
+    }
+
+    // Folds constant subexpressions in place. Returns the value of the whole expression if it is
+    // constant as well, so the caller can replace it.
+    fn fold_constants(&mut self, _folder: &ConstantFolder) -> Option<Value> {
+        None
+    }
+
+    fn as_constant(&self) -> Option<&Value> {
+        None
+    }
+
+    // The address of the variable the expression reads, if it does nothing else.
+    fn as_variable(&self) -> Option<&scope::ScopeAddress> {
+        None
+    }
+
+    // The expression in the form it is saved in with a compiled program.
+    fn image(&self) -> Result<ExpressionImage, CompilerError>;
+
+    // Appends the operations evaluating the expression to the chunk. By default the expression
+    // is evaluated as a whole, by a copy loaded from its image.
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        chunk.push(Op::Evaluate(self.image()?.load()?));
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -39,10 +375,69 @@ pub enum Value {
     Char(char),
     Bool(bool),
     Array(Vec<Value>),
-    Struct(Rc<RefCell<Option<Struct>>>),
-    StructRef(Weak<RefCell<Option<Struct>>>),
+    Struct(StructOwner),
+    StructRef(Weak<RefCell<Struct>>),
 }
 
+// A struct instance. Its owner holds the only strong pointer to it and references hold weak
+// ones, so a reference follows the instance from owner to owner and dies once it is dropped.
+pub(crate) type StructObject = Rc<RefCell<Struct>>;
+
+// Holds an owned struct. Reading it moves the instance on and leaves the owner empty.
+#[derive(Debug, PartialEq)]
+pub struct StructOwner(RefCell<Option<StructObject>>);
+
+impl StructOwner {
+    pub fn new(instance: Struct) -> Self {
+        Self(RefCell::new(Some(Rc::new(RefCell::new(instance)))))
+    }
+
+    pub(crate) fn object(&self) -> Result<StructObject, RuntimeError> {
+        self.0.borrow().clone().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::MovedValue))
+    }
+
+    // Moves the instance to a new owner. References to it stay valid.
+    fn take(&self) -> Result<StructOwner, RuntimeError> {
+        let object = self.0.take().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::MovedValue))?;
+
+        Ok(Self(RefCell::new(Some(object))))
+    }
+
+    fn holds(&self, object: &StructObject) -> bool {
+        self.0.borrow().as_ref().is_some_and(|owned| struct_holds(owned, object))
+    }
+}
+
+// Whether the object is the owned struct itself or held by it through its fields.
+pub(crate) fn struct_holds(owned: &StructObject, object: &StructObject) -> bool {
+    Rc::ptr_eq(owned, object) || owned.borrow().members.values().any(|value| value.holds(object))
+}
+
+// Negative indices count from the end of the array, -1 being the last element.
+pub(crate) fn array_index(index: i64, len: usize) -> Result<usize, RuntimeError> {
+    let resolved = if index < 0 { index + len as i64 } else { index };
+
+    if resolved < 0 || resolved as usize >= len {
+        return Err(RuntimeErrorKind::IndexOutOfBounds { index, length: len, collection: "array" }.into());
+    }
+
+    Ok(resolved as usize)
+}
+
+// Strings are indexed by character, not by byte.
+fn string_index(index: i64, len: usize) -> Result<usize, RuntimeError> {
+    array_index(index, len).map_err(|_| RuntimeError::from(RuntimeErrorKind::IndexOutOfBounds { index, length: len, collection: "string" }))
+}
+
+fn string_char(string: &str, index: i64) -> Result<Value, RuntimeError> {
+    let index = string_index(index, string.chars().count())?;
+
+    Ok(Value::Char(string.chars().nth(index).unwrap_or_default()))
+}
+
+// Copies everything the value owns: arrays, strings and owned structs, however deeply nested.
+// References are shared instead, so a copied reference points to the same struct as the
+// original. 'Values::deepCopy' copies the referenced structs as well.
 impl Clone for Value {
     fn clone(&self) -> Self {
         match self {
@@ -54,9 +449,9 @@ impl Clone for Value {
             Self::Bool(arg0) => Self::Bool(arg0.clone()),
             Self::Array(arg0) => Self::Array(arg0.clone()),
             Self::Struct(arg0) => {
-                Value::Struct(Rc::new(RefCell::new(
-                    arg0.borrow().as_ref().map(|obj| {
-                        obj.clone()
+                Value::Struct(StructOwner(RefCell::new(
+                    arg0.0.borrow().as_ref().map(|obj| {
+                        Rc::new(RefCell::new(obj.borrow().clone()))
                     })
                 )))
             },
@@ -83,6 +478,102 @@ impl PartialEq for Value {
     }
 }
 
+// The readable form of a value, as written by the IO module and the command line. Strings and
+// characters appear as they are, unless they are nested in an array or a struct.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(string) => f.write_str(string),
+            Value::Char(c) => write!(f, "{}", c),
+            other => write_nested(f, other, &mut Vec::new()),
+        }
+    }
+}
+
+// Writes a value the way it appears within an array or a struct. References are followed,
+// unless they lead back to a struct that is being written already.
+fn write_nested(f: &mut std::fmt::Formatter<'_>, value: &Value, visiting: &mut Vec<*const RefCell<Struct>>) -> std::fmt::Result {
+    match value {
+        Value::Null => f.write_str("null"),
+        Value::Integer(n) => write!(f, "{}", n),
+        Value::Float(n) => write!(f, "{}", n),
+        Value::String(string) => write!(f, "{:?}", string),
+        Value::Char(c) => write!(f, "{:?}", c),
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Array(values) => {
+            f.write_str("[")?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_nested(f, value, visiting)?;
+            }
+            f.write_str("]")
+        }
+        Value::Struct(owner) => match owner.object() {
+            Ok(object) => write_struct(f, &object, visiting),
+            Err(_) => f.write_str("<moved>"),
+        },
+        Value::StructRef(weak) => match weak.upgrade() {
+            Some(object) => {
+                f.write_str("ref ")?;
+                write_struct(f, &object, visiting)
+            }
+            None => f.write_str("<dropped>"),
+        },
+    }
+}
+
+fn write_struct(f: &mut std::fmt::Formatter<'_>, object: &StructObject, visiting: &mut Vec<*const RefCell<Struct>>) -> std::fmt::Result {
+    let instance = object.borrow();
+
+    if visiting.contains(&Rc::as_ptr(object)) {
+        return write!(f, "{} {{ .. }}", instance.get_struct_id());
+    }
+
+    visiting.push(Rc::as_ptr(object));
+
+    write!(f, "{} {{", instance.get_struct_id())?;
+    for (i, (field, value)) in instance.get_members().iter().enumerate() {
+        f.write_str(if i > 0 { ", " } else { " " })?;
+        write!(f, "{}: ", field)?;
+        write_nested(f, value, visiting)?;
+    }
+
+    visiting.pop();
+
+    if instance.get_members().values().next().is_some() {
+        f.write_str(" }")
+    } else {
+        f.write_str("}")
+    }
+}
+
+fn copy_struct(object: &StructObject, copying: &mut Vec<*const RefCell<Struct>>) -> Result<Value, RuntimeError> {
+    if copying.contains(&Rc::as_ptr(object)) {
+        return Err(RuntimeErrorKind::CyclicCopy { struct_id: object.borrow().get_struct_id().to_string() }.into());
+    }
+
+    copying.push(Rc::as_ptr(object));
+
+    let mut copy = object.borrow().clone();
+    for value in copy.get_members_mut().values_mut() {
+        *value = value.copy_following(copying)?;
+    }
+
+    copying.pop();
+
+    Ok(Value::Struct(StructOwner::new(copy)))
+}
+
+fn invalid_addressant(value: &Value, addressant: &ScopeAddressant) -> RuntimeError {
+    RuntimeErrorKind::InvalidAddressant { target: value.get_type_id(), addressant: addressant.to_string() }.into()
+}
+
+fn invalid_literal(literal: &str, expected: &'static str) -> CompilerError {
+    CompilerErrorKind::InvalidLiteral { literal: literal.to_string(), expected }.into()
+}
+
 impl TryFrom<LiteralToken> for Value {
     type Error = CompilerError;
 
@@ -93,29 +584,23 @@ impl TryFrom<LiteralToken> for Value {
             }
             LiteralToken::Integer(num) => {
                 Ok(Self::Integer(
-                    num.parse().map_err(|_| CompilerError {
-                        message: format!("Could not parse '{}' as a whole number!", num)
-                    })?
+                    num.parse().map_err(|_| invalid_literal(&num, "a whole number"))?
                 ))
             },
             LiteralToken::Decimal(num) => {
                 Ok(Self::Float(
-                    num.parse().map_err(|_| CompilerError {
-                        message: format!("Could not parse '{}' as a decimal number!", num)
-                    })?
+                    num.parse().map_err(|_| invalid_literal(&num, "a decimal number"))?
                 ))
             },
             LiteralToken::Boolean(b) => {
                 match &b as &str {
                     "true" => Ok(Self::Bool(true)),
                     "false" => Ok(Self::Bool(false)),
-                    _ => Err(CompilerError { message: format!("Could not parse {} as a boolean!", b) })
+                    _ => Err(invalid_literal(&b, "a boolean"))
                 }
             },
             LiteralToken::Char(c) => {
-                Ok(Self::Char(c.chars().next().ok_or(CompilerError {
-                    message: format!("Could not parse {} as a char!", c)
-                })?))
+                Ok(Self::Char(c.chars().next().ok_or_else(|| invalid_literal(&c, "a char"))?))
             },
             LiteralToken::String(str) => {
                 Ok(Self::String(str))
@@ -134,82 +619,131 @@ impl Value {
             Value::Char(_) => "Char".into(),
             Value::Bool(_) => "Bool".into(),
             Value::Array(_) => "Array".into(),
-            Value::Struct(object) => object
-                .borrow()
-                .as_ref()
-                .map(|obj| obj.get_struct_id().to_string())
+            Value::Struct(owner) => owner
+                .object()
+                .map(|obj| obj.borrow().get_struct_id().to_string())
                 .unwrap_or("Moved".into()),
             Value::StructRef(weak) => weak
                 .upgrade()
-                .map(|obj| obj.borrow()
-                    .as_ref()
-                    .map(|obj| obj.get_struct_id().to_string())
-                    .unwrap_or("Moved".into()))
+                .map(|obj| obj.borrow().get_struct_id().to_string())
                 .unwrap_or("Dropped".into()),
         }
     }
 
+    pub fn is_of_type(&self, primitive_type: &PrimitiveTypeToken) -> bool {
+        matches!(
+            (self, primitive_type),
+            (Value::Integer(_), PrimitiveTypeToken::Integer) |
+            (Value::Float(_), PrimitiveTypeToken::Decimal) |
+            (Value::Bool(_), PrimitiveTypeToken::Boolean) |
+            (Value::Char(_), PrimitiveTypeToken::Char) |
+            (Value::String(_), PrimitiveTypeToken::String) |
+            (Value::Array(_), PrimitiveTypeToken::Array)
+        )
+    }
+
+    // Null, false, zero, NaN, empty strings, empty arrays and dropped struct references are
+    // falsy. Every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(value) => *value,
+            Value::Integer(value) => *value != 0,
+            Value::Float(value) => *value != 0.0 && !value.is_nan(),
+            Value::String(value) => !value.is_empty(),
+            Value::Array(value) => !value.is_empty(),
+            Value::Char(_) | Value::Struct(_) => true,
+            Value::StructRef(reference) => reference.upgrade().is_some(),
+        }
+    }
+
+    // Whether the struct instance is this value or is held by it, directly or through other
+    // structs and arrays.
+    fn holds(&self, object: &StructObject) -> bool {
+        match self {
+            Value::Struct(owner) => owner.holds(object),
+            Value::Array(values) => values.iter().any(|value| value.holds(object)),
+            _ => false,
+        }
+    }
+
+    // Like `clone`, but every reference is replaced by an owned copy of the struct it points to.
+    pub(crate) fn deep_copy(&self) -> Result<Value, RuntimeError> {
+        self.copy_following(&mut Vec::new())
+    }
+
+    // The structs being copied are tracked, as references may lead back to them.
+    fn copy_following(&self, copying: &mut Vec<*const RefCell<Struct>>) -> Result<Value, RuntimeError> {
+        match self {
+            Value::Array(values) => values
+                .iter()
+                .map(|value| value.copy_following(copying))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Value::Struct(owner) => match owner.object() {
+                Ok(object) => copy_struct(&object, copying),
+                Err(_) => Ok(self.clone()),
+            },
+            Value::StructRef(weak) => {
+                let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
+
+                copy_struct(&object, copying)
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
     pub fn query(&self, address: impl IntoIterator<Item = ScopeAddressant>, contained_module_id: &String) -> Result<Value, RuntimeError> {
         let mut address = address.into_iter();
         if let Some(addressant) = address.next() {
             match self {
-                Value::Null | Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Char(_) |
-                Value::Bool(_)  => Err(RuntimeError {
-                    message: format!("Value '{:?}' doesn't acceppt addressant '{:?}'", self, addressant)
-                }),
+                Value::Null | Value::Integer(_) | Value::Float(_) | Value::Char(_) |
+                Value::Bool(_)  => Err(invalid_addressant(self, &addressant)),
+                Value::String(string) => {
+                    if let ScopeAddressant::Index(i) = addressant {
+                        string_char(string, i)?.query(address, contained_module_id)
+                    } else {
+                        Err(invalid_addressant(self, &addressant))
+                    }
+                },
                 Value::Array(arr) => {
                     if let ScopeAddressant::Index(i) = addressant {
-                        arr.get(i).ok_or(RuntimeError {
-                            message: format!("Index out of bounds! Index {} on array of length {}!", i, arr.len())
-                        })?.query(address, contained_module_id)
+                        arr[array_index(i, arr.len())?].query(address, contained_module_id)
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Arrays only accept indexing addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
-                Value::Struct(ref_cell) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let reference = ref_cell.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                Value::Struct(owner) => {
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = owner.object()?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.query(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.query(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
                 Value::StructRef(weak) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let rc = weak.upgrade().ok_or(RuntimeError {
-                            message: format!("Use of dropped value!")
-                        })?;
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
 
-                        let reference = rc.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.query(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.query(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
             }
@@ -217,18 +751,7 @@ impl Value {
             match self {
                 Value::Null | Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Char(_) |
                 Value::Bool(_) | Value::Array(_) | Value::StructRef(_) => Ok(self.clone()),
-                Value::Struct(ref_cell) => {
-                    if ref_cell.borrow().is_none() {
-                        return Err(RuntimeError {
-                            message: "Use of moved value!".into()
-                        });
-                    }
-
-                    // Move value
-                    let value = ref_cell.replace(None);
-
-                    Ok(Value::Struct(Rc::new(RefCell::new(value))))
-                }
+                Value::Struct(owner) => Ok(Value::Struct(owner.take()?)),
             }
         }
     }
@@ -237,164 +760,149 @@ impl Value {
         let mut address = address.into_iter();
         if let Some(addressant) = address.next() {
             match self {
-                Value::Null | Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Char(_) |
-                Value::Bool(_)  => Err(RuntimeError {
-                    message: format!("Value '{:?}' doesn't acceppt addressant '{:?}'", self, addressant)
-                }),
+                Value::Null | Value::Integer(_) | Value::Float(_) | Value::Char(_) |
+                Value::Bool(_)  => Err(invalid_addressant(self, &addressant)),
+                Value::String(string) => {
+                    if let ScopeAddressant::Index(i) = addressant {
+                        string_char(string, i)?.query(address, contained_module_id)
+                    } else {
+                        Err(invalid_addressant(self, &addressant))
+                    }
+                },
                 Value::Array(arr) => {
                     if let ScopeAddressant::Index(i) = addressant {
-                        arr.get(i).ok_or(RuntimeError {
-                            message: format!("Index out of bounds! Index {} on array of length {}!", i, arr.len())
-                        })?.query(address, contained_module_id)
+                        arr[array_index(i, arr.len())?].reference(address, contained_module_id)
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Arrays only accept indexing addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
-                Value::Struct(ref_cell) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let reference = ref_cell.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                Value::Struct(owner) => {
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = owner.object()?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.reference(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.reference(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
                 Value::StructRef(weak) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let rc = weak.upgrade().ok_or(RuntimeError {
-                            message: format!("Use of dropped value!")
-                        })?;
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
 
-                        let reference = rc.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.reference(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.reference(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
             }
         } else {
             match self {
                 Value::Null | Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Char(_) |
-                Value::Bool(_) | Value::Array(_) | Value::StructRef(_) => Err(RuntimeError {
-                    message: format!("Can only reference owned structs. Found {:?}!", self)
-                }),
-                Value::Struct(ref_cell) => {
-                    if ref_cell.borrow().is_none() {
-                        return Err(RuntimeError {
-                            message: "Use of moved value!".into()
-                        });
-                    }
-
-                    // Reference
-                    let weak = Rc::downgrade(&ref_cell.clone());
-
-                    Ok(Value::StructRef(weak))
-                }
+                Value::Bool(_) | Value::Array(_) | Value::StructRef(_) => Err(RuntimeErrorKind::InvalidReference { found: self.get_type_id() }.into()),
+                Value::Struct(owner) => Ok(Value::StructRef(Rc::downgrade(&owner.object()?))),
             }
         }
     }
 
-    pub fn set(&mut self, address: impl IntoIterator<Item = ScopeAddressant>, contained_module_id: &String, value: Value) -> Result<(), RuntimeError> {
+    pub fn set(&mut self, address: impl IntoIterator<Item = ScopeAddressant>, contained_module_id: &String, value: Value) -> Result<Value, RuntimeError> {
         let mut address = address.into_iter();
         if let Some(addressant) = address.next() {
             match self {
                 Value::Null | 
                 Value::Integer(_) |
                 Value::Float(_) |
-                Value::String(_) |
                 Value::Char(_) |
-                Value::Bool(_)  => Err(RuntimeError {
-                    message: format!("Value '{:?}' doesn't acceppt addressant '{:?}'", self, addressant)
-                }),
+                Value::Bool(_)  => Err(invalid_addressant(self, &addressant)),
+                Value::String(string) => {
+                    let ScopeAddressant::Index(i) = addressant else {
+                        return Err(invalid_addressant(self, &addressant));
+                    };
+
+                    if let Some(next) = address.next() {
+                        return Err(RuntimeErrorKind::InvalidAddressant { target: "Char".into(), addressant: next.to_string() }.into());
+                    }
+
+                    let Value::Char(c) = value else {
+                        return Err(RuntimeErrorKind::ExpectedType { expected: PrimitiveTypeToken::Char, found: value.get_type_id() }.into());
+                    };
+
+                    let mut chars: Vec<char> = string.chars().collect();
+                    let i = string_index(i, chars.len())?;
+                    let previous = std::mem::replace(&mut chars[i], c);
+                    *string = chars.into_iter().collect();
+
+                    Ok(Value::Char(previous))
+                },
                 Value::Array(arr) => {
                     if let ScopeAddressant::Index(i) = addressant {
-                        let len = arr.len();
-                        arr.get_mut(i).ok_or(RuntimeError {
-                            message: format!("Index out of bounds! Index {} on array of length {}!", i, len)
-                        })?.set(address, contained_module_id, value)
+                        let i = array_index(i, arr.len())?;
+                        arr[i].set(address, contained_module_id, value)
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Arrays only accept indexing addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
-                Value::Struct(ref_cell) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let mut reference = ref_cell.borrow_mut();
-                        let obj = reference.as_mut().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                Value::Struct(owner) => {
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = owner.object()?;
+                        let mut obj = object.borrow_mut();
 
                         let module_id = obj.get_struct_id().get_module_id().clone();
 
                         let members = obj.get_members_mut();
                         
                         if &module_id == contained_module_id {
-                            members.get_member_mut(&ident)?.set(address, contained_module_id, value)
+                            members.get_member_mut(&field)?.set(address, contained_module_id, value)
                         } else {
-                            members.get_public_member_mut(&ident)?.set(address, contained_module_id, value)
+                            members.get_public_member_mut(&field)?.set(address, contained_module_id, value)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
                 Value::StructRef(weak) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let rc = weak.upgrade().ok_or(RuntimeError {
-                            message: format!("Use of dropped value!")
-                        })?;
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
+
+                        // Storing a struct inside itself, directly or through its fields, would
+                        // leave the cycle without an owner outside of it, so nothing could reach
+                        // it anymore.
+                        if value.holds(&object) {
+                            return Err(RuntimeErrorKind::StructInsideItself.into());
+                        }
 
-                        let mut reference = rc.borrow_mut();
-                        let obj = reference.as_mut().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                        let mut obj = object.borrow_mut();
 
                         let module_id = obj.get_struct_id().get_module_id().clone();
 
                         let members = obj.get_members_mut();
                         
                         if &module_id == contained_module_id {
-                            members.get_member_mut(&ident)?.set(address, contained_module_id, value)
+                            members.get_member_mut(&field)?.set(address, contained_module_id, value)
                         } else {
-                            members.get_public_member_mut(&ident)?.set(address, contained_module_id, value)
+                            members.get_public_member_mut(&field)?.set(address, contained_module_id, value)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
             }
         } else {
-            *self = value;
-            Ok(())
+            Ok(std::mem::replace(self, value))
         }
     }
     
@@ -402,73 +910,63 @@ impl Value {
         let mut address = address.into_iter();
         if let Some(addressant) = address.next() {
             match self {
-                Value::Null | Value::Integer(_) | Value::Float(_) | Value::String(_) | Value::Char(_) |
-                Value::Bool(_)  => Err(RuntimeError {
-                    message: format!("Value '{:?}' doesn't acceppt addressant '{:?}'", self, addressant)
-                }),
+                Value::Null | Value::Integer(_) | Value::Float(_) | Value::Char(_) |
+                Value::Bool(_)  => Err(invalid_addressant(self, &addressant)),
+                Value::String(string) => {
+                    if let ScopeAddressant::Index(i) = addressant {
+                        string_char(string, i)?.query(address, contained_module_id)
+                    } else {
+                        Err(invalid_addressant(self, &addressant))
+                    }
+                },
                 Value::Array(arr) => {
                     if let ScopeAddressant::Index(i) = addressant {
-                        arr.get(i).ok_or(RuntimeError {
-                            message: format!("Index out of bounds! Index {} on array of length {}!", i, arr.len())
-                        })?.query(address, contained_module_id)
+                        arr[array_index(i, arr.len())?].query(address, contained_module_id)
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Arrays only accept indexing addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
-                Value::Struct(ref_cell) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let reference = ref_cell.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                Value::Struct(owner) => {
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = owner.object()?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.query(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.query(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
                 Value::StructRef(weak) => {
-                    if let ScopeAddressant::Identifier(ident) = addressant {
-                        let rc = weak.upgrade().ok_or(RuntimeError {
-                            message: format!("Use of dropped value!")
-                        })?;
+                    if let ScopeAddressant::Field(field) = addressant {
+                        let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
 
-                        let reference = rc.borrow();
-                        let obj = reference.as_ref().ok_or(RuntimeError {
-                            message: format!("Use of moved value!")
-                        })?;
+                        let obj = object.borrow();
 
                         let members = obj.get_members();
                         
                         if obj.get_struct_id().get_module_id() == contained_module_id {
-                            members.get_member(&ident)?.query(address, contained_module_id)
+                            members.get_member(&field)?.query(address, contained_module_id)
                         } else {
-                            members.get_public_member(&ident)?.query(address, contained_module_id)
+                            members.get_public_member(&field)?.query(address, contained_module_id)
                         }
                     } else {
-                        Err(RuntimeError {
-                            message: format!("Structs only accept identifier addressants. Found {:?}!", addressant)
-                        })
+                        Err(invalid_addressant(self, &addressant))
                     }
                 },
             }
         } else {
             if let Value::StructRef(weak) = self {
-                let rc = weak.upgrade().ok_or(RuntimeError {
-                    message: "Clone of dropped value".into()
-                })?;
+                let object = weak.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?;
 
-                Ok(Value::Struct(rc).clone())
+                let instance = object.borrow().clone();
+
+                Ok(Value::Struct(StructOwner::new(instance)))
             } else {
                 Ok(self.clone())
             }
@@ -477,9 +975,55 @@ impl Value {
 }
 
 impl Expression for Value {
+    fn static_type(&self, _types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        Ok(match self {
+            Value::Integer(_) => Some(PrimitiveTypeToken::Integer),
+            Value::Float(_) => Some(PrimitiveTypeToken::Decimal),
+            Value::String(_) => Some(PrimitiveTypeToken::String),
+            Value::Char(_) => Some(PrimitiveTypeToken::Char),
+            Value::Bool(_) => Some(PrimitiveTypeToken::Boolean),
+            Value::Array(_) => Some(PrimitiveTypeToken::Array),
+            Value::Null | Value::Struct(_) | Value::StructRef(_) => None,
+        })
+    }
+
+    fn summary(&self) -> String {
+        match self {
+            Value::Null => "Null".into(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => format!("{:?}", f),
+            Value::String(str) => format!("{:?}", str),
+            Value::Char(c) => format!("{:?}", c),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(arr) => {
+                let elements: Vec<String> = arr.iter().map(|element| element.summary()).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Struct(_) | Value::StructRef(_) => format!("<{}>", self.get_type_id()),
+        }
+    }
+
     fn eval(&self, _environment: &Environment) -> Result<Value, RuntimeError> {
         Ok(self.clone())
     }
+
+    fn fold_constants(&mut self, _folder: &ConstantFolder) -> Option<Value> {
+        Some(self.clone())
+    }
+
+    fn as_constant(&self) -> Option<&Value> {
+        Some(self)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        ConstantImage::try_from(self).map(ExpressionImage::Constant)
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        chunk.push(Op::Push(self.clone()));
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -507,9 +1051,7 @@ impl Member {
         if self.is_public {
             Ok(&self.value)
         } else {
-            Err(RuntimeError {
-                message: "Tried to access a private field!".into(),
-            })
+            Err(RuntimeError::from(RuntimeErrorKind::PrivateField))
         }
     }
 
@@ -517,9 +1059,7 @@ impl Member {
         if self.is_public {
             Ok(&mut self.value)
         } else {
-            Err(RuntimeError {
-                message: "Tried to access a private field!".into(),
-            })
+            Err(RuntimeError::from(RuntimeErrorKind::PrivateField))
         }
     }
 
@@ -532,94 +1072,132 @@ impl Member {
             self.value = value;
             Ok(())
         } else {
-            Err(RuntimeError {
-                message: "Tried to access a private field!".into(),
-            })
+            Err(RuntimeError::from(RuntimeErrorKind::PrivateField))
         }
     }
     
-    fn set(&mut self, value: Value) -> Result<(), RuntimeError> {
-        self.value = value;
-        Ok(())
+    fn set(&mut self, value: Value) -> Result<Value, RuntimeError> {
+        Ok(std::mem::replace(&mut self.value, value))
+    }
+}
+
+// A way to find a member, either by its name or through a field accessor that remembers where
+// the field was found last time.
+pub trait MemberKey: Display {
+    fn locate(&self, members: &MemberMap) -> Option<usize>;
+}
+
+impl MemberKey for Symbol {
+    fn locate(&self, members: &MemberMap) -> Option<usize> {
+        members.position(*self)
+    }
+}
+
+impl MemberKey for String {
+    fn locate(&self, members: &MemberMap) -> Option<usize> {
+        members.position(Symbol::find(self)?)
     }
 }
 
+// Members are kept in declaration order, so all instances of a struct type share one layout.
 #[derive(Debug, Clone, PartialEq)]
 pub struct MemberMap {
-    members: HashMap<String, Member>,
+    members: Vec<(Symbol, Member)>,
 }
 
 impl MemberMap {
     pub fn new() -> Self {
         Self {
-            members: HashMap::new(),
+            members: Vec::new(),
         }
     }
 
-    pub fn insert_member(&mut self, ident: String, value: Value, is_public: bool) -> Result<(), RuntimeError> {
-        if self.members.insert(ident.clone(), Member { value, is_public }).is_some() {
-            return Err(RuntimeError {
-                message: format!("Cannot insert key '{}' into struct as it is already present!", ident)
-            })
+    pub fn insert_member(&mut self, ident: Symbol, value: Value, is_public: bool) -> Result<(), RuntimeError> {
+        if self.position(ident).is_some() {
+            return Err(RuntimeErrorKind::DuplicateMember { member: ident.to_string() }.into())
         }
 
+        self.members.push((ident, Member { value, is_public }));
+
         Ok(())
     }
 
-    pub fn get_member(&self, ident: &String) -> Result<&Value, RuntimeError> {
-        let member = self.members.get(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
-
-        Ok(member.get_value())
+    pub(crate) fn position(&self, ident: Symbol) -> Option<usize> {
+        self.members.iter().position(|(name, _)| *name == ident)
     }
 
-    pub fn get_member_mut(&mut self, ident: &String) -> Result<&mut Value, RuntimeError> {
-        let member = self.members.get_mut(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
-
-        Ok(member.get_value_mut())
+    pub(crate) fn name_at(&self, index: usize) -> Option<Symbol> {
+        self.members.get(index).map(|(name, _)| *name)
     }
 
-    pub fn get_public_member(&self, ident: &String) -> Result<&Value, RuntimeError> {
-        let member = self.members.get(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
+    fn member(&self, key: &impl MemberKey) -> Result<&Member, RuntimeError> {
+        match key.locate(self) {
+            Some(index) => Ok(&self.members[index].1),
+            None => Err(RuntimeErrorKind::NoMember { member: key.to_string() }.into()),
+        }
+    }
 
-        member.get_value_if_public()
+    fn member_mut(&mut self, key: &impl MemberKey) -> Result<&mut Member, RuntimeError> {
+        match key.locate(self) {
+            Some(index) => Ok(&mut self.members[index].1),
+            None => Err(RuntimeErrorKind::NoMember { member: key.to_string() }.into()),
+        }
     }
 
-    pub fn get_public_member_mut(&mut self, ident: &String) -> Result<&mut Value, RuntimeError> {
-        let member = self.members.get_mut(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
+    pub fn get_member(&self, ident: &impl MemberKey) -> Result<&Value, RuntimeError> {
+        Ok(self.member(ident)?.get_value())
+    }
 
-        member.get_value_mut_if_public()
+    pub fn get_member_mut(&mut self, ident: &impl MemberKey) -> Result<&mut Value, RuntimeError> {
+        Ok(self.member_mut(ident)?.get_value_mut())
     }
 
-    pub fn set_public_member(&mut self, ident: &String, value: Value) -> Result<(), RuntimeError> {
-        let member = self.members.get_mut(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
+    pub fn get_public_member(&self, ident: &impl MemberKey) -> Result<&Value, RuntimeError> {
+        self.member(ident)?.get_value_if_public()
+    }
 
-        member.set_if_public(value)
+    pub fn get_public_member_mut(&mut self, ident: &impl MemberKey) -> Result<&mut Value, RuntimeError> {
+        self.member_mut(ident)?.get_value_mut_if_public()
     }
 
-    pub fn set_member(&mut self, ident: &String, value: Value) -> Result<(), RuntimeError> {
-        let member = self.members.get_mut(ident).ok_or(RuntimeError {
-            message: format!("No member labeled '{}'!", ident),
-        })?;
+    pub fn set_public_member(&mut self, ident: &impl MemberKey, value: Value) -> Result<(), RuntimeError> {
+        self.member_mut(ident)?.set_if_public(value)
+    }
 
-        member.set(value)
+    pub fn set_member(&mut self, ident: &impl MemberKey, value: Value) -> Result<Value, RuntimeError> {
+        self.member_mut(ident)?.set(value)
     }
 
     pub fn len(&self) -> usize {
         self.members.len()
     }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.members.iter().map(|(_, member)| member.get_value())
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.members.iter_mut().map(|(_, member)| &mut member.value)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Symbol, &Value)> {
+        self.members.iter().map(|(ident, member)| (*ident, member.get_value()))
+    }
+
+    // Name, visibility and value of every member.
+    pub(crate) fn fields(&self) -> impl Iterator<Item = (Symbol, bool, &Value)> {
+        self.members.iter().map(|(ident, member)| (*ident, member.is_public, member.get_value()))
+    }
+
+    pub(crate) fn public_members(&self) -> impl Iterator<Item = (Symbol, &Value)> {
+        self.members
+            .iter()
+            .filter(|(_, member)| member.is_public)
+            .map(|(ident, member)| (*ident, member.get_value()))
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ModuleAddress {
     module_id: String,
     identifier: String,
@@ -657,11 +1235,49 @@ impl ModuleAddress {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
     //TODO: Remove public visibility
     pub struct_id: ModuleAddress,
     pub members: MemberMap,
+    // Weak, as the context may hold structs itself, e.g. in the globals shared with the host.
+    tracker: Option<Weak<RuntimeContext>>,
+}
+
+impl Clone for Struct {
+    fn clone(&self) -> Self {
+        if let Some(context) = self.tracker.as_ref().and_then(Weak::upgrade) {
+            context.stats.retain_struct(&self.members);
+        }
+
+        Self {
+            struct_id: self.struct_id.clone(),
+            members: self.members.clone(),
+            tracker: self.tracker.clone(),
+        }
+    }
+}
+
+impl Drop for Struct {
+    fn drop(&mut self) {
+        if let Some(context) = self.tracker.as_ref().and_then(Weak::upgrade) {
+            context.stats.release_struct(&self.members);
+        }
+    }
+}
+
+impl PartialEq for Struct {
+    fn eq(&self, other: &Self) -> bool {
+        self.struct_id == other.struct_id && self.members == other.members
+    }
+}
+
+impl std::fmt::Debug for Struct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Struct")
+            .field("struct_id", &self.struct_id)
+            .field("members", &self.members)
+            .finish()
+    }
 }
 
 impl Struct {
@@ -669,6 +1285,14 @@ impl Struct {
         Self {
             struct_id,
             members: MemberMap::new(),
+            tracker: None,
+        }
+    }
+
+    pub(crate) fn track(&mut self, context: Rc<RuntimeContext>) {
+        if self.tracker.is_none() {
+            context.stats.retain_struct(&self.members);
+            self.tracker = Some(Rc::downgrade(&context));
         }
     }
 
@@ -689,7 +1313,9 @@ impl Struct {
 #[derive(Debug)]
 pub struct RuntimeObject {
     pub(crate) base_environement: Environment,
-    pub(crate) entrypoint: Option<ModuleAddress>
+    pub(crate) entrypoint: Option<ModuleAddress>,
+    // Passed to the entrypoint as an array of strings, its first argument.
+    arguments: Vec<String>,
 }
 
 impl RuntimeObject {
@@ -697,20 +1323,250 @@ impl RuntimeObject {
         Self {
             base_environement: Environment::new("".into()),
             entrypoint: None,
+            arguments: Vec::new(),
+        }
+    }
+
+    // Sets the program arguments, e.g. the command line arguments following the module name.
+    pub fn with_arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    pub fn execute(&self) -> Result<Value, RuntimeError> {
+        self.prepare()?.execute()
+    }
+
+    // Like `execute`, but stops with a timeout once the program has run the given number of
+    // instructions, calls included. Fuel set with `set_fuel` still applies if it is lower.
+    pub fn execute_with_fuel(&self, fuel: u64) -> Result<Value, RuntimeError> {
+        self.prepare()?.with_fuel(fuel).execute()
+    }
+
+    // Writes the compiled program to a '.otrc' file, which `load` runs without compiling again.
+    pub fn save(&self, path: &Path) -> Result<(), CompilerError> {
+        image::save(self, path)
+    }
+
+    pub fn load(path: &Path) -> Result<RuntimeObject, CompilerError> {
+        image::load(path)
+    }
+
+    // Like `save` and `load`, but without a file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CompilerError> {
+        image::encode(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<RuntimeObject, CompilerError> {
+        image::decode(bytes)
+    }
+
+    // Resolves the entrypoint and sets up its environment once, for hosts running the same
+    // program many times.
+    pub fn prepare(&self) -> Result<PreparedExecution<'_>, RuntimeError> {
+        let entrypoint = self.entrypoint.as_ref().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::MissingEntrypoint))?;
+
+        let (procedure_id, procedure) = self.base_environement.lookup_procedure(entrypoint)?;
+
+        let environment = self.base_environement.open_subenvironment(Scope::new(), &procedure_id);
+
+        let arguments = Value::Array(self.arguments.iter().cloned().map(Value::String).collect());
+
+        Ok(PreparedExecution { procedure_id, procedure: procedure.as_ref(), environment, arguments, fuel: None })
+    }
+
+    pub fn execute_traced(&self) -> (Result<Value, RuntimeError>, Trace) {
+        let context = &self.base_environement.context;
+
+        context.start_trace();
+        let result = self.execute();
+        let trace = context.take_trace().unwrap_or_default();
+
+        (result, trace)
+    }
+
+    // Runs the program while counting how often each procedure and instruction runs and how
+    // long they take. Timing every instruction slows the program down, so only relative times
+    // are meaningful.
+    pub fn execute_with_profile(&self) -> (Result<Value, RuntimeError>, Profile) {
+        let context = &self.base_environement.context;
+
+        context.start_profile();
+        let result = self.execute();
+        let profile = context.take_profile().unwrap_or_default();
+
+        (result, profile)
+    }
+
+    // Every 'Module::member' the program can refer to, sorted, e.g. for completion.
+    pub fn exported_addresses(&self) -> Vec<String> {
+        let mut addresses: Vec<String> = self.base_environement
+            .loaded_modules
+            .iter()
+            .flat_map(|(module_id, module)| module.exported_names().map(move |name| format!("{}::{}", module_id, name)))
+            .collect();
+
+        addresses.sort();
+
+        addresses
+    }
+
+    pub fn documentation(&self, address: &ModuleAddress) -> Option<&String> {
+        self.base_environement
+            .loaded_modules
+            .get(address.get_module_id())?
+            .get_documentation(address.get_identifier())
+    }
+
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+
+        for (module_id, module) in &self.base_environement.loaded_modules {
+            let procedures = module.procedures()
+                .filter_map(|(identifier, procedure)| Some((identifier, procedure.as_compiled()?)));
+
+            for (identifier, procedure) in procedures {
+                out.push_str(&format!("proc {}::{}{}\n", module_id, identifier, procedure.disassemble()));
+            }
         }
+
+        out
+    }
+
+    pub fn disassemble_instruction(&self, address: &ModuleAddress, pc: usize) -> Option<String> {
+        self.base_environement
+            .loaded_modules
+            .get(address.get_module_id())?
+            .get_procedure(address.get_identifier(), true)
+            .ok()?
+            .as_compiled()?
+            .disassemble_instruction(pc)
+    }
+
+    // The code passed to 'Sys::exit' if the last execution was terminated by it.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.base_environement.context.exit_code()
+    }
+
+    pub fn stats(&self) -> RuntimeStats {
+        self.base_environement.context.stats.snapshot()
+    }
+
+    // Makes a value readable by every module through 'Host::get'.
+    pub fn set_global(&self, name: impl Into<String>, value: Value) {
+        self.base_environement.context.set_global(name.into(), value);
+    }
+
+    // Reads back a global, including ones the program wrote through 'Host::set'.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.base_environement.context.get_global(name)
+    }
+
+    // Takes every event emitted through 'Emit::event' since the last call.
+    pub fn drain_events(&self) -> Vec<EmittedEvent> {
+        self.base_environement.context.drain_events()
+    }
+
+    // Limits the depth of nested calls of every execution. Calls beyond it fail.
+    pub fn set_stack_limit(&self, limit: Option<usize>) {
+        self.base_environement.context.set_stack_limit(limit);
+    }
+
+    // Limits the approximate bytes held by arrays, strings and structs. Executions holding more
+    // fail with an error.
+    pub fn set_memory_limit(&self, limit: Option<usize>) {
+        self.base_environement.context.set_memory_limit(limit);
+    }
+
+    // Limits the number of instructions of every execution, calls included.
+    pub fn set_fuel(&self, fuel: Option<u64>) {
+        self.base_environement.context.set_fuel(fuel);
+    }
+
+    // Collects the text written through the IO module instead of printing it to stdout.
+    pub fn capture_output(&self) {
+        self.base_environement.context.capture_output();
     }
 
-    pub fn execute(self) -> Result<Value, RuntimeError> {
-        let entrypoint = self.entrypoint.ok_or(RuntimeError {
-            message: "No specified entrypoint!".into()
-        })?;
+    // Takes the text captured since the last call.
+    pub fn take_output(&self) -> String {
+        self.base_environement.context.take_output()
+    }
+
+    // Serves 'IO::readLine' from the given lines instead of stdin. Once they are used up,
+    // it returns null.
+    pub fn provide_input(&self, lines: impl IntoIterator<Item = String>) {
+        self.base_environement.context.provide_input(lines.into_iter().collect());
+    }
+
+    // Calls the listener for each event as soon as it is emitted. Events are still queued
+    // for 'drain_events'.
+    pub fn on_event(&self, listener: impl Fn(&EmittedEvent) + 'static) {
+        self.base_environement.context.set_event_listener(Box::new(listener));
+    }
+}
+
+// An entrypoint ready to be executed repeatedly. Only the mutable state of the runtime, i.e.
+// the exit code and the entrypoint's scope, is reset between executions; globals set by the
+// host are kept.
+pub struct PreparedExecution<'a> {
+    procedure_id: ModuleAddress,
+    procedure: &'a dyn Procedure,
+    environment: Environment,
+    arguments: Value,
+    fuel: Option<u64>,
+}
+
+impl PreparedExecution<'_> {
+    // Declares variables in the entrypoint's scope before it runs.
+    pub fn with_variables(mut self, variables: HashMap<String, Value>) -> Self {
+        self.environment.insert_members(variables.into_iter().map(|(identifier, value)| (Symbol::from(identifier), value)));
+        self
+    }
+
+    // Limits the number of instructions of each execution, see `RuntimeObject::execute_with_fuel`.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    // Panics while running are reported as internal errors instead of unwinding into the host.
+    pub fn execute(&self) -> Result<Value, RuntimeError> {
+        let context = &self.environment.context;
+        let stack_depth = context.stats.stack_depth();
+        let budget = context.current_budget();
+
+        internal::guard("running", || self.run()).unwrap_or_else(|err| {
+            // The panic skipped the cleanup of every call it unwound through.
+            context.stats.unwind_calls(stack_depth);
+            context.exit_budget(budget);
+
+            Err(err.into())
+        })
+    }
+
+    fn run(&self) -> Result<Value, RuntimeError> {
+        let context = self.environment.context.clone();
 
-        let main_expression = ProcedureCallExpression::new(
-            entrypoint,
-            Vec::new()
-        );
+        context.reset_exit_code();
+
+        self.environment.initialize_module_variables()?;
+
+        let fuel = context.fuel_budget(self.fuel).map(|budget| context.enter_budget(&budget));
+
+        context.stats.enter_call();
+        context.record(|| TraceEvent::Call { procedure: self.procedure_id.clone() });
+        context.profile(|profile| profile.enter(&self.procedure_id));
+        let result = self.procedure.call(self.environment.clone(), vec![self.arguments.clone()]);
+        context.profile(|profile| profile.exit());
+        context.record(|| TraceEvent::Return);
+        context.stats.exit_call();
+
+        if let Some(previous) = fuel {
+            context.exit_budget(previous);
+        }
 
-        main_expression.eval(&self.base_environement)
+        result.map_err(|err| err.unwound_from(&self.procedure_id))
     }
 }
 
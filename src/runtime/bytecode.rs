@@ -0,0 +1,159 @@
+use crate::{compiler::CompilerError, runtime::{
+    Environment, Expression, ModuleAddress, RuntimeError, Value,
+    expressions::{arithmetic::{self, Comparison, IntegerFeedback}, boolean},
+    scope::ScopeAddress,
+}};
+
+// Expressions of compiled procedures are flattened into operations on a stack of values, so
+// evaluating them does not walk the expression tree. Operands are pushed before the operation
+// that takes them, in the order they are evaluated in.
+#[derive(Debug)]
+pub(crate) enum Op {
+    Push(Value),
+    // Reads a variable. The values of the dynamic indices of its address are on the stack.
+    Load { address: ScopeAddress, indices: usize },
+    // The origin names the operation in errors, see 'arithmetic::add'. Additions and comparisons
+    // collect Integer feedback of their own, as their expressions do when walked.
+    Add { origin: String, feedback: IntegerFeedback },
+    Subtract { origin: String },
+    Multiply { origin: String },
+    Divide { origin: String },
+    Power { origin: String },
+    Modulo { origin: String },
+    EuclideanModulo { origin: String },
+    GreaterThan { feedback: IntegerFeedback },
+    Equal,
+    And,
+    Or,
+    Not,
+    Truthiness,
+    // Jumps to the exit with false on the stack if the comparison does not hold. Otherwise
+    // keeps its right operand as the left one of the next comparison in the chain.
+    Compare { comparison: Comparison, exit: usize },
+    Pop,
+    Array(usize),
+    // Looks up a procedure before its arguments are evaluated, as the expression would.
+    Resolve(ModuleAddress),
+    Call { arguments: usize, origin: String },
+    // Expressions without operations of their own are evaluated as a whole.
+    Evaluate(Box<dyn Expression>),
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    ops: Vec<Op>,
+}
+
+impl Chunk {
+    pub(crate) fn compile(expression: &dyn Expression) -> Result<Chunk, CompilerError> {
+        let mut chunk = Chunk::default();
+        expression.compile(&mut chunk)?;
+
+        Ok(chunk)
+    }
+
+    pub(crate) fn push(&mut self, op: Op) {
+        self.ops.push(op);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub(crate) fn patch_exit(&mut self, at: usize, target: usize) {
+        if let Some(Op::Compare { exit, .. }) = self.ops.get_mut(at) {
+            *exit = target;
+        }
+    }
+
+    // Runs on top of the given stack and returns the value left by the last operation. The
+    // stack is left as it was found, on errors as well.
+    pub(crate) fn run(&self, environment: &Environment, stack: &mut Vec<Value>) -> Result<Value, RuntimeError> {
+        let base = stack.len();
+
+        let result = self.execute(environment, stack);
+        stack.truncate(base);
+
+        result
+    }
+
+    fn execute(&self, environment: &Environment, stack: &mut Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut callees = Vec::new();
+        let mut pc = 0;
+
+        while let Some(op) = self.ops.get(pc) {
+            pc += 1;
+
+            let value = match op {
+                Op::Push(value) => value.clone(),
+                Op::Load { address, indices: 0 } => environment.query_static_variable(address)?,
+                Op::Load { address, indices } => {
+                    let indices = stack.split_off(stack.len() - indices);
+
+                    environment.query_variable(address.with_indices(indices)?)?
+                }
+                Op::Add { origin, feedback } => binary(stack, |lhs, rhs| feedback.add(lhs, rhs, || origin.clone()))?,
+                Op::Subtract { origin } => binary(stack, |lhs, rhs| arithmetic::subtract(lhs, rhs, || origin.clone()))?,
+                Op::Multiply { origin } => binary(stack, |lhs, rhs| arithmetic::multiply(lhs, rhs, &environment.context, || origin.clone()))?,
+                Op::Divide { origin } => binary(stack, |lhs, rhs| arithmetic::divide(lhs, rhs, || origin.clone()))?,
+                Op::Power { origin } => binary(stack, |base, exponent| arithmetic::power(base, exponent, || origin.clone()))?,
+                Op::Modulo { origin } => binary(stack, |lhs, rhs| arithmetic::modulo(lhs, rhs, || origin.clone()))?,
+                Op::EuclideanModulo { origin } => binary(stack, |lhs, rhs| arithmetic::euclidean_modulo(lhs, rhs, || origin.clone()))?,
+                Op::GreaterThan { feedback } => binary(stack, |lhs, rhs| feedback.greater_than(&lhs, &rhs))?,
+                Op::Equal => binary(stack, |lhs, rhs| Ok(Value::Bool(lhs == rhs)))?,
+                Op::And => binary(stack, boolean::and)?,
+                Op::Or => binary(stack, boolean::or)?,
+                Op::Not => boolean::not(pop(stack))?,
+                Op::Truthiness => Value::Bool(pop(stack).is_truthy()),
+                Op::Compare { comparison, exit } => {
+                    let rhs = pop(stack);
+                    let lhs = pop(stack);
+
+                    if comparison.holds(&lhs, &rhs)? {
+                        rhs
+                    } else {
+                        pc = *exit;
+                        Value::Bool(false)
+                    }
+                }
+                Op::Pop => {
+                    pop(stack);
+                    continue;
+                }
+                Op::Array(length) => Value::Array(stack.split_off(stack.len() - length)),
+                Op::Resolve(procedure_id) => {
+                    callees.push(environment.lookup_procedure(procedure_id)?);
+                    continue;
+                }
+                Op::Call { arguments, origin } => {
+                    let arguments = stack.split_off(stack.len() - arguments);
+                    let (procedure_id, procedure) = callees.pop().expect("call without a resolved procedure");
+
+                    let result = environment.invoke(&procedure_id, procedure.as_ref(), arguments);
+
+                    if procedure.includes_call_site() {
+                        result.map_err(|err| err.in_call(origin.clone()))?
+                    } else {
+                        result?
+                    }
+                }
+                Op::Evaluate(expression) => expression.eval(environment)?,
+            };
+
+            stack.push(value);
+        }
+
+        Ok(stack.pop().unwrap_or(Value::Null))
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("operation without an operand")
+}
+
+fn binary(stack: &mut Vec<Value>, operation: impl FnOnce(Value, Value) -> Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+    let rhs = pop(stack);
+    let lhs = pop(stack);
+
+    operation(lhs, rhs)
+}
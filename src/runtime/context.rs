@@ -0,0 +1,318 @@
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet, VecDeque}, io::{self, BufRead, Write}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::runtime::{ModuleAddress, RuntimeError, RuntimeErrorKind, Value, events::{EmittedEvent, EventListener, EventQueue}, procedures::CallBudget, profile::Profile, stats::StatsRecorder, trace::{Trace, TraceEvent}};
+
+// Maximum depth of nested calls unless the host sets a stack limit. Calls get more stack as
+// they need it, so this only stops runaway recursion.
+pub(crate) const DEFAULT_STACK_LIMIT: usize = 10_000;
+
+// State shared by all environments opened from the same runtime object.
+#[derive(Debug, Default)]
+pub(crate) struct RuntimeContext {
+    pub(crate) stats: StatsRecorder,
+    trace: RefCell<Option<Trace>>,
+    profile: RefCell<Option<Profile>>,
+    // The value of the last throw statement, alongside the message of the error carrying it.
+    thrown: RefCell<Option<(String, Value)>>,
+    // Values shared with the host, readable and writable through the Host module.
+    globals: RefCell<HashMap<String, Value>>,
+    events: RefCell<EventQueue>,
+    // Set by 'Sys::exit'. The error carrying it cannot be caught.
+    exit_code: Cell<Option<i32>>,
+    // Limits of the innermost call with a budget, including the calls it makes.
+    budget: Cell<BudgetLimits>,
+    // Limits set by the host for every execution.
+    stack_limit: Cell<Option<usize>>,
+    fuel: Cell<Option<u64>>,
+    memory_limit: Cell<Option<usize>>,
+    // Builtin modules removed by the host's sandbox.
+    denied_capabilities: RefCell<HashSet<String>>,
+    // Text written through the IO module while the host captures it instead of stdout.
+    output: RefCell<Option<String>>,
+    // Lines read through the IO module while the host provides them instead of stdin.
+    input: RefCell<Option<VecDeque<String>>>,
+    // State of the generator behind the Random module, seeded from the clock on first use.
+    random: Cell<Option<u64>>,
+}
+
+// The instruction and allocation counts at which a budget runs out, each alongside the budget
+// itself for error messages.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct BudgetLimits {
+    instructions: Option<(u64, u64)>,
+    allocations: Option<(u64, u64)>,
+}
+
+fn tighter(current: Option<(u64, u64)>, count: u64, budget: Option<u64>) -> Option<(u64, u64)> {
+    let limit = budget.map(|budget| (count.saturating_add(budget), budget));
+
+    match (current, limit) {
+        (Some(current), Some(limit)) if current.0 <= limit.0 => Some(current),
+        (current, None) => current,
+        (_, limit) => limit,
+    }
+}
+
+impl RuntimeContext {
+    pub(crate) fn start_trace(&self) {
+        self.trace.replace(Some(Trace::default()));
+    }
+
+    pub(crate) fn take_trace(&self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    pub(crate) fn record(&self, event: impl FnOnce() -> TraceEvent) {
+        // A full trace takes no more events, so their values aren't copied either.
+        if let Some(trace) = self.trace.borrow_mut().as_mut().filter(|trace| !trace.is_truncated()) {
+            trace.push(event());
+        }
+    }
+
+    pub(crate) fn start_profile(&self) {
+        self.profile.replace(Some(Profile::default()));
+    }
+
+    pub(crate) fn take_profile(&self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    pub(crate) fn is_profiling(&self) -> bool {
+        self.profile.borrow().is_some()
+    }
+
+    pub(crate) fn profile(&self, update: impl FnOnce(&mut Profile)) {
+        if let Some(profile) = self.profile.borrow_mut().as_mut() {
+            update(profile);
+        }
+    }
+
+    pub(crate) fn set_global(&self, name: String, value: Value) {
+        self.globals.borrow_mut().insert(name, value);
+    }
+
+    pub(crate) fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get(name).cloned()
+    }
+
+    pub(crate) fn emit(&self, event: EmittedEvent) {
+        self.events.borrow_mut().push(event);
+    }
+
+    pub(crate) fn drain_events(&self) -> Vec<EmittedEvent> {
+        self.events.borrow_mut().drain()
+    }
+
+    pub(crate) fn set_event_listener(&self, listener: EventListener) {
+        self.events.borrow_mut().set_listener(listener);
+    }
+
+    pub(crate) fn exit(&self, code: i32) -> RuntimeError {
+        self.exit_code.set(Some(code));
+
+        RuntimeErrorKind::Exit { code }.into()
+    }
+
+    pub(crate) fn exit_code(&self) -> Option<i32> {
+        self.exit_code.get()
+    }
+
+    pub(crate) fn reset_exit_code(&self) {
+        self.exit_code.set(None);
+    }
+
+    pub(crate) fn write_output(&self, text: &str) -> Result<(), RuntimeError> {
+        if let Some(output) = self.output.borrow_mut().as_mut() {
+            output.push_str(text);
+            return Ok(());
+        }
+
+        let mut stdout = io::stdout().lock();
+
+        stdout.write_all(text.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|err| RuntimeError::from(RuntimeErrorKind::Io { subject: "Output".into(), action: "written", reason: err.to_string() }))
+    }
+
+    pub(crate) fn read_line(&self) -> Result<Option<String>, RuntimeError> {
+        if let Some(input) = self.input.borrow_mut().as_mut() {
+            return Ok(input.pop_front());
+        }
+
+        let mut line = String::new();
+
+        let read = io::stdin().lock().read_line(&mut line).map_err(|err| RuntimeError::from(RuntimeErrorKind::Io { subject: "Input".into(), action: "read", reason: err.to_string() }))?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        let length = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(length);
+
+        Ok(Some(line))
+    }
+
+    pub(crate) fn capture_output(&self) {
+        self.output.replace(Some(String::new()));
+    }
+
+    pub(crate) fn take_output(&self) -> String {
+        self.output.borrow_mut().as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub(crate) fn provide_input(&self, lines: VecDeque<String>) {
+        self.input.replace(Some(lines));
+    }
+
+    pub(crate) fn seed_random(&self, seed: u64) {
+        self.random.set(Some(seed));
+    }
+
+    // The next number of a splitmix64 generator. Not suitable for cryptography.
+    pub(crate) fn next_random(&self) -> u64 {
+        let state = self.random.get().unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default()
+                ^ (self as *const Self as u64)
+        });
+        let state = state.wrapping_add(0x9e3779b97f4a7c15);
+        self.random.set(Some(state));
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn deny_capability(&self, module_id: String) {
+        self.denied_capabilities.borrow_mut().insert(module_id);
+    }
+
+    pub(crate) fn is_denied(&self, module_id: &str) -> bool {
+        self.denied_capabilities.borrow().contains(module_id)
+    }
+
+    // Starts a call with a budget. Returns the limits to restore once the call has finished.
+    pub(crate) fn enter_budget(&self, budget: &CallBudget) -> BudgetLimits {
+        let previous = self.budget.get();
+
+        self.budget.set(BudgetLimits {
+            instructions: tighter(previous.instructions, self.stats.instruction_count(), budget.instructions),
+            allocations: tighter(previous.allocations, self.stats.allocation_count(), budget.allocations),
+        });
+
+        previous
+    }
+
+    pub(crate) fn current_budget(&self) -> BudgetLimits {
+        self.budget.get()
+    }
+
+    pub(crate) fn exit_budget(&self, previous: BudgetLimits) {
+        self.budget.set(previous);
+    }
+
+    pub(crate) fn check_budget(&self) -> Result<(), RuntimeError> {
+        let limits = self.budget.get();
+
+        if let Some((limit, budget)) = limits.instructions {
+            if self.stats.instruction_count() > limit {
+                return Err(RuntimeErrorKind::InstructionBudgetExceeded { budget }.into());
+            }
+        }
+
+        if let Some((limit, budget)) = limits.allocations {
+            if self.stats.allocation_count() > limit {
+                return Err(RuntimeErrorKind::AllocationBudgetExceeded { budget }.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_stack_limit(&self, limit: Option<usize>) {
+        self.stack_limit.set(limit);
+    }
+
+    // Fails before calling the given procedure if that would nest calls too deep.
+    pub(crate) fn check_stack_limit(&self, procedure_id: &ModuleAddress) -> Result<(), RuntimeError> {
+        let limit = self.stack_limit.get().unwrap_or(DEFAULT_STACK_LIMIT);
+
+        if self.stats.stack_depth() >= limit {
+            return Err(RuntimeErrorKind::StackOverflow { limit, procedure: procedure_id.clone() }.into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_memory_limit(&self, limit: Option<usize>) {
+        self.memory_limit.set(limit);
+    }
+
+    // Fails once the values held by the program take up more than the memory limit.
+    pub(crate) fn check_memory_limit(&self) -> Result<(), RuntimeError> {
+        let Some(limit) = self.memory_limit.get() else {
+            return Ok(());
+        };
+
+        let heap_bytes = self.stats.heap_bytes();
+
+        if heap_bytes > limit {
+            return Err(RuntimeErrorKind::MemoryLimitExceeded { limit, used: heap_bytes }.into());
+        }
+
+        Ok(())
+    }
+
+    // Fails if allocating the given bytes would take the program past the memory limit. Called
+    // before allocating values whose size follows from the program's data, as the limit is
+    // otherwise only checked once they are stored.
+    pub(crate) fn reserve_memory(&self, bytes: usize) -> Result<(), RuntimeError> {
+        let Some(limit) = self.memory_limit.get() else {
+            return Ok(());
+        };
+
+        let used = self.stats.heap_bytes().saturating_add(bytes);
+
+        if used > limit {
+            return Err(RuntimeErrorKind::MemoryLimitExceeded { limit, used }.into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_fuel(&self, fuel: Option<u64>) {
+        self.fuel.set(fuel);
+    }
+
+    // The budget of a whole execution: the tighter of the fuel set by the host and the given
+    // fuel of this execution, if either is set.
+    pub(crate) fn fuel_budget(&self, fuel: Option<u64>) -> Option<CallBudget> {
+        let fuel = match (self.fuel.get(), fuel) {
+            (Some(host), Some(execution)) => Some(host.min(execution)),
+            (host, execution) => host.or(execution),
+        };
+
+        fuel.map(|fuel| CallBudget { instructions: Some(fuel), allocations: None })
+    }
+
+    pub(crate) fn throw(&self, value: Value) -> RuntimeError {
+        let error = RuntimeError::from(RuntimeErrorKind::Uncaught { value: format!("{:?}", value) });
+
+        self.thrown.replace(Some((error.to_string(), value)));
+
+        error
+    }
+
+    // The value a catch clause binds for the given error: the thrown value if the error
+    // stems from a throw statement and the error message otherwise.
+    pub(crate) fn catch(&self, error: RuntimeError) -> Value {
+        match self.thrown.take() {
+            Some((message, value)) if message == error.to_string() => value,
+            _ => Value::String(error.to_string()),
+        }
+    }
+}
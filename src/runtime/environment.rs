@@ -1,38 +1,83 @@
 use super::scope::{ScopeAddress, Scope};
 
+use super::context::RuntimeContext;
+
+use super::stats::Footprint;
+
+use super::trace::TraceEvent;
+
+use super::scope::ScopeAddressant;
+
+use super::symbol::Symbol;
+
 use super::Value;
 
-use super::RuntimeError;
+use super::{RuntimeError, RuntimeErrorKind};
 
-use crate::runtime::Struct;
+use crate::compiler::{CompilerError, CompilerErrorKind};
+use crate::runtime::{Struct, struct_holds};
 use crate::runtime::module::Module;
 use crate::runtime::procedures::Procedure;
-use crate::runtime::procedures::builtin::{arrays, numbers, strings};
+use crate::runtime::procedures::builtin::{arrays, assert, bools, capabilities, convert, crypto, emit, encoding, env, files, host, io, math, numbers, process, random, reflect, runtime, strings, sys, values};
 
 use super::ModuleAddress;
 
 use std::rc::Rc;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+const BUILTIN_MODULES: &[(&str, fn() -> Module)] = &[
+    ("Arrays", arrays::get_module),
+    ("Strings", strings::get_module),
+    ("Numbers", numbers::get_module),
+    ("Math", math::get_module),
+    ("Runtime", runtime::get_module),
+    ("Host", host::get_module),
+    ("Emit", emit::get_module),
+    ("Assert", assert::get_module),
+    ("Sys", sys::get_module),
+    ("Bools", bools::get_module),
+    ("Reflect", reflect::get_module),
+    ("Capabilities", capabilities::get_module),
+    ("IO", io::get_module),
+    ("Files", files::get_module),
+    ("Env", env::get_module),
+    ("Process", process::get_module),
+    ("Random", random::get_module),
+    ("Convert", convert::get_module),
+    ("Encoding", encoding::get_module),
+    ("Crypto", crypto::get_module),
+    ("Values", values::get_module),
+];
+
+// A call that finds less stack than the red zone left continues on a new segment of the
+// given size.
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     //TODO: Remove public visibility
     pub contained_module_id: String,
-    pub loaded_modules: HashMap<String, Rc<Module>>,
+    // The module whose procedure opened this environment. Builtins check callbacks against it.
+    calling_module_id: String,
+    // Ordered by module id, so everything listing modules does so in the same order every run.
+    pub loaded_modules: BTreeMap<String, Rc<Module>>,
     pub scope: Scope,
+    pub(crate) context: Rc<RuntimeContext>,
 }
 
 impl Default for Environment {
     fn default() -> Self {
         Self {
             contained_module_id: Default::default(),
-            loaded_modules: HashMap::from_iter(vec![
-                ("Arrays".into(), Rc::new(arrays::get_module())),
-                ("Strings".into(), Rc::new(strings::get_module())),
-                ("Numbers".into(), Rc::new(numbers::get_module())),
-            ].into_iter()),
-            scope: Default::default()
+            calling_module_id: Default::default(),
+            loaded_modules: BUILTIN_MODULES
+                .iter()
+                .map(|(module_id, get_module)| (module_id.to_string(), Rc::new(get_module())))
+                .collect(),
+            scope: Default::default(),
+            context: Default::default(),
         }
     }
 }
@@ -41,63 +86,307 @@ impl Environment {
     pub fn new(contained_module_id: String) -> Self {
         Self {
             contained_module_id,
+            calling_module_id: Default::default(),
             loaded_modules: Default::default(),
             scope: Default::default(),
+            context: Default::default(),
+        }
+    }
+
+    pub fn is_builtin_module(module_id: &str) -> bool {
+        BUILTIN_MODULES.iter().any(|(builtin, _)| *builtin == module_id)
+    }
+
+    // Resolves an address as written in the contained module through that module's import aliases.
+    // Names the module does not import itself are looked up in the re-exports of its imports.
+    pub fn resolve_address(&self, address: &ModuleAddress) -> ModuleAddress {
+        self.resolve_address_in(&self.contained_module_id, address)
+    }
+
+    fn resolve_address_in(&self, module_id: &String, address: &ModuleAddress) -> ModuleAddress {
+        let Some(module) = self.loaded_modules.get(module_id) else {
+            return address.clone();
+        };
+
+        let module_id = module.resolve_alias(address.get_module_id());
+
+        let module_id = if module_id == address.get_module_id() && !self.loaded_modules.contains_key(module_id) {
+            self.resolve_reexport(module, module_id).unwrap_or_else(|| module_id.clone())
+        } else {
+            module_id.clone()
+        };
+
+        ModuleAddress::new(module_id, address.get_identifier().clone())
+    }
+
+    // Follows the chain of 'export import' declarations, nearest re-exports first.
+    fn resolve_reexport(&self, module: &Module, alias: &String) -> Option<String> {
+        let mut visited: Vec<&String> = Vec::new();
+        let mut queue: Vec<&String> = module.imported_modules().collect();
+
+        while !queue.is_empty() {
+            let mut next = Vec::new();
+
+            for module_id in queue {
+                if visited.contains(&module_id) {
+                    continue;
+                }
+                visited.push(module_id);
+
+                let Some(imported) = self.loaded_modules.get(module_id) else {
+                    continue;
+                };
+
+                if let Some(target) = imported.reexported_alias(alias) {
+                    return Some(target.clone());
+                }
+
+                next.extend(imported.reexported_modules());
+            }
+
+            queue = next;
         }
+
+        None
+    }
+
+    // Removes a builtin module for sandboxing. Calls into it fail with a dedicated error.
+    pub(crate) fn deny_capability(&mut self, module_id: &str) -> Result<(), CompilerError> {
+        if !Self::is_builtin_module(module_id) || module_id == "Capabilities" {
+            return Err(CompilerErrorKind::UndeniableCapability { module_id: module_id.to_string() }.into());
+        }
+
+        self.loaded_modules.remove(module_id);
+        self.context.deny_capability(module_id.to_string());
+
+        Ok(())
+    }
+
+    // Whether the builtin module is available to the program.
+    pub(crate) fn has_capability(&self, module_id: &str) -> bool {
+        Self::is_builtin_module(module_id) && self.loaded_modules.contains_key(module_id)
+    }
+
+    fn module_not_loaded(&self, module_id: &str) -> RuntimeError {
+        if self.context.is_denied(module_id) {
+            return RuntimeErrorKind::CapabilityDenied { module_id: module_id.to_string() }.into();
+        }
+
+        RuntimeErrorKind::ModuleNotLoaded { module_id: module_id.to_string() }.into()
+    }
+
+    pub(crate) fn link_modules(&mut self, source_modules: &HashMap<String, String>) -> Result<(), CompilerError> {
+        for (module_id, module) in self.loaded_modules.iter_mut() {
+            let module = Rc::get_mut(module).ok_or_else(|| CompilerError::from(CompilerErrorKind::SharedModule { module_id: module_id.clone(), action: "linked" }))?;
+
+            for target in module.aliases_mut() {
+                if let Some(resolved) = source_modules.get(target) {
+                    *target = resolved.clone();
+                }
+            }
+        }
+
+        Ok(())
     }
 
     pub fn get_procedure_by_address(&self, address: &ModuleAddress) -> Result<&Box<dyn Procedure>, RuntimeError> {
+        self.lookup_procedure(address).map(|(_, procedure)| procedure)
+    }
+
+    // Like `get_procedure_by_address`, but also returns the resolved address of the procedure.
+    pub(crate) fn lookup_procedure(&self, address: &ModuleAddress) -> Result<(ModuleAddress, &Box<dyn Procedure>), RuntimeError> {
+        let (address, module) = self.procedure_module(&self.contained_module_id, address)?;
+
+        let procedure = module.get_procedure(
+            address.get_identifier(),
+            address.get_module_id() == &self.contained_module_id,
+        )?;
+
+        Ok((address, procedure))
+    }
+
+    // Resolves a procedure address as written in the given module, returning the module defining it.
+    fn procedure_module(&self, module_id: &String, address: &ModuleAddress) -> Result<(ModuleAddress, &Rc<Module>), RuntimeError> {
+        let address = self.resolve_address_in(module_id, address);
+
         let module = self
             .loaded_modules
             .get(address.get_module_id())
-            .ok_or(RuntimeError {
-                message: format!(
-                    "Module \"{}\" not loaded in this environment!",
-                    address.get_module_id()
-                ),
-            })?;
-
-        module.get_procedure(
-            address.get_identifier(),
-            address.get_module_id() == &self.contained_module_id,
-        )
+            .ok_or_else(|| self.module_not_loaded(address.get_module_id()))?;
+
+        Ok((address, module))
+    }
+
+    // Calls a procedure a builtin procedure was given by name. The name is looked up as written in
+    // the module calling the builtin, so that module may pass its private procedures.
+    pub(crate) fn call_procedure(&self, address: &ModuleAddress, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let (procedure_id, module) = self.procedure_module(&self.calling_module_id, address)?;
+
+        let procedure = module.get_procedure(
+            procedure_id.get_identifier(),
+            procedure_id.get_module_id() == &self.calling_module_id,
+        )?;
+
+        self.invoke(&procedure_id, procedure.as_ref(), arguments)
+    }
+
+    // Runs a procedure in its own environment, keeping the stack depth, the trace and the profile
+    // up to date.
+    pub(crate) fn invoke(&self, procedure_id: &ModuleAddress, procedure: &dyn Procedure, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        self.context.check_stack_limit(procedure_id)?;
+
+        let environment = self.open_subenvironment(Scope::new(), procedure_id);
+
+        self.context.stats.enter_call();
+        self.context.record(|| TraceEvent::Call { procedure: procedure_id.clone() });
+        self.context.profile(|profile| profile.enter(procedure_id));
+        // Deep recursion continues on a fresh stack instead of overflowing the host's.
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || procedure.call(environment, arguments));
+        self.context.profile(|profile| profile.exit());
+        self.context.record(|| TraceEvent::Return);
+        self.context.stats.exit_call();
+
+        result.map_err(|err| err.unwound_from(procedure_id))
     }
 
     pub fn get_struct_by_address(&self, address: &ModuleAddress) -> Result<Struct, RuntimeError> {
+        let address = &self.resolve_address(address);
+
         let module = self
             .loaded_modules
             .get(address.get_module_id())
-            .ok_or(RuntimeError {
-                message: format!(
-                    "Module '{}' not loaded in this environment!",
-                    address.get_module_id()
-                ),
-            })?;
-
-        module.get_struct(
+            .ok_or_else(|| self.module_not_loaded(address.get_module_id()))?;
+
+        let mut instance = module.get_struct(
             address.get_identifier(),
             address.get_module_id() == &self.contained_module_id,
-        )
+        )?;
+
+        instance.track(self.context.clone());
+
+        Ok(instance)
+    }
+
+    fn lookup_module(&self, module_id: &str) -> Result<(String, &Rc<Module>), RuntimeError> {
+        let module_id = self.resolve_address(&ModuleAddress::new(module_id.to_string(), String::new())).module_id;
+
+        let module = self
+            .loaded_modules
+            .get(&module_id)
+            .ok_or_else(|| self.module_not_loaded(&module_id))?;
+
+        Ok((module_id, module))
+    }
+
+    // Reads a module level variable, written as 'Module::name' in the contained module.
+    pub(crate) fn query_module_variable(&self, module_id: &str, address: ScopeAddress) -> Result<Value, RuntimeError> {
+        let (module_id, module) = self.lookup_module(module_id)?;
+        let address = address.try_bake(self)?;
+
+        module.query_variable(address, module_id == self.contained_module_id, &self.contained_module_id)
+    }
+
+    pub(crate) fn set_module_variable(&self, module_id: &str, address: ScopeAddress, value: Value) -> Result<(), RuntimeError> {
+        let (module_id, module) = self.lookup_module(module_id)?;
+        let address = address.try_bake(self)?;
+
+        module.set_variable(address, module_id == self.contained_module_id, &self.contained_module_id, value)?;
+
+        Ok(())
+    }
+
+    // Runs the initializers of the module level variables before each run, module by module.
+    pub(crate) fn initialize_module_variables(&self) -> Result<(), RuntimeError> {
+        for (module_id, module) in self.loaded_modules.iter().filter(|(_, module)| module.has_variables()) {
+            let environment = self.open_subenvironment(Scope::new(), &ModuleAddress::new(module_id.clone(), String::new()));
+
+            module.initialize_variables(&environment)?;
+        }
+
+        Ok(())
     }
 
     pub fn open_subenvironment(&self, new_scope: Scope, module_address: &ModuleAddress) -> Self {
         Self {
             contained_module_id: module_address.module_id.clone(),
+            calling_module_id: self.contained_module_id.clone(),
             loaded_modules: self.loaded_modules.clone(),
             scope: new_scope,
+            context: self.context.clone(),
         }
     }
 
-    pub fn insert_members(&mut self, members: HashMap<String, Value>) {
+    pub fn insert_members(&mut self, members: impl IntoIterator<Item = (Symbol, Value)>) {
+        let members: BTreeMap<Symbol, Value> = members.into_iter().collect();
+
+        for (identifier, value) in members.iter() {
+            self.context.stats.retain(Footprint::of(value));
+
+            self.context.record(|| TraceEvent::Declare { identifier: identifier.to_string() });
+            self.context.record(|| TraceEvent::Write { identifier: identifier.to_string(), value: value.clone() });
+        }
+
         self.scope.insert_members(members);
     }
 
+    pub fn declare_variable(&mut self, identifier: Symbol) -> Result<(), RuntimeError> {
+        self.context.record(|| TraceEvent::Declare { identifier: identifier.to_string() });
+
+        self.scope.push(identifier)
+    }
+
+    pub fn grow_stack(&mut self) {
+        self.context.record(|| TraceEvent::EnterBlock);
+
+        self.scope.grow_stack();
+    }
+
+    pub fn pop_variable(&mut self, identifier: Symbol) -> Result<(), RuntimeError> {
+        let value = self.scope.pop(identifier)?;
+
+        self.context.stats.release(Footprint::of(&value));
+
+        Ok(())
+    }
+
+    pub fn shrink_stack(&mut self) {
+        self.context.record(|| TraceEvent::ExitBlock);
+
+        for value in self.scope.shrink_stack() {
+            self.context.stats.release(Footprint::of(&value));
+        }
+    }
+
+    pub(crate) fn stack_depth(&self) -> usize {
+        self.scope.stack_depth()
+    }
+
+    // Shrinks the stack until it is back at the given depth, e.g. when an error escapes a try block.
+    pub(crate) fn unwind_stack(&mut self, depth: usize) {
+        while self.scope.stack_depth() > depth {
+            self.shrink_stack();
+        }
+    }
+
+    pub(crate) fn release_scope(&mut self) {
+        for value in self.scope.values() {
+            self.context.stats.release(Footprint::of(value));
+        }
+
+        self.scope = Scope::new();
+    }
+
     pub fn query_variable(&self, address: ScopeAddress) -> Result<Value, RuntimeError> {
         let address = address.try_bake(self)?;
 
         self.scope.query_variable(address, &self.contained_module_id)
     }
 
+    // Reads a variable by an address without dynamic indices, which needs no copy of it.
+    pub(crate) fn query_static_variable(&self, address: &ScopeAddress) -> Result<Value, RuntimeError> {
+        self.scope.query_variable(address.addressants().iter().cloned(), &self.contained_module_id)
+    }
+
     pub fn set_variable(
         &mut self,
         address: ScopeAddress,
@@ -105,7 +394,66 @@ impl Environment {
     ) -> Result<(), RuntimeError> {
         let address = address.try_bake(self)?;
 
-        self.scope.set_variable(address, &self.contained_module_id, new_value)
+        let footprint = Footprint::of(&new_value);
+
+        let root = match address.first() {
+            Some(ScopeAddressant::Identifier(ident)) => Some(*ident),
+            _ => None,
+        };
+
+        let previous = self.scope.set_variable(address, &self.contained_module_id, new_value)?;
+
+        self.context.stats.retain(footprint);
+        self.context.stats.release(Footprint::of(&previous));
+
+        if let Some(root) = root {
+            self.context.record(|| TraceEvent::Write {
+                value: self.scope.peek(root).cloned().unwrap_or(Value::Null),
+                identifier: root.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Fails if storing the struct of the source variable in a field at the target would store it
+    // inside itself. Checked before the struct is moved out of its variable, so a rejected store
+    // leaves it there. Addresses with dynamic indices are left to the store itself, as checking
+    // them would evaluate their indices twice.
+    pub(crate) fn check_store(&self, target: &ScopeAddress, source: &ScopeAddress) -> Result<(), RuntimeError> {
+        let [parent @ .., ScopeAddressant::Field(_)] = target.addressants() else {
+            return Ok(());
+        };
+
+        if target.dynamic_indices().next().is_some() || source.dynamic_indices().next().is_some() {
+            return Ok(());
+        }
+
+        let Ok(Value::StructRef(source)) = self.reference_variable(source.clone()) else {
+            return Ok(());
+        };
+
+        let Ok(parent) = ScopeAddress::try_from(parent.to_vec()) else {
+            return Ok(());
+        };
+
+        // The struct holding the field, whether the parent owns it or refers to it. Reading a
+        // reference leaves it in place.
+        let parent = match self.reference_variable(parent.clone()) {
+            Ok(Value::StructRef(parent)) => parent,
+            _ => match self.query_variable(parent) {
+                Ok(Value::StructRef(parent)) => parent,
+                _ => return Ok(()),
+            },
+        };
+
+        if let (Some(source), Some(parent)) = (source.upgrade(), parent.upgrade()) {
+            if struct_holds(&source, &parent) {
+                return Err(RuntimeErrorKind::StructInsideItself.into());
+            }
+        }
+
+        Ok(())
     }
 
     pub fn reference_variable(&self, address: ScopeAddress) -> Result<Value, RuntimeError> {
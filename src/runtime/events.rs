@@ -0,0 +1,45 @@
+use std::collections::VecDeque;
+
+use crate::runtime::Value;
+
+// A structured result the program handed to its host through 'Emit::event'.
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    pub name: String,
+    pub payload: Value,
+}
+
+pub(crate) type EventListener = Box<dyn Fn(&EmittedEvent)>;
+
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    events: VecDeque<EmittedEvent>,
+    listener: Option<EventListener>,
+}
+
+impl std::fmt::Debug for EventQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventQueue")
+            .field("events", &self.events)
+            .field("listener", &self.listener.is_some())
+            .finish()
+    }
+}
+
+impl EventQueue {
+    pub(crate) fn push(&mut self, event: EmittedEvent) {
+        if let Some(listener) = &self.listener {
+            listener(&event);
+        }
+
+        self.events.push_back(event);
+    }
+
+    pub(crate) fn drain(&mut self) -> Vec<EmittedEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub(crate) fn set_listener(&mut self, listener: EventListener) {
+        self.listener = Some(listener);
+    }
+}
@@ -1,8 +1,6 @@
-use std::{cell::RefCell, rc::Rc};
-
-use crate::runtime::{
-    Environment, Expression, ModuleAddress, RuntimeError, scope::{Scope, ScopeAddress}, Value,
-};
+use crate::{compiler::{CompilerError, optimizer::ConstantFolder, type_checker::TypeEnvironment}, lexer::token::PrimitiveTypeToken, runtime::{
+    Environment, Expression, ModuleAddress, RuntimeError, RuntimeErrorKind, bytecode::{Chunk, Op}, image::{self, ExpressionImage}, scope::ScopeAddress, stats::Footprint, StructOwner, symbol::Symbol, Value,
+}};
 
 #[derive(Debug)]
 pub struct ProcedureCallExpression {
@@ -12,8 +10,14 @@ pub struct ProcedureCallExpression {
 }
 
 impl Expression for ProcedureCallExpression {
+    fn summary(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|arg| arg.summary()).collect();
+
+        format!("{}({})", self.procedure_id, arguments.join(", "))
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
-        let procedure = environment.get_procedure_by_address(&self.procedure_id)?;
+        let (procedure_id, procedure) = environment.lookup_procedure(&self.procedure_id)?;
 
         let mut arguments = Vec::with_capacity(self.arguments.len());
         for eval_result in self
@@ -24,34 +28,188 @@ impl Expression for ProcedureCallExpression {
             arguments.push(eval_result?);
         }
 
-        let environment = environment.open_subenvironment(Scope::new(), &self.procedure_id);
+        let result = environment.invoke(&procedure_id, procedure.as_ref(), arguments);
+
+        if procedure.includes_call_site() {
+            return result.map_err(|err| err.in_call(self.summary()));
+        }
 
-        Ok(procedure.call(environment, arguments)?)
+        result
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        folder.fold_all(self.arguments.iter_mut());
+
+        folder.inline_call(&self.procedure_id, &self.arguments)
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        for argument in &self.arguments {
+            argument.static_type(types)?;
+        }
+
+        Ok(None)
     }
-}
 
-impl ProcedureCallExpression {
-    pub(crate) fn new(procedure_id: ModuleAddress, arguments: Vec<Box<dyn Expression>>) -> Self {
-        Self { procedure_id, arguments }
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::ProcedureCall(self.procedure_id.clone(), image::all(&self.arguments)?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        chunk.push(Op::Resolve(self.procedure_id.clone()));
+        for argument in &self.arguments {
+            argument.compile(chunk)?;
+        }
+        chunk.push(Op::Call { arguments: self.arguments.len(), origin: self.summary() });
+
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct StructConstructionExpression {
     pub struct_id: ModuleAddress,
-    pub field_overrides: Vec<(String, Box<dyn Expression>)>
+    pub field_overrides: Vec<(Symbol, Box<dyn Expression>)>,
+    // Instance the remaining fields are copied from, as in 'Mod::Point { x: 3, ..existing }'.
+    pub base: Option<Box<dyn Expression>>,
 }
 
 impl Expression for StructConstructionExpression {
+    fn summary(&self) -> String {
+        let fields: Vec<String> = self.field_overrides
+            .iter()
+            .map(|(field, expr)| format!("{}: {}", field, expr.summary()))
+            .chain(self.base.iter().map(|base| format!("..{}", base.summary())))
+            .collect();
+
+        format!("{} {{ {} }}", self.struct_id, fields.join(", "))
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
         let mut instance = environment.get_struct_by_address(&self.struct_id)?;
 
+        if let Some(base) = &self.base {
+            let base = match base.eval(environment)? {
+                Value::Struct(base) => base.object()?,
+                Value::StructRef(base) => base.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?,
+                other => return Err(RuntimeErrorKind::InvalidSpread { found: other.get_type_id(), target: self.struct_id.to_string() }.into()),
+            };
+
+            let base = base.borrow();
+
+            if base.get_struct_id() != instance.get_struct_id() {
+                return Err(RuntimeErrorKind::InvalidSpread { found: format!("struct '{}'", base.get_struct_id()), target: instance.get_struct_id().to_string() }.into());
+            }
+
+            for (field, value) in base.get_members().iter() {
+                if self.field_overrides.iter().any(|(overridden, _)| *overridden == field) {
+                    continue;
+                }
+
+                let value = value.clone();
+                let footprint = Footprint::of(&value);
+
+                let previous = instance.get_members_mut().set_member(&field, value)?;
+
+                environment.context.stats.retain(footprint);
+                environment.context.stats.release(Footprint::of(&previous));
+            }
+        }
+
         for (field, expr) in &self.field_overrides {
             let value = expr.eval(environment)?;
-            instance.get_members_mut().set_member(field, value)?;
+            let footprint = Footprint::of(&value);
+
+            let previous = instance.get_members_mut().set_member(field, value)?;
+
+            environment.context.stats.retain(footprint);
+            environment.context.stats.release(Footprint::of(&previous));
+        }
+
+        Ok(Value::Struct(StructOwner::new(instance)))
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        folder.fold_all(self.field_overrides.iter_mut().map(|(_, expression)| expression));
+
+        if let Some(base) = &mut self.base {
+            folder.fold(base);
+        }
+
+        None
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        for (_, expr) in &self.field_overrides {
+            expr.static_type(types)?;
+        }
+
+        if let Some(base) = &self.base {
+            base.static_type(types)?;
         }
 
-        Ok(Value::Struct(Rc::new(RefCell::new(Some(instance)))))
+        Ok(None)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        let field_overrides = self.field_overrides
+            .iter()
+            .map(|(field, expr)| Ok((field.to_string(), expr.image()?)))
+            .collect::<Result<_, CompilerError>>()?;
+        let base = self.base.as_deref().map(image::boxed).transpose()?;
+
+        Ok(ExpressionImage::StructConstruction(self.struct_id.clone(), field_overrides, base))
+    }
+}
+
+#[derive(Debug)]
+pub struct ArrayLiteralExpression {
+    pub elements: Vec<Box<dyn Expression>>,
+}
+
+impl Expression for ArrayLiteralExpression {
+    fn summary(&self) -> String {
+        let elements: Vec<String> = self.elements.iter().map(|element| element.summary()).collect();
+
+        format!("[{}]", elements.join(", "))
+    }
+
+    fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
+        let mut elements = Vec::with_capacity(self.elements.len());
+        for element in &self.elements {
+            elements.push(element.eval(environment)?);
+        }
+
+        Ok(Value::Array(elements))
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all(self.elements.iter_mut()) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        for element in &self.elements {
+            element.static_type(types)?;
+        }
+
+        Ok(Some(PrimitiveTypeToken::Array))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::ArrayLiteral(image::all(&self.elements)?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        for element in &self.elements {
+            element.compile(chunk)?;
+        }
+        chunk.push(Op::Array(self.elements.len()));
+
+        Ok(())
     }
 }
 
@@ -62,9 +220,42 @@ pub struct VariableExpression {
 }
 
 impl Expression for VariableExpression {
+    fn summary(&self) -> String {
+        self.variable_address.summary()
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
         environment.query_variable(self.variable_address.clone())
     }
+
+    fn as_variable(&self) -> Option<&ScopeAddress> {
+        Some(&self.variable_address)
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        self.variable_address.fold_constants(folder);
+
+        None
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.variable_address.static_type(types)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Variable(image::address_image(&self.variable_address)?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        let mut indices = 0;
+        for index in self.variable_address.dynamic_indices() {
+            index.compile(chunk)?;
+            indices += 1;
+        }
+        chunk.push(Op::Load { address: self.variable_address.clone(), indices });
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -73,9 +264,29 @@ pub struct ReferenceExpression {
 }
 
 impl Expression for ReferenceExpression {
+    fn summary(&self) -> String {
+        format!("ref {}", self.variable_address.summary())
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
         environment.reference_variable(self.variable_address.clone())
     }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        self.variable_address.fold_constants(folder);
+
+        None
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.variable_address.static_type(types)?;
+
+        Ok(None)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Reference(image::address_image(&self.variable_address)?))
+    }
 }
 
 #[derive(Debug)]
@@ -84,9 +295,27 @@ pub struct CloneExpression {
 }
 
 impl Expression for CloneExpression {
+    fn summary(&self) -> String {
+        format!("clone {}", self.variable_address.summary())
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
         environment.clone_variable(self.variable_address.clone())
     }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        self.variable_address.fold_constants(folder);
+
+        None
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.variable_address.static_type(types)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Clone(image::address_image(&self.variable_address)?))
+    }
 }
 
 #[derive(Debug)]
@@ -102,6 +331,10 @@ impl EqualityExpression {
 }
 
 impl Expression for EqualityExpression {
+    fn summary(&self) -> String {
+        format!("({} == {})", self.lhs.summary(), self.rhs.summary())
+    }
+
     fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
         use super::Value::*;
 
@@ -110,6 +343,144 @@ impl Expression for EqualityExpression {
 
         Ok(Bool(lhs == rhs))
     }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.lhs.static_type(types)?;
+        self.rhs.static_type(types)?;
+
+        Ok(Some(PrimitiveTypeToken::Boolean))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Equality(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Equal);
+
+        Ok(())
+    }
+}
+
+// Reads a module level variable, e.g. 'Main::count' or 'Main::cache[0]'.
+#[derive(Debug)]
+pub struct ModuleVariableExpression {
+    pub module_id: String,
+    pub variable_address: ScopeAddress,
+}
+
+impl Expression for ModuleVariableExpression {
+    fn summary(&self) -> String {
+        format!("{}::{}", self.module_id, self.variable_address.summary())
+    }
+
+    fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
+        environment.query_module_variable(&self.module_id, self.variable_address.clone())
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        self.variable_address.fold_constants(folder);
+
+        None
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::ModuleVariable(self.module_id.clone(), image::address_image(&self.variable_address)?))
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleVariableAssignmentExpression {
+    pub module_id: String,
+    pub variable_address: ScopeAddress,
+    pub expression: Box<dyn Expression>,
+}
+
+impl Expression for ModuleVariableAssignmentExpression {
+    fn summary(&self) -> String {
+        format!("{}::{} = {}", self.module_id, self.variable_address.summary(), self.expression.summary())
+    }
+
+    fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
+        let value = self.expression.eval(environment)?;
+
+        environment.set_module_variable(&self.module_id, self.variable_address.clone(), value)?;
+
+        Ok(Value::Null)
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        self.variable_address.fold_constants(folder);
+        folder.fold(&mut self.expression);
+
+        None
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.expression.static_type(types)?;
+
+        Ok(None)
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::ModuleVariableAssignment(
+            self.module_id.clone(),
+            image::address_image(&self.variable_address)?,
+            image::boxed(self.expression.as_ref())?,
+        ))
+    }
+}
+
+// Whether the index of a foreach loop still points into the collection it iterates over.
+#[derive(Debug)]
+pub struct HasNextElementExpression {
+    pub collection: Symbol,
+    pub index: Symbol,
+}
+
+impl Expression for HasNextElementExpression {
+    fn summary(&self) -> String {
+        format!("({} < length {})", self.index, self.collection)
+    }
+
+    fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
+        let length = match environment.scope.peek(self.collection) {
+            Some(Value::Array(array)) => array.len(),
+            Some(Value::String(string)) => string.chars().count(),
+            other => {
+                return Err(RuntimeErrorKind::NotIterable { found: other.map(|value| value.get_type_id()).unwrap_or("Null".into()) }.into());
+            }
+        };
+
+        match environment.scope.peek(self.index) {
+            Some(Value::Integer(index)) => Ok(Value::Bool((*index as usize) < length)),
+            _ => Err(RuntimeErrorKind::UnresolvedAddress.into()),
+        }
+    }
+
+    fn static_type(&self, _types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        Ok(Some(PrimitiveTypeToken::Boolean))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::HasNextElement(self.collection.to_string(), self.index.to_string()))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        chunk.push(Op::Evaluate(Box::new(HasNextElementExpression { collection: self.collection, index: self.index })));
+
+        Ok(())
+    }
 }
 
 pub mod arithmetic;
@@ -1,39 +1,138 @@
-use crate::runtime::{expressions::Expression, Environment, RuntimeError};
+use std::{cell::Cell, mem};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::{CompilerError, optimizer::ConstantFolder, type_checker::{TypeEnvironment, infer_binary}}, lexer::token::PrimitiveTypeToken, runtime::{bytecode::{Chunk, Op}, context::RuntimeContext, expressions::Expression, Environment, RuntimeError, RuntimeErrorKind, Value, image::{self, ExpressionImage}}};
+
+// Evaluations with Integer operands in a row after which a node takes its Integer-only path.
+const SPECIALIZATION_THRESHOLD: u8 = 8;
+
+// Type feedback of a binary node. Once both operands were Integers often enough, the node
+// specializes and skips the generic match. Any other operand pair despecializes it again.
+#[derive(Debug, Default)]
+pub(crate) struct IntegerFeedback {
+    hits: Cell<u8>,
+}
+
+impl IntegerFeedback {
+    fn is_specialized(&self) -> bool {
+        self.hits.get() >= SPECIALIZATION_THRESHOLD
+    }
+
+    fn observe(&self, lhs: &Value, rhs: &Value) {
+        match (lhs, rhs) {
+            (Value::Integer(_), Value::Integer(_)) => if !self.is_specialized() {
+                self.hits.set(self.hits.get() + 1);
+            },
+            _ => self.hits.set(0),
+        }
+    }
+
+    // Adds the operands, on the Integer-only path once specialized. Used by the tree walk as well
+    // as by the compiled operation, each with a cell of its own.
+    pub(crate) fn add(&self, lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+        if self.is_specialized() {
+            if let (Value::Integer(l), Value::Integer(r)) = (&lhs, &rhs) {
+                return l.checked_add(*r).map(Value::Integer).ok_or_else(|| overflow(origin(), *l, "+", *r));
+            }
+        }
+
+        self.observe(&lhs, &rhs);
+
+        add(lhs, rhs, origin)
+    }
+
+    pub(crate) fn greater_than(&self, lhs: &Value, rhs: &Value) -> Result<Value, RuntimeError> {
+        if self.is_specialized() {
+            if let (Value::Integer(l), Value::Integer(r)) = (lhs, rhs) {
+                return Ok(Value::Bool(l > r));
+            }
+        }
+
+        self.observe(lhs, rhs);
+
+        greater_than(lhs, rhs).map(Value::Bool)
+    }
+}
+
+// Integer results have to fit into 64 bits, they never wrap around.
+fn overflow(origin: String, l: i64, operator: &str, r: i64) -> RuntimeError {
+    RuntimeErrorKind::IntegerOverflow { origin, lhs: l, operator: operator.to_string(), rhs: r }.into()
+}
 
 #[derive(Debug)]
 pub struct AddExpression {
     lhs: Box<dyn Expression>,
     rhs: Box<dyn Expression>,
+    feedback: IntegerFeedback,
 }
 
 impl AddExpression {
     pub fn new(lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> Self {
-        Self { lhs, rhs }
+        Self { lhs, rhs, feedback: IntegerFeedback::default() }
     }
 }
 
 impl Expression for AddExpression {
-    fn eval(&self, environment: &Environment) -> Result<super::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} + {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<super::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Integer(l + r)),
-            (Float(l), Float(r)) => Ok(Float(l + r)),
+        self.feedback.add(lhs, rhs, || self.summary())
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
 
-            (String(l), String(r)) => Ok(String(l.to_string() + &r)),
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
 
-            (String(l), Integer(r)) => Ok(String(l + &r.to_string())),
-            (String(l), Float(r)) => Ok(String(l + &r.to_string())),
-            (Integer(l), String(r)) => Ok(String(l.to_string() + &r)),
-            (Float(l), String(r)) => Ok(String(l.to_string() + &r)),
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            (String, String | Integer | Decimal) | (Integer | Decimal, String) => Some(String),
+            _ => None,
+        }, "add")
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!("Cannot add {} and {}!", l.get_type_id(), r.get_type_id()),
-            }),
-        }
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Add(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Add { origin: self.summary(), feedback: IntegerFeedback::default() });
+
+        Ok(())
+    }
+}
+
+// The origin names the operation in errors, as with 'euclidean_modulo'.
+pub(crate) fn add(lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(l), Integer(r)) => l.checked_add(r).map(Integer).ok_or_else(|| overflow(origin(), l, "+", r)),
+        (Float(l), Float(r)) => Ok(Float(l + r)),
+
+        (String(l), String(r)) => Ok(String(l.to_string() + &r)),
+
+        (String(l), Integer(r)) => Ok(String(l + &r.to_string())),
+        (String(l), Float(r)) => Ok(String(l + &r.to_string())),
+        (Integer(l), String(r)) => Ok(String(l.to_string() + &r)),
+        (Float(l), String(r)) => Ok(String(l.to_string() + &r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("add", &l, &r)),
     }
 }
 
@@ -50,25 +149,57 @@ impl SubtractExpression {
 }
 
 impl Expression for SubtractExpression {
-    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} - {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Integer(l - r)),
-            (Float(l), Float(r)) => Ok(Float(l - r)),
+        subtract(lhs, rhs, || self.summary())
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot subtract {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            _ => None,
+        }, "subtract")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Subtract(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Subtract { origin: self.summary() });
+
+        Ok(())
+    }
+}
+
+pub(crate) fn subtract(lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(l), Integer(r)) => l.checked_sub(r).map(Integer).ok_or_else(|| overflow(origin(), l, "-", r)),
+        (Float(l), Float(r)) => Ok(Float(l - r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("subtract", &l, &r)),
+    }
 }
 
 #[derive(Debug)]
@@ -84,25 +215,110 @@ impl MultiplyExpression {
 }
 
 impl Expression for MultiplyExpression {
-    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} * {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Integer(l * r)),
-            (Float(l), Float(r)) => Ok(Float(l * r)),
+        multiply(lhs, rhs, &environment.context, || self.summary())
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            (String, Integer) | (Integer, String) => Some(String),
+            (Array, Integer) | (Integer, Array) => Some(Array),
+            _ => None,
+        }, "multiply")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Multiply(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Multiply { origin: self.summary() });
+
+        Ok(())
+    }
+}
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot multiply {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+pub(crate) fn multiply(lhs: Value, rhs: Value, context: &RuntimeContext, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(l), Integer(r)) => l.checked_mul(r).map(Integer).ok_or_else(|| overflow(origin(), l, "*", r)),
+        (Float(l), Float(r)) => Ok(Float(l * r)),
+
+        (String(s), Integer(n)) | (Integer(n), String(s)) => repeat_string(&s, repetitions(n)?, context).map(String),
+        (Array(arr), Integer(n)) | (Integer(n), Array(arr)) => repeat_array(&arr, repetitions(n)?, context).map(Array),
+
+        (l, r) => Err(RuntimeError::invalid_operands("multiply", &l, &r)),
+    }
+}
+
+// Integer division by zero is an error. Floats follow IEEE 754 instead, so dividing one by zero
+// gives an infinity or NaN.
+fn division_by_zero(origin: String) -> RuntimeError {
+    RuntimeErrorKind::DivisionByZero { origin }.into()
+}
+
+fn repetitions(n: i64) -> Result<usize, RuntimeError> {
+    n.try_into().map_err(|_| RuntimeErrorKind::InvalidRepetition { count: n }.into())
+}
+
+// Repetitions are allocated up front, so one too large to fit into memory or the memory limit
+// is an error instead of aborting the host.
+pub(crate) fn repeat_string(string: &str, n: usize, context: &RuntimeContext) -> Result<String, RuntimeError> {
+    let length = string.len().checked_mul(n).ok_or_else(|| too_large_to_repeat(n))?;
+    context.reserve_memory(length)?;
+
+    let mut repeated = String::new();
+    repeated.try_reserve_exact(length).map_err(|_| too_large_to_repeat(n))?;
+
+    if length > 0 {
+        for _ in 0..n {
+            repeated.push_str(string);
         }
     }
+
+    Ok(repeated)
+}
+
+pub(crate) fn repeat_array(array: &[Value], n: usize, context: &RuntimeContext) -> Result<Vec<Value>, RuntimeError> {
+    let length = array.len().checked_mul(n).ok_or_else(|| too_large_to_repeat(n))?;
+    context.reserve_memory(length.saturating_mul(mem::size_of::<Value>()))?;
+
+    let mut repeated = Vec::new();
+    repeated.try_reserve_exact(length).map_err(|_| too_large_to_repeat(n))?;
+
+    if length > 0 {
+        for _ in 0..n {
+            repeated.extend(array.iter().cloned());
+        }
+    }
+
+    Ok(repeated)
+}
+
+fn too_large_to_repeat(n: usize) -> RuntimeError {
+    RuntimeErrorKind::RepetitionTooLarge { count: n }.into()
 }
 
 #[derive(Debug)]
@@ -118,25 +334,58 @@ impl DivideExpression {
 }
 
 impl Expression for DivideExpression {
-    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} / {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Integer(l / r)),
-            (Float(l), Float(r)) => Ok(Float(l / r)),
+        divide(lhs, rhs, || self.summary())
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot divide {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            _ => None,
+        }, "divide")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Divide(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Divide { origin: self.summary() });
+
+        Ok(())
+    }
+}
+
+pub(crate) fn divide(lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(_), Integer(0)) => Err(division_by_zero(origin())),
+        (Integer(l), Integer(r)) => l.checked_div(r).map(Integer).ok_or_else(|| overflow(origin(), l, "/", r)),
+        (Float(l), Float(r)) => Ok(Float(l / r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("divide", &l, &r)),
+    }
 }
 
 #[derive(Debug)]
@@ -152,34 +401,63 @@ impl PowerExpression {
 }
 
 impl Expression for PowerExpression {
-    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} ^ {})", self.base.summary(), self.exponent.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let base = self.base.eval(environment)?;
         let exponent = self.exponent.eval(environment)?;
 
-        match (base, exponent) {
-            (Integer(l), Integer(r)) => Ok(Integer(
-                l.checked_pow(r.try_into().map_err(|_| RuntimeError {
-                    message: "Could not compute power; the exponent was too large!".into(),
-                })?)
-                .ok_or(RuntimeError {
-                    message: "Overflow occured while computing power!".into(),
-                })?,
-            )),
-            (Float(l), Float(r)) => Ok(Float(l.powf(r))),
+        power(base, exponent, || self.summary())
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot compute power of {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.base, &mut self.exponent]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.base.as_ref(), self.exponent.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            _ => None,
+        }, "compute the power of")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Power(image::boxed(self.base.as_ref())?, image::boxed(self.exponent.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.base.compile(chunk)?;
+        self.exponent.compile(chunk)?;
+        chunk.push(Op::Power { origin: self.summary() });
+
+        Ok(())
+    }
+}
+
+pub(crate) fn power(base: Value, exponent: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (base, exponent) {
+        (Integer(l), Integer(r)) => Ok(Integer(
+            l.checked_pow(r.try_into().map_err(|_| RuntimeError::from(RuntimeErrorKind::ExponentTooLarge { exponent: r }))?)
+            .ok_or_else(|| overflow(origin(), l, "^", r))?,
+        )),
+        (Float(l), Float(r)) => Ok(Float(l.powf(r))),
+
+        (l, r) => Err(RuntimeError::invalid_operands("compute the power of", &l, &r)),
+    }
 }
 
+// `%` truncates like most other languages, so the result takes the sign of the dividend: -7 % 3 == -1
 #[derive(Debug)]
 pub struct ModuloExpression {
     lhs: Box<dyn Expression>,
@@ -193,57 +471,318 @@ impl ModuloExpression {
 }
 
 impl Expression for ModuloExpression {
+    fn summary(&self) -> String {
+        format!("({} % {})", self.lhs.summary(), self.rhs.summary())
+    }
+
     fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+        let lhs = self.lhs.eval(environment)?;
+        let rhs = self.rhs.eval(environment)?;
+
+        modulo(lhs, rhs, || self.summary())
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            _ => None,
+        }, "take the modulo of")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Modulo(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Modulo { origin: self.summary() });
+
+        Ok(())
+    }
+}
+
+pub(crate) fn modulo(lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(_), Integer(0)) => Err(division_by_zero(origin())),
+        // The remainder always fits, wrapping only avoids the overflow of 'MIN / -1'.
+        (Integer(l), Integer(r)) => Ok(Integer(l.wrapping_rem(r))),
+        (Float(l), Float(r)) => Ok(Float(l % r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("take the modulo of", &l, &r)),
+    }
+}
+
+// `%%` is never negative for a non-zero divisor: -7 %% 3 == 2
+#[derive(Debug)]
+pub struct EuclideanModuloExpression {
+    lhs: Box<dyn Expression>,
+    rhs: Box<dyn Expression>,
+}
+
+impl EuclideanModuloExpression {
+    pub fn new(lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> Self {
+        Self { lhs, rhs }
+    }
+}
+
+impl Expression for EuclideanModuloExpression {
+    fn summary(&self) -> String {
+        format!("({} %% {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Integer(l.rem_euclid(r))),
-            (Float(l), Float(r)) => Ok(Float(l.rem_euclid(r))),
+        euclidean_modulo(lhs, rhs, || self.summary())
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot modulate {} by {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, |lhs, rhs| match (lhs, rhs) {
+            (Integer, Integer) => Some(Integer),
+            (Decimal, Decimal) => Some(Decimal),
+            _ => None,
+        }, "take the modulo of")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::EuclideanModulo(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::EuclideanModulo { origin: self.summary() });
+
+        Ok(())
+    }
+}
+
+// The origin names the operation in errors, e.g. the expression it was written as.
+pub(crate) fn euclidean_modulo(lhs: Value, rhs: Value, origin: impl FnOnce() -> String) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(_), Integer(0)) => Err(division_by_zero(origin())),
+        (Integer(l), Integer(r)) => Ok(Integer(l.wrapping_rem_euclid(r))),
+        (Float(l), Float(r)) => Ok(Float(l.rem_euclid(r))),
+
+        (l, r) => Err(RuntimeError::invalid_operands("take the modulo of", &l, &r)),
+    }
 }
 
 #[derive(Debug)]
 pub struct GreaterThanExpression {
     lhs: Box<dyn Expression>,
     rhs: Box<dyn Expression>,
+    feedback: IntegerFeedback,
 }
 
 impl GreaterThanExpression {
     pub fn new(lhs: Box<dyn Expression>, rhs: Box<dyn Expression>) -> Self {
-        Self { lhs, rhs }
+        Self { lhs, rhs, feedback: IntegerFeedback::default() }
     }
 }
 
 impl Expression for GreaterThanExpression {
-    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
-        use super::Value::*;
+    fn summary(&self) -> String {
+        format!("({} > {})", self.lhs.summary(), self.rhs.summary())
+    }
 
+    fn eval(&self, environment: &Environment) -> Result<crate::runtime::Value, RuntimeError> {
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Integer(l), Integer(r)) => Ok(Bool(l > r)),
-            (Float(l), Float(r)) => Ok(Bool(l > r)),
+        self.feedback.greater_than(&lhs, &rhs)
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, ordering_type, "order")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::GreaterThan(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::GreaterThan { feedback: IntegerFeedback::default() });
+
+        Ok(())
+    }
+}
+
+pub(crate) fn greater_than(lhs: &Value, rhs: &Value) -> Result<bool, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Integer(l), Integer(r)) => Ok(l > r),
+        (Float(l), Float(r)) => Ok(l > r),
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Ordering is undefined on {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+        (l, r) => Err(RuntimeError::invalid_operands("order", l, r)),
+    }
+}
+
+fn ordering_type(lhs: &PrimitiveTypeToken, rhs: &PrimitiveTypeToken) -> Option<PrimitiveTypeToken> {
+    use PrimitiveTypeToken::*;
+
+    match (lhs, rhs) {
+        (Integer, Integer) | (Decimal, Decimal) => Some(Boolean),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Comparison {
+    Greater,
+    Less,
+    GreaterEquals,
+    LessEquals,
+}
+
+impl Comparison {
+    pub(crate) fn holds(&self, lhs: &Value, rhs: &Value) -> Result<bool, RuntimeError> {
+        match self {
+            Comparison::Greater => greater_than(lhs, rhs),
+            Comparison::Less => greater_than(rhs, lhs),
+            Comparison::GreaterEquals => greater_than(rhs, lhs).map(|result| !result),
+            Comparison::LessEquals => greater_than(lhs, rhs).map(|result| !result),
         }
     }
+}
+
+// `a < b < c` evaluates like `a < b && b < c`, but every operand is evaluated at most once.
+#[derive(Debug)]
+pub struct ChainedComparisonExpression {
+    operands: Vec<Box<dyn Expression>>,
+    comparisons: Vec<Comparison>,
+}
+
+impl ChainedComparisonExpression {
+    pub fn new(operands: Vec<Box<dyn Expression>>, comparisons: Vec<Comparison>) -> Self {
+        Self { operands, comparisons }
+    }
+}
+
+impl Expression for ChainedComparisonExpression {
+    fn summary(&self) -> String {
+        let mut summary = String::from("(");
+
+        for (i, operand) in self.operands.iter().enumerate() {
+            if i > 0 {
+                summary.push_str(match self.comparisons[i - 1] {
+                    Comparison::Greater => " > ",
+                    Comparison::Less => " < ",
+                    Comparison::GreaterEquals => " >= ",
+                    Comparison::LessEquals => " <= ",
+                });
+            }
+            summary.push_str(&operand.summary());
+        }
+
+        summary.push(')');
+        summary
+    }
+
+    fn eval(&self, environment: &Environment) -> Result<Value, RuntimeError> {
+        let mut operands = self.operands.iter();
+
+        let mut lhs = match operands.next() {
+            Some(operand) => operand.eval(environment)?,
+            None => return Ok(Value::Bool(true)),
+        };
+
+        for (comparison, operand) in self.comparisons.iter().zip(operands) {
+            let rhs = operand.eval(environment)?;
+
+            if !comparison.holds(&lhs, &rhs)? {
+                return Ok(Value::Bool(false));
+            }
+
+            lhs = rhs;
+        }
+
+        Ok(Value::Bool(true))
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all(self.operands.iter_mut()) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        for pair in self.operands.windows(2) {
+            infer_binary(pair[0].as_ref(), pair[1].as_ref(), types, ordering_type, "order")?;
+        }
+
+        Ok(Some(Boolean))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::ChainedComparison(image::all(&self.operands)?, self.comparisons.clone()))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        let mut operands = self.operands.iter();
+
+        let Some(first) = operands.next() else {
+            chunk.push(Op::Push(Value::Bool(true)));
+            return Ok(());
+        };
+        first.compile(chunk)?;
+
+        let mut exits = Vec::with_capacity(self.comparisons.len());
+        for (comparison, operand) in self.comparisons.iter().zip(operands) {
+            operand.compile(chunk)?;
+            exits.push(chunk.len());
+            chunk.push(Op::Compare { comparison: *comparison, exit: 0 });
+        }
+
+        chunk.push(Op::Pop);
+        chunk.push(Op::Push(Value::Bool(true)));
+
+        for exit in exits {
+            chunk.patch_exit(exit, chunk.len());
+        }
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::runtime::{expressions::Expression, RuntimeError};
+use crate::{compiler::{CompilerError, optimizer::ConstantFolder, type_checker::{TypeEnvironment, expect_type, infer_binary}}, lexer::token::PrimitiveTypeToken, runtime::{bytecode::{Chunk, Op}, expressions::Expression, RuntimeError, RuntimeErrorKind, Value, image::{self, ExpressionImage}}};
 
 #[derive(Debug)]
 pub struct AndExpression {
@@ -13,27 +13,53 @@ impl AndExpression {
 }
 
 impl Expression for AndExpression {
+    fn summary(&self) -> String {
+        format!("({} && {})", self.lhs.summary(), self.rhs.summary())
+    }
+
     fn eval(
         &self,
         environment: &crate::runtime::Environment,
     ) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
-        use super::Value::*;
-
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Bool(l), Bool(r)) => Ok(Bool(l && r)),
+        and(lhs, rhs)
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot perform boolean and operation on {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, boolean_type, "perform boolean and operation on")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::And(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::And);
+
+        Ok(())
+    }
+}
+
+pub(crate) fn and(lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Bool(l), Bool(r)) => Ok(Bool(l && r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("perform boolean and operation on", &l, &r)),
+    }
 }
 
 #[derive(Debug)]
@@ -49,27 +75,53 @@ impl OrExpression {
 }
 
 impl Expression for OrExpression {
+    fn summary(&self) -> String {
+        format!("({} || {})", self.lhs.summary(), self.rhs.summary())
+    }
+
     fn eval(
         &self,
         environment: &crate::runtime::Environment,
     ) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
-        use super::Value::*;
-
         let lhs = self.lhs.eval(environment)?;
         let rhs = self.rhs.eval(environment)?;
 
-        match (lhs, rhs) {
-            (Bool(l), Bool(r)) => Ok(Bool(l || r)),
+        or(lhs, rhs)
+    }
 
-            (l, r) => Err(RuntimeError {
-                message: format!(
-                    "Cannot perform boolean or operation on {} and {}!",
-                    l.get_type_id(),
-                    r.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold_all([&mut self.lhs, &mut self.rhs]) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        infer_binary(self.lhs.as_ref(), self.rhs.as_ref(), types, boolean_type, "perform boolean or operation on")
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Or(image::boxed(self.lhs.as_ref())?, image::boxed(self.rhs.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.lhs.compile(chunk)?;
+        self.rhs.compile(chunk)?;
+        chunk.push(Op::Or);
+
+        Ok(())
+    }
+}
+
+pub(crate) fn or(lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match (lhs, rhs) {
+        (Bool(l), Bool(r)) => Ok(Bool(l || r)),
+
+        (l, r) => Err(RuntimeError::invalid_operands("perform boolean or operation on", &l, &r)),
+    }
 }
 
 #[derive(Debug)]
@@ -84,23 +136,110 @@ impl NotExpression {
 }
 
 impl Expression for NotExpression {
+    fn summary(&self) -> String {
+        format!("!{}", self.expr.summary())
+    }
+
     fn eval(
         &self,
         environment: &crate::runtime::Environment,
     ) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
-        use super::Value::*;
+        not(self.expr.eval(environment)?)
+    }
+
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold(&mut self.expr) {
+            folder.evaluate(self)
+        } else {
+            None
+        }
+    }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        use PrimitiveTypeToken::*;
+
+        expect_type(self.expr.as_ref(), types, Boolean, "the operand of '!'")?;
 
-        let value = self.expr.eval(environment)?;
+        Ok(Some(Boolean))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Not(image::boxed(self.expr.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.expr.compile(chunk)?;
+        chunk.push(Op::Not);
+
+        Ok(())
+    }
+}
+
+pub(crate) fn not(value: Value) -> Result<Value, RuntimeError> {
+    use super::Value::*;
+
+    match value {
+        Bool(value) => Ok(Bool(!value)),
+
+        value => Err(RuntimeErrorKind::InvalidOperands { operation: "perform boolean nor operation on", lhs: value.get_type_id(), rhs: None }.into()),
+    }
+}
+
+// Converts any value to a Boolean by its truthiness, see 'Value::is_truthy'.
+#[derive(Debug)]
+pub struct TruthinessExpression {
+    expr: Box<dyn Expression>,
+}
+
+impl TruthinessExpression {
+    pub fn new(expr: Box<dyn Expression>) -> Self {
+        Self { expr }
+    }
+}
+
+impl Expression for TruthinessExpression {
+    fn summary(&self) -> String {
+        format!("Bools::from({})", self.expr.summary())
+    }
 
-        match value {
-            Bool(value) => Ok(Bool(!value)),
+    fn eval(
+        &self,
+        environment: &crate::runtime::Environment,
+    ) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
+        Ok(super::Value::Bool(self.expr.eval(environment)?.is_truthy()))
+    }
 
-            value => Err(RuntimeError {
-                message: format!(
-                    "Cannot perform boolean nor operation on {}!",
-                    value.get_type_id()
-                ),
-            }),
+    fn fold_constants(&mut self, folder: &ConstantFolder) -> Option<Value> {
+        if folder.fold(&mut self.expr) {
+            folder.evaluate(self)
+        } else {
+            None
         }
     }
+
+    fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        self.expr.static_type(types)?;
+
+        Ok(Some(PrimitiveTypeToken::Boolean))
+    }
+
+    fn image(&self) -> Result<ExpressionImage, CompilerError> {
+        Ok(ExpressionImage::Truthiness(image::boxed(self.expr.as_ref())?))
+    }
+
+    fn compile(&self, chunk: &mut Chunk) -> Result<(), CompilerError> {
+        self.expr.compile(chunk)?;
+        chunk.push(Op::Truthiness);
+
+        Ok(())
+    }
+}
+
+fn boolean_type(lhs: &PrimitiveTypeToken, rhs: &PrimitiveTypeToken) -> Option<PrimitiveTypeToken> {
+    use PrimitiveTypeToken::*;
+
+    match (lhs, rhs) {
+        (Boolean, Boolean) => Some(Boolean),
+        _ => None,
+    }
 }
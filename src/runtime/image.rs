@@ -0,0 +1,508 @@
+use std::{cell::OnceCell, collections::BTreeMap, fs, path::Path, rc::Rc, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind}, lexer::token::{PrimitiveTypeToken, SourceSpan}, runtime::{
+    Expression, ModuleAddress, RuntimeObject, Value,
+    environment::Environment,
+    expressions::{
+        ArrayLiteralExpression, CloneExpression, EqualityExpression, HasNextElementExpression, ModuleVariableAssignmentExpression,
+        ModuleVariableExpression, ProcedureCallExpression, ReferenceExpression, StructConstructionExpression, VariableExpression,
+        arithmetic::{AddExpression, ChainedComparisonExpression, Comparison, DivideExpression, EuclideanModuloExpression, GreaterThanExpression, ModuloExpression, MultiplyExpression, PowerExpression, SubtractExpression},
+        boolean::{AndExpression, NotExpression, OrExpression, TruthinessExpression},
+    },
+    module::Module,
+    procedures::{CallBudget, CompiledProcedure, Instruction},
+    scope::{FieldAccessor, ScopeAddress, ScopeAddressant},
+    symbol::Symbol,
+}};
+
+// A compiled program as it is written to a '.otrc' file, so it can be run without lexing and
+// parsing its sources again. Only the modules of the program are stored; builtin modules are
+// recreated on loading, minus the denied ones.
+
+pub const COMPILED_EXTENSION: &str = "otrc";
+
+const MAGIC: &[u8; 4] = b"OTRC";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProgramImage {
+    // Compiled programs are only loaded by the version of otr that wrote them.
+    version: String,
+    entrypoint: Option<ModuleAddress>,
+    builtin_modules: Vec<String>,
+    modules: BTreeMap<String, ModuleImage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ModuleImage {
+    pub(crate) structs: Vec<StructImage>,
+    pub(crate) private_constructors: Vec<String>,
+    pub(crate) procedures: Vec<(String, ProcedureImage, bool)>,
+    pub(crate) documentation: Vec<(String, String)>,
+    pub(crate) aliases: Vec<(String, String)>,
+    pub(crate) reexports: Vec<String>,
+    pub(crate) variables: Vec<VariableImage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StructImage {
+    pub(crate) identifier: String,
+    pub(crate) struct_id: ModuleAddress,
+    // Name, visibility and default value of every field, in declaration order.
+    pub(crate) fields: Vec<(String, bool, ConstantImage)>,
+    pub(crate) exported: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct VariableImage {
+    pub(crate) identifier: String,
+    pub(crate) initializer: Option<ExpressionImage>,
+    pub(crate) exported: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ProcedureImage {
+    arguments_identifiers: Vec<String>,
+    argument_types: Vec<Option<PrimitiveTypeToken>>,
+    return_type: Option<PrimitiveTypeToken>,
+    instructions: Vec<InstructionImage>,
+    budget: Option<CallBudget>,
+    file: Option<String>,
+    spans: Vec<SourceSpan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum InstructionImage {
+    PushVarToScope(String),
+    PopVarFromScope(String),
+    GrowStack,
+    ShrinkStack,
+    EvaluateExpression(ExpressionImage, Option<AddressImage>),
+    JumpConditional(ExpressionImage, usize),
+    Return(ExpressionImage),
+    Try(usize),
+    EndTry,
+    Catch(String),
+    Throw(ExpressionImage),
+}
+
+// A value known at compile time. Structs only exist at runtime and never are constants.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConstantImage {
+    Null,
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Char(char),
+    Bool(bool),
+    Array(Vec<ConstantImage>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AddressantImage {
+    Identifier(String),
+    Field(String),
+    Index(i64),
+    DynamicIndex(ExpressionImage),
+}
+
+pub type AddressImage = Vec<AddressantImage>;
+
+// One variant per kind of expression, see `Expression::image`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ExpressionImage {
+    Constant(ConstantImage),
+    ProcedureCall(ModuleAddress, Vec<ExpressionImage>),
+    StructConstruction(ModuleAddress, Vec<(String, ExpressionImage)>, Option<Box<ExpressionImage>>),
+    ArrayLiteral(Vec<ExpressionImage>),
+    Variable(AddressImage),
+    Reference(AddressImage),
+    Clone(AddressImage),
+    Equality(Box<ExpressionImage>, Box<ExpressionImage>),
+    ModuleVariable(String, AddressImage),
+    ModuleVariableAssignment(String, AddressImage, Box<ExpressionImage>),
+    HasNextElement(String, String),
+    Add(Box<ExpressionImage>, Box<ExpressionImage>),
+    Subtract(Box<ExpressionImage>, Box<ExpressionImage>),
+    Multiply(Box<ExpressionImage>, Box<ExpressionImage>),
+    Divide(Box<ExpressionImage>, Box<ExpressionImage>),
+    Power(Box<ExpressionImage>, Box<ExpressionImage>),
+    Modulo(Box<ExpressionImage>, Box<ExpressionImage>),
+    EuclideanModulo(Box<ExpressionImage>, Box<ExpressionImage>),
+    GreaterThan(Box<ExpressionImage>, Box<ExpressionImage>),
+    ChainedComparison(Vec<ExpressionImage>, Vec<Comparison>),
+    And(Box<ExpressionImage>, Box<ExpressionImage>),
+    Or(Box<ExpressionImage>, Box<ExpressionImage>),
+    Not(Box<ExpressionImage>),
+    Truthiness(Box<ExpressionImage>),
+}
+
+pub(crate) fn boxed(expression: &dyn Expression) -> Result<Box<ExpressionImage>, CompilerError> {
+    expression.image().map(Box::new)
+}
+
+pub(crate) fn all<'a>(expressions: impl IntoIterator<Item = &'a Box<dyn Expression>>) -> Result<Vec<ExpressionImage>, CompilerError> {
+    expressions.into_iter().map(|expression| expression.image()).collect()
+}
+
+impl TryFrom<&Value> for ConstantImage {
+    type Error = CompilerError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            Value::Null => ConstantImage::Null,
+            Value::Integer(n) => ConstantImage::Integer(*n),
+            Value::Float(f) => ConstantImage::Float(*f),
+            Value::String(s) => ConstantImage::String(s.clone()),
+            Value::Char(c) => ConstantImage::Char(*c),
+            Value::Bool(b) => ConstantImage::Bool(*b),
+            Value::Array(values) => ConstantImage::Array(values.iter().map(ConstantImage::try_from).collect::<Result<_, _>>()?),
+            other => return Err(CompilerErrorKind::NotSaveable { item: format!("A constant of type {}", other.get_type_id()) }.into()),
+        })
+    }
+}
+
+impl From<ConstantImage> for Value {
+    fn from(constant: ConstantImage) -> Self {
+        match constant {
+            ConstantImage::Null => Value::Null,
+            ConstantImage::Integer(n) => Value::Integer(n),
+            ConstantImage::Float(f) => Value::Float(f),
+            ConstantImage::String(s) => Value::String(s),
+            ConstantImage::Char(c) => Value::Char(c),
+            ConstantImage::Bool(b) => Value::Bool(b),
+            ConstantImage::Array(values) => Value::Array(values.into_iter().map(Value::from).collect()),
+        }
+    }
+}
+
+impl ExpressionImage {
+    pub(crate) fn load(self) -> Result<Box<dyn Expression>, CompilerError> {
+        let load = |image: Box<ExpressionImage>| image.load();
+
+        Ok(match self {
+            ExpressionImage::Constant(constant) => Box::new(Value::from(constant)),
+            ExpressionImage::ProcedureCall(procedure_id, arguments) => Box::new(ProcedureCallExpression {
+                procedure_id,
+                arguments: load_all(arguments)?,
+            }),
+            ExpressionImage::StructConstruction(struct_id, field_overrides, base) => Box::new(StructConstructionExpression {
+                struct_id,
+                field_overrides: field_overrides
+                    .into_iter()
+                    .map(|(field, image)| Ok((field.into(), image.load()?)))
+                    .collect::<Result<_, CompilerError>>()?,
+                base: base.map(load).transpose()?,
+            }),
+            ExpressionImage::ArrayLiteral(elements) => Box::new(ArrayLiteralExpression { elements: load_all(elements)? }),
+            ExpressionImage::Variable(address) => Box::new(VariableExpression { variable_address: load_address(address)? }),
+            ExpressionImage::Reference(address) => Box::new(ReferenceExpression { variable_address: load_address(address)? }),
+            ExpressionImage::Clone(address) => Box::new(CloneExpression { variable_address: load_address(address)? }),
+            ExpressionImage::Equality(lhs, rhs) => Box::new(EqualityExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::ModuleVariable(module_id, address) => Box::new(ModuleVariableExpression {
+                module_id,
+                variable_address: load_address(address)?,
+            }),
+            ExpressionImage::ModuleVariableAssignment(module_id, address, expression) => Box::new(ModuleVariableAssignmentExpression {
+                module_id,
+                variable_address: load_address(address)?,
+                expression: load(expression)?,
+            }),
+            ExpressionImage::HasNextElement(collection, index) => Box::new(HasNextElementExpression { collection: collection.into(), index: index.into() }),
+            ExpressionImage::Add(lhs, rhs) => Box::new(AddExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Subtract(lhs, rhs) => Box::new(SubtractExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Multiply(lhs, rhs) => Box::new(MultiplyExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Divide(lhs, rhs) => Box::new(DivideExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Power(base, exponent) => Box::new(PowerExpression::new(load(base)?, load(exponent)?)),
+            ExpressionImage::Modulo(lhs, rhs) => Box::new(ModuloExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::EuclideanModulo(lhs, rhs) => Box::new(EuclideanModuloExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::GreaterThan(lhs, rhs) => Box::new(GreaterThanExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::ChainedComparison(operands, comparisons) => {
+                if operands.len() != comparisons.len() + 1 {
+                    return Err(invalid("A chained comparison has a wrong number of operands."));
+                }
+
+                Box::new(ChainedComparisonExpression::new(load_all(operands)?, comparisons))
+            }
+            ExpressionImage::And(lhs, rhs) => Box::new(AndExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Or(lhs, rhs) => Box::new(OrExpression::new(load(lhs)?, load(rhs)?)),
+            ExpressionImage::Not(expr) => Box::new(NotExpression::new(load(expr)?)),
+            ExpressionImage::Truthiness(expr) => Box::new(TruthinessExpression::new(load(expr)?)),
+        })
+    }
+
+    // Calls `visit` with the expression and every expression nested in it, the indices of
+    // addresses included.
+    pub(crate) fn walk(&self, visit: &mut impl FnMut(&ExpressionImage)) {
+        visit(self);
+
+        match self {
+            ExpressionImage::Constant(_) | ExpressionImage::HasNextElement(..) => {}
+            ExpressionImage::ProcedureCall(_, expressions) |
+            ExpressionImage::ArrayLiteral(expressions) |
+            ExpressionImage::ChainedComparison(expressions, _) => {
+                for expression in expressions {
+                    expression.walk(visit);
+                }
+            }
+            ExpressionImage::StructConstruction(_, field_overrides, base) => {
+                for (_, expression) in field_overrides {
+                    expression.walk(visit);
+                }
+                if let Some(base) = base {
+                    base.walk(visit);
+                }
+            }
+            ExpressionImage::Variable(address) |
+            ExpressionImage::Reference(address) |
+            ExpressionImage::Clone(address) |
+            ExpressionImage::ModuleVariable(_, address) => walk_address(address, visit),
+            ExpressionImage::ModuleVariableAssignment(_, address, expression) => {
+                walk_address(address, visit);
+                expression.walk(visit);
+            }
+            ExpressionImage::Equality(lhs, rhs) |
+            ExpressionImage::Add(lhs, rhs) |
+            ExpressionImage::Subtract(lhs, rhs) |
+            ExpressionImage::Multiply(lhs, rhs) |
+            ExpressionImage::Divide(lhs, rhs) |
+            ExpressionImage::Power(lhs, rhs) |
+            ExpressionImage::Modulo(lhs, rhs) |
+            ExpressionImage::EuclideanModulo(lhs, rhs) |
+            ExpressionImage::GreaterThan(lhs, rhs) |
+            ExpressionImage::And(lhs, rhs) |
+            ExpressionImage::Or(lhs, rhs) => {
+                lhs.walk(visit);
+                rhs.walk(visit);
+            }
+            ExpressionImage::Not(expr) | ExpressionImage::Truthiness(expr) => expr.walk(visit),
+        }
+    }
+}
+
+fn walk_address(address: &AddressImage, visit: &mut impl FnMut(&ExpressionImage)) {
+    for addressant in address {
+        if let AddressantImage::DynamicIndex(expression) = addressant {
+            expression.walk(visit);
+        }
+    }
+}
+
+fn load_all(images: Vec<ExpressionImage>) -> Result<Vec<Box<dyn Expression>>, CompilerError> {
+    images.into_iter().map(ExpressionImage::load).collect()
+}
+
+pub(crate) fn address_image(address: &ScopeAddress) -> Result<AddressImage, CompilerError> {
+    address.addressants().iter().map(|addressant| Ok(match addressant {
+        ScopeAddressant::Identifier(ident) => AddressantImage::Identifier(ident.to_string()),
+        ScopeAddressant::Field(field) => AddressantImage::Field(field.name().to_string()),
+        ScopeAddressant::Index(index) => AddressantImage::Index(*index),
+        ScopeAddressant::DynamicIndex(expression) => AddressantImage::DynamicIndex(expression.image()?),
+    })).collect()
+}
+
+fn load_address(image: AddressImage) -> Result<ScopeAddress, CompilerError> {
+    let addressants = image.into_iter().map(|addressant| Ok(match addressant {
+        AddressantImage::Identifier(ident) => ScopeAddressant::Identifier(ident.into()),
+        AddressantImage::Field(name) => ScopeAddressant::Field(FieldAccessor::new(name.into())),
+        AddressantImage::Index(index) => ScopeAddressant::Index(index),
+        AddressantImage::DynamicIndex(expression) => ScopeAddressant::DynamicIndex(Rc::from(expression.load()?)),
+    })).collect::<Result<Vec<_>, CompilerError>>()?;
+
+    addressants.try_into().map_err(|_| invalid("It contains an empty address."))
+}
+
+fn invalid(reason: &str) -> CompilerError {
+    CompilerErrorKind::InvalidImage { reason: reason.to_string() }.into()
+}
+
+impl ProcedureImage {
+    pub(crate) fn new(procedure: &CompiledProcedure) -> Result<Self, CompilerError> {
+        Ok(Self {
+            arguments_identifiers: procedure.arguments_identifiers.iter().map(Symbol::to_string).collect(),
+            argument_types: procedure.argument_types.clone(),
+            return_type: procedure.return_type.clone(),
+            instructions: procedure.instructions.iter().map(InstructionImage::new).collect::<Result<_, _>>()?,
+            budget: procedure.budget,
+            file: procedure.file.map(|file| file.to_string()),
+            spans: procedure.spans.clone(),
+        })
+    }
+
+    pub(crate) fn load(self) -> Result<CompiledProcedure, CompilerError> {
+        let instructions: Vec<Instruction> = self.instructions.into_iter().map(InstructionImage::load).collect::<Result<_, _>>()?;
+
+        let targets_valid = instructions.iter().all(|instruction| match instruction {
+            Instruction::JumpConditional { jump_target: target, .. } | Instruction::Try { catch_target: target } => *target <= instructions.len(),
+            _ => true,
+        });
+
+        if !targets_valid {
+            return Err(invalid("It contains a jump out of its procedure."));
+        }
+
+        Ok(CompiledProcedure {
+            arguments_identifiers: self.arguments_identifiers.into_iter().map(Symbol::from).collect(),
+            argument_types: self.argument_types,
+            return_type: self.return_type,
+            instructions,
+            budget: self.budget,
+            bytecode: OnceCell::new(),
+            file: self.file.map(Symbol::from),
+            spans: self.spans,
+        })
+    }
+}
+
+impl InstructionImage {
+    fn new(instruction: &Instruction) -> Result<Self, CompilerError> {
+        Ok(match instruction {
+            Instruction::PushVarToScope { identifier } => InstructionImage::PushVarToScope(identifier.to_string()),
+            Instruction::PopVarFromScope { identifier } => InstructionImage::PopVarFromScope(identifier.to_string()),
+            Instruction::GrowStack => InstructionImage::GrowStack,
+            Instruction::ShrinkStack => InstructionImage::ShrinkStack,
+            Instruction::EvaluateExpression { expression, target } => InstructionImage::EvaluateExpression(
+                expression.image()?,
+                target.as_ref().map(address_image).transpose()?,
+            ),
+            Instruction::JumpConditional { condition_expression, jump_target } => {
+                InstructionImage::JumpConditional(condition_expression.image()?, *jump_target)
+            }
+            Instruction::Return { expression } => InstructionImage::Return(expression.image()?),
+            Instruction::Try { catch_target } => InstructionImage::Try(*catch_target),
+            Instruction::EndTry => InstructionImage::EndTry,
+            Instruction::Catch { identifier } => InstructionImage::Catch(identifier.to_string()),
+            Instruction::Throw { expression } => InstructionImage::Throw(expression.image()?),
+        })
+    }
+
+    fn load(self) -> Result<Instruction, CompilerError> {
+        Ok(match self {
+            InstructionImage::PushVarToScope(identifier) => Instruction::PushVarToScope { identifier: identifier.into() },
+            InstructionImage::PopVarFromScope(identifier) => Instruction::PopVarFromScope { identifier: identifier.into() },
+            InstructionImage::GrowStack => Instruction::GrowStack,
+            InstructionImage::ShrinkStack => Instruction::ShrinkStack,
+            InstructionImage::EvaluateExpression(expression, target) => Instruction::EvaluateExpression {
+                expression: expression.load()?,
+                target: target.map(load_address).transpose()?,
+            },
+            InstructionImage::JumpConditional(condition, jump_target) => Instruction::JumpConditional {
+                condition_expression: condition.load()?,
+                jump_target,
+            },
+            InstructionImage::Return(expression) => Instruction::Return { expression: expression.load()? },
+            InstructionImage::Try(catch_target) => Instruction::Try { catch_target },
+            InstructionImage::EndTry => Instruction::EndTry,
+            InstructionImage::Catch(identifier) => Instruction::Catch { identifier: identifier.into() },
+            InstructionImage::Throw(expression) => Instruction::Throw { expression: expression.load()? },
+        })
+    }
+}
+
+impl VariableImage {
+    pub(crate) fn load_initializer(&mut self) -> Result<Option<Box<dyn Expression>>, CompilerError> {
+        self.initializer.take().map(ExpressionImage::load).transpose()
+    }
+}
+
+impl ProgramImage {
+    fn new(runtime_object: &RuntimeObject) -> Result<Self, CompilerError> {
+        let mut builtin_modules = Vec::new();
+        let mut modules = BTreeMap::new();
+
+        for (module_id, module) in &runtime_object.base_environement.loaded_modules {
+            if Environment::is_builtin_module(module_id) {
+                builtin_modules.push(module_id.clone());
+            } else {
+                modules.insert(module_id.clone(), module.image(module_id)?);
+            }
+        }
+
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").into(),
+            entrypoint: runtime_object.entrypoint.clone(),
+            builtin_modules,
+            modules,
+        })
+    }
+
+    fn instantiate(self) -> Result<RuntimeObject, CompilerError> {
+        let mut runtime_object = RuntimeObject::new();
+        runtime_object.base_environement = Environment::default();
+        let environment = &mut runtime_object.base_environement;
+
+        let denied: Vec<String> = environment.loaded_modules
+            .keys()
+            .filter(|module_id| !self.builtin_modules.contains(module_id))
+            .cloned()
+            .collect();
+
+        for module_id in denied {
+            environment.deny_capability(&module_id)?;
+        }
+
+        for (module_id, module) in self.modules {
+            environment.loaded_modules.insert(module_id, Rc::new(Module::from_image(module)?));
+        }
+
+        runtime_object.entrypoint = self.entrypoint;
+
+        Ok(runtime_object)
+    }
+}
+
+// A compiled program that can be sent to and shared between threads, e.g. the workers of a
+// server. Runtime values are not thread-safe, so every thread runs its own instance of the
+// program and no value is shared between them. Instances start without the limits and globals
+// set on the original program, and without the Env capability: the environment variables are
+// shared by the whole process and setting them while other threads read them is unsound.
+#[derive(Debug, Clone)]
+pub struct SharedProgram {
+    image: Arc<ProgramImage>,
+}
+
+impl SharedProgram {
+    pub fn new(runtime_object: &RuntimeObject) -> Result<Self, CompilerError> {
+        Ok(Self { image: Arc::new(ProgramImage::new(runtime_object)?) })
+    }
+
+    // Creates a new instance of the program without compiling it again.
+    pub fn instantiate(&self) -> Result<RuntimeObject, CompilerError> {
+        let mut runtime_object = self.image.as_ref().clone().instantiate()?;
+        runtime_object.base_environement.deny_capability("Env")?;
+
+        Ok(runtime_object)
+    }
+}
+
+pub(crate) fn encode(runtime_object: &RuntimeObject) -> Result<Vec<u8>, CompilerError> {
+    let mut bytes = MAGIC.to_vec();
+
+    bincode::serialize_into(&mut bytes, &ProgramImage::new(runtime_object)?).map_err(|err| CompilerError::io("Program".into(), "serialized", err))?;
+
+    Ok(bytes)
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<RuntimeObject, CompilerError> {
+    let content = bytes.strip_prefix(MAGIC).ok_or_else(|| invalid("It is not a compiled otr program."))?;
+
+    let image: ProgramImage = bincode::deserialize(content).map_err(|err| invalid(&err.to_string()))?;
+
+    if image.version != env!("CARGO_PKG_VERSION") {
+        return Err(CompilerErrorKind::VersionMismatch { version: image.version }.into());
+    }
+
+    image.instantiate()
+}
+
+pub(crate) fn save(runtime_object: &RuntimeObject, path: &Path) -> Result<(), CompilerError> {
+    fs::write(path, encode(runtime_object)?).map_err(|err| CompilerError::io(format!("Compiled program '{}'", path.display()), "written", err))
+}
+
+pub(crate) fn load(path: &Path) -> Result<RuntimeObject, CompilerError> {
+    let bytes = fs::read(path).map_err(|err| CompilerError::io(format!("Compiled program '{}'", path.display()), "read", err))?;
+
+    // Names the file in errors about its content.
+    decode(&bytes).map_err(|err| err.in_file(Some(&path.display().to_string())))
+}
@@ -1,11 +1,28 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::{BTreeMap, BTreeSet}};
 
-use crate::{compiler::CompilerError, runtime::{ModuleAddress, RuntimeError, Struct, environment::Environment, procedures::Procedure}};
+use crate::{compiler::{CompilerError, CompilerErrorKind}, runtime::{Expression, RuntimeError, RuntimeErrorKind, Struct, Value, environment::Environment, image::{ConstantImage, ModuleImage, ProcedureImage, StructImage, VariableImage}, procedures::Procedure, scope::{BakedScopeAddress, Scope, ScopeAddressant}, symbol::Symbol}};
+
+#[derive(Debug)]
+struct VariableDeclaration {
+    identifier: Symbol,
+    initializer: Option<Box<dyn Expression>>,
+    exported: bool,
+}
 
 #[derive(Debug, Default)]
 pub struct Module {
-    struct_prototypes: HashMap<String, (Struct, bool)>,
-    procedures: HashMap<String, (Box<dyn Procedure>, bool)>,
+    struct_prototypes: BTreeMap<String, (Struct, bool)>,
+    // Structs that can only be constructed from within this module.
+    private_constructors: BTreeSet<String>,
+    procedures: BTreeMap<String, (Box<dyn Procedure>, bool)>,
+    documentation: BTreeMap<String, String>,
+    aliases: BTreeMap<String, String>,
+    // Aliases declared with 'export import', visible to the modules importing this one.
+    reexports: BTreeSet<String>,
+    // Module level variables in declaration order.
+    variable_declarations: Vec<VariableDeclaration>,
+    // The values of the module level variables during the current run.
+    variables: RefCell<Scope>,
 }
 
 impl Module {
@@ -19,42 +36,157 @@ impl Module {
                 if *exported || private_access {
                     Ok(proc)
                 } else {
-                    Err(RuntimeError {
-                        message: format!(
-                            "Procedure \"{}\" is not exported by this module!",
-                            identifier
-                        ),
-                    })
+                    Err(RuntimeErrorKind::NotExported { item: "Procedure", identifier: identifier.clone() }.into())
                 }
             }
-            None => Err(RuntimeError {
-                message: format!("Procedure \"{}\" not defined in this module!", identifier),
-            })
+            None => Err(RuntimeErrorKind::NotDefined { item: "Procedure", identifier: identifier.clone() }.into())
         }
     }
 
+    pub fn procedures(&self) -> impl Iterator<Item = (&String, &Box<dyn Procedure>)> {
+        self.procedures.iter().map(|(identifier, (procedure, _))| (identifier, procedure))
+    }
+
+    pub(crate) fn procedures_mut(&mut self) -> impl Iterator<Item = (&String, &mut Box<dyn Procedure>)> {
+        self.procedures.iter_mut().map(|(identifier, (procedure, _))| (identifier, procedure))
+    }
+
+    // Names of the procedures and structs other modules can use.
+    pub fn exported_names(&self) -> impl Iterator<Item = &String> {
+        self.procedures
+            .iter()
+            .map(|(identifier, (_, exported))| (identifier, exported))
+            .chain(self.struct_prototypes.iter().map(|(identifier, (_, exported))| (identifier, exported)))
+            .filter(|(_, exported)| **exported)
+            .map(|(identifier, _)| identifier)
+    }
+
     pub fn insert_struct(&mut self, identifier: String, prototype: Struct, exported: bool) {
         self.struct_prototypes.insert(identifier, (prototype, exported));
     }
 
+    pub fn set_private_constructor(&mut self, identifier: String) {
+        self.private_constructors.insert(identifier);
+    }
+
     pub fn get_struct(&self, identifier: &String, private_access: bool) -> Result<Struct, RuntimeError> {
         match self.struct_prototypes.get(identifier) {
+            Some(_) if !private_access && self.private_constructors.contains(identifier) => {
+                Err(RuntimeErrorKind::PrivateConstructor { identifier: identifier.clone() }.into())
+            }
             Some((prototype, exported)) => {
                 if *exported || private_access {
                     Ok(prototype.clone())
                 } else {
-                    Err(RuntimeError {
-                        message: format!(
-                            "Struct \"{}\" is not exported by this module!",
-                            identifier
-                        ),
-                    })
+                    Err(RuntimeErrorKind::NotExported { item: "Struct", identifier: identifier.clone() }.into())
                 }
             }
-            None => Err(RuntimeError {
-                message: format!("Struct \"{}\" not defined in this module!", identifier),
-            })
+            None => Err(RuntimeErrorKind::NotDefined { item: "Struct", identifier: identifier.clone() }.into())
+        }
+    }
+
+    pub fn declare_variable(&mut self, identifier: String, initializer: Option<Box<dyn Expression>>) -> Result<(), CompilerError> {
+        let identifier = Symbol::intern(&identifier);
+
+        if self.variable_declarations.iter().any(|declaration| declaration.identifier == identifier) {
+            return Err(CompilerErrorKind::DuplicateModuleVariable { identifier: identifier.to_string() }.into());
         }
+
+        self.variable_declarations.push(VariableDeclaration { identifier, initializer, exported: false });
+
+        Ok(())
+    }
+
+    // Resets the module level variables and runs their initializers in declaration order.
+    pub(crate) fn initialize_variables(&self, environment: &Environment) -> Result<(), RuntimeError> {
+        *self.variables.borrow_mut() = Scope::new();
+
+        for VariableDeclaration { identifier, initializer, .. } in &self.variable_declarations {
+            let value = match initializer {
+                Some(initializer) => initializer.eval(environment)?,
+                None => Value::Null,
+            };
+
+            self.variables.borrow_mut().insert_members([(*identifier, value)]);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn has_variables(&self) -> bool {
+        !self.variable_declarations.is_empty()
+    }
+
+    fn check_variable_access(&self, address: &BakedScopeAddress, private_access: bool) -> Result<(), RuntimeError> {
+        let Some(ScopeAddressant::Identifier(identifier)) = address.first() else {
+            return Err(RuntimeErrorKind::ExpectedIdentifier.into());
+        };
+
+        match self.variable_declarations.iter().find(|declaration| declaration.identifier == *identifier) {
+            Some(declaration) if declaration.exported || private_access => Ok(()),
+            Some(_) => Err(RuntimeErrorKind::NotExported { item: "Variable", identifier: identifier.to_string() }.into()),
+            None => Err(RuntimeErrorKind::NotDefined { item: "Variable", identifier: identifier.to_string() }.into()),
+        }
+    }
+
+    pub(crate) fn query_variable(&self, address: BakedScopeAddress, private_access: bool, contained_module_id: &String) -> Result<Value, RuntimeError> {
+        self.check_variable_access(&address, private_access)?;
+
+        self.variables.borrow().query_variable(address, contained_module_id)
+    }
+
+    // Returns the value that was replaced.
+    pub(crate) fn set_variable(&self, address: BakedScopeAddress, private_access: bool, contained_module_id: &String, value: Value) -> Result<Value, RuntimeError> {
+        self.check_variable_access(&address, private_access)?;
+
+        self.variables.borrow_mut().set_variable(address, contained_module_id, value)
+    }
+
+    pub fn set_alias(&mut self, alias: String, module_id: String) {
+        self.aliases.insert(alias, module_id);
+    }
+
+    // Maps a module id as written inside this module to the id it was loaded under.
+    pub fn resolve_alias<'a>(&'a self, module_id: &'a String) -> &'a String {
+        self.aliases.get(module_id).unwrap_or(module_id)
+    }
+
+    pub fn reexport_alias(&mut self, alias: String) {
+        self.reexports.insert(alias);
+    }
+
+    pub(crate) fn reexported_alias(&self, alias: &String) -> Option<&String> {
+        self.reexports.get(alias).and_then(|alias| self.aliases.get(alias))
+    }
+
+    pub(crate) fn reexported_modules(&self) -> impl Iterator<Item = &String> {
+        self.reexports.iter().filter_map(|alias| self.aliases.get(alias))
+    }
+
+    // The ids of all modules this module imports.
+    pub(crate) fn imported_modules(&self) -> impl Iterator<Item = &String> {
+        self.aliases.values()
+    }
+
+    // A copy holding only the imports, which is all that is needed to resolve addresses.
+    pub(crate) fn imports(&self) -> Module {
+        Module {
+            aliases: self.aliases.clone(),
+            reexports: self.reexports.clone(),
+            ..Module::default()
+        }
+    }
+
+    pub(crate) fn aliases_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.aliases.values_mut()
+    }
+
+    pub fn set_documentation(&mut self, identifier: String, documentation: String) {
+        self.documentation.insert(identifier, documentation);
+    }
+
+    pub fn get_documentation(&self, identifier: &String) -> Option<&String> {
+        self.documentation.get(identifier)
     }
 
     pub fn set_member_visibility(&mut self, member_ident: &String, visibility: bool) -> Result<(), CompilerError> {
@@ -67,9 +199,91 @@ impl Module {
             member.1 = visibility;
             return Ok(());
         }
+        if let Some(member) = self.variable_declarations.iter_mut().find(|declaration| declaration.identifier == *member_ident.as_str()) {
+            member.exported = visibility;
+            return Ok(());
+        }
+
+        Err(CompilerErrorKind::MemberNotFound { member: member_ident.to_string() }.into())
+    }
+
+    // The module in the form it is saved in with a compiled program. Every procedure has to be
+    // compiled, which only builtin modules violate.
+    pub(crate) fn image(&self, module_id: &str) -> Result<ModuleImage, CompilerError> {
+        let structs = self.struct_prototypes
+            .iter()
+            .map(|(identifier, (prototype, exported))| Ok(StructImage {
+                identifier: identifier.clone(),
+                struct_id: prototype.get_struct_id().clone(),
+                fields: prototype.get_members()
+                    .fields()
+                    .map(|(field, is_public, value)| Ok((field.to_string(), is_public, ConstantImage::try_from(value)?)))
+                    .collect::<Result<_, CompilerError>>()?,
+                exported: *exported,
+            }))
+            .collect::<Result<_, CompilerError>>()?;
+
+        let procedures = self.procedures
+            .iter()
+            .map(|(identifier, (procedure, exported))| {
+                let procedure = procedure.as_compiled().ok_or_else(|| CompilerError::from(CompilerErrorKind::NotSaveable { item: format!("Procedure '{}::{}', which is not compiled,", module_id, identifier) }))?;
+
+                Ok((identifier.clone(), ProcedureImage::new(procedure)?, *exported))
+            })
+            .collect::<Result<_, CompilerError>>()?;
 
-        Err(CompilerError {
-            message: format!("Member '{}' not found!", member_ident)
+        let variables = self.variable_declarations
+            .iter()
+            .map(|declaration| Ok(VariableImage {
+                identifier: declaration.identifier.to_string(),
+                initializer: declaration.initializer.as_ref().map(|initializer| initializer.image()).transpose()?,
+                exported: declaration.exported,
+            }))
+            .collect::<Result<_, CompilerError>>()?;
+
+        Ok(ModuleImage {
+            structs,
+            private_constructors: self.private_constructors.iter().cloned().collect(),
+            procedures,
+            documentation: self.documentation.clone().into_iter().collect(),
+            aliases: self.aliases.clone().into_iter().collect(),
+            reexports: self.reexports.iter().cloned().collect(),
+            variables,
         })
     }
+
+    pub(crate) fn from_image(image: ModuleImage) -> Result<Module, CompilerError> {
+        let mut module = Module::default();
+
+        for StructImage { identifier, struct_id, fields, exported } in image.structs {
+            let mut prototype = Struct::new(struct_id);
+
+            for (field, is_public, value) in fields {
+                prototype.get_members_mut().insert_member(field.into(), value.into(), is_public).map_err(|err| CompilerError::from(CompilerErrorKind::InvalidImage { reason: err.to_string() }))?;
+            }
+
+            module.insert_struct(identifier, prototype, exported);
+        }
+
+        for (identifier, procedure, exported) in image.procedures {
+            module.insert_procedure(identifier, Box::new(procedure.load()?), exported);
+        }
+
+        for mut variable in image.variables {
+            let initializer = variable.load_initializer()?;
+
+            module.variable_declarations.push(VariableDeclaration {
+                identifier: Symbol::intern(&variable.identifier),
+                initializer,
+                exported: variable.exported,
+            });
+        }
+
+        module.private_constructors = image.private_constructors.into_iter().collect();
+        module.documentation = image.documentation.into_iter().collect();
+        module.aliases = image.aliases.into_iter().collect();
+        module.reexports = image.reexports.into_iter().collect();
+
+        Ok(module)
+    }
 }
@@ -1,21 +1,36 @@
-use std::{any::Any, collections::HashMap};
+use std::{cell::OnceCell, collections::HashMap, time::Instant};
 
-use crate::{compiler::{CompilerError, expression_parser::ExpressionParser}, lexer::token::{KeywordToken, OperatorToken, ParenthesisType, PunctuationToken, Token}, runtime::{
-    Environment, Expression, RuntimeError, scope::ScopeAddress, ScopeAddressant, Value, expressions::boolean::NotExpression,
+use serde::{Deserialize, Serialize};
+
+use crate::{compiler::{CompilerError, CompilerErrorKind, ast::{self, Span, Statement, StatementKind}, expression_parser::ExpressionParser, lowering::ProcedureLowering}, lexer::token::{KeywordToken, OperatorToken, ParenthesisType, PrimitiveTypeToken, PunctuationToken, SourceSpan, Token}, runtime::{
+    Environment, Expression, Position, RuntimeError, RuntimeErrorKind, bytecode::Chunk, scope::ScopeAddress, ScopeAddressant, Value, symbol::Symbol, trace::TraceEvent,
 }};
 
 pub trait Procedure: std::fmt::Debug {
     fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError>;
+
+    fn as_compiled(&self) -> Option<&CompiledProcedure> {
+        None
+    }
+
+    fn as_compiled_mut(&mut self) -> Option<&mut CompiledProcedure> {
+        None
+    }
+
+    // Whether errors raised by the procedure should name the call that failed.
+    fn includes_call_site(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug)]
 pub enum Instruction {
     //TODO: Remove public viisibility
     PushVarToScope {
-        identifier: String,
+        identifier: Symbol,
     },
     PopVarFromScope {
-        identifier: String,
+        identifier: Symbol,
     },
     GrowStack,
     ShrinkStack,
@@ -30,13 +45,71 @@ pub enum Instruction {
     Return {
         expression: Box<dyn Expression>,
     },
+    Try {
+        catch_target: usize,
+    },
+    EndTry,
+    Catch {
+        identifier: Symbol,
+    },
+    Throw {
+        expression: Box<dyn Expression>,
+    },
+}
+
+impl Instruction {
+    // Jumps emitted with a constant 'true' condition never fall through to the next instruction.
+    pub(crate) fn is_unconditional_jump(&self) -> bool {
+        match self {
+            Instruction::JumpConditional { condition_expression, .. } => condition_expression.summary() == "true",
+            _ => false,
+        }
+    }
+}
+
+enum ControlFlow {
+    Next,
+    Jump(usize),
+    Return(Value),
+}
+
+// Evaluates the expression of an instruction, by its bytecode if it could be compiled.
+struct Evaluator<'a> {
+    chunk: Option<&'a Chunk>,
+    stack: &'a mut Vec<Value>,
+}
+
+impl Evaluator<'_> {
+    fn evaluate(self, expression: &dyn Expression, environment: &Environment) -> Result<Value, RuntimeError> {
+        match self.chunk {
+            Some(chunk) => chunk.run(environment, self.stack),
+            None => expression.eval(environment),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct CompiledProcedure {
     //TODO: Remove public visibility
-    pub arguments_identifiers: Vec<String>,
+    pub arguments_identifiers: Vec<Symbol>,
+    pub argument_types: Vec<Option<PrimitiveTypeToken>>,
+    pub return_type: Option<PrimitiveTypeToken>,
     pub instructions: Vec<Instruction>,
+    pub budget: Option<CallBudget>,
+    // The expression of every instruction as bytecode, compiled on the first call. Instructions
+    // whose expression cannot be compiled evaluate it as a tree.
+    pub(crate) bytecode: OnceCell<Vec<Option<Chunk>>>,
+    // The file the procedure was read from and the span of the statement of every instruction,
+    // for the stack traces of errors. Empty for procedures built without their source.
+    pub(crate) file: Option<Symbol>,
+    pub(crate) spans: Vec<SourceSpan>,
+}
+
+// Limits set with '@budget' for a single call, counting the calls it makes as well.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CallBudget {
+    pub instructions: Option<u64>,
+    pub allocations: Option<u64>,
 }
 
 impl Procedure for CompiledProcedure {
@@ -45,136 +118,293 @@ impl Procedure for CompiledProcedure {
         mut environment: Environment,
         arguments: Vec<Value>,
     ) -> Result<Value, RuntimeError> {
-        let members = HashMap::from_iter(
-            self.arguments_identifiers
-                .clone()
-                .into_iter()
-                .zip(arguments.into_iter()),
-        );
+        for ((identifier, expected_type), argument) in self.arguments_identifiers.iter()
+            .zip(self.argument_types.iter())
+            .zip(arguments.iter())
+        {
+            if let Some(expected_type) = expected_type {
+                if !argument.is_of_type(expected_type) {
+                    return Err(RuntimeErrorKind::TypeMismatch {
+                        argument: Some(identifier.to_string()),
+                        expected: expected_type.clone(),
+                        found: argument.get_type_id().to_string(),
+                    }.into());
+                }
+            }
+        }
 
-        environment.insert_members(members);
+        environment.insert_members(self.arguments_identifiers.iter().cloned().zip(arguments));
 
-        let mut pc = 0;
+        let previous_budget = self.budget.as_ref().map(|budget| environment.context.enter_budget(budget));
 
-        while pc < self.instructions.len() {
-            match &self.instructions[pc] {
-                Instruction::PushVarToScope { identifier } => {
-                    environment.scope.push(identifier.clone())?;
-                }
-                Instruction::PopVarFromScope { identifier } => {
-                    environment.scope.pop(identifier)?;
-                }
-                Instruction::GrowStack => {
-                    environment.scope.grow_stack();
-                }
-                Instruction::ShrinkStack => {
-                    environment.scope.shrink_stack();
-                }
-                Instruction::EvaluateExpression { expression, target } => {
-                    let eval_result = expression.eval(&environment)?;
+        let result = self.run(&mut environment);
 
-                    if let Some(target) = target {
-                        environment.set_variable(target.clone(), eval_result)?;
-                    }
-                }
-                Instruction::JumpConditional {
-                    condition_expression: procedure,
-                    jump_target,
-                } => {
-                    let returned_value = procedure.eval(&mut environment)?;
-
-                    match returned_value {
-                        Value::Bool(value) => {
-                            if value {
-                                pc = *jump_target;
-                                continue;
-                            }
-                        }
-                        _ => {
-                            return Err(RuntimeError {
-                                message: format!(
-                                    "Expected Bool, found {}!",
-                                    returned_value.get_type_id()
-                                ),
-                            })
-                        }
-                    }
-                }
-                Instruction::Return {
-                    expression: procedure,
-                } => return procedure.eval(&mut environment),
-            }
+        if let Some(previous_budget) = previous_budget {
+            environment.context.exit_budget(previous_budget);
+        }
+
+        environment.release_scope();
 
-            pc += 1;
+        let result = result?;
+
+        if let Some(return_type) = &self.return_type {
+            if !result.is_of_type(return_type) {
+                return Err(RuntimeErrorKind::TypeMismatch {
+                    argument: None,
+                    expected: return_type.clone(),
+                    found: result.get_type_id().to_string(),
+                }.into());
+            }
         }
 
-        Ok(Value::Null)
+        Ok(result)
+    }
+
+    fn as_compiled(&self) -> Option<&CompiledProcedure> {
+        Some(self)
+    }
+
+    fn as_compiled_mut(&mut self) -> Option<&mut CompiledProcedure> {
+        // The instructions may be changed, so they are compiled again on the next call.
+        self.bytecode.take();
+
+        Some(self)
     }
 }
 
+impl CompiledProcedure {
+    pub fn disassemble(&self) -> String {
+        let labels = self.labels();
 
+        let arguments: Vec<String> = self.arguments_identifiers
+            .iter()
+            .zip(self.argument_types.iter())
+            .map(|(identifier, argument_type)| match argument_type {
+                Some(argument_type) => format!("{}: {:?}", identifier, argument_type),
+                None => identifier.to_string(),
+            })
+            .collect();
 
-trait ScopeExcapeHandler: std::fmt::Debug {
-    fn resolve(&self, instructions: &mut Vec<Instruction>);
+        let mut out = format!("({})", arguments.join(", "));
+        if let Some(return_type) = &self.return_type {
+            out.push_str(&format!(" -> {:?}", return_type));
+        }
+        out.push('\n');
 
-    fn as_any(&self) -> &dyn Any;
-}
+        for (pc, instruction) in self.instructions.iter().enumerate() {
+            if let Some(label) = labels.get(&pc) {
+                out.push_str(&format!("{}:\n", label));
+            }
+            out.push_str(&format!("{:>6}  {}\n", pc, Self::format_instruction(instruction, &labels)));
+        }
 
-#[derive(Debug)]
-struct IfScopeEscapeHandler {
-    target_instruction: usize,
-}
+        out
+    }
 
-impl ScopeExcapeHandler for IfScopeEscapeHandler {
-    fn resolve(&self, instructions: &mut Vec<Instruction>) {
-        instructions.push(Instruction::ShrinkStack);
+    pub fn disassemble_instruction(&self, pc: usize) -> Option<String> {
+        let instruction = self.instructions.get(pc)?;
 
-        let next_ic = instructions.len();
+        Some(Self::format_instruction(instruction, &self.labels()))
+    }
 
-        if let Some(Instruction::JumpConditional {
-            condition_expression: _,
-            jump_target 
-        }) = instructions.get_mut(self.target_instruction) {
-            *jump_target = next_ic;
-        } else {
-            panic!("Tried resolving if scope escape but initial jump is missing!");
+    fn labels(&self) -> HashMap<usize, String> {
+        let mut targets: Vec<usize> = self.instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::JumpConditional { jump_target, .. } => Some(*jump_target),
+                Instruction::Try { catch_target } => Some(*catch_target),
+                _ => None,
+            })
+            .collect();
+
+        targets.sort();
+        targets.dedup();
+
+        targets
+            .into_iter()
+            .enumerate()
+            .map(|(i, target)| (target, format!("L{}", i)))
+            .collect()
+    }
+
+    fn format_instruction(instruction: &Instruction, labels: &HashMap<usize, String>) -> String {
+        match instruction {
+            Instruction::PushVarToScope { identifier } => format!("let {}", identifier),
+            Instruction::PopVarFromScope { identifier } => format!("drop {}", identifier),
+            Instruction::GrowStack => "grow".into(),
+            Instruction::ShrinkStack => "shrink".into(),
+            Instruction::EvaluateExpression { expression, target: Some(target) } => {
+                format!("{} = {}", target.summary(), expression.summary())
+            }
+            Instruction::EvaluateExpression { expression, target: None } => {
+                format!("eval {}", expression.summary())
+            }
+            Instruction::JumpConditional { condition_expression, jump_target } => {
+                let label = labels.get(jump_target).cloned().unwrap_or_else(|| jump_target.to_string());
+
+                if instruction.is_unconditional_jump() {
+                    format!("jump {}", label)
+                } else {
+                    format!("jump {} if {}", label, condition_expression.summary())
+                }
+            }
+            Instruction::Return { expression } => format!("return {}", expression.summary()),
+            Instruction::Try { catch_target } => {
+                format!("try catch {}", labels.get(catch_target).cloned().unwrap_or_else(|| catch_target.to_string()))
+            }
+            Instruction::EndTry => "end try".into(),
+            Instruction::Catch { identifier } => format!("catch {}", identifier),
+            Instruction::Throw { expression } => format!("throw {}", expression.summary()),
         }
     }
-    
-    fn as_any(&self) -> &dyn Any {
-        self
+
+    fn compile_bytecode(&self) -> Vec<Option<Chunk>> {
+        self.instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::EvaluateExpression { expression, .. }
+                | Instruction::JumpConditional { condition_expression: expression, .. }
+                | Instruction::Return { expression }
+                | Instruction::Throw { expression } => Chunk::compile(expression.as_ref()).ok(),
+                _ => None,
+            })
+            .collect()
     }
-}
 
-#[derive(Debug)]
-struct WhileScopeEscapeHandler {
-    target_instruction: usize,
-}
+    fn run(&self, environment: &mut Environment) -> Result<Value, RuntimeError> {
+        let bytecode = self.bytecode.get_or_init(|| self.compile_bytecode());
+        let mut stack = Vec::new();
 
-impl ScopeExcapeHandler for WhileScopeEscapeHandler {
-    fn resolve(&self, instructions: &mut Vec<Instruction>) {
-        instructions.push(Instruction::ShrinkStack);
-        instructions.push(Instruction::JumpConditional {
-            condition_expression: Box::new(Value::Bool(true)),
-            jump_target: self.target_instruction
-        });
-        let next_ic = instructions.len();
-        if let Some(Instruction::JumpConditional {
-            condition_expression: _,
-            jump_target 
-        }) = instructions.get_mut(self.target_instruction) {
-            
-            *jump_target = next_ic;
-        } else {
-            panic!("Tried resolving if scope escape but initial jump is missing!");
+        let mut pc = 0;
+
+        // The catch target and stack depth of every enclosing try block.
+        let mut handlers: Vec<(usize, usize)> = Vec::new();
+        let mut caught = None;
+
+        while pc < self.instructions.len() {
+            // Checked outside of the instruction so that running out of budget or memory cannot
+            // be caught within the call.
+            environment.context.check_budget().map_err(|err| self.locate(err, pc))?;
+            environment.context.check_memory_limit().map_err(|err| self.locate(err, pc))?;
+
+            environment.context.stats.record_instruction();
+            environment.context.record(|| TraceEvent::Instruction { pc });
+
+            let started = environment.context.is_profiling().then(Instant::now);
+            let flow = self.execute(pc, environment, Evaluator { chunk: bytecode[pc].as_ref(), stack: &mut stack }, &mut handlers, &mut caught);
+            if let Some(started) = started {
+                environment.context.profile(|profile| profile.instruction(pc, started));
+            }
+
+            match flow {
+                Ok(ControlFlow::Next) => pc += 1,
+                Ok(ControlFlow::Jump(target)) => pc = target,
+                Ok(ControlFlow::Return(value)) => return Ok(value),
+                Err(err) => {
+                    if environment.context.exit_code().is_some() {
+                        return Err(err);
+                    }
+
+                    let Some((catch_target, depth)) = handlers.pop() else {
+                        return Err(self.locate(err, pc));
+                    };
+
+                    environment.unwind_stack(depth);
+                    caught = Some(environment.context.catch(err));
+                    pc = catch_target;
+                }
+            }
         }
+
+        Ok(Value::Null)
     }
-    
-    fn as_any(&self) -> &dyn Any {
-        self
+
+    // Records the instruction an error leaves the procedure at for its stack trace.
+    fn locate(&self, err: RuntimeError, pc: usize) -> RuntimeError {
+        match self.spans.get(pc) {
+            Some(span) => err.at_position(Position { file: self.file, span: *span }),
+            None => err,
+        }
+    }
+
+    fn execute(
+        &self,
+        pc: usize,
+        environment: &mut Environment,
+        evaluator: Evaluator,
+        handlers: &mut Vec<(usize, usize)>,
+        caught: &mut Option<Value>,
+    ) -> Result<ControlFlow, RuntimeError> {
+        match &self.instructions[pc] {
+            Instruction::PushVarToScope { identifier } => {
+                environment.declare_variable(*identifier)?;
+            }
+            Instruction::PopVarFromScope { identifier } => {
+                environment.pop_variable(*identifier)?;
+            }
+            Instruction::GrowStack => {
+                environment.grow_stack();
+            }
+            Instruction::ShrinkStack => {
+                environment.shrink_stack();
+            }
+            Instruction::EvaluateExpression { expression, target } => {
+                if let (Some(target), Some(source)) = (target, expression.as_variable()) {
+                    environment.check_store(target, source)?;
+                }
+
+                let eval_result = evaluator.evaluate(expression.as_ref(), environment)?;
+
+                if let Some(target) = target {
+                    environment.set_variable(target.clone(), eval_result)?;
+                }
+            }
+            Instruction::JumpConditional {
+                condition_expression: procedure,
+                jump_target,
+            } => {
+                let returned_value = evaluator.evaluate(procedure.as_ref(), environment)?;
+
+                match returned_value {
+                    Value::Bool(value) => {
+                        if value {
+                            return Ok(ControlFlow::Jump(*jump_target));
+                        }
+                    }
+                    _ => {
+                        return Err(RuntimeErrorKind::ExpectedType { expected: PrimitiveTypeToken::Boolean, found: returned_value.get_type_id() }.into())
+                    }
+                }
+            }
+            Instruction::Return {
+                expression: procedure,
+            } => return Ok(ControlFlow::Return(evaluator.evaluate(procedure.as_ref(), environment)?)),
+            Instruction::Try { catch_target } => {
+                handlers.push((*catch_target, environment.stack_depth()));
+            }
+            Instruction::EndTry => {
+                handlers.pop();
+            }
+            Instruction::Catch { identifier } => {
+                environment.declare_variable(*identifier)?;
+                environment.set_variable(
+                    vec![ScopeAddressant::Identifier(*identifier)].try_into().unwrap(),
+                    caught.take().unwrap_or(Value::Null)
+                )?;
+            }
+            Instruction::Throw { expression } => {
+                let value = evaluator.evaluate(expression.as_ref(), environment)?;
+
+                return Err(environment.context.throw(value));
+            }
+        }
+
+        Ok(ControlFlow::Next)
     }
 }
 
+
+
 #[derive(Debug)]
 enum CompiledProcedureBuilderState {
     Base,
@@ -190,36 +420,91 @@ enum CompiledProcedureBuilderState {
         condition_expression: Vec<Token>,
         parenthesis_index: usize,
     },
+    // Extends the if statement, which was taken back out of its block.
     ElseStatement {
-        original_jump: usize,
+        condition: ast::Expression,
+        body: Vec<Statement>,
+        start: usize,
     },
     WhileStatement {
         condition_expression: Vec<Token>,
         parenthesis_index: usize,
     },
+    LoopStatement,
+    ForeachStatement {
+        item: Option<String>,
+        collection: Option<Vec<Token>>,
+    },
+    Break,
     Indeterminate {
         tokens: Vec<Token>,
     },
     Return {
         expression: Vec<Token>,
-    }
+    },
+    TryStatement,
+    CatchStatement {
+        try_block: OpenBlock,
+        tokens: Vec<Token>,
+    },
+    Throw {
+        expression: Vec<Token>,
+    },
 }
 
+// A block whose closing brace was not read yet, with the statements read within it so far.
+#[derive(Debug)]
+struct OpenBlock {
+    kind: BlockKind,
+    start: usize,
+    statements: Vec<Statement>,
+}
+
+#[derive(Debug)]
+enum BlockKind {
+    If { condition: ast::Expression },
+    Else { condition: ast::Expression, body: Vec<Statement> },
+    While { condition: ast::Expression },
+    Loop,
+    For { item: Symbol, collection: ast::Expression },
+    Try,
+    Catch { body: Vec<Statement>, identifier: Symbol },
+}
+
+// Reads the tokens of a procedure body into its syntax tree, statement by statement. The
+// instructions are only emitted by 'build', once the whole body was read.
 #[derive(Debug)]
 pub struct CompiledProcedureBuilder {
     procedure: CompiledProcedure,
     state: CompiledProcedureBuilderState,
-    scope_stack: Vec<Box<dyn ScopeExcapeHandler + 'static>>,
-    last_popped_scope: Option<Box<dyn ScopeExcapeHandler + 'static>>,
+    body: Vec<Statement>,
+    blocks: Vec<OpenBlock>,
+    // Set after a try block is closed, as the next token has to start its catch clause.
+    pending_try: Option<OpenBlock>,
+    // Whether if and while conditions have to evaluate to Booleans.
+    strict_conditions: bool,
+    // Whether a trailing expression statement becomes the return value.
+    implicit_result: bool,
+    // The position of the next token in the body, and of the first one of the current statement.
+    position: usize,
+    statement_start: usize,
+    // The span of every token of the body read so far, by position.
+    token_spans: Vec<SourceSpan>,
 }
 
 impl CompiledProcedureBuilder {
     pub fn new() -> Self {
         Self {
-            procedure: CompiledProcedure { arguments_identifiers: Vec::new(), instructions: Vec::new() },
+            procedure: CompiledProcedure { arguments_identifiers: Vec::new(), argument_types: Vec::new(), return_type: None, instructions: Vec::new(), budget: None, bytecode: OnceCell::new(), file: None, spans: Vec::new() },
             state: CompiledProcedureBuilderState::Base,
-            scope_stack: Vec::new(),
-            last_popped_scope: None,
+            body: Vec::new(),
+            blocks: Vec::new(),
+            pending_try: None,
+            strict_conditions: true,
+            implicit_result: false,
+            position: 0,
+            statement_start: 0,
+            token_spans: Vec::new(),
         }
     }
 
@@ -232,15 +517,60 @@ impl CompiledProcedureBuilder {
     }
 
     pub fn push_argument_identifier(mut self, ident: String) -> Self {
-        self.procedure.arguments_identifiers.push(ident);
+        self.procedure.arguments_identifiers.push(ident.into());
+        self.procedure.argument_types.push(None);
+        self
+    }
+
+    pub fn set_argument_type(mut self, argument_type: PrimitiveTypeToken) -> Self {
+        if let Some(last) = self.procedure.argument_types.last_mut() {
+            *last = Some(argument_type);
+        }
+        self
+    }
+
+    pub fn with_strict_conditions(mut self, strict_conditions: bool) -> Self {
+        self.strict_conditions = strict_conditions;
+        self
+    }
+
+    pub fn with_implicit_result(mut self, implicit_result: bool) -> Self {
+        self.implicit_result = implicit_result;
+        self
+    }
+
+    pub fn with_file(mut self, file: Option<&String>) -> Self {
+        self.procedure.file = file.map(Symbol::from);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: CallBudget) -> Self {
+        self.procedure.budget = Some(budget);
+        self
+    }
+
+    pub fn set_return_type(mut self, return_type: PrimitiveTypeToken) -> Self {
+        self.procedure.return_type = Some(return_type);
         self
     }
 
     pub fn scope_stack_size(&self) -> usize {
-        self.scope_stack.len()
+        self.blocks.len()
     }
 
-    pub fn read(mut self, token: Token) -> Result<Self, CompilerError> {
+    pub fn read(mut self, token: Token, span: SourceSpan) -> Result<Self, CompilerError> {
+        let position = self.position;
+        self.position += 1;
+        self.token_spans.push(span);
+
+        if let Some(try_block) = self.pending_try.take() {
+            if let Token::Keyword(KeywordToken::Catch) = token {
+                self.state = CompiledProcedureBuilderState::CatchStatement { try_block, tokens: Vec::new() };
+                return Ok(self);
+            }
+
+            return Err(CompilerError::unexpected_token("'catch'", token));
+        }
 
         if let Token::Punctuation(PunctuationToken::Semicolon) = token {
             return self.finish_current_instruction()
@@ -249,6 +579,8 @@ impl CompiledProcedureBuilder {
         use CompiledProcedureBuilderState::*;
         match &mut self.state {
             Base => {
+                self.statement_start = position;
+
                 match token {
                     Token::Keyword(KeywordToken::Let) => {
                         self.state = VarDeclaration { ident: None, expression: None }
@@ -257,37 +589,53 @@ impl CompiledProcedureBuilder {
                         self.state = IfStatement { condition_expression: Vec::new(), parenthesis_index: 0 }
                     }
                     Token::Keyword(KeywordToken::Else) => {
-                        let last_scope = self.last_popped_scope.as_ref()
-                            .ok_or(CompilerError {
-                                message: "Missing if-clause!".into()
-                            })?;
-                        
-                        let if_clause = last_scope.as_any()
-                            .downcast_ref::<IfScopeEscapeHandler>().ok_or(CompilerError {
-                                message: "else-clauses can only extend 'if' clauses!".into()
-                            })?;
-                        
-                        self.state = ElseStatement { original_jump: if_clause.target_instruction };
+                        let statements = self.statements_mut();
+
+                        match statements.pop() {
+                            Some(Statement { kind: StatementKind::If { condition, body, otherwise: None }, span }) => {
+                                self.state = ElseStatement { condition, body, start: span.start };
+                            }
+                            Some(other) => {
+                                statements.push(other);
+
+                                return Err(CompilerErrorKind::MisplacedElse.into());
+                            }
+                            None => {
+                                return Err(CompilerErrorKind::MisplacedElse.into());
+                            }
+                        }
                     }
                     Token::Keyword(KeywordToken::While) => {
                         self.state = WhileStatement { condition_expression: Vec::new(), parenthesis_index: 0 }
                     }
+                    Token::Keyword(KeywordToken::Loop) => {
+                        self.state = LoopStatement
+                    }
+                    Token::Keyword(KeywordToken::For) => {
+                        self.state = ForeachStatement { item: None, collection: None }
+                    }
+                    Token::Keyword(KeywordToken::Break) => {
+                        self.state = Break
+                    }
                     Token::Keyword(KeywordToken::Return) => {
                         self.state = Return { expression: Vec::new() }
                     }
+                    Token::Keyword(KeywordToken::Try) => {
+                        self.state = TryStatement
+                    }
+                    Token::Keyword(KeywordToken::Catch) => {
+                        return Err(CompilerErrorKind::MisplacedCatch.into());
+                    }
+                    Token::Keyword(KeywordToken::Throw) => {
+                        self.state = Throw { expression: Vec::new() }
+                    }
 
                     Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Closing)) => {
-                        let handler = self.scope_stack
+                        let block = self.blocks
                             .pop()
-                            .ok_or(CompilerError {
-                                message: "Invalid closing curly brace!".into()
-                            })?;
-                        
-                        handler.resolve(&mut self.procedure.instructions);
+                            .ok_or_else(|| CompilerError::from(CompilerErrorKind::UnexpectedClosingBrace))?;
 
-                        
-                        
-                        self.last_popped_scope = Some(handler);
+                        self.close_block(block);
                     }
 
                     other => {
@@ -300,9 +648,7 @@ impl CompiledProcedureBuilder {
                     if let Token::Identifier(ident) = token {
                         self.state = VarDeclaration { ident: Some(ident), expression: expression.take() }
                     } else {
-                        return Err(CompilerError {
-                            message: format!("Unexprected token. Expected identifier, found {:?}!", token)
-                        });
+                        return Err(CompilerError::unexpected_token("identifier", token));
                     }
                 } else {
                     if let Some(expr) = expression {
@@ -311,14 +657,12 @@ impl CompiledProcedureBuilder {
                         if let Token::Operator(OperatorToken::Assignment) = token {
                             self.state = VarDeclaration { ident: ident.take(), expression: Some(Vec::new()) }
                         } else {
-                            return Err(CompilerError {
-                                message: format!("Unexprected token. Expected '=', found {:?}!", token)
-                            });
+                            return Err(CompilerError::unexpected_token("'='", token));
                         }
                     }
                 }
             },
-            Assignment { address, expression } => {
+            Assignment { address: _, expression } => {
                 expression.push(token);
             },
             IfStatement { condition_expression, parenthesis_index } => {
@@ -328,7 +672,7 @@ impl CompiledProcedureBuilder {
                         ParenthesisType::Closing => if *parenthesis_index > 0 {
                             *parenthesis_index -= 1
                         } else {
-                            return Err(CompilerError { message: "Invalid parenthesis structure!".into() })
+                            return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets))
                         },
                     }
                 }
@@ -341,16 +685,14 @@ impl CompiledProcedureBuilder {
 
                 condition_expression.push(token);
             },
-            ElseStatement { original_jump: _ } => {
+            ElseStatement { .. } => {
                 match token {
                     Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) => {
                         return self.finish_current_instruction();
                     }
 
                     other => {
-                        return Err(CompilerError {
-                            message: format!("Unexpected token. Expected '{{', found {:?}!", other)
-                        });
+                        return Err(CompilerError::unexpected_token("'{'", other));
                     }
                 }
             }
@@ -361,7 +703,7 @@ impl CompiledProcedureBuilder {
                         ParenthesisType::Closing => if *parenthesis_index > 0 {
                             *parenthesis_index -= 1
                         } else {
-                            return Err(CompilerError { message: "Invalid parenthesis structure!".into() })
+                            return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets))
                         },
                     }
                 }
@@ -374,6 +716,45 @@ impl CompiledProcedureBuilder {
 
                 condition_expression.push(token);
             },
+            LoopStatement => {
+                match token {
+                    Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) => {
+                        return self.finish_current_instruction();
+                    }
+
+                    other => {
+                        return Err(CompilerError::unexpected_token("'{'", other));
+                    }
+                }
+            }
+            ForeachStatement { item, collection } => {
+                if item.is_none() {
+                    match token {
+                        Token::Identifier(ident) => *item = Some(ident),
+                        other => {
+                            return Err(CompilerError::unexpected_token("identifier", other));
+                        }
+                    }
+                } else if let Some(tokens) = collection {
+                    if let Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) = token {
+                        if ExpressionParser::unclosed_brackets(tokens.iter())? == 0 {
+                            return self.finish_current_instruction();
+                        }
+                    }
+
+                    tokens.push(token);
+                } else {
+                    match token {
+                        Token::Keyword(KeywordToken::In) => *collection = Some(Vec::new()),
+                        other => {
+                            return Err(CompilerError::unexpected_token("'in'", other));
+                        }
+                    }
+                }
+            }
+            Break => {
+                return Err(CompilerError::unexpected_token("';'", token));
+            }
             Indeterminate { tokens } => {
                 match token {
                     Token::Operator(OperatorToken::Assignment) => {
@@ -388,147 +769,217 @@ impl CompiledProcedureBuilder {
             Return { expression } => {
                 expression.push(token);
             },
+            TryStatement => {
+                match token {
+                    Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) => {
+                        return self.finish_current_instruction();
+                    }
+
+                    other => {
+                        return Err(CompilerError::unexpected_token("'{'", other));
+                    }
+                }
+            }
+            CatchStatement { try_block: _, tokens } => {
+                if let Token::Punctuation(PunctuationToken::CurlyBraces(ParenthesisType::Opening)) = token {
+                    return self.finish_current_instruction();
+                }
+
+                tokens.push(token);
+            },
+            Throw { expression } => {
+                expression.push(token);
+            },
         }
 
 
         Ok(self)
     }
 
+    // The statements of the innermost open block.
+    fn statements_mut(&mut self) -> &mut Vec<Statement> {
+        match self.blocks.last_mut() {
+            Some(block) => &mut block.statements,
+            None => &mut self.body,
+        }
+    }
+
+    fn push_statement(&mut self, kind: StatementKind, span: Span) {
+        self.statements_mut().push(Statement { kind, span });
+    }
+
+    fn open_block(&mut self, kind: BlockKind, start: usize) {
+        self.blocks.push(OpenBlock { kind, start, statements: Vec::new() });
+    }
+
+    // Called on the closing brace of the block, which the span of its statement ends with.
+    fn close_block(&mut self, block: OpenBlock) {
+        let span = Span::new(block.start, self.position);
+
+        let kind = match block.kind {
+            BlockKind::If { condition } => StatementKind::If { condition, body: block.statements, otherwise: None },
+            BlockKind::Else { condition, body } => StatementKind::If { condition, body, otherwise: Some(block.statements) },
+            BlockKind::While { condition } => StatementKind::While { condition, body: block.statements },
+            BlockKind::Loop => StatementKind::Loop { body: block.statements },
+            BlockKind::For { item, collection } => StatementKind::For { item, collection, body: block.statements },
+            BlockKind::Try => {
+                self.pending_try = Some(block);
+                return;
+            }
+            BlockKind::Catch { body, identifier } => StatementKind::Try { body, identifier, handler: block.statements },
+        };
+
+        self.push_statement(kind, span);
+    }
+
+    // Called on the semicolon ending the current statement, or on the opening brace of its block.
     fn finish_current_instruction(mut self) -> Result<Self, CompilerError> {
-        match &mut self.state {
+        let start = self.statement_start;
+        let span = Span::new(start, self.position - 1);
+
+        match std::mem::replace(&mut self.state, CompiledProcedureBuilderState::Base) {
             CompiledProcedureBuilderState::Base => {
             },
             CompiledProcedureBuilderState::VarDeclaration { ident, expression } => {
-                let ident = ident.clone().ok_or(CompilerError {
-                    message: "Missing variable identifier!".into()
-                })?;
-                self.procedure.instructions.push(
-                    Instruction::PushVarToScope { identifier: ident.clone() }
-                );
-                if let Some(expression) = expression {
-                    let expression = ExpressionParser::parse(expression.to_owned())?;
-
-                    self.procedure.instructions.push(
-                        Instruction::EvaluateExpression { expression, target: Some(vec![
-                            ScopeAddressant::Identifier(ident)
-                        ].try_into().unwrap()) }
-                    )
-                }
+                let identifier = Symbol::intern(&ident.ok_or_else(|| CompilerError::from(CompilerErrorKind::MissingName { item: "variable" }))?);
+
+                let value = expression
+                    .map(|expression| ExpressionParser::parse_at(expression, start + 3))
+                    .transpose()?;
+
+                self.push_statement(StatementKind::Let { identifier, value }, span);
             },
             CompiledProcedureBuilderState::Assignment { address, expression } => {
-                let target = Some(ScopeAddress::try_from(address.to_owned())?);
+                let value_start = start + address.len() + 1;
+
+                // Module variables, e.g. 'Main::count = Main::count + 1;'
+                if let [Token::Identifier(module_id), Token::Punctuation(PunctuationToken::DoubleColon), ..] = address.as_slice() {
+                    let module_id = module_id.clone();
+                    let target = ExpressionParser::parse_address(address.into_iter().skip(2), start + 2)?;
+                    let value = ExpressionParser::parse_at(expression, value_start)?;
 
-                let expression = ExpressionParser::parse(expression.to_owned())?;
+                    self.push_statement(StatementKind::AssignModuleVariable { module_id, target, value }, span);
+                } else {
+                    let target = ExpressionParser::parse_address(address, start)?;
+                    let value = ExpressionParser::parse_at(expression, value_start)?;
 
-                self.procedure.instructions.push(Instruction::EvaluateExpression { expression, target });
+                    self.push_statement(StatementKind::Assign { target, value }, span);
+                }
             },
             CompiledProcedureBuilderState::IfStatement { condition_expression, parenthesis_index } => {
-                if *parenthesis_index > 0 {
-                    return Err(CompilerError {
-                        message: "Invalid parenthesis structure!".into()
-                     });
+                if parenthesis_index > 0 {
+                    return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets));
                 }
 
-                let condition_expression = Box::new(NotExpression::new(
-                    ExpressionParser::parse(condition_expression.to_owned())?
-                ));
-
-                self.scope_stack.push(
-                    Box::new(IfScopeEscapeHandler { target_instruction: self.procedure.instructions.len() })
-                );
-                
-                self.procedure.instructions.push(
-                    Instruction::JumpConditional { condition_expression, jump_target: usize::MAX }
-                );
-                self.procedure.instructions.push(
-                    Instruction::GrowStack
-                );
-            },
-            CompiledProcedureBuilderState::ElseStatement { original_jump } => {
-                let instruction = &mut self.procedure.instructions[*original_jump];
-
-                match instruction {
-                    Instruction::JumpConditional { condition_expression: _, jump_target } => {
-                        *jump_target += 1;
-
-                        self.scope_stack.push(
-                            Box::new(IfScopeEscapeHandler { target_instruction: self.procedure.instructions.len() })
-                        );
-
-                        self.procedure.instructions.push(Instruction::JumpConditional {
-                            condition_expression: Box::new(Value::Bool(true)),
-                            jump_target: usize::MAX
-                        });
+                let condition = ExpressionParser::parse_at(condition_expression, start + 1)?;
 
-                        self.procedure.instructions.push(
-                            Instruction::GrowStack
-                        );
-                    }
-
-                    _ => {
-                        return Err(CompilerError {
-                            message: "Instruction referenced by 'if' scope handler is not of type JumpConditional!".into()
-                        })
-                    }
-                }
+                self.open_block(BlockKind::If { condition }, start);
+            },
+            CompiledProcedureBuilderState::ElseStatement { condition, body, start } => {
+                self.open_block(BlockKind::Else { condition, body }, start);
             }
             CompiledProcedureBuilderState::WhileStatement { condition_expression, parenthesis_index } => {
-                if *parenthesis_index > 0 {
-                    return Err(CompilerError {
-                        message: "Invalid parenthesis structure!".into()
-                     });
+                if parenthesis_index > 0 {
+                    return Err(CompilerError::from(CompilerErrorKind::UnbalancedBrackets));
                 }
 
-                let condition_expression = Box::new(NotExpression::new(
-                    ExpressionParser::parse(condition_expression.to_owned())?
-                ));
-
-                
-                self.scope_stack.push(
-                    Box::new(WhileScopeEscapeHandler { target_instruction: self.procedure.instructions.len() })
-                );
-                
-                self.procedure.instructions.push(
-                    Instruction::JumpConditional { condition_expression, jump_target: usize::MAX }
-                );
-                self.procedure.instructions.push(Instruction::GrowStack);
+                let condition = ExpressionParser::parse_at(condition_expression, start + 1)?;
+
+                self.open_block(BlockKind::While { condition }, start);
+            },
+            CompiledProcedureBuilderState::LoopStatement => {
+                self.open_block(BlockKind::Loop, start);
+            },
+            CompiledProcedureBuilderState::ForeachStatement { item, collection } => {
+                let (Some(item), Some(collection)) = (item, collection) else {
+                    return Err(CompilerErrorKind::IncompleteForeach.into());
+                };
+
+                let collection = ExpressionParser::parse_at(collection, start + 3)?;
+
+                self.open_block(BlockKind::For { item: Symbol::intern(&item), collection }, start);
+            },
+            CompiledProcedureBuilderState::Break => {
+                self.push_statement(StatementKind::Break, span);
             },
             CompiledProcedureBuilderState::Indeterminate { tokens } => {
-                let expression = ExpressionParser::parse(tokens.to_owned())?;
+                let expression = ExpressionParser::parse_at(tokens, start)?;
 
-                self.procedure.instructions.push(
-                    Instruction::EvaluateExpression { expression, target: None }
-                );
+                self.push_statement(StatementKind::Expression(expression), span);
             },
             CompiledProcedureBuilderState::Return { expression } => {
                 let expression = if expression.is_empty() {
-                    Box::new(Value::Null)
+                    None
                 } else {
-                    ExpressionParser::parse(expression.to_owned())?
+                    Some(ExpressionParser::parse_at(expression, start + 1)?)
+                };
+
+                self.push_statement(StatementKind::Return(expression), span);
+            },
+            CompiledProcedureBuilderState::TryStatement => {
+                self.open_block(BlockKind::Try, start);
+            },
+            CompiledProcedureBuilderState::CatchStatement { try_block, tokens } => {
+                let identifier = match tokens.as_slice() {
+                    [
+                        Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Opening)),
+                        Token::Identifier(identifier),
+                        Token::Punctuation(PunctuationToken::Parenthesis(ParenthesisType::Closing)),
+                    ] => Symbol::intern(identifier),
+                    _ => return Err(CompilerError::unexpected_token("'(identifier)' after 'catch'", tokens)),
                 };
 
-                self.procedure.instructions.push(
-                    Instruction::Return { expression }
-                );
+                self.open_block(BlockKind::Catch { body: try_block.statements, identifier }, try_block.start);
+            },
+            CompiledProcedureBuilderState::Throw { expression } => {
+                let expression = ExpressionParser::parse_at(expression, start + 1)?;
+
+                self.push_statement(StatementKind::Throw(expression), span);
             },
         }
-        self.state = CompiledProcedureBuilderState::Base;
+
         Ok(self)
     }
 
     pub fn build(self) -> Result<CompiledProcedure, CompilerError> {
+        if self.pending_try.is_some() {
+            return Err(CompilerErrorKind::MissingCatch.into());
+        }
+
         if let CompiledProcedureBuilderState::Base = self.state {
-            if !self.scope_stack.is_empty() {
-                return Err(CompilerError {
-                    message: "Unclosed scope!".into()
-                });
+            if !self.blocks.is_empty() {
+                return Err(CompilerErrorKind::UnclosedScope.into());
+            }
+
+            let mut body = self.body;
+
+            if self.implicit_result {
+                if let Some(Statement { kind: StatementKind::Expression(_), .. }) = body.last() {
+                    if let Some(Statement { kind: StatementKind::Expression(expression), span }) = body.pop() {
+                        body.push(Statement { kind: StatementKind::Return(Some(expression)), span });
+                    }
+                }
+            }
+
+            let mut procedure = self.procedure;
+            let (instructions, spans) = ProcedureLowering::new(self.strict_conditions).lower(body)?;
+            procedure.instructions = instructions;
+            procedure.spans = spans.iter().map(|span| self.token_spans.get(span.start).copied().unwrap_or_default()).collect();
+
+            let end = procedure.instructions.len();
+            let jumps_to_end = procedure.instructions.iter().any(|instruction| matches!(
+                instruction,
+                Instruction::JumpConditional { jump_target, .. } if *jump_target == end
+            ));
+
+            if jumps_to_end || !matches!(procedure.instructions.last(), Some(Instruction::Return { .. } | Instruction::Throw { .. })) {
+                procedure.instructions.push(Instruction::Return { expression: Box::new(Value::Null) });
+                procedure.spans.push(self.token_spans.last().copied().unwrap_or_default());
             }
 
-            Ok(self.procedure)
+            Ok(procedure)
         } else {
-            Err(CompilerError {
-                message: "Incomplete instruction!".into()
-            })
+            Err(CompilerErrorKind::IncompleteStatement.into())
         }
     }
 }
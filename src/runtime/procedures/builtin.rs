@@ -1,4 +1,40 @@
+use crate::runtime::{RuntimeError, Value};
 
 pub mod arrays;
 pub mod strings;
-pub mod numbers;
\ No newline at end of file
+pub mod numbers;
+pub mod math;
+pub mod runtime;
+pub mod host;
+pub mod emit;
+pub mod assert;
+pub mod sys;
+pub mod bools;
+pub mod reflect;
+pub mod capabilities;
+pub mod io;
+pub mod files;
+pub mod env;
+pub mod process;
+pub mod random;
+pub mod convert;
+pub mod encoding;
+pub mod crypto;
+pub mod values;
+
+// The argument at the index, which has to be a String. 'name' describes it in errors, e.g. "path".
+pub(crate) fn string_argument(arguments: &[Value], index: usize, name: &'static str, procedure: &'static str) -> Result<String, RuntimeError> {
+    match arguments.get(index) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(other) => Err(RuntimeError::argument_type(procedure, name, "String", other)),
+        None => Err(RuntimeError::missing_argument(procedure, Some(name))),
+    }
+}
+
+pub(crate) fn integer_argument(arguments: &[Value], index: usize, name: &'static str, procedure: &'static str) -> Result<i64, RuntimeError> {
+    match arguments.get(index) {
+        Some(Value::Integer(value)) => Ok(*value),
+        Some(other) => Err(RuntimeError::argument_type(procedure, name, "Integer", other)),
+        None => Err(RuntimeError::missing_argument(procedure, Some(name))),
+    }
+}
@@ -1,14 +1,63 @@
-use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+use std::{cmp::Ordering, mem};
 
+use crate::runtime::{ModuleAddress, RuntimeError, RuntimeErrorKind, Value, array_index, environment::Environment, module::Module, procedures::Procedure};
+
+// Arrays are values, so procedures changing an array return the changed copy instead, e.g.
+// 'values = Arrays::push(values, 4);'. Elements taken out are read beforehand, e.g. with 'values[-1]'.
 pub(crate) fn get_module() -> Module {
     let mut module = Module::default();
 
     module.insert_procedure("new".into(), Box::new(NewArrayProcedure), true);
     module.insert_procedure("size".into(), Box::new(ArraySizeProcedure), true);
+    module.insert_procedure("setOrGrow".into(), Box::new(ArraySetOrGrowProcedure), true);
+    module.insert_procedure("push".into(), Box::new(ArrayPushProcedure), true);
+    module.insert_procedure("pop".into(), Box::new(ArrayPopProcedure), true);
+    module.insert_procedure("insertAt".into(), Box::new(ArrayInsertAtProcedure), true);
+    module.insert_procedure("removeAt".into(), Box::new(ArrayRemoveAtProcedure), true);
+    module.insert_procedure("concat".into(), Box::new(ArrayConcatProcedure), true);
+    module.insert_procedure("slice".into(), Box::new(ArraySliceProcedure), true);
+    module.insert_procedure("reverse".into(), Box::new(ArrayReverseProcedure), true);
+    module.insert_procedure("sort".into(), Box::new(ArraySortProcedure), true);
+    module.insert_procedure("sortBy".into(), Box::new(ArraySortByProcedure), true);
+    module.insert_procedure("map".into(), Box::new(ArrayMapProcedure), true);
+    module.insert_procedure("filter".into(), Box::new(ArrayFilterProcedure), true);
+    module.insert_procedure("reduce".into(), Box::new(ArrayReduceProcedure), true);
+    module.insert_procedure("contains".into(), Box::new(ArrayContainsProcedure), true);
+    module.insert_procedure("indexOf".into(), Box::new(ArrayIndexOfProcedure), true);
+    module.insert_procedure("join".into(), Box::new(ArrayJoinProcedure), true);
 
     module
 }
 
+fn array_argument(arguments: &mut impl Iterator<Item = Value>, procedure: &'static str) -> Result<Vec<Value>, RuntimeError> {
+    match arguments.next() {
+        Some(Value::Array(array)) => Ok(array),
+        Some(other) => Err(RuntimeError::argument_type(procedure, "array", "Array", &other)),
+        None => Err(RuntimeError::missing_argument(procedure, Some("array"))),
+    }
+}
+
+// Procedures are passed by name, like "Main::compare". Besides exported procedures, the calling
+// module may pass its own private ones.
+fn procedure_argument(arguments: &mut impl Iterator<Item = Value>, procedure: &'static str) -> Result<ModuleAddress, RuntimeError> {
+    match arguments.next() {
+        Some(Value::String(name)) => match name.split_once("::") {
+            Some((module_id, identifier)) => Ok(ModuleAddress::new(module_id.into(), identifier.into())),
+            None => Err(RuntimeError::invalid_argument(procedure, format!("'{}' is not a procedure name like \"Module::procedure\"!", name))),
+        },
+        Some(other) => Err(RuntimeError::argument_type(procedure, "procedure", "String", &other)),
+        None => Err(RuntimeError::missing_argument(procedure, Some("procedure"))),
+    }
+}
+
+fn index_argument(arguments: &mut impl Iterator<Item = Value>, procedure: &'static str) -> Result<i64, RuntimeError> {
+    match arguments.next() {
+        Some(Value::Integer(index)) => Ok(index),
+        Some(other) => Err(RuntimeError::argument_type(procedure, "index", "Integer", &other)),
+        None => Err(RuntimeError::missing_argument(procedure, Some("index"))),
+    }
+}
+
 
 #[derive(Debug)]
 pub(crate) struct NewArrayProcedure;
@@ -20,9 +69,7 @@ impl Procedure for NewArrayProcedure {
         if let Value::Integer(size) = size {
             Ok(Value::Array(vec![Value::Null; *size as usize]))
         } else {
-            Err(RuntimeError {
-                message: format!("Array size needs to be of type Integer, found {}!", size.get_type_id())
-            })
+            Err(RuntimeError::argument_type("Arrays::new", "size", "Integer", size))
         }
     }
 }
@@ -32,15 +79,364 @@ pub(crate) struct ArraySizeProcedure;
 
 impl Procedure for ArraySizeProcedure {
     fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
-        let arg = arguments.first().ok_or(RuntimeError {
-            message: "Missing argument!".into(),
-        })?;
+        let arg = arguments.first().ok_or_else(|| RuntimeError::missing_argument("Arrays::size", None))?;
 
         match arg {
             Value::Array(arr) => Ok(Value::Integer(arr.len() as i64)),
-            other => Err(RuntimeError {
-                message: format!("Cannot identify size of {}!", other.get_type_id()),
-            }),
+            other => Err(RuntimeError::argument_type("Arrays::size", "value", "Array or String", other)),
+        }
+    }
+}
+
+// Returns the array with the value set at the index. Indices past the end grow the array,
+// filling the gap with nulls. Negative indices count from the end and cannot grow it.
+#[derive(Debug)]
+pub(crate) struct ArraySetOrGrowProcedure;
+
+impl Procedure for ArraySetOrGrowProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let mut array = array_argument(&mut arguments, "Arrays::setOrGrow")?;
+
+        let index = match index_argument(&mut arguments, "Arrays::setOrGrow")? {
+            index if index >= array.len() as i64 => index as usize,
+            index => array_index(index, array.len())?,
+        };
+
+        let value = arguments.next().unwrap_or(Value::Null);
+
+        if index >= array.len() {
+            // The gap is allocated up front, so an index too large for memory is an error.
+            let additional = index - array.len() + 1;
+            let bytes = additional.saturating_mul(mem::size_of::<Value>());
+            environment.context.reserve_memory(bytes)?;
+            array.try_reserve_exact(additional).map_err(|_| RuntimeError::from(RuntimeErrorKind::AllocationTooLarge { bytes }))?;
+
+            array.resize(index + 1, Value::Null);
         }
+
+        array[index] = value;
+
+        Ok(Value::Array(array))
     }
-}
\ No newline at end of file
+}
+
+// Returns the array with the values appended, e.g. 'Arrays::push(values, 4, 5)'.
+#[derive(Debug)]
+pub(crate) struct ArrayPushProcedure;
+
+impl Procedure for ArrayPushProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let mut array = array_argument(&mut arguments, "Arrays::push")?;
+        array.extend(arguments);
+
+        Ok(Value::Array(array))
+    }
+}
+
+// Returns the array without its last element.
+#[derive(Debug)]
+pub(crate) struct ArrayPopProcedure;
+
+impl Procedure for ArrayPopProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut array = array_argument(&mut arguments.into_iter(), "Arrays::pop")?;
+
+        if array.pop().is_none() {
+            return Err(RuntimeError::invalid_argument("Arrays::pop", "Cannot pop from an empty array!"));
+        }
+
+        Ok(Value::Array(array))
+    }
+}
+
+// Returns the array with the value inserted before the index. An index equal to the size
+// appends the value, negative indices count from the end.
+#[derive(Debug)]
+pub(crate) struct ArrayInsertAtProcedure;
+
+impl Procedure for ArrayInsertAtProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let mut array = array_argument(&mut arguments, "Arrays::insertAt")?;
+
+        let index = match index_argument(&mut arguments, "Arrays::insertAt")? {
+            index if index == array.len() as i64 => array.len(),
+            index => array_index(index, array.len())?,
+        };
+
+        array.insert(index, arguments.next().unwrap_or(Value::Null));
+
+        Ok(Value::Array(array))
+    }
+}
+
+// Returns the array without the element at the index.
+#[derive(Debug)]
+pub(crate) struct ArrayRemoveAtProcedure;
+
+impl Procedure for ArrayRemoveAtProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let mut array = array_argument(&mut arguments, "Arrays::removeAt")?;
+        let index = array_index(index_argument(&mut arguments, "Arrays::removeAt")?, array.len())?;
+
+        array.remove(index);
+
+        Ok(Value::Array(array))
+    }
+}
+
+// Joins any number of arrays into a new one.
+#[derive(Debug)]
+pub(crate) struct ArrayConcatProcedure;
+
+impl Procedure for ArrayConcatProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter().peekable();
+        let mut joined = Vec::new();
+
+        while arguments.peek().is_some() {
+            joined.extend(array_argument(&mut arguments, "Arrays::concat")?);
+        }
+
+        Ok(Value::Array(joined))
+    }
+}
+
+// The elements from the first index up to, but not including, the second one. Without the
+// second index the slice reaches to the end. Negative indices count from the end.
+#[derive(Debug)]
+pub(crate) struct ArraySliceProcedure;
+
+impl Procedure for ArraySliceProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::slice")?;
+        let len = array.len() as i64;
+
+        let from = index_argument(&mut arguments, "Arrays::slice")?;
+        let to = match arguments.next() {
+            None | Some(Value::Null) => len,
+            Some(Value::Integer(to)) => to,
+            Some(other) => return Err(RuntimeError::argument_type("Arrays::slice", "index", "Integer", &other)),
+        };
+
+        let resolve = |index: i64| if index < 0 { index + len } else { index };
+        let (start, end) = (resolve(from), resolve(to));
+
+        if start < 0 || end > len || start > end {
+            return Err(RuntimeError::invalid_argument("Arrays::slice", format!("Cannot take the slice from {} to {} of an array of length {}!", from, to, len)));
+        }
+
+        Ok(Value::Array(array[start as usize..end as usize].to_vec()))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ArrayReverseProcedure;
+
+impl Procedure for ArrayReverseProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut array = array_argument(&mut arguments.into_iter(), "Arrays::reverse")?;
+        array.reverse();
+
+        Ok(Value::Array(array))
+    }
+}
+
+// A stable merge sort. Unlike the sort of the standard library it stops at the first error and
+// accepts comparators that are not a total order.
+fn merge_sort(
+    mut values: Vec<Value>,
+    compare: &mut impl FnMut(&Value, &Value) -> Result<Ordering, RuntimeError>,
+) -> Result<Vec<Value>, RuntimeError> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+
+    let right = values.split_off(values.len() / 2);
+    let mut left = merge_sort(values, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let next = if compare(r, l)? == Ordering::Less { right.next() } else { left.next() };
+        merged.extend(next);
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+
+    Ok(merged)
+}
+
+// Numbers are ordered by value, Integers and Floats alike, Strings and Chars lexicographically.
+fn natural_order(lhs: &Value, rhs: &Value) -> Result<Ordering, RuntimeError> {
+    match (lhs, rhs) {
+        (Value::Integer(l), Value::Integer(r)) => Ok(l.cmp(r)),
+        (Value::Float(l), Value::Float(r)) => Ok(l.total_cmp(r)),
+        (Value::Integer(l), Value::Float(r)) => Ok((*l as f64).total_cmp(r)),
+        (Value::Float(l), Value::Integer(r)) => Ok(l.total_cmp(&(*r as f64))),
+        (Value::String(l), Value::String(r)) => Ok(l.cmp(r)),
+        (Value::Char(l), Value::Char(r)) => Ok(l.cmp(r)),
+        (l, r) => Err(RuntimeError::invalid_operands("order", l, r)),
+    }
+}
+
+// Returns a sorted copy of the array. Equal elements keep their order.
+#[derive(Debug)]
+pub(crate) struct ArraySortProcedure;
+
+impl Procedure for ArraySortProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let array = array_argument(&mut arguments.into_iter(), "Arrays::sort")?;
+
+        Ok(Value::Array(merge_sort(array, &mut natural_order)?))
+    }
+}
+
+// Like 'Arrays::sort', but orders the elements with a procedure taking two elements and returning
+// a negative Integer if the first one comes first, zero if they are equal and a positive one otherwise.
+#[derive(Debug)]
+pub(crate) struct ArraySortByProcedure;
+
+impl Procedure for ArraySortByProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::sortBy")?;
+        let comparator = procedure_argument(&mut arguments, "Arrays::sortBy")?;
+
+        let mut compare = |lhs: &Value, rhs: &Value| match environment.call_procedure(&comparator, vec![lhs.clone(), rhs.clone()])? {
+            Value::Integer(order) => Ok(order.cmp(&0)),
+            other => Err(RuntimeError::invalid_argument("Arrays::sortBy", format!("The comparator has to return an Integer, found {}!", other.get_type_id()))),
+        };
+
+        Ok(Value::Array(merge_sort(array, &mut compare)?))
+    }
+}
+
+// Returns the results of calling the procedure with each element.
+#[derive(Debug)]
+pub(crate) struct ArrayMapProcedure;
+
+impl Procedure for ArrayMapProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::map")?;
+        let mapper = procedure_argument(&mut arguments, "Arrays::map")?;
+
+        let mapped = array
+            .into_iter()
+            .map(|value| environment.call_procedure(&mapper, vec![value]))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Value::Array(mapped))
+    }
+}
+
+// Keeps the elements the procedure returns true for.
+#[derive(Debug)]
+pub(crate) struct ArrayFilterProcedure;
+
+impl Procedure for ArrayFilterProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::filter")?;
+        let predicate = procedure_argument(&mut arguments, "Arrays::filter")?;
+
+        let mut kept = Vec::new();
+
+        for value in array {
+            match environment.call_procedure(&predicate, vec![value.clone()])? {
+                Value::Bool(true) => kept.push(value),
+                Value::Bool(false) => {}
+                other => return Err(RuntimeError::invalid_argument("Arrays::filter", format!("The predicate has to return a Bool, found {}!", other.get_type_id()))),
+            }
+        }
+
+        Ok(Value::Array(kept))
+    }
+}
+
+// Combines the elements from first to last by calling the procedure with the result so far and
+// the next element. Without an initial value the first element is used, so the array may not be empty.
+#[derive(Debug)]
+pub(crate) struct ArrayReduceProcedure;
+
+impl Procedure for ArrayReduceProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let mut array = array_argument(&mut arguments, "Arrays::reduce")?.into_iter();
+        let reducer = procedure_argument(&mut arguments, "Arrays::reduce")?;
+
+        let initial = arguments.next().or_else(|| array.next()).ok_or_else(|| RuntimeError::invalid_argument("Arrays::reduce", "Cannot reduce an empty array without an initial value!"))?;
+
+        array.try_fold(initial, |result, value| environment.call_procedure(&reducer, vec![result, value]))
+    }
+}
+
+// Whether an element is equal to the value, as compared by '=='.
+#[derive(Debug)]
+pub(crate) struct ArrayContainsProcedure;
+
+impl Procedure for ArrayContainsProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::contains")?;
+        let value = arguments.next().unwrap_or(Value::Null);
+
+        Ok(Value::Bool(array.contains(&value)))
+    }
+}
+
+// The index of the first element equal to the value, or null if there is none.
+#[derive(Debug)]
+pub(crate) struct ArrayIndexOfProcedure;
+
+impl Procedure for ArrayIndexOfProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::indexOf")?;
+        let value = arguments.next().unwrap_or(Value::Null);
+
+        Ok(array
+            .iter()
+            .position(|element| *element == value)
+            .map(|index| Value::Integer(index as i64))
+            .unwrap_or(Value::Null))
+    }
+}
+
+// Joins the elements into a String, written the same way 'IO::print' writes them.
+#[derive(Debug)]
+pub(crate) struct ArrayJoinProcedure;
+
+impl Procedure for ArrayJoinProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let array = array_argument(&mut arguments, "Arrays::join")?;
+        let separator = match arguments.next() {
+            Some(Value::String(separator)) => separator,
+            Some(Value::Char(separator)) => separator.to_string(),
+            None | Some(Value::Null) => String::new(),
+            Some(other) => return Err(RuntimeError::argument_type("Arrays::join", "separator", "String", &other)),
+        };
+
+        Ok(Value::String(array.iter().map(Value::to_string).collect::<Vec<_>>().join(&separator)))
+    }
+}
@@ -0,0 +1,70 @@
+use crate::runtime::{RuntimeError, RuntimeErrorKind, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("isTrue".into(), Box::new(AssertIsTrueProcedure), true);
+    module.insert_procedure("equals".into(), Box::new(AssertEqualsProcedure), true);
+    module.insert_procedure("panic".into(), Box::new(PanicProcedure), true);
+
+    module
+}
+
+fn failure(reason: String, custom_message: Option<&Value>) -> RuntimeError {
+    match custom_message {
+        Some(Value::String(custom_message)) => RuntimeErrorKind::AssertionFailed { message: Some(custom_message.clone()), reason }.into(),
+        _ => RuntimeErrorKind::AssertionFailed { message: None, reason }.into(),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AssertIsTrueProcedure;
+
+impl Procedure for AssertIsTrueProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let condition = arguments.first().ok_or_else(|| RuntimeError::missing_argument("Assert::isTrue", Some("condition")))?;
+
+        match condition {
+            Value::Bool(true) => Ok(Value::Null),
+            other => Err(failure(format!("Expected true, found {:?}.", other), arguments.get(1))),
+        }
+    }
+
+    fn includes_call_site(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AssertEqualsProcedure;
+
+impl Procedure for AssertEqualsProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let (Some(actual), Some(expected)) = (arguments.first(), arguments.get(1)) else {
+            return Err(RuntimeError::missing_argument("Assert::equals", Some(if arguments.is_empty() { "actual" } else { "expected" })));
+        };
+
+        if actual == expected {
+            Ok(Value::Null)
+        } else {
+            Err(failure(format!("Expected {:?}, found {:?}.", expected, actual), arguments.get(2)))
+        }
+    }
+
+    fn includes_call_site(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PanicProcedure;
+
+impl Procedure for PanicProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        match arguments.first() {
+            Some(Value::String(message)) => Err(RuntimeErrorKind::Panic { message: Some(message.clone()) }.into()),
+            Some(other) => Err(RuntimeErrorKind::Panic { message: Some(format!("{:?}", other)) }.into()),
+            None => Err(RuntimeErrorKind::Panic { message: None }.into()),
+        }
+    }
+}
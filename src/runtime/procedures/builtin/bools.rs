@@ -0,0 +1,22 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("from".into(), Box::new(BoolsFromProcedure), true);
+
+    module
+}
+
+// Converts a value to a Boolean by its truthiness: Null, false, zero, NaN, empty strings,
+// empty arrays and dropped struct references are false, everything else is true.
+#[derive(Debug)]
+pub(crate) struct BoolsFromProcedure;
+
+impl Procedure for BoolsFromProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let value = arguments.first().ok_or_else(|| RuntimeError::missing_argument("Bools::from", None))?;
+
+        Ok(Value::Bool(value.is_truthy()))
+    }
+}
@@ -0,0 +1,23 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("has".into(), Box::new(CapabilitiesHasProcedure), true);
+
+    module
+}
+
+// Whether a builtin module is available, so scripts can run under sandboxed hosts.
+#[derive(Debug)]
+pub(crate) struct CapabilitiesHasProcedure;
+
+impl Procedure for CapabilitiesHasProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        match arguments.first() {
+            Some(Value::String(module_id)) => Ok(Value::Bool(environment.has_capability(module_id))),
+            Some(other) => Err(RuntimeError::argument_type("Capabilities::has", "name", "String", other)),
+            None => Err(RuntimeError::missing_argument("Capabilities::has", None)),
+        }
+    }
+}
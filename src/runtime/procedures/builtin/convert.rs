@@ -0,0 +1,111 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+// Conversions between the basic types. A conversion either keeps the value exactly or fails,
+// except for Floats turned into Integers, which are truncated towards zero.
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("toString".into(), Box::new(ConvertToStringProcedure), true);
+    module.insert_procedure("toInteger".into(), Box::new(ConvertToIntegerProcedure), true);
+    module.insert_procedure("toFloat".into(), Box::new(ConvertToFloatProcedure), true);
+    module.insert_procedure("toBool".into(), Box::new(ConvertToBoolProcedure), true);
+
+    module
+}
+
+fn argument<'a>(arguments: &'a [Value], procedure: &'static str) -> Result<&'a Value, RuntimeError> {
+    arguments.first().ok_or_else(|| RuntimeError::missing_argument(procedure, None))
+}
+
+fn unsupported(value: &Value, procedure: &'static str) -> RuntimeError {
+    RuntimeError::invalid_argument(procedure, format!("Cannot convert a value of type {}!", value.get_type_id()))
+}
+
+fn digit(char: char, procedure: &'static str) -> Result<i64, RuntimeError> {
+    char.to_digit(10).map(|digit| digit as i64).ok_or_else(|| RuntimeError::invalid_argument(procedure, format!("Cannot convert '{}', it is not a digit!", char)))
+}
+
+fn not_bool(value: &str) -> RuntimeError {
+    RuntimeError::invalid_argument("Convert::toBool", format!("Cannot convert {}, only \"true\", \"false\", 1 and 0 are Bools!", value))
+}
+
+// Writes the value the same way as 'IO::print'. Strings are returned as they are.
+#[derive(Debug)]
+pub(crate) struct ConvertToStringProcedure;
+
+impl Procedure for ConvertToStringProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        Ok(Value::String(argument(&arguments, "Convert::toString")?.to_string()))
+    }
+}
+
+// Floats are truncated towards zero and have to be finite and within the Integer range.
+// Strings have to be a whole decimal number, surrounding whitespace aside. Chars have to be a
+// digit. Bools are 1 and 0.
+#[derive(Debug)]
+pub(crate) struct ConvertToIntegerProcedure;
+
+impl Procedure for ConvertToIntegerProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let value = argument(&arguments, "Convert::toInteger")?;
+
+        let integer = match value {
+            Value::Integer(n) => *n,
+            // The upper bound is exclusive, as i64::MAX is not exactly representable as a Float.
+            Value::Float(f) if f.is_finite() && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => f.trunc() as i64,
+            Value::Float(f) => return Err(RuntimeError::invalid_argument("Convert::toInteger", format!("Cannot convert {:e}, it is out of the Integer range!", f))),
+            Value::String(s) => s.trim().parse().map_err(|_| RuntimeError::invalid_argument("Convert::toInteger", format!("Cannot convert \"{}\", it is not a whole number!", s)))?,
+            Value::Char(c) => digit(*c, "Convert::toInteger")?,
+            Value::Bool(b) => *b as i64,
+            other => return Err(unsupported(other, "Convert::toInteger")),
+        };
+
+        Ok(Value::Integer(integer))
+    }
+}
+
+// Integers are converted to the nearest Float. Strings have to be a decimal number, surrounding
+// whitespace aside, and may also be "inf" or "NaN". Chars have to be a digit. Bools are 1.0 and 0.0.
+#[derive(Debug)]
+pub(crate) struct ConvertToFloatProcedure;
+
+impl Procedure for ConvertToFloatProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let value = argument(&arguments, "Convert::toFloat")?;
+
+        let float = match value {
+            Value::Integer(n) => *n as f64,
+            Value::Float(f) => *f,
+            Value::String(s) => s.trim().parse().map_err(|_| RuntimeError::invalid_argument("Convert::toFloat", format!("Cannot convert \"{}\", it is not a number!", s)))?,
+            Value::Char(c) => digit(*c, "Convert::toFloat")? as f64,
+            Value::Bool(b) => *b as i64 as f64,
+            other => return Err(unsupported(other, "Convert::toFloat")),
+        };
+
+        Ok(Value::Float(float))
+    }
+}
+
+// Only "true" and "false" and the Integers 1 and 0 are converted, anything else is an error.
+// 'Bools::from' converts any value by its truthiness instead.
+#[derive(Debug)]
+pub(crate) struct ConvertToBoolProcedure;
+
+impl Procedure for ConvertToBoolProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let value = argument(&arguments, "Convert::toBool")?;
+
+        let bool = match value {
+            Value::Bool(b) => *b,
+            Value::String(s) if s.trim() == "true" => true,
+            Value::String(s) if s.trim() == "false" => false,
+            Value::Integer(1) => true,
+            Value::Integer(0) => false,
+            Value::String(s) => return Err(not_bool(&format!("\"{}\"", s))),
+            Value::Integer(n) => return Err(not_bool(&n.to_string())),
+            other => return Err(unsupported(other, "Convert::toBool")),
+        };
+
+        Ok(Value::Bool(bool))
+    }
+}
@@ -0,0 +1,44 @@
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::{Procedure, builtin::string_argument}};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("sha256".into(), Box::new(CryptoHashProcedure::SHA256), true);
+    module.insert_procedure("sha1".into(), Box::new(CryptoHashProcedure::SHA1), true);
+    module.insert_procedure("md5".into(), Box::new(CryptoHashProcedure::MD5), true);
+
+    module
+}
+
+fn hex<D: Digest>(content: &str) -> String {
+    D::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Hashes the UTF-8 bytes of a string and returns the lowercase hex digest. SHA-1 and MD5 are
+// broken for security purposes and only meant for checksums.
+#[derive(Debug)]
+pub(crate) struct CryptoHashProcedure {
+    name: &'static str,
+    hash: fn(&str) -> String,
+}
+
+impl CryptoHashProcedure {
+    const SHA256: Self = Self { name: "Crypto::sha256", hash: hex::<Sha256> };
+    const SHA1: Self = Self { name: "Crypto::sha1", hash: hex::<Sha1> };
+    const MD5: Self = Self { name: "Crypto::md5", hash: hex::<Md5> };
+}
+
+impl Procedure for CryptoHashProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let content = string_argument(&arguments, 0, "string", self.name)?;
+
+        Ok(Value::String((self.hash)(&content)))
+    }
+}
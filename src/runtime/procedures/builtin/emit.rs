@@ -0,0 +1,30 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, events::EmittedEvent, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("event".into(), Box::new(EmitEventProcedure), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct EmitEventProcedure;
+
+impl Procedure for EmitEventProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let name = match arguments.next() {
+            Some(Value::String(name)) => name,
+            Some(other) => return Err(RuntimeError::argument_type("Emit::event", "name", "String", &other)),
+            None => return Err(RuntimeError::missing_argument("Emit::event", Some("name"))),
+        };
+
+        let payload = arguments.next().unwrap_or(Value::Null);
+
+        environment.context.emit(EmittedEvent { name, payload });
+
+        Ok(Value::Null)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::{Procedure, builtin::string_argument}};
+
+// Encodes the UTF-8 bytes of strings. Decoded bytes have to be valid UTF-8 again.
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("base64Encode".into(), Box::new(Base64EncodeProcedure), true);
+    module.insert_procedure("base64Decode".into(), Box::new(Base64DecodeProcedure), true);
+    module.insert_procedure("hexEncode".into(), Box::new(HexEncodeProcedure), true);
+    module.insert_procedure("hexDecode".into(), Box::new(HexDecodeProcedure), true);
+
+    module
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn decoded_text(bytes: Vec<u8>, procedure: &'static str) -> Result<Value, RuntimeError> {
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| RuntimeError::invalid_argument(procedure, "The decoded bytes are not valid UTF-8."))
+}
+
+// Standard base64 with padding, as in RFC 4648.
+#[derive(Debug)]
+pub(crate) struct Base64EncodeProcedure;
+
+impl Procedure for Base64EncodeProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Encoding::base64Encode")?;
+        let mut encoded = String::with_capacity(string.len().div_ceil(3) * 4);
+
+        for chunk in string.as_bytes().chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | (*byte as u32) << (16 - 8 * i));
+
+            for i in 0..4 {
+                match i <= chunk.len() {
+                    true => encoded.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char),
+                    false => encoded.push('='),
+                }
+            }
+        }
+
+        Ok(Value::String(encoded))
+    }
+}
+
+// Expects padded standard base64. Whitespace is not allowed.
+#[derive(Debug)]
+pub(crate) struct Base64DecodeProcedure;
+
+impl Procedure for Base64DecodeProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let procedure = "Encoding::base64Decode";
+        let string = string_argument(&arguments, 0, "string", procedure)?;
+        let input = string.as_bytes();
+
+        if input.len() % 4 != 0 {
+            return Err(RuntimeError::invalid_argument(procedure, "Its length has to be a multiple of 4."));
+        }
+
+        let mut bytes = Vec::with_capacity(input.len() / 4 * 3);
+        let chunks = input.len() / 4;
+
+        for (index, chunk) in input.chunks(4).enumerate() {
+            let padding = chunk.iter().rev().take_while(|byte| **byte == b'=').count();
+
+            if padding > 2 || (padding > 0 && index + 1 < chunks) {
+                return Err(RuntimeError::invalid_argument(procedure, "Padding may only end the string."));
+            }
+
+            let mut bits = 0u32;
+
+            for (i, byte) in chunk[..4 - padding].iter().enumerate() {
+                let value = BASE64_ALPHABET.iter().position(|symbol| symbol == byte).ok_or_else(|| {
+                    RuntimeError::invalid_argument(procedure, format!("'{}' is not a base64 character.", *byte as char))
+                })?;
+
+                bits |= (value as u32) << (18 - 6 * i);
+            }
+
+            bytes.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+        }
+
+        decoded_text(bytes, procedure)
+    }
+}
+
+// Two lowercase digits per byte.
+#[derive(Debug)]
+pub(crate) struct HexEncodeProcedure;
+
+impl Procedure for HexEncodeProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Encoding::hexEncode")?;
+
+        Ok(Value::String(string.bytes().map(|byte| format!("{:02x}", byte)).collect()))
+    }
+}
+
+// Accepts both lowercase and uppercase digits.
+#[derive(Debug)]
+pub(crate) struct HexDecodeProcedure;
+
+impl Procedure for HexDecodeProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let procedure = "Encoding::hexDecode";
+        let string = string_argument(&arguments, 0, "string", procedure)?;
+
+        if string.len() % 2 != 0 {
+            return Err(RuntimeError::invalid_argument(procedure, "Its length has to be even."));
+        }
+
+        let digit = |byte: u8| (byte as char).to_digit(16).ok_or_else(|| {
+            RuntimeError::invalid_argument(procedure, format!("'{}' is not a hex digit.", byte as char))
+        });
+
+        let bytes = string
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| Ok((digit(pair[0])? * 16 + digit(pair[1])?) as u8))
+            .collect::<Result<Vec<_>, RuntimeError>>()?;
+
+        decoded_text(bytes, procedure)
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::{Procedure, builtin::string_argument}};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("get".into(), Box::new(EnvGetProcedure), true);
+    module.insert_procedure("set".into(), Box::new(EnvSetProcedure), true);
+    module.insert_procedure("all".into(), Box::new(EnvAllProcedure), true);
+
+    module
+}
+
+// Names containing '=' or NUL cannot be set by the operating system, so they are rejected.
+fn variable_name(arguments: &[Value], procedure: &'static str) -> Result<String, RuntimeError> {
+    let name = string_argument(arguments, 0, "name", procedure)?;
+
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(RuntimeError::invalid_argument(procedure, format!("'{}' is not a valid environment variable name!", name)));
+    }
+
+    Ok(name)
+}
+
+// Returns null for variables that are not set or not valid unicode.
+#[derive(Debug)]
+pub(crate) struct EnvGetProcedure;
+
+impl Procedure for EnvGetProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = variable_name(&arguments, "Env::get")?;
+
+        Ok(env::var(name).map(Value::String).unwrap_or(Value::Null))
+    }
+}
+
+// Setting a variable to null removes it.
+#[derive(Debug)]
+pub(crate) struct EnvSetProcedure;
+
+impl Procedure for EnvSetProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = variable_name(&arguments, "Env::set")?;
+
+        match arguments.get(1) {
+            Some(Value::String(value)) if value.contains('\0') => return Err(RuntimeError::invalid_argument("Env::set", format!("Value of environment variable '{}' may not contain NUL!", name))),
+            Some(Value::String(value)) => env::set_var(name, value),
+            Some(Value::Null) => env::remove_var(name),
+            Some(other) => return Err(RuntimeError::argument_type("Env::set", "value", "String", other)),
+            None => return Err(RuntimeError::missing_argument("Env::set", Some("value"))),
+        }
+
+        Ok(Value::Null)
+    }
+}
+
+// Lists the variables as a map, i.e. [name, value] pairs sorted by name. Variables that are
+// not valid unicode are left out.
+#[derive(Debug)]
+pub(crate) struct EnvAllProcedure;
+
+impl Procedure for EnvAllProcedure {
+    fn call(&self, _environment: Environment, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut variables: Vec<(String, String)> = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        variables.sort();
+
+        Ok(Value::Array(
+            variables
+                .into_iter()
+                .map(|(name, value)| Value::Array(vec![Value::String(name), Value::String(value)]))
+                .collect()
+        ))
+    }
+}
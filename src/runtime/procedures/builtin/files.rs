@@ -0,0 +1,85 @@
+use std::{fs, io::Write};
+
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::{Procedure, builtin::string_argument}};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("readToString".into(), Box::new(FilesReadToStringProcedure), true);
+    module.insert_procedure("write".into(), Box::new(FilesWriteProcedure { append: false }), true);
+    module.insert_procedure("append".into(), Box::new(FilesWriteProcedure { append: true }), true);
+    module.insert_procedure("exists".into(), Box::new(FilesExistsProcedure), true);
+    module.insert_procedure("listDir".into(), Box::new(FilesListDirProcedure), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct FilesReadToStringProcedure;
+
+impl Procedure for FilesReadToStringProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let path = string_argument(&arguments, 0, "path", "Files::readToString")?;
+
+        fs::read_to_string(&path).map(Value::String).map_err(|err| RuntimeError::io(format!("File '{}'", path), "read", err))
+    }
+}
+
+// Creates the file if it does not exist. Appending keeps the previous content.
+#[derive(Debug)]
+pub(crate) struct FilesWriteProcedure {
+    append: bool,
+}
+
+impl Procedure for FilesWriteProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let procedure = if self.append { "Files::append" } else { "Files::write" };
+
+        let path = string_argument(&arguments, 0, "path", procedure)?;
+        let content = string_argument(&arguments, 1, "content", procedure)?;
+
+        fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .map_err(|err| RuntimeError::io(format!("File '{}'", path), "written", err))?;
+
+        Ok(Value::Null)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct FilesExistsProcedure;
+
+impl Procedure for FilesExistsProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let path = string_argument(&arguments, 0, "path", "Files::exists")?;
+
+        Ok(Value::Bool(fs::exists(&path).unwrap_or(false)))
+    }
+}
+
+// The names of the entries of a directory, sorted.
+#[derive(Debug)]
+pub(crate) struct FilesListDirProcedure;
+
+impl Procedure for FilesListDirProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let path = string_argument(&arguments, 0, "path", "Files::listDir")?;
+
+        let error = |err: std::io::Error| RuntimeError::io(format!("Directory '{}'", path), "listed", err);
+
+        let mut names = fs::read_dir(&path)
+            .map_err(error)?
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(error)?;
+
+        names.sort();
+
+        Ok(Value::Array(names.into_iter().map(Value::String).collect()))
+    }
+}
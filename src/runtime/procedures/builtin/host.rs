@@ -0,0 +1,48 @@
+use crate::runtime::{RuntimeError, RuntimeErrorKind, Value, environment::Environment, module::Module, procedures::{Procedure, builtin::string_argument}};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("get".into(), Box::new(HostGetProcedure), true);
+    module.insert_procedure("set".into(), Box::new(HostSetProcedure), true);
+    module.insert_procedure("has".into(), Box::new(HostHasProcedure), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct HostGetProcedure;
+
+impl Procedure for HostGetProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = string_argument(&arguments, 0, "name", "Host::get")?;
+
+        environment.context.get_global(&name).ok_or_else(|| RuntimeError::from(RuntimeErrorKind::UndefinedGlobal { name: name.clone() }))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct HostSetProcedure;
+
+impl Procedure for HostSetProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = string_argument(&arguments, 0, "name", "Host::set")?;
+
+        let value = arguments.into_iter().nth(1).ok_or_else(|| RuntimeError::missing_argument("Host::set", Some("value")))?;
+
+        environment.context.set_global(name, value);
+
+        Ok(Value::Null)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct HostHasProcedure;
+
+impl Procedure for HostHasProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = string_argument(&arguments, 0, "name", "Host::has")?;
+
+        Ok(Value::Bool(environment.context.get_global(&name).is_some()))
+    }
+}
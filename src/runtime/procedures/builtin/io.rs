@@ -0,0 +1,41 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("print".into(), Box::new(IoPrintProcedure { line_break: false }), true);
+    module.insert_procedure("println".into(), Box::new(IoPrintProcedure { line_break: true }), true);
+    module.insert_procedure("readLine".into(), Box::new(IoReadLineProcedure), true);
+
+    module
+}
+
+// Writes its arguments separated by spaces.
+#[derive(Debug)]
+pub(crate) struct IoPrintProcedure {
+    line_break: bool,
+}
+
+impl Procedure for IoPrintProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut output = arguments.iter().map(Value::to_string).collect::<Vec<_>>().join(" ");
+
+        if self.line_break {
+            output.push('\n');
+        }
+
+        environment.context.write_output(&output)?;
+
+        Ok(Value::Null)
+    }
+}
+
+// Reads a line without its line break. Returns null once the input is exhausted.
+#[derive(Debug)]
+pub(crate) struct IoReadLineProcedure;
+
+impl Procedure for IoReadLineProcedure {
+    fn call(&self, environment: Environment, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        Ok(environment.context.read_line()?.map(Value::String).unwrap_or(Value::Null))
+    }
+}
@@ -0,0 +1,93 @@
+use crate::runtime::{ModuleAddress, RuntimeError, Struct, StructOwner, Value, environment::Environment, expressions::arithmetic::euclidean_modulo, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("mod".into(), Box::new(MathModProcedure), true);
+    module.insert_procedure("checkedAdd".into(), Box::new(CheckedArithmeticProcedure::ADD), true);
+    module.insert_procedure("checkedSub".into(), Box::new(CheckedArithmeticProcedure::SUB), true);
+    module.insert_procedure("checkedMul".into(), Box::new(CheckedArithmeticProcedure::MUL), true);
+    module.insert_procedure("checkedDiv".into(), Box::new(CheckedArithmeticProcedure::DIV), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct MathModProcedure;
+
+impl Procedure for MathModProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let lhs = arguments.next().ok_or_else(|| RuntimeError::missing_argument("Math::mod", Some("dividend")))?;
+        let rhs = arguments.next().ok_or_else(|| RuntimeError::missing_argument("Math::mod", Some("divisor")))?;
+
+        euclidean_modulo(lhs, rhs, || "Math::mod".into())
+    }
+}
+
+
+// Arithmetic that reports overflow and division by zero through a 'Math::Result' struct
+// ({ ok, value, error }) instead of a RuntimeError.
+#[derive(Debug)]
+pub(crate) struct CheckedArithmeticProcedure {
+    name: &'static str,
+    operation: &'static str,
+    integer_operation: fn(i64, i64) -> Option<i64>,
+    float_operation: fn(f64, f64) -> f64,
+    divides: bool,
+}
+
+impl CheckedArithmeticProcedure {
+    const ADD: Self = Self { name: "checkedAdd", operation: "add", integer_operation: i64::checked_add, float_operation: |l, r| l + r, divides: false };
+    const SUB: Self = Self { name: "checkedSub", operation: "subtract", integer_operation: i64::checked_sub, float_operation: |l, r| l - r, divides: false };
+    const MUL: Self = Self { name: "checkedMul", operation: "multiply", integer_operation: i64::checked_mul, float_operation: |l, r| l * r, divides: false };
+    const DIV: Self = Self { name: "checkedDiv", operation: "divide", integer_operation: i64::checked_div, float_operation: |l, r| l / r, divides: true };
+}
+
+impl Procedure for CheckedArithmeticProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let (Some(lhs), Some(rhs)) = (arguments.first(), arguments.get(1)) else {
+            return Err(RuntimeError::missing_argument(&format!("Math::{}", self.name), Some(if arguments.is_empty() { "first operand" } else { "second operand" })));
+        };
+
+        let outcome = match (lhs, rhs) {
+            (Value::Integer(_), Value::Integer(0)) | (Value::Float(_), Value::Float(0.0)) if self.divides => {
+                Err("Division by zero".to_string())
+            }
+            (Value::Integer(l), Value::Integer(r)) => (self.integer_operation)(*l, *r)
+                .map(Value::Integer)
+                .ok_or_else(|| "Integer overflow".to_string()),
+            (Value::Float(l), Value::Float(r)) => {
+                let result = (self.float_operation)(*l, *r);
+                if result.is_finite() {
+                    Ok(Value::Float(result))
+                } else {
+                    Err("Decimal overflow".to_string())
+                }
+            }
+            (l, r) => return Err(RuntimeError::invalid_operands(self.operation, l, r)),
+        };
+
+        Ok(math_result(&environment, outcome))
+    }
+}
+
+fn math_result(environment: &Environment, outcome: Result<Value, String>) -> Value {
+    let mut instance = Struct::new(ModuleAddress::new("Math".into(), "Result".into()));
+
+    let (ok, value, error) = match outcome {
+        Ok(value) => (true, value, Value::Null),
+        Err(error) => (false, Value::Null, Value::String(error)),
+    };
+
+    let members = instance.get_members_mut();
+    // The members are freshly created, so inserting them cannot collide.
+    let _ = members.insert_member("ok".into(), Value::Bool(ok), true);
+    let _ = members.insert_member("value".into(), value, true);
+    let _ = members.insert_member("error".into(), error, true);
+
+    instance.track(environment.context.clone());
+
+    Value::Struct(StructOwner::new(instance))
+}
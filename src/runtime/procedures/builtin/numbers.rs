@@ -13,9 +13,7 @@ pub(crate) struct NumberParseProcedure;
 
 impl Procedure for NumberParseProcedure {
     fn call(&self, _environment: crate::runtime::environment::Environment, arguments: Vec<crate::runtime::Value>) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
-        let value = arguments.get(0).ok_or(RuntimeError {
-            message: "Missing argument for 'Numbers::parse'!".into()
-        })?;
+        let value = arguments.get(0).ok_or_else(|| RuntimeError::missing_argument("Numbers::parse", None))?;
 
         match value {
 
@@ -23,9 +21,7 @@ impl Procedure for NumberParseProcedure {
                 let n = *c as u8;
 
                 if n < '0' as u8 || n > '9' as u8 {
-                    Err(RuntimeError {
-                        message: format!("'{}' is not a valid digit!", c)
-                    })
+                    Err(RuntimeError::invalid_argument("Numbers::parse", format!("'{}' is not a valid digit!", c)))
                 } else {
                     Ok(Value::Integer((n - '0' as u8) as i64))
                 }
@@ -36,15 +32,11 @@ impl Procedure for NumberParseProcedure {
                 } else if let Ok(float) = str.parse() {
                     Ok(Value::Float(float))
                 } else {
-                    Err(RuntimeError {
-                        message: format!("'{}' is not a valid number!", str)
-                    })
+                    Err(RuntimeError::invalid_argument("Numbers::parse", format!("'{}' is not a valid number!", str)))
                 }
             }
 
-            other => Err(RuntimeError {
-                message: format!("Cannot parse number from value of type {}!", other.get_type_id())
-            })
+            other => Err(RuntimeError::argument_type("Numbers::parse", "value", "Char or String", other))
         }
     }
 }
\ No newline at end of file
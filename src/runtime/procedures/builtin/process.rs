@@ -0,0 +1,82 @@
+use std::{process::{Command, Stdio}, thread};
+
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("run".into(), Box::new(ProcessRunProcedure), true);
+    module.insert_procedure("spawn".into(), Box::new(ProcessSpawnProcedure), true);
+
+    module
+}
+
+// The program is looked up in the PATH. Its arguments are optional and passed as they are,
+// without going through a shell.
+fn command(arguments: &[Value], procedure: &'static str) -> Result<Command, RuntimeError> {
+    let program = match arguments.first() {
+        Some(Value::String(program)) => program,
+        Some(other) => return Err(RuntimeError::argument_type(procedure, "command", "String", other)),
+        None => return Err(RuntimeError::missing_argument(procedure, Some("command"))),
+    };
+
+    let mut command = Command::new(program);
+
+    match arguments.get(1) {
+        Some(Value::Array(values)) => for value in values {
+            match value {
+                Value::String(argument) => command.arg(argument),
+                other => return Err(RuntimeError::argument_type(procedure, "argument", "String", other)),
+            };
+        },
+        Some(Value::Null) | None => {}
+        Some(other) => return Err(RuntimeError::argument_type(procedure, "arguments", "Array", other)),
+    }
+
+    Ok(command)
+}
+
+fn not_started(procedure: &'static str, arguments: &[Value], err: std::io::Error) -> RuntimeError {
+    RuntimeError::io(format!("Command {:?} of '{}'", arguments[0], procedure), "started", err)
+}
+
+// Waits for the command and returns [exit code, stdout, stderr]. The exit code is null if the
+// command was terminated by a signal. The command reads no input.
+#[derive(Debug)]
+pub(crate) struct ProcessRunProcedure;
+
+impl Procedure for ProcessRunProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let output = command(&arguments, "Process::run")?
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| not_started("Process::run", &arguments, err))?;
+
+        Ok(Value::Array(vec![
+            output.status.code().map(|code| Value::Integer(code as i64)).unwrap_or(Value::Null),
+            Value::String(String::from_utf8_lossy(&output.stdout).to_string()),
+            Value::String(String::from_utf8_lossy(&output.stderr).to_string()),
+        ]))
+    }
+}
+
+// Starts the command without waiting for it and returns its process id. The command writes
+// to the same stdout and stderr as the program.
+#[derive(Debug)]
+pub(crate) struct ProcessSpawnProcedure;
+
+impl Procedure for ProcessSpawnProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut child = command(&arguments, "Process::spawn")?
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|err| not_started("Process::spawn", &arguments, err))?;
+
+        let id = child.id();
+
+        // Reaps the child once it exits, so it does not linger as a zombie.
+        thread::spawn(move || child.wait());
+
+        Ok(Value::Integer(id as i64))
+    }
+}
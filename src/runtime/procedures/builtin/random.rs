@@ -0,0 +1,97 @@
+use crate::runtime::{RuntimeError, Value, context::RuntimeContext, environment::Environment, module::Module, procedures::{Procedure, builtin::integer_argument}};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("int".into(), Box::new(RandomIntProcedure), true);
+    module.insert_procedure("float".into(), Box::new(RandomFloatProcedure), true);
+    module.insert_procedure("shuffle".into(), Box::new(RandomShuffleProcedure), true);
+    module.insert_procedure("seed".into(), Box::new(RandomSeedProcedure), true);
+
+    module
+}
+
+// A uniformly distributed number below the bound, which has to be positive.
+fn below(context: &RuntimeContext, bound: u64) -> u64 {
+    let zone = u64::MAX - u64::MAX % bound;
+
+    loop {
+        let value = context.next_random();
+
+        if value < zone {
+            return value % bound;
+        }
+    }
+}
+
+// Both bounds are inclusive.
+#[derive(Debug)]
+pub(crate) struct RandomIntProcedure;
+
+impl Procedure for RandomIntProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let min = integer_argument(&arguments, 0, "minimum", "Random::int")?;
+        let max = integer_argument(&arguments, 1, "maximum", "Random::int")?;
+
+        if min > max {
+            return Err(RuntimeError::invalid_argument("Random::int", format!("The minimum is greater than the maximum! {} > {}", min, max)));
+        }
+
+        // Zero if the range covers every Integer.
+        let span = (max.wrapping_sub(min) as u64).wrapping_add(1);
+
+        let offset = match span {
+            0 => environment.context.next_random(),
+            span => below(&environment.context, span),
+        };
+
+        Ok(Value::Integer(min.wrapping_add(offset as i64)))
+    }
+}
+
+// A Float in [0, 1).
+#[derive(Debug)]
+pub(crate) struct RandomFloatProcedure;
+
+impl Procedure for RandomFloatProcedure {
+    fn call(&self, environment: Environment, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let bits = environment.context.next_random() >> 11;
+
+        Ok(Value::Float(bits as f64 / (1u64 << 53) as f64))
+    }
+}
+
+// Returns a shuffled copy of the array.
+#[derive(Debug)]
+pub(crate) struct RandomShuffleProcedure;
+
+impl Procedure for RandomShuffleProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut values = match arguments.into_iter().next() {
+            Some(Value::Array(values)) => values,
+            Some(other) => return Err(RuntimeError::argument_type("Random::shuffle", "array", "Array", &other)),
+            None => return Err(RuntimeError::missing_argument("Random::shuffle", Some("array"))),
+        };
+
+        for i in (1..values.len()).rev() {
+            let j = below(&environment.context, i as u64 + 1) as usize;
+            values.swap(i, j);
+        }
+
+        Ok(Value::Array(values))
+    }
+}
+
+// The same seed yields the same numbers on every run and platform.
+#[derive(Debug)]
+pub(crate) struct RandomSeedProcedure;
+
+impl Procedure for RandomSeedProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let seed = integer_argument(&arguments, 0, "seed", "Random::seed")?;
+
+        environment.context.seed_random(seed as u64);
+
+        Ok(Value::Null)
+    }
+}
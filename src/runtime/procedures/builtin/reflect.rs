@@ -0,0 +1,115 @@
+use crate::runtime::{ModuleAddress, RuntimeError, RuntimeErrorKind, StructOwner, Value, stats::Footprint, environment::Environment, module::Module, procedures::Procedure};
+
+// Maps are represented as arrays of [key, value] pairs with String keys, sorted by key.
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("toMap".into(), Box::new(ReflectToMapProcedure), true);
+    module.insert_procedure("fromMap".into(), Box::new(ReflectFromMapProcedure), true);
+    module.insert_procedure("modules".into(), Box::new(ReflectModulesProcedure), true);
+
+    module
+}
+
+// Lists the public fields of a struct. Private fields are left out.
+#[derive(Debug)]
+pub(crate) struct ReflectToMapProcedure;
+
+impl Procedure for ReflectToMapProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let instance = match arguments.first() {
+            Some(Value::Struct(instance)) => instance.object()?,
+            Some(Value::StructRef(instance)) => instance.upgrade().ok_or_else(|| RuntimeError::from(RuntimeErrorKind::DroppedValue))?,
+            Some(other) => return Err(RuntimeError::argument_type("Reflect::toMap", "value", "Struct", other)),
+            None => return Err(RuntimeError::missing_argument("Reflect::toMap", None)),
+        };
+
+        let instance = instance.borrow();
+
+        let mut fields: Vec<(&str, &Value)> = instance.get_members()
+            .public_members()
+            .map(|(field, value)| (field.as_str(), value))
+            .collect();
+        fields.sort_by_key(|(field, _)| *field);
+
+        Ok(Value::Array(
+            fields
+                .into_iter()
+                .map(|(field, value)| Value::Array(vec![Value::String(field.to_string()), value.clone()]))
+                .collect()
+        ))
+    }
+}
+
+// Lists the ids of all loaded modules, builtin ones included, sorted by id.
+#[derive(Debug)]
+pub(crate) struct ReflectModulesProcedure;
+
+impl Procedure for ReflectModulesProcedure {
+    fn call(&self, environment: Environment, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        Ok(Value::Array(
+            environment
+                .loaded_modules
+                .keys()
+                .map(|module_id| Value::String(module_id.clone()))
+                .collect()
+        ))
+    }
+}
+
+// Builds an exported struct from a map. Every public field has to be present in the map;
+// unknown and private fields are rejected.
+#[derive(Debug)]
+pub(crate) struct ReflectFromMapProcedure;
+
+impl Procedure for ReflectFromMapProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+
+        let struct_id = match arguments.next() {
+            Some(Value::String(struct_id)) => struct_id,
+            _ => return Err(RuntimeError::invalid_argument("Reflect::fromMap", "Expected a struct name like \"Module::Struct\" as the first argument!")),
+        };
+
+        let (module_id, identifier) = struct_id.split_once("::").ok_or_else(|| RuntimeError::invalid_argument("Reflect::fromMap", format!("'{}' is not a struct name like \"Module::Struct\"!", struct_id)))?;
+
+        let entries = match arguments.next() {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(RuntimeError::invalid_argument("Reflect::fromMap", "Expected a map as the second argument!")),
+        };
+
+        let mut instance = environment.get_struct_by_address(&ModuleAddress::new(module_id.into(), identifier.into()))?;
+
+        let mut missing: Vec<String> = instance.get_members().public_members().map(|(field, _)| field.to_string()).collect();
+
+        for entry in entries {
+            let (field, value) = match entry {
+                Value::Array(pair) if pair.len() == 2 => {
+                    let mut pair = pair.into_iter();
+                    match (pair.next(), pair.next()) {
+                        (Some(Value::String(field)), Some(value)) => (field, value),
+                        _ => return Err(RuntimeError::invalid_argument("Reflect::fromMap", "Map keys have to be of type String!")),
+                    }
+                }
+                _ => return Err(RuntimeError::invalid_argument("Reflect::fromMap", "Map entries have to be [key, value] pairs!")),
+            };
+
+            let member = instance.get_members_mut().get_public_member_mut(&field).map_err(|err| RuntimeError::invalid_argument("Reflect::fromMap", format!("Cannot set field '{}' of struct '{}'! {}", field, struct_id, err)))?;
+
+            let footprint = Footprint::of(&value);
+            let previous = std::mem::replace(member, value);
+
+            environment.context.stats.retain(footprint);
+            environment.context.stats.release(Footprint::of(&previous));
+
+            missing.retain(|missing| missing != &field);
+        }
+
+        if let Some(field) = missing.iter().min() {
+            return Err(RuntimeError::invalid_argument("Reflect::fromMap", format!("Missing field '{}' for struct '{}'!", field, struct_id)));
+        }
+
+        Ok(Value::Struct(StructOwner::new(instance)))
+    }
+}
@@ -0,0 +1,32 @@
+use crate::runtime::{ModuleAddress, RuntimeError, Struct, StructOwner, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("stats".into(), Box::new(RuntimeStatsProcedure), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct RuntimeStatsProcedure;
+
+impl Procedure for RuntimeStatsProcedure {
+    fn call(&self, environment: Environment, _arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let stats = environment.context.stats.snapshot();
+
+        let mut instance = Struct::new(ModuleAddress::new("Runtime".into(), "Stats".into()));
+
+        let members = instance.get_members_mut();
+        members.insert_member("liveStructs".into(), Value::Integer(stats.live_structs as i64), true)?;
+        members.insert_member("liveArrays".into(), Value::Integer(stats.live_arrays as i64), true)?;
+        members.insert_member("liveStrings".into(), Value::Integer(stats.live_strings as i64), true)?;
+        members.insert_member("stackDepth".into(), Value::Integer(stats.stack_depth as i64), true)?;
+        members.insert_member("instructionCount".into(), Value::Integer(stats.instruction_count as i64), true)?;
+        members.insert_member("heapBytes".into(), Value::Integer(stats.heap_bytes as i64), true)?;
+
+        instance.track(environment.context.clone());
+
+        Ok(Value::Struct(StructOwner::new(instance)))
+    }
+}
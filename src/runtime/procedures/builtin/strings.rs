@@ -1,4 +1,4 @@
-use crate::runtime::{RuntimeError, Value, module::Module, procedures::Procedure};
+use crate::runtime::{RuntimeError, RuntimeErrorKind, Value, environment::Environment, expressions::arithmetic::repeat_string, module::Module, procedures::{Procedure, builtin::{integer_argument, string_argument}}};
 
 
 pub(crate) fn get_module() -> Module {
@@ -7,27 +7,45 @@ pub(crate) fn get_module() -> Module {
     module.insert_procedure("length".into(), Box::new(StringLengthProcdure), true);
     module.insert_procedure("toCharArray".into(), Box::new(StringToCharArrayProcedure), true);
     module.insert_procedure("split".into(), Box::new(StringSplitProcedure), true);
+    module.insert_procedure("substring".into(), Box::new(StringSubstringProcedure), true);
+    module.insert_procedure("replace".into(), Box::new(StringReplaceProcedure), true);
+    module.insert_procedure("trim".into(), Box::new(StringTrimProcedure), true);
+    module.insert_procedure("toUpper".into(), Box::new(StringCaseProcedure::UPPER), true);
+    module.insert_procedure("toLower".into(), Box::new(StringCaseProcedure::LOWER), true);
+    module.insert_procedure("capitalize".into(), Box::new(StringCaseProcedure::CAPITALIZE), true);
+    module.insert_procedure("contains".into(), Box::new(StringSearchProcedure::CONTAINS), true);
+    module.insert_procedure("startsWith".into(), Box::new(StringSearchProcedure::STARTS_WITH), true);
+    module.insert_procedure("endsWith".into(), Box::new(StringSearchProcedure::ENDS_WITH), true);
+    module.insert_procedure("indexOf".into(), Box::new(StringIndexOfProcedure), true);
+    module.insert_procedure("format".into(), Box::new(StringFormatProcedure), true);
+    module.insert_procedure("repeat".into(), Box::new(StringRepeatProcedure), true);
+    module.insert_procedure("padStart".into(), Box::new(StringPadProcedure::START), true);
+    module.insert_procedure("padEnd".into(), Box::new(StringPadProcedure::END), true);
     
     module
 }
 
+// A String, or a Char taken as a String of length one.
+fn text_argument(arguments: &[Value], index: usize, name: &'static str, procedure: &'static str) -> Result<String, RuntimeError> {
+    match arguments.get(index) {
+        Some(Value::Char(char)) => Ok(char.to_string()),
+        _ => string_argument(arguments, index, name, procedure),
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct StringLengthProcdure;
 
 impl Procedure for StringLengthProcdure {
     fn call(&self, _environment: crate::runtime::environment::Environment, arguments: Vec<crate::runtime::Value>) -> Result<crate::runtime::Value, crate::runtime::RuntimeError> {
-        let str = arguments.get(0).ok_or(RuntimeError {
-            message: "Missing argument for 'Strings::length'!".into()
-        })?;
+        let str = arguments.get(0).ok_or_else(|| RuntimeError::missing_argument("Strings::length", None))?;
 
         match str {
             Value::String(str) => {
-                Ok(Value::Integer(str.len() as i64))
+                Ok(Value::Integer(str.chars().count() as i64))
             }
 
-            other => {Err(RuntimeError {
-                message: format!("Cannot compute string length for value of type '{}'", other.get_type_id())
-            })}
+            other => {Err(RuntimeError::argument_type("Strings::length", "value", "String", other))}
         }
     }
 }
@@ -37,18 +55,14 @@ pub(crate) struct StringToCharArrayProcedure;
 
 impl Procedure for StringToCharArrayProcedure {
     fn call(&self, _environment: crate::runtime::environment::Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
-        let str = arguments.get(0).ok_or(RuntimeError {
-            message: "Missing argument for 'Strings::toCharArray'!".into()
-        })?;
+        let str = arguments.get(0).ok_or_else(|| RuntimeError::missing_argument("Strings::toCharArray", None))?;
 
         match str {
             Value::String(str) => {
                 Ok(Value::Array(str.chars().map(|c| Value::Char(c)).collect()))
             }
 
-            other => {Err(RuntimeError {
-                message: format!("Cannot compute Char array from value of type '{}'", other.get_type_id())
-            })}
+            other => {Err(RuntimeError::argument_type("Strings::toCharArray", "value", "String", other))}
         }
     }
 }
@@ -58,24 +72,249 @@ pub(crate) struct StringSplitProcedure;
 
 impl Procedure for StringSplitProcedure {
     fn call(&self, _environment: crate::runtime::environment::Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
-        let str = arguments.get(0).ok_or(RuntimeError {
-            message: "Missing string argument for 'Strings::toCharArray'!".into()
-        })?;
+        let str = arguments.get(0).ok_or_else(|| RuntimeError::missing_argument("Strings::split", Some("string")))?;
         let str = if let Value::String(str) = str { str } else {
-            return Err(RuntimeError {
-                message: format!("Cannot split value of type '{}'!", str.get_type_id())
-            });
+            return Err(RuntimeError::argument_type("Strings::split", "string", "String", str));
         };
 
-        let pattern = arguments.get(1).ok_or(RuntimeError {
-            message: "Missing pattern argument for 'Strings::toCharArray'!".into()
-        })?;
+        let pattern = arguments.get(1).ok_or_else(|| RuntimeError::missing_argument("Strings::split", Some("pattern")))?;
         let pattern = if let Value::String(pattern) = pattern { pattern } else {
-            return Err(RuntimeError {
-                message: format!("Cannot split value of type '{}'!", pattern.get_type_id())
-            });
+            return Err(RuntimeError::argument_type("Strings::split", "pattern", "String", pattern));
         };
 
         Ok(Value::Array(str.split(pattern).map(|part| Value::String(part.into())).collect()))
     }
-}
\ No newline at end of file
+}
+
+// The characters from the first index up to, but not including, the second one. Without the
+// second index the substring reaches to the end. Negative indices count from the end.
+#[derive(Debug)]
+pub(crate) struct StringSubstringProcedure;
+
+impl Procedure for StringSubstringProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Strings::substring")?;
+        let len = string.chars().count() as i64;
+
+        let from = integer_argument(&arguments, 1, "start index", "Strings::substring")?;
+        let to = match arguments.get(2) {
+            None | Some(Value::Null) => len,
+            Some(_) => integer_argument(&arguments, 2, "end index", "Strings::substring")?,
+        };
+
+        let resolve = |index: i64| if index < 0 { index + len } else { index };
+        let (start, end) = (resolve(from), resolve(to));
+
+        if start < 0 || end > len || start > end {
+            return Err(RuntimeError::invalid_argument("Strings::substring", format!("Cannot take the substring from {} to {} of a string of length {}!", from, to, len)));
+        }
+
+        Ok(Value::String(string.chars().skip(start as usize).take((end - start) as usize).collect()))
+    }
+}
+
+// Replaces every occurrence of the pattern, which may be a String or a Char.
+#[derive(Debug)]
+pub(crate) struct StringReplaceProcedure;
+
+impl Procedure for StringReplaceProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Strings::replace")?;
+        let pattern = text_argument(&arguments, 1, "pattern", "Strings::replace")?;
+        let replacement = text_argument(&arguments, 2, "replacement", "Strings::replace")?;
+
+        if pattern.is_empty() {
+            return Err(RuntimeError::invalid_argument("Strings::replace", "The pattern may not be empty!"));
+        }
+
+        Ok(Value::String(string.replace(&pattern, &replacement)))
+    }
+}
+
+// Removes leading and trailing whitespace.
+#[derive(Debug)]
+pub(crate) struct StringTrimProcedure;
+
+impl Procedure for StringTrimProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Strings::trim")?;
+
+        Ok(Value::String(string.trim().to_string()))
+    }
+}
+
+// Case conversions follow unicode, so a single character may turn into several, e.g. 'ß' into "SS".
+#[derive(Debug)]
+pub(crate) struct StringCaseProcedure {
+    name: &'static str,
+    convert: fn(&str) -> String,
+}
+
+impl StringCaseProcedure {
+    const UPPER: Self = Self { name: "Strings::toUpper", convert: str::to_uppercase };
+    const LOWER: Self = Self { name: "Strings::toLower", convert: str::to_lowercase };
+    // Only the first character is changed, the rest is kept as it is.
+    const CAPITALIZE: Self = Self { name: "Strings::capitalize", convert: |string| {
+        let mut chars = string.chars();
+
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } };
+}
+
+impl Procedure for StringCaseProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", self.name)?;
+
+        Ok(Value::String((self.convert)(&string)))
+    }
+}
+
+// The searched text may be a String or a Char. Every string contains the empty one.
+#[derive(Debug)]
+pub(crate) struct StringSearchProcedure {
+    name: &'static str,
+    matches: fn(&str, &str) -> bool,
+}
+
+impl StringSearchProcedure {
+    const CONTAINS: Self = Self { name: "Strings::contains", matches: |string, search| string.contains(search) };
+    const STARTS_WITH: Self = Self { name: "Strings::startsWith", matches: |string, search| string.starts_with(search) };
+    const ENDS_WITH: Self = Self { name: "Strings::endsWith", matches: |string, search| string.ends_with(search) };
+}
+
+impl Procedure for StringSearchProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", self.name)?;
+        let search = text_argument(&arguments, 1, "search", self.name)?;
+
+        Ok(Value::Bool((self.matches)(&string, &search)))
+    }
+}
+
+// The char index of the first occurrence, or null if there is none.
+#[derive(Debug)]
+pub(crate) struct StringIndexOfProcedure;
+
+impl Procedure for StringIndexOfProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", "Strings::indexOf")?;
+        let search = text_argument(&arguments, 1, "search", "Strings::indexOf")?;
+
+        Ok(match string.find(&search) {
+            Some(byte_index) => Value::Integer(string[..byte_index].chars().count() as i64),
+            None => Value::Null,
+        })
+    }
+}
+
+// Replaces each '{}' in the template with the next argument, written the same way as by
+// 'IO::print'. '{{' and '}}' stand for literal braces. There has to be exactly one argument per
+// placeholder.
+#[derive(Debug)]
+pub(crate) struct StringFormatProcedure;
+
+impl Procedure for StringFormatProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let template = string_argument(&arguments, 0, "template", "Strings::format")?;
+        let values = &arguments[1..];
+
+        let mut output = String::new();
+        let mut used = 0;
+        let mut chars = template.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            match (char, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                    output.push(char);
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    let value = values.get(used).ok_or_else(|| RuntimeError::invalid_argument("Strings::format", format!("The template has more placeholders than the {} arguments given!", values.len())))?;
+                    output.push_str(&value.to_string());
+                    used += 1;
+                }
+                ('{' | '}', _) => return Err(RuntimeError::invalid_argument("Strings::format", format!("Unmatched '{}' in the template! Use '{}{}' for a literal brace.", char, char, char))),
+                _ => output.push(char),
+            }
+        }
+
+        if used < values.len() {
+            return Err(RuntimeError::invalid_argument("Strings::format", format!("{} arguments were given for {} placeholders!", values.len(), used)));
+        }
+
+        Ok(Value::String(output))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StringRepeatProcedure;
+
+impl Procedure for StringRepeatProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = text_argument(&arguments, 0, "string", "Strings::repeat")?;
+        let count = integer_argument(&arguments, 1, "count", "Strings::repeat")?;
+
+        if count < 0 {
+            return Err(RuntimeError::invalid_argument("Strings::repeat", format!("The count may not be negative, found {}!", count)));
+        }
+
+        repeat_string(&string, count as usize, &environment.context).map(Value::String)
+    }
+}
+
+// Fills the string up to the given number of chars. The fill defaults to a space and may be a
+// String or a Char; a longer fill is repeated and cut off where the length is reached. Strings
+// that are long enough already are returned as they are.
+#[derive(Debug)]
+pub(crate) struct StringPadProcedure {
+    name: &'static str,
+    at_start: bool,
+}
+
+impl StringPadProcedure {
+    const START: Self = Self { name: "Strings::padStart", at_start: true };
+    const END: Self = Self { name: "Strings::padEnd", at_start: false };
+}
+
+impl Procedure for StringPadProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let string = string_argument(&arguments, 0, "string", self.name)?;
+        let length = integer_argument(&arguments, 1, "length", self.name)?;
+        let fill = match arguments.get(2) {
+            None | Some(Value::Null) => " ".to_string(),
+            Some(_) => text_argument(&arguments, 2, "fill", self.name)?,
+        };
+
+        if fill.is_empty() {
+            return Err(RuntimeError::invalid_argument(self.name, "The fill may not be empty!"));
+        }
+
+        let missing = (length.max(0) as usize).saturating_sub(string.chars().count());
+
+        // The padding is allocated up front, so a length too large for memory is an error.
+        let fill_chars = fill.chars().count();
+        let bytes = (missing / fill_chars)
+            .checked_mul(fill.len())
+            .and_then(|bytes| bytes.checked_add(fill.chars().take(missing % fill_chars).map(char::len_utf8).sum()))
+            .and_then(|bytes| bytes.checked_add(string.len()))
+            .unwrap_or(usize::MAX);
+        environment.context.reserve_memory(bytes)?;
+
+        let mut padded = String::new();
+        padded.try_reserve_exact(bytes).map_err(|_| RuntimeError::from(RuntimeErrorKind::AllocationTooLarge { bytes }))?;
+
+        if !self.at_start {
+            padded.push_str(&string);
+        }
+        padded.extend(fill.chars().cycle().take(missing));
+        if self.at_start {
+            padded.push_str(&string);
+        }
+
+        Ok(Value::String(padded))
+    }
+}
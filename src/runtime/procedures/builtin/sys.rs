@@ -0,0 +1,24 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("exit".into(), Box::new(SysExitProcedure), true);
+
+    module
+}
+
+#[derive(Debug)]
+pub(crate) struct SysExitProcedure;
+
+impl Procedure for SysExitProcedure {
+    fn call(&self, environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let code = match arguments.first() {
+            Some(Value::Integer(code)) => i32::try_from(*code).map_err(|_| RuntimeError::invalid_argument("Sys::exit", format!("Exit code {} is out of range!", code)))?,
+            Some(other) => return Err(RuntimeError::argument_type("Sys::exit", "exit code", "Integer", other)),
+            None => 0,
+        };
+
+        Err(environment.context.exit(code))
+    }
+}
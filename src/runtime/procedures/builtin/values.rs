@@ -0,0 +1,22 @@
+use crate::runtime::{RuntimeError, Value, environment::Environment, module::Module, procedures::Procedure};
+
+pub(crate) fn get_module() -> Module {
+    let mut module = Module::default();
+
+    module.insert_procedure("deepCopy".into(), Box::new(ValuesDeepCopyProcedure), true);
+
+    module
+}
+
+// Copies a value along with every struct it references, so the copy shares nothing with the
+// original. Fails for references that are dead or lead back to a struct being copied.
+#[derive(Debug)]
+pub(crate) struct ValuesDeepCopyProcedure;
+
+impl Procedure for ValuesDeepCopyProcedure {
+    fn call(&self, _environment: Environment, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let value = arguments.first().ok_or_else(|| RuntimeError::missing_argument("Values::deepCopy", None))?;
+
+        value.deep_copy()
+    }
+}
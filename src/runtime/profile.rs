@@ -0,0 +1,109 @@
+use std::{collections::{BTreeMap, HashMap}, fmt::{self, Display}, time::{Duration, Instant}};
+
+use crate::runtime::ModuleAddress;
+
+// How often each procedure and each of its instructions ran during an execution and how long
+// they took. Times are cumulative: the time of a call includes the calls it makes, the time of
+// an instruction the calls made by its expression.
+#[derive(Debug, Default)]
+pub struct Profile {
+    procedures: HashMap<ModuleAddress, ProcedureProfile>,
+    // The procedures running at the moment, innermost last, with the start of their call.
+    frames: Vec<(ModuleAddress, Instant)>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProcedureProfile {
+    pub calls: u64,
+    pub time: Duration,
+    // By the index of the instruction, as shown by 'otr ast'. Builtin procedures have
+    // no instructions.
+    pub instructions: BTreeMap<usize, InstructionProfile>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InstructionProfile {
+    pub executions: u64,
+    pub time: Duration,
+}
+
+impl Profile {
+    pub(crate) fn enter(&mut self, procedure: &ModuleAddress) {
+        self.procedures.entry(procedure.clone()).or_default().calls += 1;
+        self.frames.push((procedure.clone(), Instant::now()));
+    }
+
+    pub(crate) fn exit(&mut self) {
+        if let Some((procedure, started)) = self.frames.pop() {
+            if let Some(profile) = self.procedures.get_mut(&procedure) {
+                profile.time += started.elapsed();
+            }
+        }
+    }
+
+    // Counts an instruction of the innermost procedure that started running at the given time.
+    pub(crate) fn instruction(&mut self, pc: usize, started: Instant) {
+        let elapsed = started.elapsed();
+
+        let Some((procedure, _)) = self.frames.last() else {
+            return;
+        };
+
+        if let Some(profile) = self.procedures.get_mut(procedure) {
+            let instruction = profile.instructions.entry(pc).or_default();
+
+            instruction.executions += 1;
+            instruction.time += elapsed;
+        }
+    }
+
+    pub fn procedure(&self, address: &ModuleAddress) -> Option<&ProcedureProfile> {
+        self.procedures.get(address)
+    }
+
+    // Every procedure that was called, the one that took longest first.
+    pub fn procedures(&self) -> Vec<(&ModuleAddress, &ProcedureProfile)> {
+        let mut procedures: Vec<_> = self.procedures.iter().collect();
+
+        procedures.sort_by(|(a, a_profile), (b, b_profile)| {
+            b_profile.time.cmp(&a_profile.time).then_with(|| a.to_string().cmp(&b.to_string()))
+        });
+
+        procedures
+    }
+
+    // The instructions that took longest over all procedures, at most the given number.
+    pub fn hot_instructions(&self, count: usize) -> Vec<(&ModuleAddress, usize, InstructionProfile)> {
+        let mut instructions: Vec<_> = self.procedures
+            .iter()
+            .flat_map(|(address, profile)| profile.instructions.iter().map(move |(pc, instruction)| (address, *pc, *instruction)))
+            .collect();
+
+        instructions.sort_by(|(a, a_pc, a_profile), (b, b_pc, b_profile)| {
+            b_profile.time.cmp(&a_profile.time)
+                .then_with(|| a.to_string().cmp(&b.to_string()))
+                .then_with(|| a_pc.cmp(b_pc))
+        });
+        instructions.truncate(count);
+
+        instructions
+    }
+}
+
+// A report of the procedures and the ten hottest instructions.
+impl Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<32} {:>10} {:>14}", "procedure", "calls", "time")?;
+        for (address, profile) in self.procedures() {
+            writeln!(f, "{:<32} {:>10} {:>14?}", address.to_string(), profile.calls, profile.time)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:<32} {:>10} {:>14}", "instruction", "executions", "time")?;
+        for (address, pc, instruction) in self.hot_instructions(10) {
+            writeln!(f, "{:<32} {:>10} {:>14?}", format!("{} @ {}", address, pc), instruction.executions, instruction.time)?;
+        }
+
+        Ok(())
+    }
+}
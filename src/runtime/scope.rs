@@ -1,25 +1,66 @@
-use std::{collections::HashMap, ops::Deref, rc::Rc};
+use std::{cell::Cell, collections::BTreeMap, fmt::Display, rc::Rc};
 
 use derive_more::{Deref, IntoIterator};
 
-use crate::{compiler::{CompilerError, expression_parser::ExpressionParser}, lexer::token::{ParenthesisType, PunctuationToken, Token}, runtime::{Expression, RuntimeError, Value, environment::Environment}};
+use crate::{compiler::{CompilerError, optimizer::ConstantFolder, type_checker::{TypeEnvironment, expect_type}}, lexer::token::PrimitiveTypeToken, runtime::{Expression, MemberKey, MemberMap, RuntimeError, RuntimeErrorKind, Value, environment::Environment, symbol::Symbol}};
 
 
 #[derive(Debug, Clone)]
 pub enum ScopeAddressant {
-    Identifier(String),
-    Index(usize),
+    Identifier(Symbol),
+    Field(FieldAccessor),
+    Index(i64),
     DynamicIndex(Rc<dyn Expression>),
 }
 
+// A struct field following the variable in an address, as in 'point.x'. The index the field
+// was last found at is shared by all clones of the address, so once a struct type was seen the
+// field is read by its index. Structs of other types fall back to a lookup by name.
+#[derive(Debug, Clone)]
+pub struct FieldAccessor {
+    name: Symbol,
+    index: Rc<Cell<usize>>,
+}
+
+impl FieldAccessor {
+    pub fn new(name: Symbol) -> Self {
+        Self { name, index: Rc::new(Cell::new(0)) }
+    }
+
+    pub fn name(&self) -> Symbol {
+        self.name
+    }
+}
+
+impl Display for FieldAccessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl MemberKey for FieldAccessor {
+    fn locate(&self, members: &MemberMap) -> Option<usize> {
+        let index = self.index.get();
+
+        if members.name_at(index) == Some(self.name) {
+            return Some(index);
+        }
+
+        let index = members.position(self.name)?;
+        self.index.set(index);
+
+        Some(index)
+    }
+}
+
 impl From<&str> for ScopeAddressant {
     fn from(value: &str) -> Self {
         Self::Identifier(value.into())
     }
 }
 
-impl From<usize> for ScopeAddressant {
-    fn from(value: usize) -> Self {
+impl From<i64> for ScopeAddressant {
+    fn from(value: i64) -> Self {
         Self::Index(value)
     }
 }
@@ -30,6 +71,18 @@ impl<E: Expression + 'static> From<E> for ScopeAddressant {
     }
 }
 
+// E.g. "field 'x'", for errors about addressing a value.
+impl Display for ScopeAddressant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Identifier(identifier) => write!(f, "identifier '{}'", identifier),
+            Self::Field(field) => write!(f, "field '{}'", field),
+            Self::Index(index) => write!(f, "index {}", index),
+            Self::DynamicIndex(expression) => write!(f, "index '{}'", expression.summary()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScopeAddress(Vec<ScopeAddressant>);
 
@@ -45,77 +98,75 @@ impl TryFrom<Vec<ScopeAddressant>> for ScopeAddress {
     }
 }
 
-impl TryFrom<Vec<Token>> for ScopeAddress {
-    type Error = CompilerError;
+impl ScopeAddress {
+    pub(crate) fn addressants(&self) -> &[ScopeAddressant] {
+        &self.0
+    }
 
-    fn try_from(value: Vec<Token>) -> Result<Self, Self::Error> {
-        let mut tokens = value.into_iter();
-        
-        let mut addressants = Vec::new();
+    pub fn as_identifier(&self) -> Option<Symbol> {
+        match self.0.as_slice() {
+            [ScopeAddressant::Identifier(ident)] => Some(*ident),
+            _ => None,
+        }
+    }
 
-        while let Some(token) = tokens.next() {
-            match token {
-                Token::Identifier(ident) => {
-                    addressants.push(ScopeAddressant::Identifier(ident));
-                }
-                Token::Punctuation(PunctuationToken::Dot) => {}
-                Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Opening)) => {
-                    let index_expression = ExpressionParser::take_until_closing(
-                        &mut tokens,
-                        Token::Punctuation(PunctuationToken::SquareBrackets(ParenthesisType::Closing))
-                    )?;
+    pub fn static_type(&self, types: &TypeEnvironment) -> Result<Option<PrimitiveTypeToken>, CompilerError> {
+        for addressant in &self.0 {
+            if let ScopeAddressant::DynamicIndex(expression) = addressant {
+                expect_type(expression.as_ref(), types, PrimitiveTypeToken::Integer, "index")?;
+            }
+        }
 
-                    let index_expression = ExpressionParser::parse(index_expression)?;
+        Ok(self.as_identifier().and_then(|ident| types.get(&ident)).cloned())
+    }
 
-                    addressants.push(ScopeAddressant::DynamicIndex(index_expression.into()));
-                }
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
 
-                other => {
-                    return Err(CompilerError {
-                        message: format!("Invalid address. Found unexpected token {:?}!", other)
-                    });
+        for addressant in &self.0 {
+            match addressant {
+                ScopeAddressant::Identifier(ident) => {
+                    if !summary.is_empty() {
+                        summary.push('.');
+                    }
+                    summary.push_str(ident.as_str());
                 }
+                ScopeAddressant::Field(field) => summary.push_str(&format!(".{}", field)),
+                ScopeAddressant::Index(idx) => summary.push_str(&format!("[{}]", idx)),
+                ScopeAddressant::DynamicIndex(expression) => summary.push_str(&format!("[{}]", expression.summary())),
             }
         }
 
+        summary
+    }
 
-        addressants.try_into().map_err(|_| CompilerError { message: "Address could not be parsed!".into() })
+    // Folds the index expressions. When resolving slots, constant indices are stored as plain
+    // indices, so they are not evaluated on every access.
+    pub(crate) fn fold_constants(&mut self, folder: &ConstantFolder) {
+        for addressant in self.0.iter_mut() {
+            let ScopeAddressant::DynamicIndex(expression) = addressant else {
+                continue;
+            };
+            let Some(value) = Rc::get_mut(expression).and_then(|expression| expression.fold_constants(folder)) else {
+                continue;
+            };
+
+            *addressant = match value {
+                Value::Integer(index) if folder.resolves_slots() => ScopeAddressant::Index(index),
+                value => ScopeAddressant::DynamicIndex(Rc::new(value)),
+            };
+        }
     }
-}
 
-impl ScopeAddress {
     pub(crate) fn try_bake(self, environment: &Environment) -> Result<BakedScopeAddress, RuntimeError> {
         let mut out = Vec::with_capacity(self.0.len());
 
         for addressant in self.0 {
             let addressant = match addressant {
                 ScopeAddressant::Identifier(ident) => ScopeAddressant::Identifier(ident),
+                ScopeAddressant::Field(field) => ScopeAddressant::Field(field),
                 ScopeAddressant::Index(idx) => ScopeAddressant::Index(idx),
-                ScopeAddressant::DynamicIndex(expression) => {
-                    let value = expression.eval(environment)?;
-                    let idx: usize = match value {
-                        Value::Integer(value) => {
-                            let idx =
-                                value.try_into().map_err(|err: std::num::TryFromIntError| {
-                                    RuntimeError {
-                                        message: err.to_string(),
-                                    }
-                                })?;
-
-                            idx
-                        }
-                        _ => {
-                            return Err(RuntimeError {
-                                message: format!(
-                                    "Mismatched types! Expected Integer, found {}!",
-                                    value.get_type_id()
-                                ),
-                            })
-                        }
-                    };
-
-                    ScopeAddressant::Index(idx)
-                }
+                ScopeAddressant::DynamicIndex(expression) => ScopeAddressant::Index(index(expression.eval(environment)?)?),
             };
 
             out.push(addressant);
@@ -123,13 +174,42 @@ impl ScopeAddress {
 
         Ok(BakedScopeAddress(out))
     }
+
+    pub(crate) fn dynamic_indices(&self) -> impl Iterator<Item = &Rc<dyn Expression>> {
+        self.0.iter().filter_map(|addressant| match addressant {
+            ScopeAddressant::DynamicIndex(expression) => Some(expression),
+            _ => None,
+        })
+    }
+
+    // The address with its dynamic indices replaced by already evaluated ones, in order.
+    pub(crate) fn with_indices(&self, indices: impl IntoIterator<Item = Value>) -> Result<ScopeAddress, RuntimeError> {
+        let mut indices = indices.into_iter();
+
+        self.0
+            .iter()
+            .map(|addressant| match addressant {
+                ScopeAddressant::DynamicIndex(_) => Ok(ScopeAddressant::Index(index(indices.next().unwrap_or(Value::Null))?)),
+                addressant => Ok(addressant.clone()),
+            })
+            .collect::<Result<_, _>>()
+            .map(ScopeAddress)
+    }
+}
+
+fn index(value: Value) -> Result<i64, RuntimeError> {
+    match value {
+        Value::Integer(value) => Ok(value),
+        _ => Err(RuntimeErrorKind::ExpectedType { expected: PrimitiveTypeToken::Integer, found: value.get_type_id() }.into()),
+    }
 }
 
 #[derive(Deref, IntoIterator)]
 pub(crate) struct BakedScopeAddress(Vec<ScopeAddressant>);
 
+// Frames are ordered by symbol, so values are released in the same order every run.
 #[derive(Debug, Clone)]
-struct Stack (Vec<HashMap<String, Value>>);
+struct Stack (Vec<BTreeMap<Symbol, Value>>);
 
 impl Default for Stack {
     fn default() -> Self {
@@ -139,100 +219,74 @@ impl Default for Stack {
 
 impl Stack {
     fn new() -> Self {
-        Self(vec![HashMap::new()])    
+        Self(vec![BTreeMap::new()])    
     }
 
-    fn from_members(members: HashMap<String, Value>) -> Self {
-        Self(vec![members])
+    fn from_members(members: impl IntoIterator<Item = (Symbol, Value)>) -> Self {
+        Self(vec![members.into_iter().collect()])
     }
 
-    fn insert_members(&mut self, members: HashMap<String, Value>) {
+    fn insert_members(&mut self, members: impl IntoIterator<Item = (Symbol, Value)>) {
         let last = self.0.len() - 1;
-        self.0[last].extend(members.into_iter());
+        self.0[last].extend(members);
     }
     
     fn grow(&mut self) {
-        self.0.push(HashMap::new());
+        self.0.push(BTreeMap::new());
     }
 
-    fn shrink(&mut self) {
-        self.0.pop();
+    fn shrink(&mut self) -> Option<BTreeMap<Symbol, Value>> {
+        self.0.pop()
     }
 
-    fn push(&mut self, identifier: String, value: Value) -> Result<(), RuntimeError> {
-        let last = self.0.len() - 1;
-        if self.0[last].insert(identifier.clone(), value).is_some() {
-            return Err(RuntimeError {
-                message: format!("Variable '{}' already present in this scope!", identifier)
-            });
-        }
+    fn depth(&self) -> usize {
+        self.0.len()
+    }
 
-        Ok(())
+    fn values(&self) -> impl Iterator<Item = &Value> {
+        self.0.iter().flat_map(|frame| frame.values())
     }
 
-    fn pop(&mut self, identifier: &String) -> Result<(), RuntimeError> {
+    fn push(&mut self, identifier: Symbol, value: Value) -> Result<(), RuntimeError> {
         let last = self.0.len() - 1;
-        if self.0[last].remove(identifier).is_none() {
-            return Err(RuntimeError {
-                message: format!("Variable '{}' cannot be popped from the stack as it is not present!", identifier)
-            });
+        if self.0[last].insert(identifier, value).is_some() {
+            return Err(RuntimeErrorKind::DuplicateVariable { identifier: identifier.to_string() }.into());
         }
 
         Ok(())
     }
 
-    fn get(&self, identifier: &String) -> Result<&Value, RuntimeError> {
+    fn pop(&mut self, identifier: Symbol) -> Result<Value, RuntimeError> {
+        let last = self.0.len() - 1;
+        self.0[last].remove(&identifier).ok_or_else(|| RuntimeErrorKind::UndefinedVariable { identifier: identifier.to_string() }.into())
+    }
+
+    fn get(&self, identifier: Symbol) -> Result<&Value, RuntimeError> {
         for i in (0..self.0.len()).rev() {
-            if let Some(value) = self.0[i].get(identifier) {
+            if let Some(value) = self.0[i].get(&identifier) {
                 return Ok(value);
             }
         }
 
-        Err(RuntimeError {
-            message: format!(
-                "Could not find the variable '{}' in this scope!",
-                identifier
-            ),
-        })
+        Err(RuntimeError::from(RuntimeErrorKind::UndefinedVariable { identifier: identifier.to_string() }))
     }
 
-    fn get_mut(&mut self, identifier: &String) -> Result<&mut Value, RuntimeError> {
+    fn get_mut(&mut self, identifier: Symbol) -> Result<&mut Value, RuntimeError> {
         let last = self.0.len() - 1;
         
         let mut idx = None;
 
         for i in (0..=last).rev() {
-            if self.0[i].contains_key(identifier) {
+            if self.0[i].contains_key(&identifier) {
                 idx = Some(i);
                 break;
             }
         }
 
         if let Some(i) = idx {
-            return Ok(self.0[i].get_mut(identifier).unwrap());
+            return Ok(self.0[i].get_mut(&identifier).unwrap());
         }
-        Err(RuntimeError {
-            message: format!(
-                "Could not find the variable '{}' in this scope!",
-                identifier
-            ),
-        })
-    }
-
-    fn set(&mut self, identifier: &String, new_value: Value) -> Result<(), RuntimeError> {
-        for i in (0..self.0.len()).rev() {
-            if let Some(value) = self.0[i].get_mut(identifier) {
-                *value = new_value;
-                return Ok(());
-            }
-        }
-
-        Err(RuntimeError {
-            message: format!(
-                "Could not find the variable '{}' in this scope!",
-                identifier
-            ),
-        })
+        Err(RuntimeError::from(RuntimeErrorKind::UndefinedVariable { identifier: identifier.to_string() }))
     }
 }
 
@@ -249,33 +303,47 @@ impl Scope {
         }
     }
 
-    pub fn from_members(members: HashMap<String, Value>) -> Self {
+    pub fn from_members(members: impl IntoIterator<Item = (Symbol, Value)>) -> Self {
         Self { stack: Stack::from_members(members) }
     }
 
-    pub fn insert_members(&mut self, members: HashMap<String, Value>) {
+    pub fn insert_members(&mut self, members: impl IntoIterator<Item = (Symbol, Value)>) {
         self.stack.insert_members(members);
     }
 
-    pub fn push(&mut self, identifier: String) -> Result<(), RuntimeError> {
+    pub fn push(&mut self, identifier: Symbol) -> Result<(), RuntimeError> {
         self.stack.push(identifier, Value::Null)
     }
 
-    pub fn pop(&mut self, identifier: &String) -> Result<(), RuntimeError> {
-        self.stack.pop(&identifier)
+    pub fn pop(&mut self, identifier: Symbol) -> Result<Value, RuntimeError> {
+        self.stack.pop(identifier)
     }
 
     pub fn grow_stack(&mut self) {
         self.stack.grow();
     }
 
-    pub fn shrink_stack(&mut self) {
-        self.stack.shrink();
+    pub fn shrink_stack(&mut self) -> Vec<Value> {
+        self.stack.shrink()
+            .map(|frame| frame.into_values().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        self.stack.depth()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.stack.values()
+    }
+
+    pub fn peek(&self, identifier: Symbol) -> Option<&Value> {
+        self.stack.get(identifier).ok()
     }
 
     pub(crate) fn query_variable(
         &self,
-        address: BakedScopeAddress,
+        address: impl IntoIterator<Item = ScopeAddressant>,
         contained_module_id: &String,
     ) -> Result<Value, RuntimeError> {
         let mut address = address.into_iter();
@@ -285,19 +353,20 @@ impl Scope {
         let first_identifier = match first_addressant {
             ScopeAddressant::Identifier(ident) => ident,
             ScopeAddressant::Index(_) => {
-                return Err(RuntimeError {
-                    message: "Expected variable identifier, found index!".into(),
-                })
+                return Err(RuntimeErrorKind::ExpectedIdentifier.into())
+            }
+            ScopeAddressant::Field(_) => {
+                panic!("Found field as first addressant!");
             }
             ScopeAddressant::DynamicIndex(_) => {
                 panic!("Found dynamic index as addressant after baking!");
             }
         };
 
-        self.stack.get(&first_identifier)?.query(address, contained_module_id)
+        self.stack.get(first_identifier)?.query(address, contained_module_id)
     }
 
-    pub(crate) fn set_variable(&mut self, address: BakedScopeAddress, contained_module_id: &String, value: Value) -> Result<(), RuntimeError> {
+    pub(crate) fn set_variable(&mut self, address: BakedScopeAddress, contained_module_id: &String, value: Value) -> Result<Value, RuntimeError> {
         let mut address = address.into_iter();
 
         let first_addressant = address.next().unwrap();
@@ -305,16 +374,17 @@ impl Scope {
         let first_identifier = match first_addressant {
             ScopeAddressant::Identifier(ident) => ident,
             ScopeAddressant::Index(_) => {
-                return Err(RuntimeError {
-                    message: "Expected variable identifier, found index!".into(),
-                })
+                return Err(RuntimeErrorKind::ExpectedIdentifier.into())
+            }
+            ScopeAddressant::Field(_) => {
+                panic!("Found field as first addressant!");
             }
             ScopeAddressant::DynamicIndex(_) => {
                 panic!("Found dynamic index as addressant after baking!");
             }
         };
 
-        self.stack.get_mut(&first_identifier)?.set(address, contained_module_id, value)
+        self.stack.get_mut(first_identifier)?.set(address, contained_module_id, value)
     }
 
     pub(crate) fn reference_variable(&self, address: BakedScopeAddress, contained_module_id: &String) -> Result<Value, RuntimeError> {
@@ -325,16 +395,17 @@ impl Scope {
         let first_identifier = match first_addressant {
             ScopeAddressant::Identifier(ident) => ident,
             ScopeAddressant::Index(_) => {
-                return Err(RuntimeError {
-                    message: "Expected variable identifier, found index!".into(),
-                })
+                return Err(RuntimeErrorKind::ExpectedIdentifier.into())
+            }
+            ScopeAddressant::Field(_) => {
+                panic!("Found field as first addressant!");
             }
             ScopeAddressant::DynamicIndex(_) => {
                 panic!("Found dynamic index as addressant after baking!");
             }
         };
 
-        self.stack.get(&first_identifier)?.reference(address, contained_module_id)
+        self.stack.get(first_identifier)?.reference(address, contained_module_id)
     }
 
     pub(crate) fn clone_variable(&self, address: BakedScopeAddress, contained_module_id: &String) -> Result<Value, RuntimeError> {
@@ -345,15 +416,16 @@ impl Scope {
         let first_identifier = match first_addressant {
             ScopeAddressant::Identifier(ident) => ident,
             ScopeAddressant::Index(_) => {
-                return Err(RuntimeError {
-                    message: "Expected variable identifier, found index!".into(),
-                })
+                return Err(RuntimeErrorKind::ExpectedIdentifier.into())
+            }
+            ScopeAddressant::Field(_) => {
+                panic!("Found field as first addressant!");
             }
             ScopeAddressant::DynamicIndex(_) => {
                 panic!("Found dynamic index as addressant after baking!");
             }
         };
 
-        self.stack.get(&first_identifier)?.clone_variable(address, contained_module_id)
+        self.stack.get(first_identifier)?.clone_variable(address, contained_module_id)
     }
 }
@@ -0,0 +1,144 @@
+use std::{cell::Cell, mem};
+
+use crate::runtime::{MemberMap, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeStats {
+    pub live_structs: usize,
+    pub live_arrays: usize,
+    pub live_strings: usize,
+    pub stack_depth: usize,
+    pub instruction_count: u64,
+    // Approximate bytes held by live arrays, strings and structs.
+    pub heap_bytes: usize,
+}
+
+// Arrays and strings held by a stored value. Structs are left out as every
+// struct instance accounts for its own members.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Footprint {
+    arrays: usize,
+    strings: usize,
+    // The elements of arrays and the text of strings, without the bookkeeping of allocators.
+    bytes: usize,
+}
+
+impl Footprint {
+    pub(crate) fn of(value: &Value) -> Self {
+        let mut footprint = Self::default();
+        footprint.add(value);
+        footprint
+    }
+
+    pub(crate) fn of_members(members: &MemberMap) -> Self {
+        let mut footprint = Self::default();
+        for value in members.values() {
+            footprint.bytes += mem::size_of::<Value>();
+            footprint.add(value);
+        }
+        footprint
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::String(string) => {
+                self.strings += 1;
+                self.bytes += string.len();
+            }
+            Value::Array(arr) => {
+                self.arrays += 1;
+                self.bytes += arr.len() * mem::size_of::<Value>();
+                for value in arr {
+                    self.add(value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    structs: Cell<usize>,
+    arrays: Cell<usize>,
+    strings: Cell<usize>,
+    stack_depth: Cell<usize>,
+    instructions: Cell<u64>,
+    heap_bytes: Cell<usize>,
+    // Every struct, array and string retained so far, released or not.
+    allocations: Cell<u64>,
+}
+
+impl StatsRecorder {
+    pub(crate) fn snapshot(&self) -> RuntimeStats {
+        RuntimeStats {
+            live_structs: self.structs.get(),
+            live_arrays: self.arrays.get(),
+            live_strings: self.strings.get(),
+            stack_depth: self.stack_depth.get(),
+            instruction_count: self.instructions.get(),
+            heap_bytes: self.heap_bytes.get(),
+        }
+    }
+
+    pub(crate) fn retain(&self, footprint: Footprint) {
+        self.arrays.set(self.arrays.get() + footprint.arrays);
+        self.strings.set(self.strings.get() + footprint.strings);
+        self.heap_bytes.set(self.heap_bytes.get() + footprint.bytes);
+        self.allocations.set(self.allocations.get() + (footprint.arrays + footprint.strings) as u64);
+    }
+
+    pub(crate) fn release(&self, footprint: Footprint) {
+        self.arrays.set(self.arrays.get().saturating_sub(footprint.arrays));
+        self.strings.set(self.strings.get().saturating_sub(footprint.strings));
+        self.heap_bytes.set(self.heap_bytes.get().saturating_sub(footprint.bytes));
+    }
+
+    pub(crate) fn retain_struct(&self, members: &MemberMap) {
+        self.structs.set(self.structs.get() + 1);
+        self.allocations.set(self.allocations.get() + 1);
+        self.retain(Footprint::of_members(members));
+    }
+
+    pub(crate) fn release_struct(&self, members: &MemberMap) {
+        self.structs.set(self.structs.get().saturating_sub(1));
+        self.release(Footprint::of_members(members));
+    }
+
+    pub(crate) fn enter_call(&self) {
+        self.stack_depth.set(self.stack_depth.get() + 1);
+    }
+
+    pub(crate) fn exit_call(&self) {
+        self.stack_depth.set(self.stack_depth.get().saturating_sub(1));
+    }
+
+    // Returns to the given depth, e.g. after calls were left without exiting them.
+    pub(crate) fn unwind_calls(&self, depth: usize) {
+        self.stack_depth.set(depth);
+    }
+
+    pub(crate) fn stack_depth(&self) -> usize {
+        self.stack_depth.get()
+    }
+
+    pub(crate) fn record_instruction(&self) {
+        self.instructions.set(self.instructions.get() + 1);
+    }
+
+    pub(crate) fn instruction_count(&self) -> u64 {
+        self.instructions.get()
+    }
+
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.heap_bytes.get()
+    }
+
+    pub(crate) fn allocation_count(&self) -> u64 {
+        self.allocations.get()
+    }
+}
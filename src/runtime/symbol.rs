@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fmt, sync::{Mutex, OnceLock}};
+
+// An interned identifier. Symbols are compared and hashed like integers, so variables and
+// struct members are looked up without comparing names.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// Names are never freed, as a program may keep using a symbol for as long as it runs.
+#[derive(Default)]
+struct Interner {
+    names: Vec<&'static str>,
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        let mut interner = interner().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(symbol) = interner.symbols.get(name) {
+            return *symbol;
+        }
+
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let symbol = Symbol(interner.names.len() as u32);
+
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+
+        symbol
+    }
+
+    // The symbol of a name that was interned before. Names nothing was interned for cannot be
+    // found in any scope or struct.
+    pub fn find(name: &str) -> Option<Self> {
+        let interner = interner().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        interner.symbols.get(name).copied()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        let interner = interner().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        interner.names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Self::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Shown as the name it stands for, so debug output reads the same as before interning.
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,163 @@
+use std::mem;
+
+use crate::runtime::{ModuleAddress, Value, stats::Footprint};
+
+// Every write keeps a copy of the written value, so recording stops once the events hold about
+// this many bytes.
+pub const TRACE_BYTE_LIMIT: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    Call {
+        procedure: ModuleAddress,
+    },
+    Return,
+    Instruction {
+        pc: usize,
+    },
+    EnterBlock,
+    ExitBlock,
+    Declare {
+        identifier: String,
+    },
+    Write {
+        identifier: String,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+    bytes: usize,
+    truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub procedure: ModuleAddress,
+    pub pc: Option<usize>,
+    blocks: Vec<Vec<(String, Value)>>,
+}
+
+impl TraceFrame {
+    fn new(procedure: ModuleAddress) -> Self {
+        Self {
+            procedure,
+            pc: None,
+            blocks: vec![Vec::new()],
+        }
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = &(String, Value)> {
+        self.blocks.iter().flatten()
+    }
+
+    fn declare(&mut self, identifier: String) {
+        if let Some(block) = self.blocks.last_mut() {
+            block.push((identifier, Value::Null));
+        }
+    }
+
+    fn write(&mut self, identifier: String, value: Value) {
+        for block in self.blocks.iter_mut().rev() {
+            if let Some(variable) = block.iter_mut().find(|(ident, _)| *ident == identifier) {
+                variable.1 = value;
+                return;
+            }
+        }
+
+        if let Some(block) = self.blocks.last_mut() {
+            block.push((identifier, value));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceState {
+    pub step: usize,
+    pub frames: Vec<TraceFrame>,
+}
+
+impl Trace {
+    pub(crate) fn push(&mut self, event: TraceEvent) {
+        let bytes = match &event {
+            TraceEvent::Write { value, .. } => mem::size_of::<TraceEvent>() + Footprint::of(value).bytes(),
+            _ => mem::size_of::<TraceEvent>(),
+        };
+
+        if self.bytes + bytes > TRACE_BYTE_LIMIT {
+            self.truncated = true;
+            return;
+        }
+
+        self.bytes += bytes;
+        self.events.push(event);
+    }
+
+    // Whether recording stopped before the end of the run, see `TRACE_BYTE_LIMIT`.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub fn steps(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Instruction { .. }))
+            .count()
+    }
+
+    // Reconstructs the call stack as it was right before the instruction with the given step number ran.
+    pub fn state_at(&self, step: usize) -> Option<TraceState> {
+        let mut frames: Vec<TraceFrame> = Vec::new();
+        let mut current_step = 0;
+
+        for event in &self.events {
+            match event {
+                TraceEvent::Call { procedure } => {
+                    frames.push(TraceFrame::new(procedure.clone()));
+                }
+                TraceEvent::Return => {
+                    frames.pop();
+                }
+                TraceEvent::Instruction { pc } => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.pc = Some(*pc);
+                    }
+
+                    if current_step == step {
+                        return Some(TraceState { step, frames });
+                    }
+
+                    current_step += 1;
+                }
+                TraceEvent::EnterBlock => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.blocks.push(Vec::new());
+                    }
+                }
+                TraceEvent::ExitBlock => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.blocks.pop();
+                    }
+                }
+                TraceEvent::Declare { identifier } => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.declare(identifier.clone());
+                    }
+                }
+                TraceEvent::Write { identifier, value } => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.write(identifier.clone(), value.clone());
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
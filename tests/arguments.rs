@@ -0,0 +1,56 @@
+use otr::runtime::Value;
+
+use common::compile;
+
+mod common;
+
+#[test]
+fn arguments_are_passed_to_the_entrypoint() {
+    let runtime_object = compile(r#"
+        module Main {
+            @entrypoint
+            proc main(args) {
+                return [Arrays::size(args), args[1]];
+            }
+            export main;
+        }
+    "#).with_arguments(vec!["first".to_string(), "second".to_string()]);
+
+    match runtime_object.execute() {
+        Ok(Value::Array(values)) => {
+            assert!(matches!(values[0], Value::Integer(2)));
+            assert!(matches!(&values[1], Value::String(argument) if argument == "second"));
+        }
+        other => panic!("Expected an array, found {:?}!", other),
+    }
+}
+
+#[test]
+fn arguments_default_to_an_empty_array() {
+    let runtime_object = compile(r#"
+        module Main {
+            @entrypoint
+            proc main(args) {
+                return Arrays::size(args);
+            }
+            export main;
+        }
+    "#);
+
+    assert!(matches!(runtime_object.execute(), Ok(Value::Integer(0))));
+}
+
+#[test]
+fn entrypoints_may_ignore_arguments() {
+    let runtime_object = compile(r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                return 1;
+            }
+            export main;
+        }
+    "#).with_arguments(vec!["unused".to_string()]);
+
+    assert!(matches!(runtime_object.execute(), Ok(Value::Integer(1))));
+}
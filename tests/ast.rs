@@ -0,0 +1,47 @@
+use std::str::FromStr;
+
+use otr::{compiler::{ast::{Accessor, BinaryOperator, Expression, ExpressionKind, Span}, expression_parser::ExpressionParser}, lexer::{FragmentStream, Tokenizer}};
+
+fn parse(source: &str) -> Expression {
+    let fragments = FragmentStream::from_str(source).unwrap();
+    let tokens = Tokenizer::default().tokenize(fragments).unwrap();
+
+    ExpressionParser::parse_syntax(tokens.into_tokens()).unwrap_or_else(|err| panic!("{:?}", err))
+}
+
+#[test]
+fn operands_span_their_tokens() {
+    let expression = parse("a + (b * -2)");
+    assert_eq!(expression.span, Span::new(0, 7));
+
+    let ExpressionKind::Binary { operator: BinaryOperator::Add, lhs, rhs } = expression.kind else {
+        panic!("expected an addition, found {:?}", expression.kind);
+    };
+    assert_eq!(lhs.span, Span::new(0, 1));
+    assert_eq!(rhs.span, Span::new(2, 7));
+
+    let ExpressionKind::Binary { operator: BinaryOperator::Multiply, rhs: factor, .. } = rhs.kind else {
+        panic!("expected a multiplication, found {:?}", rhs.kind);
+    };
+    assert_eq!(factor.span, Span::new(5, 6));
+}
+
+#[test]
+fn arguments_and_indices_span_their_tokens() {
+    let expression = parse("Main::sum(xs[i + 1], 3)");
+    assert_eq!(expression.span, Span::new(0, 13));
+
+    let ExpressionKind::Call { arguments, .. } = expression.kind else {
+        panic!("expected a call, found {:?}", expression.kind);
+    };
+    assert_eq!(arguments[0].span, Span::new(4, 10));
+    assert_eq!(arguments[1].span, Span::new(11, 12));
+
+    let ExpressionKind::Variable(address) = &arguments[0].kind else {
+        panic!("expected a variable, found {:?}", arguments[0].kind);
+    };
+    let Accessor::Index(index) = &address.accessors[0] else {
+        panic!("expected an index, found {:?}", address.accessors[0]);
+    };
+    assert_eq!(index.span, Span::new(6, 9));
+}
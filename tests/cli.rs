@@ -0,0 +1,49 @@
+use std::{env, fs, process::Command};
+
+fn usage_error(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_otr"))
+        .args(args)
+        .current_dir(env::temp_dir())
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success(), "{:?} succeeded", args);
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn ast_needs_a_module() {
+    assert_eq!(usage_error(&["ast"]), "Missing argument! Usage: otr ast <module>\n");
+}
+
+#[test]
+fn add_needs_a_package() {
+    assert_eq!(usage_error(&["add"]), "Missing argument! Usage: otr add <package>\n");
+}
+
+#[test]
+fn lock_needs_a_module() {
+    assert_eq!(usage_error(&["lock"]), "Missing argument! Usage: otr lock <module>\n");
+}
+
+#[test]
+fn failed_adds_leave_nothing_behind() {
+    let dir = env::temp_dir().join(format!("otr-cli-add-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("index.toml"), "[packages.options]\ngit = \"--upload-pack=touch injected\"\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_otr"))
+        .args(["add", "options"])
+        .env("OTR_INDEX", "index.toml")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(!dir.join("deps").join("options").exists());
+    assert!(!dir.join("injected").exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,63 @@
+// Fixtures shared by the integration tests. Each test uses only some of them.
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use otr::{compiler::{Compiler, file_reader::{FileReader, ImportAddress}}, runtime::RuntimeObject};
+
+// A loop calling a procedure, which sums the squares below 5 to 30.
+pub const SQUARES: &str = r#"
+module Main {
+    proc square(n) {
+        return n * n;
+    }
+
+    @entrypoint
+    proc main() {
+        let sum = 0;
+        let i = 0;
+        while (i < 5) {
+            sum = sum + Main::square(i);
+            i = i + 1;
+        }
+        return sum;
+    }
+    export main;
+}
+"#;
+
+// A verifying compiler for the given modules, starting at the first one. Modules not given are
+// read from the root.
+pub fn compiler_at(root: &Path, sources: &[(&str, &str)]) -> Compiler {
+    let mut file_reader = FileReader::new(root.to_path_buf());
+    for (module_id, source) in sources {
+        file_reader = file_reader.with_source(*module_id, *source);
+    }
+    file_reader.enqueue(ImportAddress { module_id: sources[0].0.to_string(), path: None });
+
+    Compiler::new(file_reader).with_verification(true)
+}
+
+pub fn compiler(source: &str) -> Compiler {
+    compiler_at(Path::new(""), &[("Main", source)])
+}
+
+pub fn compile(source: &str) -> RuntimeObject {
+    compiler(source).compile().unwrap_or_else(|err| panic!("{}", err))
+}
+
+// A fixed xorshift generator, so failures can be reproduced.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
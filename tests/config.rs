@@ -0,0 +1,187 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use otr::{config::Config, manifest::Manifest};
+
+use common::compile;
+
+mod common;
+
+const RECURSION: &str = r#"
+module Main {
+    proc down(n) {
+        if (n == 0) {
+            return 0;
+        }
+        return Main::down(n - 1);
+    }
+
+    @entrypoint
+    proc main() {
+        return Main::down(20);
+    }
+    export main;
+}
+"#;
+
+fn vars(pairs: &[(&str, &str)]) -> Config {
+    let vars: HashMap<String, String> = pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+
+    Config::from_vars(|name| vars.get(name).cloned()).unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[test]
+fn flags_are_taken_out_of_the_arguments() {
+    let args = ["Main", "--fuel", "100", "first", "--no-strict", "--stack-limit", "8", "--memory-limit", "4096"].map(String::from).to_vec();
+
+    let (config, remaining) = Config::from_args(args).unwrap_or_else(|err| panic!("{}", err));
+
+    assert_eq!(config, Config { stack_limit: Some(8), fuel: Some(100), memory_limit: Some(4096), strict: Some(false) });
+    assert_eq!(remaining, ["Main", "first"]);
+
+    assert!(Config::from_args(vec!["--fuel".to_string()]).is_err());
+    assert!(Config::from_args(vec!["--stack-limit".to_string(), "-1".to_string()]).is_err());
+}
+
+#[test]
+fn environment_variables_are_parsed() {
+    assert_eq!(vars(&[]), Config::default());
+    assert_eq!(
+        vars(&[("OTR_STACK_LIMIT", "64"), ("OTR_FUEL", " 1000 "), ("OTR_MEMORY_LIMIT", "65536"), ("OTR_STRICT", "off")]),
+        Config { stack_limit: Some(64), fuel: Some(1000), memory_limit: Some(65536), strict: Some(false) }
+    );
+
+    assert!(Config::from_vars(|name| (name == "OTR_FUEL").then(|| "lots".to_string())).is_err());
+    assert!(Config::from_vars(|name| (name == "OTR_STRICT").then(|| "maybe".to_string())).is_err());
+}
+
+#[test]
+fn earlier_sources_take_precedence() {
+    let flags = Config { fuel: Some(10), ..Default::default() };
+    let env = vars(&[("OTR_FUEL", "20"), ("OTR_STACK_LIMIT", "30")]);
+    let manifest = Config::from_manifest(&Manifest { strict_conditions: Some(false), ..Default::default() });
+    let file = Config { stack_limit: Some(40), strict: Some(true), fuel: Some(50), memory_limit: Some(60) };
+
+    let config = flags.or(env).or(manifest).or(file);
+
+    assert_eq!(config, Config { stack_limit: Some(30), fuel: Some(10), memory_limit: Some(60), strict: Some(false) });
+    assert!(Config::default().strict_conditions());
+}
+
+#[test]
+fn config_files_are_read() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join("config_files_are_read.toml");
+
+    fs::write(&path, "stack_limit = 12\nstrict = false\n").unwrap();
+    assert_eq!(Config::load(&path).ok(), Some(Config { stack_limit: Some(12), fuel: None, memory_limit: None, strict: Some(false) }));
+
+    fs::write(&path, "stack_limt = 12\n").unwrap();
+    assert!(Config::load(&path).is_err());
+
+    assert_eq!(Config::load(&dir.join("missing.toml")).ok(), Some(Config::default()));
+}
+
+#[test]
+fn limits_are_applied_to_the_runtime() {
+    let runtime_object = compile(RECURSION);
+
+    Config { stack_limit: Some(10), ..Default::default() }.apply(&runtime_object);
+    let err = runtime_object.execute().unwrap_err();
+    assert_eq!(err.code(), Some("E0018"));
+
+    Config { stack_limit: Some(100), fuel: Some(20), ..Default::default() }.apply(&runtime_object);
+    let err = runtime_object.execute().unwrap_err();
+    assert_eq!(err.code(), Some("E0015"));
+
+    Config::default().apply(&runtime_object);
+    assert!(runtime_object.execute().is_ok());
+}
+
+#[test]
+fn fuel_stops_endless_loops_with_a_timeout() {
+    let runtime_object = compile(r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                let i = 0;
+                loop {
+                    i = i + 1;
+                }
+            }
+            export main;
+        }
+    "#);
+
+    let err = runtime_object.execute_with_fuel(1000).unwrap_err();
+    assert!(err.is_timeout());
+    assert_eq!(err.code(), Some("E0015"));
+
+    // The fuel of the host still applies when it is lower
+    runtime_object.set_fuel(Some(10));
+    assert!(runtime_object.execute_with_fuel(u64::MAX).unwrap_err().is_timeout());
+}
+
+#[test]
+fn budgets_of_procedures_time_out_their_calls() {
+    let runtime_object = compile(r#"
+        module Main {
+            @budget(instructions = 100)
+            proc spin() {
+                loop {}
+            }
+
+            @entrypoint
+            proc main() {
+                Main::spin();
+                return 0;
+            }
+            export main;
+        }
+    "#);
+
+    assert!(runtime_object.execute().unwrap_err().is_timeout());
+    assert!(compile(RECURSION).execute_with_fuel(1_000_000).is_ok());
+}
+
+#[test]
+fn memory_limits_stop_growing_programs() {
+    let runtime_object = compile(r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                let words = [];
+                loop {
+                    words = Arrays::push(words, "word");
+                }
+            }
+            export main;
+        }
+    "#);
+
+    Config { memory_limit: Some(64 * 1024), fuel: Some(1_000_000), ..Default::default() }.apply(&runtime_object);
+    let err = runtime_object.execute().unwrap_err();
+    assert_eq!(err.code(), Some("E0022"));
+    assert!(!err.is_timeout());
+
+    // The values of the failed run were released again
+    assert!(runtime_object.stats().heap_bytes < 64 * 1024);
+}
+
+#[test]
+fn memory_limits_stop_single_huge_allocations() {
+    for call in ["Arrays::setOrGrow([], 100000000000000, 1)", "Strings::padStart(\"a\", 100000000000000, \"x\")", "Strings::repeat(\"a\", 100000000000000)", "[1] * 100000000000000"] {
+        let runtime_object = compile(&format!(r#"
+            module Main {{
+                @entrypoint
+                proc main() {{
+                    return {};
+                }}
+                export main;
+            }}
+        "#, call));
+
+        Config { memory_limit: Some(1_000_000), ..Default::default() }.apply(&runtime_object);
+        let err = runtime_object.execute().unwrap_err();
+        assert_eq!(err.code(), Some("E0022"), "{}: {}", call, err);
+    }
+}
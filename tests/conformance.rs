@@ -0,0 +1,43 @@
+use std::{path::Path, sync::Mutex};
+
+use otr::conformance::{self, RunOptions};
+
+#[test]
+fn conformance() {
+    check_conformance(RunOptions::default());
+}
+
+#[test]
+fn conformance_optimized() {
+    check_conformance(RunOptions { optimize: true, ..Default::default() });
+}
+
+#[test]
+fn conformance_reloaded() {
+    check_conformance(RunOptions { reload: true, ..Default::default() });
+}
+
+#[test]
+fn conformance_optimized_reloaded() {
+    check_conformance(RunOptions { optimize: true, reload: true });
+}
+
+// Cases touch the file system, so the runs must not overlap.
+static RUNNING: Mutex<()> = Mutex::new(());
+
+fn check_conformance(options: RunOptions) {
+    let _running = RUNNING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(conformance::CONFORMANCE_DIR);
+
+    let results = conformance::run_with(&dir, options).unwrap_or_else(|err| panic!("{}", err));
+    assert!(!results.is_empty(), "No conformance cases found in '{}'!", dir.display());
+
+    let failures: Vec<String> = results
+        .iter()
+        .filter(|result| !result.passed())
+        .map(|result| format!("{}\n  expected: {}\n  actual:   {}", result.case.name, result.expected, result.actual))
+        .collect();
+
+    assert!(failures.is_empty(), "{} conformance cases failed:\n{}", failures.len(), failures.join("\n"));
+}
@@ -0,0 +1,129 @@
+use std::{fs, panic::{self, AssertUnwindSafe}, path::Path};
+
+use otr::{conformance, runtime::Value};
+
+use common::{Rng, compile, compiler_at};
+
+mod common;
+
+// Splits a source into words, single punctuation characters and whitespace.
+fn pieces(source: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+
+    for c in source.chars() {
+        let joins = pieces.last().and_then(|last| last.chars().last()).is_some_and(|last| {
+            (last.is_alphanumeric() || last == '_') && (c.is_alphanumeric() || c == '_')
+                || last.is_whitespace() && c.is_whitespace()
+        });
+
+        match pieces.last_mut() {
+            Some(last) if joins => last.push(c),
+            _ => pieces.push(c.to_string()),
+        }
+    }
+
+    pieces
+}
+
+// Debug builds let internal panics through, so any panic fails the test.
+#[test]
+fn mutated_programs_compile_without_panicking() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(conformance::CONFORMANCE_DIR);
+    let mut rng = Rng(0x853c49e6748fea9b);
+    let mut panics = Vec::new();
+
+    for case in conformance::discover(&dir).unwrap_or_else(|err| panic!("{}", err)) {
+        let Ok(source) = fs::read_to_string(case.expected_path.with_extension("otr")) else {
+            continue;
+        };
+        let original = pieces(&source);
+
+        for _ in 0..300 {
+            let mut mutated = original.clone();
+
+            for _ in 0..1 + rng.below(3) {
+                let position = rng.below(mutated.len());
+                let other = original[rng.below(original.len())].clone();
+
+                match rng.below(3) {
+                    0 => { mutated.remove(position); }
+                    1 => mutated.insert(position, other),
+                    _ => mutated[position] = other,
+                }
+            }
+
+            let mutated: String = mutated.concat();
+            if panic::catch_unwind(AssertUnwindSafe(|| compiler_at(&dir, &[(&case.name, &mutated)]).compile())).is_err() {
+                panics.push((case.name.clone(), mutated));
+            }
+        }
+    }
+
+    for (name, source) in &panics {
+        eprintln!("=== {}\n{}", name, source);
+    }
+    assert!(panics.is_empty(), "{} mutated programs panicked!", panics.len());
+}
+
+// A panicking event listener stands in for a bug in the runtime.
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "listener failed"))]
+fn panics_while_running_become_internal_errors() {
+    let source = r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                Emit::event("fail", 1);
+                return 1;
+            }
+            export main;
+        }
+    "#;
+
+    let runtime_object = compile(source);
+
+    runtime_object.set_stack_limit(Some(2));
+    runtime_object.on_event(|event| if event.name == "fail" {
+        panic!("listener failed");
+    });
+
+    let err = runtime_object.execute().unwrap_err();
+    assert_eq!(err.code(), Some("E0019"));
+
+    // The runtime is still usable afterwards.
+    runtime_object.on_event(|_| {});
+    assert!(matches!(runtime_object.execute(), Ok(Value::Integer(1))));
+}
+
+// Test threads have a small stack, which plain recursion would overflow long before the
+// default stack limit.
+#[test]
+fn runaway_recursion_fails_with_an_error() {
+    let source = r#"
+        module Main {
+            proc down(n) {
+                if (n == 0) {
+                    return 0;
+                }
+                return Main::down(n - 1) + 1;
+            }
+
+            @entrypoint
+            proc main(args) {
+                let depth = Convert::toInteger(args[0]);
+                return Main::down(depth);
+            }
+            export main;
+        }
+    "#;
+
+    let runtime_object = compile(source);
+
+    let deep = runtime_object.with_arguments(vec!["5000".into()]);
+    assert!(matches!(deep.execute(), Ok(Value::Integer(5000))));
+
+    let runaway = deep.with_arguments(vec!["100000".into()]);
+    let err = runaway.execute().unwrap_err();
+    assert_eq!(err.code(), Some("E0018"));
+    assert_eq!(err.to_string(), "Maximum call depth of 10000 exceeded when calling 'Main::down'!");
+}
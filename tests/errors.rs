@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use otr::{compiler::{CompilerError, CompilerErrorKind}, lexer::token::PrimitiveTypeToken, runtime::{ModuleAddress, RuntimeErrorKind}};
+
+use common::{compile, compiler_at};
+
+mod common;
+
+fn compile_error(sources: &[(&str, &str)]) -> CompilerError {
+    match compiler_at(Path::new(""), sources).compile() {
+        Ok(_) => panic!("Expected {:?} not to compile!", sources),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn errors_name_the_file_and_token_they_were_found_at() {
+    let err = compile_error(&[
+        ("Main", "import Util;\nmodule Main {\n    proc main() { return Util::one(); }\n}"),
+        ("Util", "module Util {\n    proc one() {\n        return 1;\n    }\n    proc 2() {}\n}"),
+    ]);
+
+    assert_eq!(err.file.as_deref(), Some("<Util>"));
+
+    let span = err.span.unwrap();
+    assert_eq!((span.line, span.column), (5, 10));
+    assert_eq!(err.to_string(), "<Util>:5:10: Unexpected token. Expected identifier, found Literal(Integer(\"2\"))!");
+}
+
+#[test]
+fn errors_of_the_whole_program_have_no_location() {
+    let err = compile_error(&[("Main", "import Missing;\nmodule Main {}")]);
+
+    assert!(err.span.is_none());
+    assert_eq!(err.to_string(), err.message());
+    assert!(matches!(err.kind, CompilerErrorKind::ImportNotFound { ref module, .. } if module.module_id == "Missing"));
+}
+
+#[test]
+fn compiler_errors_have_kinds() {
+    let err = compile_error(&[("Main", "module Main {
+    proc main() {
+        return (1 + 2;
+    }
+}")]);
+    assert!(matches!(err.kind, CompilerErrorKind::UnbalancedBrackets), "{}", err);
+
+    let err = compile_error(&[("Main", "module Main {
+    @fast
+    proc main() {}
+}")]);
+    assert!(matches!(err.kind, CompilerErrorKind::UnsupportedDecorator { ref name } if name == "fast"), "{}", err);
+    assert_eq!(err.code(), Some("E0016"));
+}
+
+#[test]
+fn runtime_errors_have_kinds() {
+    let runtime_object = compile(r#"
+module Main {
+    proc half(n: Integer) {
+        return n / 0;
+    }
+
+    @entrypoint
+    proc main(args) {
+        if (Arrays::size(args) > 0) {
+            return Main::half("two");
+        }
+        return Main::half(2);
+    }
+    export main;
+}
+"#);
+
+    let err = runtime_object.execute().unwrap_err();
+    assert!(matches!(err.kind(), RuntimeErrorKind::DivisionByZero { .. }), "{}", err);
+    assert_eq!(err.code(), Some("E0021"));
+
+    let err = runtime_object.with_arguments(vec!["typed".into()]).execute().unwrap_err();
+    assert_eq!(err.kind(), &RuntimeErrorKind::TypeMismatch { argument: Some("n".into()), expected: PrimitiveTypeToken::Integer, found: "String".into() });
+    assert_eq!(err.to_string(), "Type mismatch for argument 'n'! Expected Integer, found String!");
+}
+
+#[test]
+fn runtime_errors_trace_the_calls_they_occurred_in() {
+    let runtime_object = compile(r#"
+module Main {
+    proc half(n) {
+        return n / 0;
+    }
+
+    proc down(n) {
+        if (n == 0) {
+            return Main::half(n);
+        }
+        return Main::down(n - 1);
+    }
+
+    @entrypoint
+    proc main() {
+        return Main::down(2);
+    }
+    export main;
+}
+"#);
+
+    let err = runtime_object.execute().unwrap_err();
+    let frames: Vec<String> = err.stack_trace().iter().map(ToString::to_string).collect();
+    assert_eq!(frames, [
+        "Main::half (<Main>:4:9)",
+        "Main::down (<Main>:9:13)",
+        "Main::down (<Main>:11:9)",
+        "Main::down (<Main>:11:9)",
+        "Main::main (<Main>:16:9)",
+    ]);
+    assert_eq!(err.stack_trace()[0].procedure, ModuleAddress::from(("Main", "half")));
+    assert_eq!(err.to_string(), "Division by zero in '(n / 0)'!");
+}
+
+#[test]
+fn error_codes_follow_the_kind_not_the_message() {
+    let runtime_object = compile(r#"
+module Main {
+    @entrypoint
+    proc main(args) {
+        if (Arrays::size(args) > 0) {
+            throw "Index out of bounds!";
+        }
+        let numbers = [1, 2];
+        return numbers[5];
+    }
+    export main;
+}
+"#);
+
+    let err = runtime_object.execute().unwrap_err();
+    assert!(matches!(err.kind(), RuntimeErrorKind::IndexOutOfBounds { .. }), "{}", err);
+    assert_eq!(err.code(), Some("E0010"));
+
+    let err = runtime_object.with_arguments(vec!["thrown".into()]).execute().unwrap_err();
+    assert!(matches!(err.kind(), RuntimeErrorKind::Uncaught { .. }), "{}", err);
+    assert_eq!(err.code(), None);
+}
@@ -0,0 +1,35 @@
+use std::path::Path;
+
+use otr::{repl::{REPL_MODULE_ID, Session}, runtime::Value};
+
+use common::compiler_at;
+
+mod common;
+
+fn eval(snippet: &str, implicit_results: bool) -> Value {
+    compiler_at(Path::new(""), &[(REPL_MODULE_ID, &Session::new().program(snippet))])
+        .with_implicit_results(implicit_results)
+        .compile()
+        .unwrap_or_else(|err| panic!("{}", err))
+        .execute()
+        .unwrap_or_else(|err| panic!("{:?}", err))
+}
+
+#[test]
+fn trailing_expression_is_returned() {
+    assert!(matches!(eval("let x = 2; x * 3", true), Value::Integer(6)));
+    assert!(matches!(eval("let x = 2; x * 3;", true), Value::Integer(6)));
+    assert!(matches!(eval("let values = [1]; if (true) { values[0] = 5; } values[0]", true), Value::Integer(5)));
+}
+
+#[test]
+fn explicit_returns_and_statements_are_kept() {
+    assert!(matches!(eval("1; return 7;", true), Value::Integer(7)));
+    assert!(matches!(eval("let x = 2;", true), Value::Null));
+    assert!(matches!(eval("let x = 2; if (true) { x; }", true), Value::Null));
+}
+
+#[test]
+fn results_are_only_implicit_when_enabled() {
+    assert!(matches!(eval("let x = 2; x * 3", false), Value::Null));
+}
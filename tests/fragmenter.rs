@@ -0,0 +1,106 @@
+use std::{fs, path::Path, str::FromStr};
+
+use otr::{conformance, lexer::{FragmentStream, FragmentationError, Tokenizer}};
+
+use common::Rng;
+
+mod common;
+
+// Characters the fragmenter treats specially, plus a few it does not know.
+const ALPHABET: &[char] = &[
+    'a', 'Z', '_', '0', '7', '.', ';', '(', ')', '{', '}', '[', ']', '+', '-', '=', ' ', '\t', '\n', '\r',
+    '"', '\'', '\\', '`', '#', 'x', 'u', 'n', 'ä', '\u{feff}', '\u{a0}', '\0', '🦀',
+];
+
+fn sources() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(conformance::CONFORMANCE_DIR);
+
+    conformance::discover(&dir)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .into_iter()
+        .filter_map(|case| fs::read_to_string(case.expected_path.with_extension("otr")).ok())
+        .collect()
+}
+
+#[test]
+fn random_input_never_panics() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+
+    for _ in 0..20_000 {
+        let length = rng.below(40);
+        let input: String = (0..length).map(|_| ALPHABET[rng.below(ALPHABET.len())]).collect();
+
+        let _ = FragmentStream::from_str(&input);
+    }
+}
+
+#[test]
+fn truncated_sources_never_panic() {
+    for source in sources() {
+        for (end, _) in source.char_indices() {
+            let _ = FragmentStream::from_str(&source[..end]);
+        }
+    }
+}
+
+#[test]
+fn mutated_sources_never_panic() {
+    let mut rng = Rng(0x2545f4914f6cdd1d);
+
+    for source in sources() {
+        let chars: Vec<char> = source.chars().collect();
+
+        for _ in 0..200 {
+            let mut mutated = chars.clone();
+            let position = rng.below(mutated.len() + 1);
+
+            match rng.below(3) {
+                0 if position < mutated.len() => {
+                    mutated.remove(position);
+                }
+                1 if position < mutated.len() => mutated[position] = ALPHABET[rng.below(ALPHABET.len())],
+                _ => mutated.insert(position, ALPHABET[rng.below(ALPHABET.len())]),
+            }
+
+            let _ = FragmentStream::from_str(&mutated.into_iter().collect::<String>());
+        }
+    }
+}
+
+#[test]
+fn unterminated_literals_report_their_line() {
+    for (source, expected_line) in [
+        ("let a = 1;\nlet b = \"open", 2),
+        ("let a = 'x", 1),
+        ("let a = '", 1),
+        ("\n\nlet a = \"\"\"\nmultiple\nlines", 3),
+        ("let a = \"escaped \\\"", 1),
+    ] {
+        match FragmentStream::from_str(source) {
+            Err(FragmentationError::UnterminatedLiteral { line }) => assert_eq!(line, expected_line, "in {:?}", source),
+            other => panic!("Expected an unterminated literal in {:?}, found {:?}!", source, other),
+        }
+    }
+}
+
+#[test]
+fn tokens_span_their_source() {
+    let source = "\u{feff}let s = \"ä\\n\";\r\n  x->y";
+    let tokens = Tokenizer::default().tokenize(FragmentStream::from_str(source).unwrap()).unwrap();
+
+    let spans: Vec<(&str, usize, usize)> = tokens.0
+        .iter()
+        .map(|(_, span)| (&source[span.start..span.end], span.line, span.column))
+        .collect();
+
+    assert_eq!(spans, [
+        ("let", 1, 1),
+        ("s", 1, 5),
+        ("=", 1, 7),
+        ("\"ä\\n\"", 1, 9),
+        (";", 1, 14),
+        ("x", 2, 3),
+        ("->", 2, 4),
+        ("y", 2, 6),
+    ]);
+}
@@ -0,0 +1,139 @@
+use std::{env, fs, thread};
+
+use otr::runtime::{RuntimeErrorKind, RuntimeObject, Value, image::SharedProgram};
+
+use common::{compile, compiler};
+
+mod common;
+
+#[test]
+fn saved_programs_run_like_compiled_ones() {
+    let runtime_object = compile(r#"
+        module Main {
+            struct Counter {
+                count
+            }
+            export Counter;
+
+            let start = 40;
+
+            proc bump(counter) {
+                counter.count = counter.count + 1;
+            }
+
+            @entrypoint
+            proc main(args) {
+                let counter = Main::Counter { count: Main::start };
+                Main::bump(ref counter);
+                Main::bump(ref counter);
+                return [counter.count, args[0]];
+            }
+            export main;
+        }
+    "#);
+
+    let path = env::temp_dir().join(format!("otr-image-{}.otrc", std::process::id()));
+    runtime_object.save(&path).unwrap_or_else(|err| panic!("{}", err));
+    let loaded = RuntimeObject::load(&path).unwrap_or_else(|err| panic!("{}", err));
+    fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.with_arguments(vec!["argument".to_string()]);
+
+    match loaded.execute() {
+        Ok(Value::Array(values)) => {
+            assert!(matches!(values[0], Value::Integer(42)));
+            assert!(matches!(&values[1], Value::String(argument) if argument == "argument"));
+        }
+        other => panic!("Expected an array, found {:?}!", other),
+    }
+}
+
+#[test]
+fn denied_capabilities_stay_denied() {
+    let runtime_object = compiler(r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                Sys::exit(3);
+            }
+            export main;
+        }
+    "#)
+        .deny_capability("Sys")
+        .compile()
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let loaded = RuntimeObject::from_bytes(&runtime_object.to_bytes().unwrap()).unwrap();
+
+    let err = loaded.execute().unwrap_err();
+    assert!(matches!(err.kind(), RuntimeErrorKind::CapabilityDenied { module_id } if module_id == "Sys"), "{}", err);
+}
+
+#[test]
+fn other_files_are_rejected() {
+    for bytes in [&b"module Main {}"[..], b"OTRC", b""] {
+        let err = RuntimeObject::from_bytes(bytes).unwrap_err();
+
+        assert!(err.message().starts_with("Compiled program is invalid!"), "{}", err);
+    }
+}
+
+#[test]
+fn shared_programs_run_on_other_threads() {
+    fn assert_thread_safe<T: Send + Sync>() {}
+    assert_thread_safe::<SharedProgram>();
+
+    let runtime_object = compile(r#"
+        module Main {
+            let calls = 0;
+
+            @entrypoint
+            proc main(args) {
+                Main::calls = Main::calls + 1;
+                return Main::calls + Arrays::size(args);
+            }
+            export main;
+        }
+    "#);
+
+    let program = SharedProgram::new(&runtime_object).unwrap();
+
+    let workers: Vec<_> = (0..4)
+        .map(|worker| {
+            let program = program.clone();
+
+            thread::spawn(move || {
+                let instance = program.instantiate().unwrap().with_arguments(vec![String::new(); worker]);
+
+                match instance.execute() {
+                    Ok(Value::Integer(result)) => result,
+                    other => panic!("Expected an Integer, found {:?}!", other),
+                }
+            })
+        })
+        .collect();
+
+    let results: Vec<i64> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+
+    assert_eq!(results, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn shared_instances_cannot_use_the_environment() {
+    let source = r#"
+        module Main {
+            @entrypoint
+            proc main() {
+                Env::set("OTR_SHARED_PROGRAM_TEST", "set");
+                return Env::get("OTR_SHARED_PROGRAM_TEST");
+            }
+            export main;
+        }
+    "#;
+
+    let program = SharedProgram::new(&compile(source)).unwrap();
+    let instance = thread::spawn(move || program.instantiate().unwrap().execute().map(|_| ()).map_err(|err| err.kind().clone())).join().unwrap();
+
+    assert_eq!(instance, Err(RuntimeErrorKind::CapabilityDenied { module_id: "Env".to_string() }));
+    assert!(env::var("OTR_SHARED_PROGRAM_TEST").is_err());
+}
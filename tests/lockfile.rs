@@ -0,0 +1,85 @@
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+fn project(name: &str, manifest: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("otr-lockfile-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("deps").join("util")).unwrap();
+
+    fs::write(dir.join("otr.toml"), manifest).unwrap();
+    fs::write(dir.join("deps").join("util").join("Util.otr"), "module Util { proc one() { return 1; } export one; }").unwrap();
+    write_main(&dir, 1);
+    fs::write(dir.join("Other.otr"), "module Other { @entrypoint proc main() { return 2; } export main; }").unwrap();
+
+    dir
+}
+
+fn write_main(dir: &Path, offset: i64) {
+    fs::write(dir.join("Main.otr"), format!("import Util;\nmodule Main {{ @entrypoint proc main() {{ return Util::one() + {}; }} export main; }}", offset)).unwrap();
+}
+
+fn otr(dir: &Path, args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_otr")).args(args).current_dir(dir).output().unwrap();
+
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+const DEPENDENCIES: &str = "[dependencies.util]\npath = \"deps/util\"\n";
+
+#[test]
+fn runs_leave_the_lockfile_alone() {
+    let dir = project("runs", DEPENDENCIES);
+
+    assert_eq!(otr(&dir, &["Main"]), (true, "2\n".to_string()));
+    assert!(!dir.join("otr.lock").exists());
+
+    write_main(&dir, 2);
+    assert_eq!(otr(&dir, &["Main"]), (true, "3\n".to_string()));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn locked_projects_report_every_difference() {
+    let dir = project("locked", DEPENDENCIES);
+
+    assert_eq!(otr(&dir, &["lock", "Main"]), (true, "Locked 2 modules of Main in otr.lock.\n".to_string()));
+    let lockfile = fs::read_to_string(dir.join("otr.lock")).unwrap();
+    assert!(lockfile.contains("deps/util/Util.otr"), "{}", lockfile);
+    assert!(lockfile.contains("Main.otr"), "{}", lockfile);
+    assert_eq!(otr(&dir, &["Main"]), (true, "2\n".to_string()));
+
+    // Entry modules are locked one by one.
+    let (success, output) = otr(&dir, &["Other"]);
+    assert!(!success);
+    assert!(output.contains("added    Other.otr"), "{}", output);
+    assert!(output.contains("Run 'otr lock Other'"), "{}", output);
+    assert_eq!(otr(&dir, &["lock", "Other"]), (true, "Locked 1 modules of Other in otr.lock.\n".to_string()));
+    assert_eq!(otr(&dir, &["Other"]), (true, "2\n".to_string()));
+    assert_eq!(otr(&dir, &["Main"]), (true, "2\n".to_string()));
+
+    fs::write(dir.join("deps").join("util").join("Util.otr"), "module Util { proc one() { return 10; } export one; }").unwrap();
+    let (success, output) = otr(&dir, &["Main"]);
+    assert!(!success);
+    assert!(output.contains("changed  deps/util/Util.otr"), "{}", output);
+
+    fs::write(dir.join("Main.otr"), "module Main { @entrypoint proc main() { return 1; } export main; }").unwrap();
+    let (success, output) = otr(&dir, &["Main"]);
+    assert!(!success);
+    assert!(output.contains("changed  Main.otr"), "{}", output);
+    assert!(output.contains("removed  deps/util/Util.otr"), "{}", output);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn projects_may_opt_out_of_the_lockfile() {
+    let dir = project("unlocked", &format!("locked = false\n\n{}", DEPENDENCIES));
+
+    assert_eq!(otr(&dir, &["lock", "Main"]), (true, "Locked 2 modules of Main in otr.lock.\n".to_string()));
+
+    write_main(&dir, 2);
+    assert_eq!(otr(&dir, &["Main"]), (true, "3\n".to_string()));
+    assert_eq!(otr(&dir, &["Other"]), (true, "2\n".to_string()));
+
+    let _ = fs::remove_dir_all(&dir);
+}
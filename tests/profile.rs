@@ -0,0 +1,41 @@
+use otr::runtime::{ModuleAddress, Value};
+
+use common::{SQUARES, compile};
+
+mod common;
+
+#[test]
+fn calls_and_instructions_are_counted() {
+    let (result, profile) = compile(SQUARES).execute_with_profile();
+
+    assert!(matches!(result, Ok(Value::Integer(30))));
+
+    let main = profile.procedure(&("Main", "main").into()).unwrap();
+    let square = profile.procedure(&("Main", "square").into()).unwrap();
+
+    assert_eq!(main.calls, 1);
+    assert_eq!(square.calls, 5);
+    assert!(main.time >= square.time);
+
+    // The body of the loop runs five times, its condition once more.
+    let executions: Vec<u64> = main.instructions.values().map(|instruction| instruction.executions).collect();
+    assert!(executions.contains(&5));
+    assert!(executions.contains(&6));
+    assert_eq!(square.instructions.values().map(|instruction| instruction.executions).sum::<u64>(), 5);
+
+    let (hottest, _, _) = profile.hot_instructions(1)[0];
+    assert!([ModuleAddress::from(("Main", "main")), ("Main", "square").into()].contains(hottest));
+}
+
+#[test]
+fn profiling_ends_with_its_execution() {
+    let runtime_object = compile(SQUARES);
+
+    let (_, profile) = runtime_object.execute_with_profile();
+    assert_eq!(profile.procedures().len(), 2);
+
+    runtime_object.execute().unwrap();
+
+    let (_, profile) = runtime_object.execute_with_profile();
+    assert_eq!(profile.procedure(&("Main", "square").into()).unwrap().calls, 5);
+}
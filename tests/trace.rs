@@ -0,0 +1,78 @@
+use std::{env, fs, io::Write, path::Path, process::{Command, Output, Stdio}};
+
+use otr::runtime::Value;
+
+use common::{SQUARES, compile};
+
+mod common;
+
+fn otr(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_otr"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn replays_end_where_the_recorded_run_ended() {
+    let dir = env::temp_dir().join(format!("otr-replay-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("Main.otr"), SQUARES).unwrap();
+
+    let run = otr(&dir, &["Main"], "");
+    // Jumps past the last step, prints the variables there and quits.
+    let replay = otr(&dir, &["replay", "Main"], "g 1000\np\nq\n");
+    let _ = fs::remove_dir_all(&dir);
+
+    let run = String::from_utf8(run.stdout).unwrap();
+    let replay = String::from_utf8(replay.stdout).unwrap();
+
+    assert_eq!(run, "30\n");
+    assert_eq!(replay.lines().next(), Some("30"));
+
+    let variables = replay.rsplit("#0 Main::main").next().unwrap();
+    assert!(variables.contains("sum = 30"), "{}", replay);
+    assert!(variables.contains("i = 5"), "{}", replay);
+}
+
+#[test]
+fn replay_needs_a_module() {
+    let output = otr(&env::temp_dir(), &["replay"], "");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("Missing argument! Usage: otr replay"));
+}
+
+#[test]
+fn traces_stop_growing_at_their_limit() {
+    let runtime_object = compile(r#"
+module Main {
+    @entrypoint
+    proc main() {
+        let i = 0;
+        while (i < 100) {
+            let text = "x" * 1000000;
+            i = i + 1;
+        }
+        return i;
+    }
+    export main;
+}
+"#);
+
+    let (result, trace) = runtime_object.execute_traced();
+
+    assert!(matches!(result, Ok(Value::Integer(100))));
+    assert!(trace.is_truncated());
+    assert!(trace.steps() > 0);
+
+    let (_, trace) = compile(SQUARES).execute_traced();
+    assert!(!trace.is_truncated());
+}